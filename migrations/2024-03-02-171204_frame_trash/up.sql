@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE frames ADD COLUMN deleted_at VARCHAR;
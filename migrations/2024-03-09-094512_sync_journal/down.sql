@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX frames_uuid;
+ALTER TABLE frames DROP COLUMN updated_at;
+ALTER TABLE frames DROP COLUMN uuid;
+DROP TABLE settings;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE settings (
+	key VARCHAR NOT NULL PRIMARY KEY,
+	value VARCHAR NOT NULL
+);
+
+ALTER TABLE frames ADD COLUMN uuid VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE frames ADD COLUMN updated_at VARCHAR NOT NULL DEFAULT '';
+
+-- random version 4 uuids for existing frames
+UPDATE frames SET uuid = lower(
+	hex(randomblob(4)) || '-' ||
+	hex(randomblob(2)) || '-4' ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	substr('89ab', abs(random()) % 4 + 1, 1) ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	hex(randomblob(6))
+);
+UPDATE frames SET updated_at = COALESCE(deleted_at, end, start);
+
+CREATE UNIQUE INDEX frames_uuid ON frames(uuid);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE tags_per_project ADD COLUMN is_default BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE projects ADD COLUMN estimate_minutes INTEGER;
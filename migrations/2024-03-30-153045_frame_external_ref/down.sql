@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX frames_external_ref;
+ALTER TABLE frames DROP COLUMN external_ref;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE frames ADD COLUMN external_ref VARCHAR;
+
+CREATE INDEX frames_external_ref ON frames(external_ref);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE locked_periods (
+	id INTEGER NOT NULL PRIMARY KEY,
+	start VARCHAR NOT NULL,
+	end VARCHAR NOT NULL,
+	locked_at VARCHAR NOT NULL
+);
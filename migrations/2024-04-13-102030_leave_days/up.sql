@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE leave_days (
+	day VARCHAR NOT NULL PRIMARY KEY,
+	kind VARCHAR NOT NULL,
+	name VARCHAR
+);
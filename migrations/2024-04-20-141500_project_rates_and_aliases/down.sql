@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE project_aliases;
+
+ALTER TABLE projects DROP COLUMN hourly_rate_cents;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE projects ADD COLUMN hourly_rate_cents INTEGER;
+
+CREATE TABLE project_aliases (
+	alias VARCHAR NOT NULL PRIMARY KEY,
+	project_id INTEGER NOT NULL REFERENCES projects(id)
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER daily_totals_add_inserted;
+DROP TRIGGER daily_totals_move_updated;
+DROP TRIGGER daily_totals_remove_deleted;
+
+DROP TABLE daily_totals;
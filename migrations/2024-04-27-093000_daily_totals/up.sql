@@ -0,0 +1,53 @@
+-- Your SQL goes here
+CREATE TABLE daily_totals (
+	day VARCHAR NOT NULL,
+	project_id INTEGER NOT NULL REFERENCES projects(id),
+	milliseconds BIGINT NOT NULL,
+	PRIMARY KEY(day, project_id)
+);
+
+-- Like in reports, a frame counts fully for the day it starts on, in the offset it was tracked
+-- in, which is the date part of the RFC 3339 timestamp. Running and trashed frames count nowhere.
+INSERT INTO daily_totals (day, project_id, milliseconds)
+SELECT substr(start, 1, 10), project,
+	SUM(CAST(round((julianday(end) - julianday(start)) * 86400000) AS INTEGER))
+FROM frames
+WHERE end IS NOT NULL AND deleted_at IS NULL
+GROUP BY substr(start, 1, 10), project;
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+END;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX projects_uuid;
+DROP INDEX tags_uuid;
+
+ALTER TABLE projects DROP COLUMN uuid;
+ALTER TABLE tags DROP COLUMN uuid;
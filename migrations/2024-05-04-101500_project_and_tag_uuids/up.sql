@@ -0,0 +1,24 @@
+-- Your SQL goes here
+ALTER TABLE projects ADD COLUMN uuid VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE tags ADD COLUMN uuid VARCHAR NOT NULL DEFAULT '';
+
+-- random version 4 uuids for existing projects and tags
+UPDATE projects SET uuid = lower(
+	hex(randomblob(4)) || '-' ||
+	hex(randomblob(2)) || '-4' ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	substr('89ab', abs(random()) % 4 + 1, 1) ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	hex(randomblob(6))
+);
+UPDATE tags SET uuid = lower(
+	hex(randomblob(4)) || '-' ||
+	hex(randomblob(2)) || '-4' ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	substr('89ab', abs(random()) % 4 + 1, 1) ||
+	substr(hex(randomblob(2)), 2) || '-' ||
+	hex(randomblob(6))
+);
+
+CREATE UNIQUE INDEX projects_uuid ON projects(uuid);
+CREATE UNIQUE INDEX tags_uuid ON tags(uuid);
@@ -0,0 +1,89 @@
+-- This file should undo anything in `up.sql`
+
+-- The tables are rebuilt with the constraints they had before.
+CREATE TABLE frames_old (
+	id INTEGER NOT NULL PRIMARY KEY,
+	project INTEGER NOT NULL,
+	start VARCHAR NOT NULL,
+	end VARCHAR,
+	deleted_at VARCHAR,
+	uuid VARCHAR NOT NULL DEFAULT '',
+	updated_at VARCHAR NOT NULL DEFAULT '',
+	external_ref VARCHAR,
+	FOREIGN KEY(project) REFERENCES projects(id)
+);
+INSERT INTO frames_old (id, project, start, end, deleted_at, uuid, updated_at, external_ref)
+SELECT id, project, start, end, deleted_at, uuid, updated_at, external_ref FROM frames;
+DROP TABLE frames;
+ALTER TABLE frames_old RENAME TO frames;
+CREATE UNIQUE INDEX frames_uuid ON frames(uuid);
+CREATE INDEX frames_external_ref ON frames(external_ref);
+
+CREATE TABLE tags_per_project_old (
+	project_id INTEGER NOT NULL,
+	tag_id INTEGER NOT NULL,
+	is_default BOOLEAN NOT NULL DEFAULT 0,
+	FOREIGN KEY(project_id) REFERENCES projects(id),
+	FOREIGN KEY(tag_id) REFERENCES tags(id),
+	PRIMARY KEY(project_id, tag_id)
+);
+INSERT INTO tags_per_project_old (project_id, tag_id, is_default)
+SELECT project_id, tag_id, is_default FROM tags_per_project;
+DROP TABLE tags_per_project;
+ALTER TABLE tags_per_project_old RENAME TO tags_per_project;
+
+CREATE TABLE project_aliases_old (
+	alias VARCHAR NOT NULL PRIMARY KEY,
+	project_id INTEGER NOT NULL REFERENCES projects(id)
+);
+INSERT INTO project_aliases_old (alias, project_id) SELECT alias, project_id FROM project_aliases;
+DROP TABLE project_aliases;
+ALTER TABLE project_aliases_old RENAME TO project_aliases;
+
+CREATE TABLE daily_totals_old (
+	day VARCHAR NOT NULL,
+	project_id INTEGER NOT NULL REFERENCES projects(id),
+	milliseconds BIGINT NOT NULL,
+	PRIMARY KEY(day, project_id)
+);
+INSERT INTO daily_totals_old (day, project_id, milliseconds)
+SELECT day, project_id, milliseconds FROM daily_totals;
+DROP TABLE daily_totals;
+ALTER TABLE daily_totals_old RENAME TO daily_totals;
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+END;
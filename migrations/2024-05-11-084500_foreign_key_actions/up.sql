@@ -0,0 +1,96 @@
+-- Your SQL goes here
+
+-- SQLite can't change constraints, so the tables referencing projects and tags are rebuilt.
+-- Frames keep their project, deleting a project that still has frames fails. Everything else
+-- belonging to a project or tag is deleted with it.
+
+-- Rows the new constraints would reject can only be left over from bugs.
+DELETE FROM tags_per_project
+WHERE project_id NOT IN (SELECT id FROM projects) OR tag_id NOT IN (SELECT id FROM tags);
+DELETE FROM project_aliases WHERE project_id NOT IN (SELECT id FROM projects);
+DELETE FROM daily_totals WHERE project_id NOT IN (SELECT id FROM projects);
+
+CREATE TABLE frames_new (
+	id INTEGER NOT NULL PRIMARY KEY,
+	project INTEGER NOT NULL REFERENCES projects(id) ON DELETE RESTRICT,
+	start VARCHAR NOT NULL,
+	end VARCHAR,
+	deleted_at VARCHAR,
+	uuid VARCHAR NOT NULL DEFAULT '',
+	updated_at VARCHAR NOT NULL DEFAULT '',
+	external_ref VARCHAR
+);
+INSERT INTO frames_new (id, project, start, end, deleted_at, uuid, updated_at, external_ref)
+SELECT id, project, start, end, deleted_at, uuid, updated_at, external_ref FROM frames;
+-- Also drops the indexes and the triggers maintaining daily_totals.
+DROP TABLE frames;
+ALTER TABLE frames_new RENAME TO frames;
+CREATE UNIQUE INDEX frames_uuid ON frames(uuid);
+CREATE INDEX frames_external_ref ON frames(external_ref);
+
+CREATE TABLE tags_per_project_new (
+	project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+	tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+	is_default BOOLEAN NOT NULL DEFAULT 0,
+	PRIMARY KEY(project_id, tag_id)
+);
+INSERT INTO tags_per_project_new (project_id, tag_id, is_default)
+SELECT project_id, tag_id, is_default FROM tags_per_project;
+DROP TABLE tags_per_project;
+ALTER TABLE tags_per_project_new RENAME TO tags_per_project;
+
+CREATE TABLE project_aliases_new (
+	alias VARCHAR NOT NULL PRIMARY KEY,
+	project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE
+);
+INSERT INTO project_aliases_new (alias, project_id) SELECT alias, project_id FROM project_aliases;
+DROP TABLE project_aliases;
+ALTER TABLE project_aliases_new RENAME TO project_aliases;
+
+CREATE TABLE daily_totals_new (
+	day VARCHAR NOT NULL,
+	project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+	milliseconds BIGINT NOT NULL,
+	PRIMARY KEY(day, project_id)
+);
+INSERT INTO daily_totals_new (day, project_id, milliseconds)
+SELECT day, project_id, milliseconds FROM daily_totals;
+DROP TABLE daily_totals;
+ALTER TABLE daily_totals_new RENAME TO daily_totals;
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+END;
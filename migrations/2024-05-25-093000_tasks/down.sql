@@ -0,0 +1,59 @@
+-- This file should undo anything in `up.sql`
+-- SQLite can't drop a column with a foreign key, so frames are rebuilt like in
+-- 2024-05-11-084500_foreign_key_actions.
+CREATE TABLE frames_new (
+	id INTEGER NOT NULL PRIMARY KEY,
+	project INTEGER NOT NULL REFERENCES projects(id) ON DELETE RESTRICT,
+	start VARCHAR NOT NULL,
+	end VARCHAR,
+	deleted_at VARCHAR,
+	uuid VARCHAR NOT NULL DEFAULT '',
+	updated_at VARCHAR NOT NULL DEFAULT '',
+	external_ref VARCHAR,
+	note VARCHAR
+);
+INSERT INTO frames_new (id, project, start, end, deleted_at, uuid, updated_at, external_ref, note)
+SELECT id, project, start, end, deleted_at, uuid, updated_at, external_ref, note FROM frames;
+DROP TABLE frames;
+ALTER TABLE frames_new RENAME TO frames;
+CREATE UNIQUE INDEX frames_uuid ON frames(uuid);
+CREATE INDEX frames_external_ref ON frames(external_ref);
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT substr(NEW.start, 1, 10), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = substr(OLD.start, 1, 10) AND project_id = OLD.project AND milliseconds = 0;
+END;
+
+DROP TABLE tasks;
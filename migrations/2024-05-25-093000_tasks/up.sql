@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE tasks (
+	id INTEGER NOT NULL PRIMARY KEY,
+	project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+	name VARCHAR NOT NULL,
+	done BOOLEAN NOT NULL DEFAULT 0,
+	UNIQUE(project_id, name)
+);
+
+ALTER TABLE frames ADD COLUMN task_id INTEGER REFERENCES tasks(id) ON DELETE SET NULL;
+CREATE INDEX frames_task_id ON frames(task_id);
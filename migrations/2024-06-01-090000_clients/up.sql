@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE clients (
+	id INTEGER NOT NULL PRIMARY KEY,
+	name VARCHAR NOT NULL UNIQUE
+);
+
+ALTER TABLE projects ADD COLUMN client_id INTEGER REFERENCES clients(id) ON DELETE SET NULL;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- `projects.hourly_rate_cents` stays the rate before the first of these.
+CREATE TABLE rates (
+	project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+	effective_from DATE NOT NULL,
+	cents INTEGER NOT NULL,
+	PRIMARY KEY(project_id, effective_from)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE clients DROP COLUMN currency;
+ALTER TABLE projects DROP COLUMN currency;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE projects ADD COLUMN currency VARCHAR;
+ALTER TABLE clients ADD COLUMN currency VARCHAR;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- A budget limits either the time or the money spent on a project per period.
+CREATE TABLE budgets (
+	project_id INTEGER NOT NULL PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+	minutes INTEGER,
+	cents INTEGER,
+	period VARCHAR NOT NULL,
+	CHECK ((minutes IS NULL) != (cents IS NULL))
+);
@@ -0,0 +1,71 @@
+-- Your SQL goes here
+
+-- Timestamps were stored as RFC 3339 in the offset they were tracked in. SQL compares them as
+-- text, which gets the order wrong across offsets, e.g. after a daylight saving time change. They
+-- are stored in UTC with nine fractional digits from now on, e.g. "2024-03-04T07:00:00.000000000Z".
+-- SQLite only keeps milliseconds when converting, the other digits are zero.
+
+-- The triggers would update daily_totals for every converted frame.
+DROP TRIGGER daily_totals_add_inserted;
+DROP TRIGGER daily_totals_move_updated;
+DROP TRIGGER daily_totals_remove_deleted;
+
+UPDATE frames SET
+	start = strftime('%Y-%m-%dT%H:%M:%S', start) || substr(strftime('%f', start), 3) || '000000Z',
+	end = strftime('%Y-%m-%dT%H:%M:%S', end) || substr(strftime('%f', end), 3) || '000000Z',
+	deleted_at = strftime('%Y-%m-%dT%H:%M:%S', deleted_at) || substr(strftime('%f', deleted_at), 3) || '000000Z',
+	updated_at = strftime('%Y-%m-%dT%H:%M:%S', updated_at) || substr(strftime('%f', updated_at), 3) || '000000Z';
+UPDATE locked_periods SET
+	start = strftime('%Y-%m-%dT%H:%M:%S', start) || substr(strftime('%f', start), 3) || '000000Z',
+	end = strftime('%Y-%m-%dT%H:%M:%S', end) || substr(strftime('%f', end), 3) || '000000Z',
+	locked_at = strftime('%Y-%m-%dT%H:%M:%S', locked_at) || substr(strftime('%f', locked_at), 3) || '000000Z';
+UPDATE projects SET
+	last_access_time = strftime('%Y-%m-%dT%H:%M:%S', last_access_time) || substr(strftime('%f', last_access_time), 3) || '000000Z';
+UPDATE tags SET
+	last_access_time = strftime('%Y-%m-%dT%H:%M:%S', last_access_time) || substr(strftime('%f', last_access_time), 3) || '000000Z';
+
+-- Frames count for the day they start on in local time, the offset they were tracked in is gone.
+DELETE FROM daily_totals;
+INSERT INTO daily_totals (day, project_id, milliseconds)
+SELECT date(frames.start, 'localtime'), project,
+	SUM(CAST(round((julianday(end) - julianday(start)) * 86400000) AS INTEGER))
+FROM frames
+WHERE end IS NOT NULL AND deleted_at IS NULL
+GROUP BY date(frames.start, 'localtime'), project;
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (date(NEW.start, 'localtime'), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL;
+	DELETE FROM daily_totals
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT date(NEW.start, 'localtime'), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project AND milliseconds = 0;
+END;
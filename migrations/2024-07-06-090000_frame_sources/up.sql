@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- How a frame was created, e.g. 'cli' or 'import'. Unknown for frames created before.
+ALTER TABLE frames ADD COLUMN source VARCHAR;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Rules add a tag to the project of frames whose project name, note or external reference
+-- matches the pattern, see `ttt rules`.
+CREATE TABLE rules (
+	id INTEGER NOT NULL PRIMARY KEY,
+	field VARCHAR NOT NULL,
+	pattern VARCHAR NOT NULL,
+	tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE
+);
@@ -0,0 +1,47 @@
+-- Your SQL goes here
+
+-- Breaks are tracked to document them, but do not count as working time. On-call time counts,
+-- but is told apart in reports.
+ALTER TABLE frames ADD COLUMN kind VARCHAR NOT NULL DEFAULT 'work';
+
+-- All frames are work so far, so the totals stay as they are.
+DROP TRIGGER daily_totals_add_inserted;
+DROP TRIGGER daily_totals_move_updated;
+DROP TRIGGER daily_totals_remove_deleted;
+
+CREATE TRIGGER daily_totals_add_inserted AFTER INSERT ON frames
+WHEN NEW.end IS NOT NULL AND NEW.deleted_at IS NULL AND NOT NEW.draft AND NEW.kind <> 'break'
+BEGIN
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	VALUES (date(NEW.start, 'localtime'), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER))
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+-- One trigger for both halves of an update, the order of several triggers is unspecified.
+CREATE TRIGGER daily_totals_move_updated AFTER UPDATE ON frames
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project
+		AND OLD.end IS NOT NULL AND OLD.deleted_at IS NULL AND NOT OLD.draft AND OLD.kind <> 'break';
+	DELETE FROM daily_totals
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project AND milliseconds = 0;
+	INSERT INTO daily_totals (day, project_id, milliseconds)
+	SELECT date(NEW.start, 'localtime'), NEW.project,
+		CAST(round((julianday(NEW.end) - julianday(NEW.start)) * 86400000) AS INTEGER)
+	WHERE NEW.end IS NOT NULL AND NEW.deleted_at IS NULL AND NOT NEW.draft AND NEW.kind <> 'break'
+	ON CONFLICT (day, project_id) DO UPDATE SET milliseconds = milliseconds + excluded.milliseconds;
+END;
+
+CREATE TRIGGER daily_totals_remove_deleted AFTER DELETE ON frames
+WHEN OLD.end IS NOT NULL AND OLD.deleted_at IS NULL AND NOT OLD.draft AND OLD.kind <> 'break'
+BEGIN
+	UPDATE daily_totals
+	SET milliseconds = milliseconds
+		- CAST(round((julianday(OLD.end) - julianday(OLD.start)) * 86400000) AS INTEGER)
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project;
+	DELETE FROM daily_totals
+	WHERE day = date(OLD.start, 'localtime') AND project_id = OLD.project AND milliseconds = 0;
+END;
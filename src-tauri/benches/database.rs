@@ -0,0 +1,108 @@
+//! Queries on a database with many frames, to check that indices and aggregations in SQL pay
+//! off and to catch regressions.
+//!
+//! The database with `TTT_BENCH_FRAMES` frames, one million by default, is generated into the
+//! temporary directory on the first run and reused afterwards, as filling it takes a while.
+//!
+//! ```sh
+//! cargo bench --bench database
+//! TTT_BENCH_FRAMES=10000 cargo bench --bench database -- current_frame
+//! ```
+
+use std::{env, fs, path::PathBuf};
+
+use chrono::Duration;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ttt::{
+    database::{ArchivedState, ConnectionTarget, Database},
+    model::{FrameKind, NewFrame, TimeSpan, Timestamp},
+};
+
+const PROJECTS: usize = 20;
+const CHUNK_SIZE: usize = 100_000;
+
+fn frame_count() -> usize {
+    env::var("TTT_BENCH_FRAMES")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(1_000_000)
+}
+
+/// Open the benchmark database, generating it if needed. Frames are a quarter hour long with
+/// five minutes in between and go back from now, the last one is still running.
+fn open_database() -> Database {
+    let count = frame_count();
+    let path = env::temp_dir().join(format!("ttt-bench-{count}.sqlite"));
+    if !path.exists() {
+        let partial = PathBuf::from(format!("{}.partial", path.display()));
+        let _ = fs::remove_file(&partial);
+        generate(&partial, count);
+        fs::rename(&partial, &path).expect("Failed to move the benchmark database");
+    }
+    Database::new(ConnectionTarget::Path(path)).expect("Failed to open the benchmark database")
+}
+
+fn generate(path: &std::path::Path, count: usize) {
+    let mut db = Database::new(ConnectionTarget::Path(path.to_owned()))
+        .expect("Failed to create the benchmark database");
+    let projects: Vec<_> = (0..PROJECTS)
+        .map(|i| db.create_project(format!("project-{i}")).unwrap())
+        .collect();
+
+    let now = Timestamp::now();
+    for first in (0..count).step_by(CHUNK_SIZE) {
+        let frames: Vec<_> = (first..count.min(first + CHUNK_SIZE))
+            .map(|i| {
+                let start = Timestamp(now.0 - Duration::minutes(20 * (i as i64 + 1)));
+                let end = (i > 0).then(|| Timestamp(start.0 + Duration::minutes(15)));
+                (projects[i % PROJECTS].id(), start, end, i.to_string())
+            })
+            .collect();
+        let frames = frames
+            .iter()
+            .map(|(project, start, end, uuid)| NewFrame {
+                project: *project,
+                start,
+                end: end.as_ref(),
+                deleted_at: None,
+                uuid,
+                updated_at: &now,
+                external_ref: None,
+                note: None,
+                source: None,
+                draft: false,
+                kind: FrameKind::Work,
+            })
+            .collect();
+        db.insert_frames_bulk(frames, false).unwrap();
+    }
+}
+
+fn days_back(days: i64) -> TimeSpan {
+    let end = Timestamp::now();
+    TimeSpan::new(Timestamp(end.0 - Duration::days(days)), end).unwrap()
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let mut db = open_database();
+
+    c.bench_function("current_frame", |b| {
+        b.iter(|| black_box(db.current_frame().unwrap()))
+    });
+    for (name, days) in [("week", 7), ("year", 365)] {
+        let span = days_back(days);
+        c.bench_function(&format!("get_frames_in_span/{name}"), |b| {
+            b.iter(|| black_box(db.get_frames_in_span(span, ArchivedState::Both).unwrap()))
+        });
+        c.bench_function(&format!("daily_totals/{name}"), |b| {
+            b.iter(|| black_box(db.daily_totals(span).unwrap()))
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_queries
+}
+criterion_main!(benches);
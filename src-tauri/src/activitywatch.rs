@@ -0,0 +1,334 @@
+//! Reconstruct frames from the window events recorded by [ActivityWatch], for days on which
+//! tracking was forgotten.
+//!
+//! The events are read from a bucket export of ActivityWatch and assigned to projects by the
+//! rules of a TOML file. Time spent away from the keyboard, as recorded by the AFK watcher, is
+//! left out if the export contains its bucket:
+//!
+//! ```toml
+//! # Names are matched case insensitively and only need to appear somewhere in the app or title.
+//! [[rule]]
+//! project = "website"
+//! title = ["localhost:3000", "github.com/me/website"]
+//!
+//! # If both app and title are given, both need to match.
+//! [[rule]]
+//! project = "client-x"
+//! app = ["slack"]
+//! title = ["client-x"]
+//! ```
+//!
+//! [ActivityWatch]: https://activitywatch.net
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    model::{TimeSpan, Timestamp},
+};
+
+/// Events of the same project that are closer together than this end up in one frame.
+const MERGE_GAP_MINUTES: i64 = 5;
+
+/// Suggestions shorter than this are dropped, e.g. quickly switching through windows.
+const MIN_DURATION_MINUTES: i64 = 2;
+
+/// Export of one or more buckets, as created by "Export bucket" in the ActivityWatch web UI.
+#[derive(Debug, Deserialize)]
+pub struct Export {
+    buckets: HashMap<String, Bucket>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Bucket {
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    timestamp: DateTime<FixedOffset>,
+
+    /// Length in seconds
+    duration: f64,
+
+    data: EventData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct EventData {
+    app: String,
+    title: String,
+
+    /// "afk" or "not-afk" for events of the AFK watcher
+    status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    project: String,
+
+    #[serde(default)]
+    app: Vec<String>,
+
+    #[serde(default)]
+    title: Vec<String>,
+}
+
+/// A frame reconstructed from the events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub project: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Export {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_json::from_str(text)
+            .map_err(|e| Error::InvalidImport(format!("Not an ActivityWatch export: {e}")))
+    }
+}
+
+impl Rules {
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| Error::InvalidImport(format!("Invalid rules: {e}")))
+    }
+
+    /// The project of the first rule matching the window.
+    fn project(&self, data: &EventData) -> Option<&str> {
+        let contains = |text: &str, patterns: &[String]| {
+            let text = text.to_lowercase();
+            patterns.is_empty() || patterns.iter().any(|p| text.contains(&p.to_lowercase()))
+        };
+        self.rules
+            .iter()
+            .find(|rule| contains(&data.app, &rule.app) && contains(&data.title, &rule.title))
+            .map(|rule| rule.project.as_str())
+    }
+}
+
+impl Suggestion {
+    pub fn duration(&self) -> Duration {
+        self.end.0 - self.start.0
+    }
+}
+
+/// Turn the window events of the buckets whose id contains `bucket` into suggested frames
+/// within `span`, oldest first.
+pub fn suggest(
+    export: &Export,
+    bucket: &str,
+    rules: &Rules,
+    span: Option<TimeSpan>,
+) -> Result<Vec<Suggestion>> {
+    let mut windows = Vec::new();
+    let mut afk = Vec::new();
+    for (id, content) in &export.buckets {
+        if id.contains("afk") {
+            afk.extend(
+                content
+                    .events
+                    .iter()
+                    .filter(|e| e.data.status.as_deref() == Some("afk"))
+                    .map(interval),
+            );
+        } else if id.contains(bucket) {
+            windows.extend(&content.events);
+        }
+    }
+    if windows.is_empty() {
+        return Err(Error::InvalidImport(format!(
+            "The export contains no events of a bucket matching \"{bucket}\""
+        )));
+    }
+    windows.sort_by_key(|e| e.timestamp);
+
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for event in windows {
+        let Some(project) = rules.project(&event.data) else {
+            continue;
+        };
+        let (mut start, mut end) = interval(event);
+        if let Some(span) = span {
+            start = start.max(span.start().0);
+            end = end.min(span.end().0);
+        }
+        // Outside of the span
+        if start >= end {
+            continue;
+        }
+        for (start, end) in subtract((start, end), &afk) {
+            match suggestions.last_mut() {
+                Some(last)
+                    if last.project == project
+                        && start - last.end.0 <= Duration::minutes(MERGE_GAP_MINUTES) =>
+                {
+                    last.end = last.end.max(end.into());
+                }
+                _ => suggestions.push(Suggestion {
+                    project: project.to_owned(),
+                    start: start.into(),
+                    end: end.into(),
+                }),
+            }
+        }
+    }
+    suggestions.retain(|s| s.duration() >= Duration::minutes(MIN_DURATION_MINUTES));
+    Ok(suggestions)
+}
+
+type Interval = (DateTime<FixedOffset>, DateTime<FixedOffset>);
+
+fn interval(event: &Event) -> Interval {
+    let duration = Duration::milliseconds((event.duration * 1000.0) as i64);
+    (event.timestamp, event.timestamp + duration)
+}
+
+/// The parts of `interval` not covered by any of `gaps`.
+fn subtract(interval: Interval, gaps: &[Interval]) -> Vec<Interval> {
+    let mut parts = vec![interval];
+    for &(gap_start, gap_end) in gaps {
+        parts = parts
+            .into_iter()
+            .flat_map(|(start, end)| {
+                [(start, end.min(gap_start)), (start.max(gap_end), end)]
+                    .into_iter()
+                    .filter(|(start, end)| start < end)
+            })
+            .collect();
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RULES: &str = r#"
+        [[rule]]
+        project = "website"
+        title = ["localhost:3000"]
+
+        [[rule]]
+        project = "client-x"
+        app = ["Slack"]
+    "#;
+
+    fn event(time: &str, minutes: f64, data: &str) -> String {
+        format!(
+            r#"{{"timestamp": "2024-03-04T{time}:00+00:00", "duration": {}, "data": {data}}}"#,
+            minutes * 60.0
+        )
+    }
+
+    fn suggestions(
+        window: &[String],
+        afk: &[String],
+        span: Option<TimeSpan>,
+    ) -> Vec<(String, String, String)> {
+        let export = Export::parse(&format!(
+            r#"{{"buckets": {{
+                "aw-watcher-window_host": {{"events": [{}]}},
+                "aw-watcher-afk_host": {{"events": [{}]}}
+            }}}}"#,
+            window.join(","),
+            afk.join(",")
+        ))
+        .unwrap();
+        suggest(&export, "window", &Rules::parse(RULES).unwrap(), span)
+            .unwrap()
+            .into_iter()
+            .map(|s| {
+                (
+                    s.project,
+                    s.start.0.format("%H:%M").to_string(),
+                    s.end.0.format("%H:%M").to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn expected(project: &str, start: &str, end: &str) -> (String, String, String) {
+        (project.to_owned(), start.to_owned(), end.to_owned())
+    }
+
+    #[test]
+    fn test_events_are_merged_per_project() {
+        let website = r#"{"app": "firefox", "title": "Shop - localhost:3000"}"#;
+        let slack = r#"{"app": "Slack", "title": "general"}"#;
+        let window = [
+            event("09:00", 20.0, website),
+            // Short interruptions by other windows do not split the frame
+            event("09:20", 1.0, r#"{"app": "Mail", "title": "Inbox"}"#),
+            event("09:21", 30.0, website),
+            event("09:51", 15.0, slack),
+            // Too short to be a frame of its own
+            event("10:06", 1.0, website),
+        ];
+
+        assert_eq!(
+            suggestions(&window, &[], None),
+            vec![
+                expected("website", "09:00", "09:51"),
+                expected("client-x", "09:51", "10:06"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_afk_time_is_left_out() {
+        let website = r#"{"app": "firefox", "title": "localhost:3000"}"#;
+        let window = [event("09:00", 120.0, website)];
+        let afk = [
+            event("09:00", 30.0, r#"{"status": "not-afk"}"#),
+            event("09:30", 45.0, r#"{"status": "afk"}"#),
+        ];
+
+        assert_eq!(
+            suggestions(&window, &afk, None),
+            vec![
+                expected("website", "09:00", "09:30"),
+                expected("website", "10:15", "11:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_outside_the_span_are_left_out() {
+        let website = r#"{"app": "firefox", "title": "localhost:3000"}"#;
+        let window = [
+            event("08:00", 30.0, website),
+            event("11:00", 58.0, website),
+            // Would stretch the frame before up to the end of the span
+            event("12:02", 30.0, website),
+        ];
+        let time = |hour| {
+            Timestamp(
+                DateTime::parse_from_rfc3339(&format!("2024-03-04T{hour}:00:00+00:00")).unwrap(),
+            )
+        };
+        let span = TimeSpan::new(time("09"), time("12")).unwrap();
+
+        assert_eq!(
+            suggestions(&window, &[], Some(span)),
+            vec![expected("website", "11:00", "11:58")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        assert!(Rules::parse("[[rule]]\nprojekt = \"website\"").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+//! Machine interface for editor plugins and scripts, see `ttt api --stdin`. Each line of input is
+//! one JSON command, each is answered with one line of JSON:
+//!
+//! ```text
+//! > {"id": 1, "command": "start", "project": "website", "task": "layout", "ref": "PROJ-123"}
+//! < {"id":1,"ok":true,"result":{"stopped":null,"started":{"id":4,"project":2,...}}}
+//! > {"command": "add", "project": "website", "start": "2024-03-04T09:00:00+01:00", "end": "2024-03-04T10:30:00+01:00"}
+//! > {"command": "stop"}
+//! > {"command": "current"}
+//! > {"command": "frames", "start": "2024-03-04T00:00:00+01:00", "end": "2024-03-11T00:00:00+01:00"}
+//! > {"command": "projects"}
+//! > {"command": "heartbeat", "file": "/home/me/src/website/index.html", "repo": "website"}
+//! < {"ok":false,"error":"Project nope does not exist"}
+//! ```
+//!
+//! Heartbeats let editors start and switch tracking on their own, see [`crate::heartbeat`].
+//!
+//! `id` is optional and copied into the answer. Failed commands are answered with `"ok": false`
+//! and the next line is read regardless. Like on the command line, starting and stopping is left
+//! to a running GUI, see [`crate::instance`].
+
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::Config,
+    database::{ArchivedState, Database},
+    heartbeat,
+    instance::{self, Request, Tracking},
+    model::{Frame, FrameSource, Project, Task, TimeSpan, Timestamp},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    /// Stop the current frame and start one for the project, or a task of it.
+    Start {
+        project: String,
+        #[serde(default)]
+        task: Option<String>,
+        #[serde(default, rename = "ref")]
+        external_ref: Option<String>,
+    },
+
+    /// Stop the frame of the project, or the one started last.
+    Stop {
+        #[serde(default)]
+        project: Option<String>,
+    },
+
+    /// Add a frame that was forgotten to be tracked.
+    Add {
+        project: String,
+        start: Timestamp,
+        end: Timestamp,
+    },
+
+    /// The running frames.
+    Current,
+
+    /// The frames overlapping a time span.
+    Frames { start: Timestamp, end: Timestamp },
+
+    /// All projects that are not archived.
+    Projects,
+
+    /// A file was edited, track its project unless it is tracked already.
+    Heartbeat {
+        #[serde(default)]
+        file: Option<String>,
+        #[serde(default)]
+        repo: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Line {
+    #[serde(default)]
+    id: Option<Value>,
+
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Debug, Serialize)]
+struct Answer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    ok: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A frame together with its project, so clients need not look up project ids.
+#[derive(Debug, Serialize)]
+struct ProjectFrame {
+    project: Project,
+    frame: Frame,
+}
+
+/// Answer the commands of `input` on `output` until the input ends. Only failing to read or
+/// write ends it early.
+pub fn serve(
+    db: &mut Database,
+    config: &Config,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = answer(db, config, &line);
+        serde_json::to_writer(&mut output, &answer)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn answer(db: &mut Database, config: &Config, line: &str) -> Answer {
+    let (id, result) = match serde_json::from_str::<Line>(line) {
+        Ok(Line { id, command }) => (id, execute(db, config, command)),
+        Err(err) => {
+            // Keep the id of commands that are valid JSON but not a known command.
+            let id = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|value| value.get("id").cloned());
+            (id, Err(format!("Invalid command: {err}")))
+        }
+    };
+    match result {
+        Ok(result) => Answer {
+            id,
+            ok: true,
+            result: Some(result),
+            error: None,
+        },
+        Err(error) => Answer {
+            id,
+            ok: false,
+            result: None,
+            error: Some(error),
+        },
+    }
+}
+
+fn execute(db: &mut Database, config: &Config, command: Command) -> Result<Value, String> {
+    match command {
+        Command::Start {
+            project,
+            task,
+            external_ref,
+        } => {
+            let mut project = lookup_project(db, &project)?;
+            let task = match task {
+                Some(name) => Some(
+                    db.lookup_task(project.id(), &name)
+                        .map_err(|err| err.to_string())?
+                        .ok_or_else(|| format!("Task {}/{name} does not exist", project.name))?,
+                ),
+                None => None,
+            };
+            to_value(start(
+                db,
+                &mut project,
+                task.as_ref(),
+                external_ref,
+                FrameSource::Api,
+            )?)
+        }
+        Command::Stop { project } => {
+            let running = db.current_frames().map_err(|err| err.to_string())?;
+            let frame = match project {
+                Some(name) => {
+                    let project = lookup_project(db, &name)?;
+                    running
+                        .into_iter()
+                        .find(|frame| frame.project == project.id())
+                        .ok_or_else(|| format!("Project {name} is not being tracked"))?
+                }
+                None => match running.into_iter().last() {
+                    Some(frame) => frame,
+                    None => return to_value(Tracking::default()),
+                },
+            };
+            let request = Request::StopFrame {
+                frame_id: frame.id(),
+                snap_minutes: None,
+            };
+            let tracking = match instance::forward(db.path(), &request) {
+                Some(response) => response?,
+                None => Tracking {
+                    stopped: Some(db.stop_frame(frame.id()).map_err(|err| err.to_string())?),
+                    started: None,
+                },
+            };
+            to_value(tracking)
+        }
+        Command::Add {
+            project,
+            start,
+            end,
+        } => {
+            let span = TimeSpan::new(start, end).map_err(|err| err.to_string())?;
+            let mut project = lookup_project(db, &project)?;
+            let frame = db
+                .add_frame(&mut project, span, FrameSource::Api)
+                .map_err(|err| err.to_string())?;
+            to_value(frame)
+        }
+        Command::Current => {
+            let running = db.current_frames().map_err(|err| err.to_string())?;
+            let mut frames = Vec::new();
+            for frame in running {
+                let project = db
+                    .lookup_project(frame.project)
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| format!("Found no project for id {}", frame.project))?;
+                frames.push(ProjectFrame { project, frame });
+            }
+            to_value(frames)
+        }
+        Command::Frames { start, end } => {
+            let span = TimeSpan::new(start, end).map_err(|err| err.to_string())?;
+            let frames: Vec<_> = db
+                .get_frames_in_span(span, ArchivedState::Both)
+                .map_err(|err| err.to_string())?
+                .into_iter()
+                .map(|(project, frame)| ProjectFrame { project, frame })
+                .collect();
+            to_value(frames)
+        }
+        Command::Projects => to_value(
+            db.all_projects(ArchivedState::NotArchived)
+                .map_err(|err| err.to_string())?,
+        ),
+        Command::Heartbeat { file, repo } => {
+            let name = heartbeat::project_name(
+                &config.heartbeat,
+                repo.as_deref(),
+                file.as_deref().map(Path::new),
+            );
+            let project = match name {
+                Some(name) => db
+                    .lookup_project_by_name(name)
+                    .map_err(|err| err.to_string())?,
+                None => None,
+            };
+            let Some(mut project) = project else {
+                // Files outside of known projects are not an error, most editors send them.
+                return to_value(Tracking::default());
+            };
+            let running = db.current_frames().map_err(|err| err.to_string())?;
+            if running.iter().any(|frame| frame.project == project.id()) {
+                return to_value(Tracking::default());
+            }
+            to_value(start(db, &mut project, None, None, FrameSource::Hook)?)
+        }
+    }
+}
+
+/// Stop the current frame and start one for `project`, through a running GUI if there is one.
+fn start(
+    db: &mut Database,
+    project: &mut Project,
+    task: Option<&Task>,
+    external_ref: Option<String>,
+    source: FrameSource,
+) -> Result<Tracking, String> {
+    let request = Request::Start {
+        project_id: project.id(),
+        task_id: task.map(Task::id),
+        external_ref: external_ref.clone(),
+        source: Some(source),
+    };
+    if let Some(response) = instance::forward(db.path(), &request) {
+        return response;
+    }
+    let external_ref = external_ref.as_deref();
+    let started = db.stop_before_start().and_then(|stopped| {
+        let started = match task {
+            Some(task) => db.start_task(project, task, external_ref, source),
+            None => db.start(project, external_ref, source),
+        }?;
+        Ok(Tracking {
+            stopped,
+            started: Some(started),
+        })
+    });
+    started.map_err(|err| err.to_string())
+}
+
+fn lookup_project(db: &mut Database, name: &str) -> Result<Project, String> {
+    db.lookup_project_by_name(name)
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("Project {name} does not exist"))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exchange(db: &mut Database, config: &Config, input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve(db, config, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_commands_are_answered_line_by_line() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let input = r#"{"id": 1, "command": "start", "project": "website"}
+
+{"id": "two", "command": "current"}
+{"command": "start", "project": "nope"}
+{"id": 4, "command": "fly"}
+not json
+{"command": "add", "project": "website", "start": "2024-03-04T09:00:00+01:00", "end": "2024-03-04T10:30:00+01:00"}
+{"command": "stop"}
+"#;
+        let answers = exchange(&mut db, &Config::default(), input);
+        assert_eq!(answers.len(), 7);
+
+        assert_eq!(answers[0]["id"], 1);
+        assert_eq!(answers[0]["ok"], true);
+        assert_eq!(answers[0]["result"]["started"]["project"], website.id());
+        assert_eq!(answers[1]["id"], "two");
+        assert_eq!(answers[1]["result"][0]["project"]["name"], "website");
+        assert_eq!(answers[2]["ok"], false);
+        assert_eq!(answers[2]["error"], "Project nope does not exist");
+        assert_eq!(answers[3]["id"], 4);
+        assert_eq!(answers[3]["ok"], false);
+        assert!(answers[4].get("id").is_none());
+        assert_eq!(answers[5]["ok"], true);
+        assert!(answers[6]["result"]["stopped"]["end"].is_string());
+        assert_eq!(answers[6]["result"]["stopped"]["source"], "api");
+        assert!(db.current_frames().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_heartbeats_switch_projects() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let client = db.create_project("client-x").unwrap();
+        let config = Config {
+            heartbeat: vec![heartbeat::HeartbeatRule {
+                project: "client-x".to_owned(),
+                repo: None,
+                path: Some("/work/client-x".into()),
+            }],
+            ..Default::default()
+        };
+        let input = r#"{"command": "heartbeat", "file": "/src/website/index.html", "repo": "website"}
+{"command": "heartbeat", "file": "/src/website/style.css", "repo": "website"}
+{"command": "heartbeat", "file": "/work/client-x/main.rs", "repo": "backend"}
+{"command": "heartbeat", "file": "/tmp/notes.txt"}
+"#;
+        let answers = exchange(&mut db, &config, input);
+        assert_eq!(answers[0]["result"]["started"]["project"], website.id());
+        assert!(answers[1]["result"]["started"].is_null());
+        assert_eq!(answers[2]["result"]["stopped"]["project"], website.id());
+        assert_eq!(answers[2]["result"]["started"]["project"], client.id());
+        assert_eq!(answers[3]["ok"], true);
+        let current = db.current_frame().unwrap();
+        assert_eq!(current.project, client.id());
+        assert_eq!(current.source, Some(FrameSource::Hook));
+    }
+}
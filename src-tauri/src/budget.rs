@@ -0,0 +1,248 @@
+//! Limits of the time or money spent on a project per period, see `ttt budget`. `ttt start`,
+//! `ttt current` and the GUI warn once [`WARN_AT`] percent of a budget are used, and again once
+//! all of it is.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+
+use crate::{
+    database::Database,
+    duration::{DurationExt, DurationFormat},
+    error::Result,
+    model::{Budget, BudgetPeriod, Frame, Project, Rate, TimeSpan, Timestamp},
+    report::Money,
+};
+
+/// Share of a budget in percent from which on it is warned about.
+pub const WARN_AT: i64 = 80;
+
+/// How much of the budget of a project is used up in the current period.
+#[derive(Debug)]
+pub struct BudgetUse {
+    pub project: String,
+    pub budget: Budget,
+
+    /// Minutes or cents, like the budget.
+    pub used: i64,
+
+    /// Currency of the rates of the project, for money budgets.
+    currency: Option<String>,
+}
+
+impl BudgetUse {
+    /// Sum up the `frames` of the project of `budget` within the current period, see
+    /// [`period_span`]. Money is billed with the `rates` of the project, like in reports.
+    pub fn new(
+        project: &Project,
+        budget: Budget,
+        frames: &[Frame],
+        rates: &[Rate],
+        currency: Option<&str>,
+    ) -> Self {
+        let used = if budget.minutes.is_some() {
+            let seconds: i64 = frames.iter().map(|f| f.duration().num_seconds()).sum();
+            seconds / 60
+        } else {
+            let cent_seconds: i64 = frames
+                .iter()
+                .filter_map(|frame| {
+                    let cents = project.rate_at(rates, frame.start.0.date_naive())?;
+                    Some(frame.duration().num_seconds() * i64::from(cents))
+                })
+                .sum();
+            (cent_seconds + 1800) / 3600
+        };
+        Self {
+            project: project.name.clone(),
+            budget,
+            used,
+            currency: currency.map(str::to_owned),
+        }
+    }
+
+    fn limit(&self) -> i64 {
+        self.budget
+            .minutes
+            .or(self.budget.cents)
+            .unwrap_or(0)
+            .into()
+    }
+
+    /// Used share of the budget in percent.
+    pub fn percentage(&self) -> i64 {
+        match self.limit() {
+            0 => 100,
+            limit => self.used * 100 / limit,
+        }
+    }
+
+    /// 0 below [`WARN_AT`], then [`WARN_AT`] and 100 once the budget is used up, so callers can
+    /// warn only when this rises.
+    pub fn level(&self) -> i64 {
+        match self.percentage() {
+            p if p >= 100 => 100,
+            p if p >= WARN_AT => WARN_AT,
+            _ => 0,
+        }
+    }
+
+    fn amount(&self, amount: i64, durations: DurationFormat) -> String {
+        if self.budget.minutes.is_some() {
+            Duration::minutes(amount).format_as(durations)
+        } else {
+            Money::new(amount, self.currency.as_deref()).to_string()
+        }
+    }
+
+    /// The budget, e.g. "40h per month".
+    pub fn limit_text(&self, durations: DurationFormat) -> String {
+        let limit = self.amount(self.limit(), durations);
+        match self.budget.period {
+            BudgetPeriod::Total => format!("{limit} in total"),
+            period => format!("{limit} per {period}"),
+        }
+    }
+
+    pub fn used_text(&self, durations: DurationFormat) -> String {
+        self.amount(self.used, durations)
+    }
+
+    /// What to warn about, `None` below [`WARN_AT`] percent.
+    pub fn warning(&self, durations: DurationFormat) -> Option<String> {
+        let what = match self.level() {
+            0 => return None,
+            100 => "Budget exceeded",
+            _ => "Budget warning",
+        };
+        let period = match self.budget.period {
+            BudgetPeriod::Week => "this week",
+            BudgetPeriod::Month => "this month",
+            BudgetPeriod::Year => "this year",
+            BudgetPeriod::Total => "in total",
+        };
+        Some(format!(
+            "{what}: {} used {} of {} {period} ({}%)",
+            self.project,
+            self.used_text(durations),
+            self.amount(self.limit(), durations),
+            self.percentage()
+        ))
+    }
+}
+
+/// The period of a budget that `today` falls into, weeks start on `week_start` like in time
+/// spans, see [`crate::config::Config::week_start`].
+pub fn period_span(period: BudgetPeriod, today: NaiveDate, week_start: Weekday) -> TimeSpan {
+    let (first, next) = match period {
+        BudgetPeriod::Week => {
+            let first = today.week(week_start).first_day();
+            (first, first + Duration::days(7))
+        }
+        BudgetPeriod::Month => {
+            let first = today.with_day(1).expect("Every month has a first day");
+            (first, first + Months::new(1))
+        }
+        BudgetPeriod::Year => {
+            let first = NaiveDate::from_ymd_opt(today.year(), 1, 1).expect("Valid date");
+            (first, first + Months::new(12))
+        }
+        BudgetPeriod::Total => (
+            NaiveDate::from_ymd_opt(1970, 1, 1).expect("Valid date"),
+            today + Duration::days(1),
+        ),
+    };
+    let midnight = |day: NaiveDate| Timestamp::from_naive(day.and_time(Default::default()));
+    TimeSpan::new(midnight(first), midnight(next)).expect("Periods end after they start")
+}
+
+/// How much of the budget of `project` is used today, `None` if it has no budget. `currency` is
+/// the one of its rates, see [`Database::currencies`].
+pub fn check(
+    db: &mut Database,
+    project: &Project,
+    currency: Option<&str>,
+    week_start: Weekday,
+) -> Result<Option<BudgetUse>> {
+    let Some(budget) = db.budget(project.id())? else {
+        return Ok(None);
+    };
+    let today = Timestamp::now().to_naive().date();
+    let frames =
+        db.project_frames_in_span(project.id(), period_span(budget.period, today, week_start))?;
+    let rates = db.rates()?.remove(&project.id()).unwrap_or_default();
+    Ok(Some(BudgetUse::new(
+        project, budget, &frames, &rates, currency,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{database::ArchivedState, model::FrameSource};
+
+    #[test]
+    fn test_budget_use() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        db.set_hourly_rate(&mut website, Some(10000)).unwrap();
+        let start = Timestamp::from_ymdhms(2024, 3, 4, 8, 0, 0);
+        db.add_frame(
+            &mut website,
+            TimeSpan::new(start, start + Duration::hours(9)).unwrap(),
+            FrameSource::Cli,
+        )
+        .unwrap();
+        let frames: Vec<_> = db
+            .get_frames_in_span(
+                period_span(
+                    BudgetPeriod::Week,
+                    NaiveDate::from_ymd_opt(2024, 3, 6).unwrap(),
+                    Weekday::Mon,
+                ),
+                ArchivedState::Both,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|(_, frame)| frame)
+            .collect();
+        assert_eq!(frames.len(), 1);
+
+        let budget = |minutes, cents| Budget {
+            project_id: website.id(),
+            minutes,
+            cents,
+            period: BudgetPeriod::Week,
+        };
+        let time = BudgetUse::new(&website, budget(Some(600), None), &frames, &[], None);
+        assert_eq!(
+            (time.used, time.percentage(), time.level()),
+            (540, 90, WARN_AT)
+        );
+        assert_eq!(
+            time.warning(DurationFormat::Hms).unwrap(),
+            "Budget warning: website used 9h of 10h this week (90%)"
+        );
+
+        let money = BudgetUse::new(
+            &website,
+            budget(None, Some(80000)),
+            &frames,
+            &[],
+            Some("EUR"),
+        );
+        assert_eq!((money.used, money.level()), (90000, 100));
+        assert_eq!(money.limit_text(DurationFormat::Hms), "€800.00 per week");
+
+        let relaxed = BudgetUse::new(&website, budget(Some(6000), None), &frames, &[], None);
+        assert_eq!(relaxed.warning(DurationFormat::Hms), None);
+    }
+
+    #[test]
+    fn test_period_span_week_start() {
+        let tuesday = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let sunday = period_span(BudgetPeriod::Week, tuesday, Weekday::Sun);
+        assert_eq!(sunday.start(), Timestamp::from_ymdhms(2024, 3, 3, 0, 0, 0));
+        assert_eq!(sunday.end(), Timestamp::from_ymdhms(2024, 3, 10, 0, 0, 0));
+        let monday = period_span(BudgetPeriod::Week, tuesday, Weekday::Mon);
+        assert_eq!(monday.start(), Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0));
+    }
+}
@@ -0,0 +1,169 @@
+//! A zip file with what is needed to look into a problem, to be attached to issues, see
+//! `ttt debug-bundle`. It holds the version and features of ttt, the configuration with secrets
+//! redacted, and the schema version and statistics of the database, but no frames.
+
+/// Features ttt was built with.
+const FEATURES: &[(&str, bool)] = &[
+    ("sqlcipher", cfg!(feature = "sqlcipher")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("slack", cfg!(feature = "slack")),
+    ("caldav", cfg!(feature = "caldav")),
+    ("calendar-url", cfg!(feature = "calendar-url")),
+    ("pdf", cfg!(feature = "pdf")),
+    ("self-update", cfg!(feature = "self-update")),
+];
+
+/// Configuration keys whose values are replaced, they may hold credentials or private links.
+const SECRETS: &[&str] = &["password", "username", "token", "secret", "url"];
+
+/// Version, platform and enabled features.
+pub fn version() -> String {
+    let features: Vec<_> = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    format!(
+        "ttt {}\nPlatform: {} {}\nFeatures: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        if features.is_empty() {
+            "none".to_owned()
+        } else {
+            features.join(", ")
+        }
+    )
+}
+
+/// The configuration file `text` with the values of [`SECRETS`] replaced. Comments are lost.
+pub fn redact_config(text: &str) -> String {
+    fn redact(table: &mut toml::Table) {
+        for (key, value) in table.iter_mut() {
+            match value {
+                toml::Value::Table(inner) => redact(inner),
+                toml::Value::Array(items) => items
+                    .iter_mut()
+                    .filter_map(toml::Value::as_table_mut)
+                    .for_each(redact),
+                _ if SECRETS.iter().any(|secret| key.contains(secret)) => {
+                    *value = toml::Value::String("(redacted)".to_owned());
+                }
+                _ => {}
+            }
+        }
+    }
+    match text.parse::<toml::Table>() {
+        Ok(mut table) => {
+            redact(&mut table);
+            toml::to_string(&table).expect("Tables are serializable")
+        }
+        // The message quotes the text, which could hold secrets, so only the line is kept.
+        Err(e) => {
+            let line = e
+                .span()
+                .map_or(0, |span| text[..span.start].lines().count());
+            format!("# The configuration file is invalid around line {line}\n")
+        }
+    }
+}
+
+/// Pack `files`, given by name and content, into a zip archive. The files are stored without
+/// compression, they are small.
+pub fn zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    // Version 2.0, names in UTF-8, stored, 1980-01-01 00:00
+    const VERSION: u16 = 20;
+    const UTF8: u16 = 1 << 11;
+    const DATE: u16 = (1 << 5) | 1;
+
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, content) in files {
+        let offset = out.len() as u32;
+        let mut header = Vec::new();
+        for value in [VERSION, UTF8, 0, 0, DATE] {
+            header.extend(value.to_le_bytes());
+        }
+        header.extend(crc32(content).to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((content.len() as u32).to_le_bytes());
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+
+        out.extend(0x0403_4b50u32.to_le_bytes());
+        out.extend(&header);
+        out.extend(name.as_bytes());
+        out.extend(content);
+
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend(VERSION.to_le_bytes());
+        directory.extend(&header);
+        // Comment length, disk, internal and external attributes
+        directory.extend([0; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+
+    let start = out.len() as u32;
+    out.extend(&directory);
+    out.extend(0x0605_4b50u32.to_le_bytes());
+    out.extend([0; 4]);
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((directory.len() as u32).to_le_bytes());
+    out.extend(start.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+/// CRC-32 as used by zip, computed bit by bit, which is fast enough for a few kilobytes.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redact_config() {
+        let config = "currency = \"EUR\"\n\
+            [caldav]\n\
+            url = \"https://dav.example.com/me\"\n\
+            password = \"hunter2\"\n\
+            [[heartbeat]]\n\
+            project = \"website\"\n";
+
+        let redacted = redact_config(config);
+        assert!(!redacted.contains("hunter2") && !redacted.contains("example.com"));
+        assert!(redacted.contains("password = \"(redacted)\""));
+        assert!(redacted.contains("currency = \"EUR\""));
+        assert!(redacted.contains("project = \"website\""));
+        assert!(!redact_config("password = hunter2").contains("hunter2"));
+    }
+
+    #[test]
+    fn test_zip() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let zip = zip(&[("a.txt", b"hello".to_vec()), ("b.txt", Vec::new())]);
+        assert!(zip.starts_with(b"PK\x03\x04"));
+        // The end of the central directory lists both files.
+        let end = &zip[zip.len() - 22..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let start = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert!(zip[start..].starts_with(b"PK\x01\x02"));
+    }
+}
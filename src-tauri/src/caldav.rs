@@ -0,0 +1,154 @@
+//! Publish frames as events of a CalDAV calendar, e.g. one of Nextcloud, available with the
+//! `caldav` feature.
+//!
+//! Every frame becomes an event named after the uuid of the frame, so pushing a frame again
+//! updates its event. The password is read from TTT_CALDAV_PASSWORD if it is not configured:
+//!
+//! ```toml
+//! [caldav]
+//! url = "https://cloud.example.com/remote.php/dav/calendars/me/ttt/"
+//! username = "me"
+//! ```
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    model::{Frame, Project, Timestamp},
+};
+
+/// Environment variable that holds the password, if it is not part of the configuration.
+pub const PASSWORD_VARIABLE: &str = "TTT_CALDAV_PASSWORD";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CalDavConfig {
+    /// Address of the calendar collection
+    pub url: String,
+
+    pub username: String,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Number of events that were created or updated.
+    pub pushed: usize,
+
+    /// Number of events removed because their frame is in the trash.
+    pub deleted: usize,
+}
+
+/// Create or update the events of the finished `frames` and delete the events of `trashed`
+/// frames.
+pub fn push(
+    config: &CalDavConfig,
+    frames: &[(Project, Frame)],
+    trashed: &[Frame],
+) -> Result<Summary> {
+    let password = match &config.password {
+        Some(password) => password.clone(),
+        None => std::env::var(PASSWORD_VARIABLE).map_err(|_| {
+            Error::CalDav(format!(
+                "No password configured and {PASSWORD_VARIABLE} is not set"
+            ))
+        })?,
+    };
+    let authorization = format!(
+        "Basic {}",
+        BASE64_STANDARD.encode(format!("{}:{password}", config.username))
+    );
+    let event_url =
+        |frame: &Frame| format!("{}/{}.ics", config.url.trim_end_matches('/'), frame.uuid());
+
+    let mut summary = Summary::default();
+    for (project, frame) in frames.iter().filter(|(_, frame)| frame.end.is_some()) {
+        ureq::put(&event_url(frame))
+            .set("Authorization", &authorization)
+            .set("Content-Type", "text/calendar; charset=utf-8")
+            .send_string(&to_ics(project, frame))
+            .map_err(|e| Error::CalDav(e.to_string()))?;
+        summary.pushed += 1;
+    }
+    for frame in trashed {
+        match ureq::delete(&event_url(frame))
+            .set("Authorization", &authorization)
+            .call()
+        {
+            Ok(_) => summary.deleted += 1,
+            // Never pushed or already deleted
+            Err(ureq::Error::Status(404, _)) => {}
+            Err(e) => return Err(Error::CalDav(e.to_string())),
+        }
+    }
+    Ok(summary)
+}
+
+/// The frame as iCalendar object with a single event.
+fn to_ics(project: &Project, frame: &Frame) -> String {
+    let mut ics = String::new();
+    let mut line = |text: String| {
+        ics.push_str(&text);
+        ics.push_str("\r\n");
+    };
+    line("BEGIN:VCALENDAR".to_owned());
+    line("VERSION:2.0".to_owned());
+    line("PRODID:-//ttt//ttt//EN".to_owned());
+    line("BEGIN:VEVENT".to_owned());
+    line(format!("UID:{}", frame.uuid()));
+    line(format!("DTSTAMP:{}", utc(frame.updated_at)));
+    line(format!("DTSTART:{}", utc(frame.start)));
+    line(format!(
+        "DTEND:{}",
+        utc(frame.end.expect("Frame is finished"))
+    ));
+    line(format!("SUMMARY:{}", escape(&project.name)));
+    if let Some(external_ref) = &frame.external_ref {
+        line(format!("DESCRIPTION:{}", escape(external_ref)));
+    }
+    line("END:VEVENT".to_owned());
+    line("END:VCALENDAR".to_owned());
+    ics
+}
+
+fn utc(timestamp: Timestamp) -> String {
+    timestamp.0.naive_utc().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a text value, see RFC 5545 section 3.3.11.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        database::Database,
+        model::{FrameSource, TimeSpan},
+    };
+
+    #[test]
+    fn test_to_ics() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website, shop").unwrap();
+        let span = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 3, 4, 9, 0, 0),
+            Timestamp::from_ymdhms(2024, 3, 4, 10, 30, 0),
+        )
+        .unwrap();
+        let frame = db.add_frame(&mut project, span, FrameSource::Cli).unwrap();
+
+        let ics = to_ics(&project, &frame);
+
+        assert!(ics.contains(&format!("\r\nUID:{}\r\n", frame.uuid())));
+        assert!(ics.contains(&format!("\r\nDTSTART:{}\r\n", utc(span.start()))));
+        assert!(ics.contains(&format!("\r\nDTEND:{}\r\n", utc(span.end()))));
+        assert!(ics.contains("\r\nSUMMARY:website\\, shop\r\n"));
+        assert!(!ics.contains("DESCRIPTION"));
+    }
+}
@@ -0,0 +1,436 @@
+//! Meetings from iCalendar files, which are added as draft frames, see `ttt import ics`.
+//!
+//! Only events with a start and end time are meetings, all-day events like vacations or
+//! birthdays are left out, as are cancelled events and events declined by the user. Times with
+//! a `TZID` are taken as local time. Daily and weekly recurring events are repeated within the
+//! time span at the same local time, leaving out the occurrences in `EXDATE` and those changed
+//! by an event with a `RECURRENCE-ID`. Of events recurring otherwise, only the first occurrence
+//! is imported.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+
+use crate::{
+    error::{Error, Result},
+    leave::{unescape, unfold},
+    model::{TimeSpan, Timestamp},
+};
+
+/// A timed event of the calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meeting {
+    pub span: TimeSpan,
+    pub summary: Option<String>,
+}
+
+/// The meetings starting within `span`, sorted by start, with an entry per occurrence of
+/// recurring events. Events declined by the attendee with the address `email` are left out.
+pub fn parse_meetings(ics: &str, span: TimeSpan, email: Option<&str>) -> Result<Vec<Meeting>> {
+    if !ics.contains("BEGIN:VCALENDAR") {
+        return Err(Error::InvalidImport(
+            "The file is not an iCalendar file".to_owned(),
+        ));
+    }
+    let mut events = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in unfold(ics) {
+        let (name, value) = line.split_once(':').unwrap_or((&line, ""));
+        let (name, parameters) = name.split_once(';').unwrap_or((name, ""));
+        let parameters = parameters.to_ascii_uppercase();
+        match (name.to_ascii_uppercase().as_str(), &mut event) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(Event::default()),
+            ("END", Some(_)) if value == "VEVENT" => events.extend(event.take()),
+            ("UID", Some(current)) => current.uid = Some(value.trim().to_owned()),
+            ("RRULE", Some(current)) => current.rule = Rule::parse(value),
+            ("EXDATE", Some(current)) => {
+                for value in value.split(',') {
+                    current.exceptions.extend(time(&parameters, value)?);
+                }
+            }
+            ("RECURRENCE-ID", Some(current)) => {
+                current.recurrence_id = time(&parameters, value)?;
+            }
+            ("DTSTART", Some(current)) => current.start = time(&parameters, value)?,
+            ("DTEND", Some(current)) => current.end = time(&parameters, value)?,
+            ("DURATION", Some(current)) => current.duration = duration(value),
+            ("SUMMARY", Some(current)) => current.summary = Some(unescape(value)),
+            ("STATUS", Some(current)) => {
+                current.skip |= value.eq_ignore_ascii_case("CANCELLED");
+            }
+            ("ATTENDEE", Some(current)) => {
+                let address = value.trim().trim_start_matches("mailto:");
+                current.skip |= parameters.contains("PARTSTAT=DECLINED")
+                    && email.is_some_and(|email| address.eq_ignore_ascii_case(email));
+            }
+            _ => {}
+        }
+    }
+
+    // Changed occurrences are events of their own, with the UID of the recurring event and the
+    // start of the occurrence they replace. Cancelled ones replace it with nothing.
+    let changed: Vec<_> = events
+        .iter()
+        .filter_map(|event| Some((event.uid.as_deref()?, event.recurrence_id?)))
+        .collect();
+    let mut meetings = Vec::new();
+    for event in &events {
+        let Some(meeting) = event.meeting() else {
+            continue;
+        };
+        let starts = match (&event.rule, event.recurrence_id) {
+            (Some(rule), None) => rule.occurrences(meeting.span.start(), span.end()),
+            _ => vec![meeting.span.start()],
+        };
+        for start in starts {
+            let replaced = event.recurrence_id.is_none()
+                && changed
+                    .iter()
+                    .any(|&(uid, at)| event.uid.as_deref() == Some(uid) && at == start);
+            if start < span.start()
+                || start >= span.end()
+                || replaced
+                || event.exceptions.contains(&start)
+            {
+                continue;
+            }
+            let end = Timestamp(start.0 + meeting.span.duration());
+            if let Ok(occurrence) = TimeSpan::new(start, end) {
+                meetings.push(Meeting {
+                    span: occurrence,
+                    summary: meeting.summary.clone(),
+                });
+            }
+        }
+    }
+    meetings.sort_by_key(|meeting| meeting.span.start());
+    Ok(meetings)
+}
+
+#[derive(Default)]
+struct Event {
+    /// Only set for events with a time
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    duration: Option<Duration>,
+    summary: Option<String>,
+    uid: Option<String>,
+    rule: Option<Rule>,
+
+    /// Starts of occurrences left out of the recurrence
+    exceptions: Vec<Timestamp>,
+
+    /// Start of the occurrence of a recurring event this event changes
+    recurrence_id: Option<Timestamp>,
+
+    /// Cancelled or declined
+    skip: bool,
+}
+
+impl Event {
+    fn meeting(&self) -> Option<Meeting> {
+        if self.skip {
+            return None;
+        }
+        let start = self.start?;
+        let end = self
+            .end
+            .or_else(|| Some(Timestamp(start.0 + self.duration?)))?;
+        Some(Meeting {
+            span: TimeSpan::new(start, end).ok()?,
+            summary: self.summary.clone(),
+        })
+    }
+}
+
+/// A daily or weekly `RRULE`, see RFC 5545 section 3.3.10.
+#[derive(Debug, PartialEq)]
+struct Rule {
+    weekly: bool,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<Timestamp>,
+
+    /// Days of weekly rules, empty for the day of the first occurrence
+    days: Vec<Weekday>,
+}
+
+impl Rule {
+    /// The rule of an `RRULE` value like `FREQ=WEEKLY;BYDAY=MO,WE`, or `None` for other
+    /// frequencies.
+    fn parse(value: &str) -> Option<Rule> {
+        let mut rule = Rule {
+            weekly: false,
+            interval: 1,
+            count: None,
+            until: None,
+            days: Vec::new(),
+        };
+        let mut frequency = None;
+        for part in value.trim().split(';') {
+            let (name, value) = part.split_once('=')?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.to_ascii_uppercase()),
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|&n| n > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => {
+                    // A date includes the whole day.
+                    let day = NaiveDate::parse_from_str(value.trim(), "%Y%m%d")
+                        .ok()
+                        .and_then(|day| day.and_hms_opt(23, 59, 59));
+                    rule.until = match day {
+                        Some(day) => Some(Timestamp::from_naive(day)),
+                        None => time("", value).ok()?,
+                    };
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        rule.days.push(weekday(day)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        rule.weekly = match frequency?.as_str() {
+            "DAILY" => false,
+            "WEEKLY" => true,
+            _ => return None,
+        };
+        rule.days.sort_by_key(Weekday::num_days_from_monday);
+        Some(rule)
+    }
+
+    /// Starts of the occurrences before `end` of an event first starting at `first`. They are
+    /// at the same local time, also after a change of daylight saving time.
+    fn occurrences(&self, first: Timestamp, end: Timestamp) -> Vec<Timestamp> {
+        let first = first.to_local().naive_local();
+        let (date, time) = (first.date(), first.time());
+        let days = match self.days.as_slice() {
+            [] => vec![date.weekday()],
+            days => days.to_vec(),
+        };
+        let mut starts = Vec::new();
+        for period in 0.. {
+            let step = i64::from(self.interval) * period;
+            let dates: Vec<NaiveDate> = if self.weekly {
+                let week = date - Duration::days(date.weekday().num_days_from_monday().into())
+                    + Duration::weeks(step);
+                days.iter()
+                    .map(|day| week + Duration::days(day.num_days_from_monday().into()))
+                    .filter(|day| *day >= date)
+                    .collect()
+            } else {
+                vec![date + Duration::days(step)]
+            };
+            for date in dates {
+                let start = Timestamp::from_naive(date.and_time(time));
+                let counted = u32::try_from(starts.len()).unwrap_or(u32::MAX);
+                if start >= end
+                    || self.until.is_some_and(|until| start > until)
+                    || self.count.is_some_and(|count| counted >= count)
+                {
+                    return starts;
+                }
+                starts.push(start);
+            }
+        }
+        starts
+    }
+}
+
+/// A day of `BYDAY` like `MO`. Numbered days like `1MO` of monthly rules are not understood.
+fn weekday(day: &str) -> Option<Weekday> {
+    Some(match day.trim().to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// The time of a `DTSTART` or `DTEND`, or `None` for the date of an all-day event.
+fn time(parameters: &str, value: &str) -> Result<Option<Timestamp>> {
+    let value = value.trim();
+    if parameters.contains("VALUE=DATE") && !value.contains('T') {
+        return Ok(None);
+    }
+    let invalid =
+        |e: chrono::ParseError| Error::InvalidImport(format!("Invalid time {value}: {e}"));
+    Ok(Some(match value.strip_suffix('Z') {
+        Some(utc) => {
+            let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(invalid)?;
+            Timestamp(Utc.from_utc_datetime(&time).fixed_offset())
+        }
+        None => Timestamp::from_naive(
+            NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(invalid)?,
+        ),
+    }))
+}
+
+/// A duration like `PT1H30M`, see RFC 5545 section 3.3.6. Negative durations are invalid for
+/// events.
+fn duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.trim().strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let count: i64 = number.parse().ok()?;
+                number.clear();
+                let part = match unit {
+                    'W' => Duration::weeks(count),
+                    'D' => Duration::days(count),
+                    'H' => Duration::hours(count),
+                    'M' => Duration::minutes(count),
+                    'S' => Duration::seconds(count),
+                    _ => return None,
+                };
+                total = total + part;
+            }
+        }
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_meetings() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240709T080000Z\r\n\
+            DTEND:20240709T090000Z\r\n\
+            SUMMARY:Planning\\, sprint 12\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Europe/Berlin:20240708T100000\r\n\
+            DURATION:PT15M\r\n\
+            SUMMARY:Standup\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20240710\r\n\
+            SUMMARY:Vacation\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240710T120000Z\r\n\
+            DTEND:20240710T130000Z\r\n\
+            ATTENDEE;CN=Me;PARTSTAT=DECLINED:mailto:me@example.com\r\n\
+            SUMMARY:Declined\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240711T120000Z\r\n\
+            DTEND:20240711T130000Z\r\n\
+            STATUS:CANCELLED\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240720T120000Z\r\n\
+            DTEND:20240720T130000Z\r\n\
+            SUMMARY:Next week\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let week = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 7, 8, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 7, 15, 0, 0, 0),
+        )
+        .unwrap();
+
+        let meetings = parse_meetings(ics, week, Some("Me@Example.com")).unwrap();
+        let summaries: Vec<_> = meetings
+            .iter()
+            .map(|meeting| meeting.summary.as_deref().unwrap())
+            .collect();
+        assert_eq!(summaries, ["Standup", "Planning, sprint 12"]);
+        assert_eq!(
+            meetings[0].span.start(),
+            Timestamp::from_ymdhms(2024, 7, 8, 10, 0, 0)
+        );
+        assert_eq!(meetings[0].span.duration(), Duration::minutes(15));
+        assert_eq!(meetings[1].span.duration(), Duration::hours(1));
+
+        // Without knowing the user, declined events are kept.
+        assert_eq!(parse_meetings(ics, week, None).unwrap().len(), 3);
+        assert!(parse_meetings("{}", week, None).is_err());
+    }
+
+    #[test]
+    fn test_recurring_meetings() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup\r\n\
+            DTSTART;TZID=Europe/Berlin:20240701T100000\r\n\
+            DURATION:PT15M\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR\r\n\
+            EXDATE;TZID=Europe/Berlin:20240710T100000\r\n\
+            SUMMARY:Standup\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup\r\n\
+            RECURRENCE-ID;TZID=Europe/Berlin:20240712T100000\r\n\
+            DTSTART;TZID=Europe/Berlin:20240712T140000\r\n\
+            DURATION:PT15M\r\n\
+            SUMMARY:Standup moved\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240707T090000\r\n\
+            DTEND:20240707T100000\r\n\
+            RRULE:FREQ=DAILY;INTERVAL=2;COUNT=3\r\n\
+            SUMMARY:Review\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240601T090000\r\n\
+            DTEND:20240601T100000\r\n\
+            RRULE:FREQ=MONTHLY\r\n\
+            SUMMARY:Monthly\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let week = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 7, 8, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 7, 15, 0, 0, 0),
+        )
+        .unwrap();
+
+        let meetings = parse_meetings(ics, week, None).unwrap();
+        let meetings: Vec<_> = meetings
+            .iter()
+            .map(|meeting| (meeting.span.start(), meeting.summary.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            meetings,
+            [
+                (Timestamp::from_ymdhms(2024, 7, 8, 10, 0, 0), "Standup"),
+                (Timestamp::from_ymdhms(2024, 7, 9, 9, 0, 0), "Review"),
+                (Timestamp::from_ymdhms(2024, 7, 11, 9, 0, 0), "Review"),
+                (
+                    Timestamp::from_ymdhms(2024, 7, 12, 14, 0, 0),
+                    "Standup moved"
+                ),
+            ]
+        );
+
+        let rule = Rule::parse("FREQ=WEEKLY;INTERVAL=2;UNTIL=20240722").unwrap();
+        assert_eq!(
+            rule.occurrences(
+                Timestamp::from_ymdhms(2024, 7, 1, 10, 0, 0),
+                Timestamp::from_ymdhms(2024, 12, 1, 0, 0, 0)
+            ),
+            [
+                Timestamp::from_ymdhms(2024, 7, 1, 10, 0, 0),
+                Timestamp::from_ymdhms(2024, 7, 15, 10, 0, 0),
+            ]
+        );
+        assert_eq!(Rule::parse("FREQ=YEARLY"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(duration("P1DT2H"), Some(Duration::hours(26)));
+        assert_eq!(duration("1H"), None);
+    }
+}
@@ -1,15 +1,44 @@
-use std::{error::Error, process::ExitCode};
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use clap::{arg, Args, Parser, Subcommand};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use inquire::{
     list_option::ListOption, validator::Validation, Confirm, CustomType, CustomUserError,
-    DateSelect, MultiSelect, Select,
+    DateSelect, MultiSelect, Select, Text,
+};
+
+use serde::{
+    ser::{SerializeSeq, Serializer},
+    Serialize,
 };
 
-use crate::model::{Frame, TimeSpan, Timestamp};
+use crate::model::{
+    serialize_duration, Budget, BudgetPeriod, Frame, FrameDetails, FrameKind, FrameSource,
+    LeaveDay, LeaveKind, NewFrame, Project, RuleField, Tag, Task, TimeSpan, Timestamp,
+};
 use crate::{
-    database::{ArchivedState, Database},
-    DurationExt,
+    budget,
+    config::Config,
+    database::{is_duplicate, ArchivedState, Database, DuplicatePolicy},
+    duration::{DurationExt, DurationFormat},
+    export::Pseudonyms,
+    filter::Filter,
+    gaps::{find_gaps, WorkingHours},
+    gui::tauri_main,
+    instance::{self, Request},
+    prompt::Prompt,
+    report::{Attribution, Comparison, Estimate, GroupNames, Grouping, Money, Report},
+    schedule::{format_difference, Schedule},
+    stats::Statistics,
+    table::{Align, Table},
+    timespan_parser::{self, Diagnostic},
+    trend::{Trend, Window},
 };
 
 #[derive(Parser)]
@@ -18,263 +47,4303 @@ pub struct Cli {
     /// Action to perform
     #[clap(subcommand)]
     pub action: Option<Action>,
+
+    /// Use the database at the given path instead of the default one
+    #[arg(long, global = true)]
+    pub database: Option<PathBuf>,
+
+    /// How to print durations, overrides `duration_format` from the configuration file
+    #[arg(long, global = true, value_enum)]
+    pub duration_format: Option<DurationFormat>,
+
+    /// Allow changes to frames within periods locked with `ttt lock`
+    #[arg(long, global = true)]
+    pub force_unlock: bool,
+
+    /// Answer all confirmations with yes, e.g. when deleting frames from a script
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+
+    /// Never ask questions, fail instead. The default when stdin is not a terminal, e.g. in cron
+    /// jobs
+    #[arg(long, global = true)]
+    pub no_input: bool,
+}
+
+impl Cli {
+    /// Whether the command works on the database without applying pending migrations, see
+    /// [`Database::new_unmigrated`]. `ttt db migrate` applies them itself, so it can list the
+    /// pending ones. Bug reports should show them as well, also when a migration fails.
+    pub fn keeps_database_unmigrated(&self) -> bool {
+        matches!(
+            self.action,
+            Some(Action::Db(DbAction::Migrate { .. }) | Action::DebugBundle { .. })
+        )
+    }
 }
 
 #[derive(Debug, Parser)]
 pub struct AnalyzeOptions {
+    /// Time span to analyze, e.g. "last weekend". If neither this nor --since-yesterday is given,
+    /// the time span is asked for interactively.
+    timespan: Vec<String>,
+
     /// Show the last 24h
-    #[clap(short, long, action, default_value = "false")]
+    #[clap(
+        short,
+        long,
+        action,
+        default_value = "false",
+        conflicts_with = "timespan"
+    )]
     since_yesterday: bool,
+
+    /// List the untracked periods within working hours instead of the frames. Weekends are
+    /// ignored.
+    #[arg(long)]
+    show_gaps: bool,
+
+    /// Start of the working hours, used with --show-gaps
+    #[arg(long, default_value = "08:00")]
+    work_start: NaiveTime,
+
+    /// End of the working hours, used with --show-gaps
+    #[arg(long, default_value = "18:00")]
+    work_end: NaiveTime,
+
+    /// Ignore gaps shorter than this many minutes
+    #[arg(long, default_value_t = 5)]
+    min_gap: u32,
+
+    /// Only include frames matching this expression, e.g.
+    /// 'project =~ "client.*" and duration > 15min'
+    #[arg(long)]
+    filter: Option<Filter>,
+
+    /// Leave out the column names
+    #[arg(long)]
+    no_header: bool,
 }
 
 impl AnalyzeOptions {
     pub fn is_interactive(&self) -> bool {
-        !self.since_yesterday
+        !self.since_yesterday && self.timespan.is_empty()
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct ReportOptions {
+    /// Period to summarize: "daily", "weekly", "monthly" for the last complete day, week or
+    /// month, or a time span like "last 2 weeks"
+    #[arg(default_value = "weekly")]
+    period: Vec<String>,
+
+    /// How to print the report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    format: ReportFormat,
+
+    /// Print the report as markdown, same as `--format markdown`
+    #[arg(long, conflicts_with_all = ["format", "email"])]
+    stdout_md: bool,
+
+    /// Print the report as an email that can be piped into `sendmail -t`, same as
+    /// `--format email`
+    #[arg(long, conflicts_with = "format")]
+    email: bool,
+
+    /// Recipient of the email
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Write the report to this file instead of printing it
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Render the report with this Handlebars template instead of a built-in format, see the
+    /// documentation of the `template` module for the available variables
+    #[arg(long, conflicts_with_all = ["format", "stdout_md", "email"])]
+    template: Option<PathBuf>,
+
+    /// Sum up the time per project, referenced ticket, tag, task or client
+    #[arg(long, value_enum, default_value_t = Grouping::Project)]
+    by: Grouping,
+
+    /// Only count the projects of this client, see `ttt new-client`
+    #[arg(long)]
+    client: Option<String>,
+
+    /// Add what the time is billed, with the rate of each project in effect when a frame started,
+    /// see `ttt project set-rate`
+    #[arg(long)]
+    billing: bool,
+
+    /// With --by tag, whether frames count fully for every tag of their project or are split
+    /// between them
+    #[arg(long, value_enum, default_value_t = Attribution::Full)]
+    attribution: Attribution,
+
+    /// Only include frames matching this expression, e.g.
+    /// 'project =~ "client.*" and duration > 15min'
+    #[arg(long)]
+    filter: Option<Filter>,
+}
+
+impl ReportOptions {
+    fn format(&self) -> ReportFormat {
+        if self.stdout_md {
+            ReportFormat::Markdown
+        } else if self.email || self.to.is_some() {
+            ReportFormat::Email
+        } else {
+            self.format
+        }
+    }
+
+    fn span(&self, config: &Config) -> Result<TimeSpan, Diagnostic> {
+        let words: Vec<&str> = match self.period.as_slice() {
+            [period] if period == "daily" => vec!["yesterday"],
+            [period] if period == "weekly" => vec!["last", "week"],
+            [period] if period == "monthly" => vec!["last", "month"],
+            period => period.iter().map(String::as_str).collect(),
+        };
+        parse_timespan(&words, config)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+    Email,
+    /// Machine readable, durations are ISO 8601 (e.g. "PT1H23M") and timestamps RFC 3339
+    Json,
+    /// Standalone page with charts, e.g. for clients
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+    Table,
+    /// Comma separated values with a header line
+    Csv,
+    /// An array with an object per row
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Action {
     /// Start tracking an activity
     Start {
-        /// Name of the project to start. If no name is given, interactive mode is used to
-        /// determine the project.
+        /// Name of the project to start, or "project/task" to track a task of the project. If no
+        /// name is given, interactive mode is used to determine the project.
         name: Option<String>,
+
+        /// Issue or ticket the time is spent on, e.g. "PROJ-123"
+        #[arg(long = "ref")]
+        external_ref: Option<String>,
+
+        /// Create the project or task if it does not exist, instead of asking
+        #[arg(long, requires = "name")]
+        create: bool,
+
+        /// Unarchive the project if it is archived, instead of asking
+        #[arg(long, requires = "name")]
+        unarchive: bool,
     },
 
     /// Stop tracking the current activity
-    Stop,
+    Stop {
+        /// Project whose frame to stop, needed when several frames run concurrently
+        project: Option<String>,
+
+        /// Stop all running frames
+        #[arg(long, conflicts_with = "project")]
+        all: bool,
+
+        /// Move the end back to the last full quarter hour, or to the last multiple of the given
+        /// minutes, so a short break before stopping is not counted
+        #[arg(
+            long,
+            value_name = "MINUTES",
+            num_args = 0..=1,
+            default_missing_value = "15",
+            value_parser = clap::value_parser!(u32).range(1..=1440)
+        )]
+        snap: Option<u32>,
+    },
+
+    /// Track a break, which is left out of the working time in reports
+    #[command(subcommand)]
+    Break(BreakAction),
+
+    /// Change the kind of a frame, e.g. `ttt mark 1a2b3c4 on-call` for time on call, which is
+    /// paid with the on-call multiplier in `ttt report --billing`
+    Mark {
+        /// Id of the frame as shown by `ttt list frames`, or any longer prefix of its uuid
+        id: String,
+
+        kind: FrameKind,
+    },
+
+    /// Print the current project and how long it has been running. Exits with 0 while tracking
+    /// and with 3 when idle.
+    Current {
+        /// Print nothing, only report the state through the exit code
+        #[arg(short, long, conflicts_with = "elapsed_seconds")]
+        quiet: bool,
+
+        /// Print only the number of seconds the current frame has been running
+        #[arg(long)]
+        elapsed_seconds: bool,
+    },
+
+    /// Print nothing, exit with 0 if the project, or any project, is being tracked and with 3
+    /// otherwise, e.g. `ttt is-tracking website && cargo watch`.
+    IsTracking {
+        /// Project to check, by name or alias
+        project: Option<String>,
+    },
+
+    /// Change the running frame without stopping it, e.g.
+    /// `ttt annotate --project other +tag -m "actually doing X"`. With concurrent frames, the one
+    /// started last is changed.
+    Annotate {
+        /// Tags to add to the project of the frame, like "+review"
+        #[arg(value_parser = parse_added_tag)]
+        tags: Vec<String>,
+
+        /// Move the frame to this project, or to a task with "project/task"
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Text to append to the note of the frame
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+
+    /// Add a project. Without a name, the name and tags are asked for.
+    NewProject {
+        name: Option<String>,
+
+        /// Tag the project, missing tags are created. Can be given several times.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        #[command(flatten)]
+        options: NewProjectOptions,
+    },
+
+    /// Add a tag
+    NewTag { name: String },
+
+    /// Add a client, whose projects can be reported together with `ttt report --client` and
+    /// `--by client`
+    NewClient {
+        name: String,
+
+        /// Currency the client is billed in, e.g. EUR
+        #[arg(long, value_parser = parse_currency)]
+        currency: Option<String>,
+    },
+
+    /// Manage clients.
+    #[command(subcommand)]
+    Client(ClientAction),
+
+    /// Open the graphical interface
+    Gui {
+        /// Started with the session, see `get_autostart` in the GUI
+        #[arg(long, hide = true)]
+        autostart: bool,
+    },
+
+    /// Tag projects interactively
+    Tag {
+        project: Option<String>,
+        tags: Vec<String>,
+
+        /// Unarchive the project and tags if they are archived, instead of asking
+        #[arg(long, requires = "project")]
+        unarchive: bool,
+    },
+
+    /// Analyze activities performed in a time frame
+    Analyze(AnalyzeOptions),
+
+    /// Add a frame that was forgotten to be tracked
+    Add {
+        /// Name of the project the time was spent on
+        project: String,
+
+        /// Start of the frame, e.g. "2024-03-04 12:30"
+        #[arg(long, value_parser = parse_timestamp)]
+        start: Timestamp,
+
+        /// End of the frame, e.g. "2024-03-04 13:30"
+        #[arg(long, value_parser = parse_timestamp)]
+        end: Timestamp,
+    },
+
+    /// List available projects, tags or frames.
+    List {
+        /// Leave out the column names
+        #[arg(long, global = true)]
+        no_header: bool,
+
+        #[command(subcommand)]
+        action: ListAction,
+    },
+
+    /// Summarize the tracked time of a period, e.g. from a weekly cron job:
+    /// `ttt report --email --to me@example.com | sendmail -t`
+    Report(ReportOptions),
+
+    /// Show the flex time balance, i.e. the time worked more or less than the working hours
+    /// configured in `config.toml`, per week.
+    Balance,
+
+    /// Show statistics like the longest streak of tracked days or the busiest weekday.
+    Stats {
+        /// Period to look at, e.g. "last month"
+        #[arg(default_value = "this month")]
+        timespan: Vec<String>,
+    },
+
+    /// Compare the tracked time of two periods, e.g. `ttt compare "this month" "last month"`.
+    Compare {
+        /// Period to look at
+        #[arg(default_value = "this week")]
+        current: String,
+
+        /// Period to compare it to
+        #[arg(default_value = "last week")]
+        previous: String,
+
+        /// Sum up the time per project, referenced ticket, tag, task or client
+        #[arg(long, value_enum, default_value_t = Grouping::Project)]
+        by: Grouping,
+
+        /// Print the comparison as JSON, durations are ISO 8601 and changes in percent
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the tracked time of each of the last days, weeks or months, e.g.
+    /// `ttt trend --window week --last 12 --project website`
+    Trend {
+        #[arg(long, value_enum, default_value_t = Window::Week)]
+        window: Window,
+
+        /// Number of windows, the current one included
+        #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+        last: u32,
+
+        /// Also show the time of this project. Can be given several times.
+        #[arg(long = "project")]
+        projects: Vec<String>,
+
+        /// How to print the windows. CSV has the time in hours, JSON as ISO 8601 durations.
+        #[arg(long, value_enum, default_value_t = TableFormat::Table)]
+        format: TableFormat,
+    },
+
+    /// Manage projects.
+    #[command(subcommand)]
+    Project(ProjectAction),
+
+    /// Limit the time or money spent on a project per period, e.g. `ttt budget website 40h`.
+    /// `start`, `current` and the GUI warn once 80% and once all of it are used. Without a limit,
+    /// shows how much of the budgets is used.
+    Budget {
+        project: Option<String>,
+
+        /// Hours like "40h", or an amount of money in the currency of the project like "2000"
+        #[arg(value_parser = parse_budget, requires = "project")]
+        limit: Option<BudgetLimit>,
+
+        /// Period the budget has to last
+        #[arg(long, value_enum, default_value_t = BudgetPeriod::Month, requires = "limit")]
+        per: BudgetPeriod,
+
+        /// Remove the budget of the project
+        #[arg(long, requires = "project", conflicts_with = "limit")]
+        clear: bool,
+    },
+
+    /// Manage the tasks within projects, which are started with `ttt start <project>/<task>`.
+    #[command(subcommand)]
+    Task(TaskAction),
+
+    /// Move objects to the trash.
+    #[command(subcommand)]
+    Delete(DeleteAction),
+
+    /// Inspect, restore or permanently delete frames in the trash.
+    #[command(subcommand)]
+    Trash(TrashAction),
+
+    /// Offer to merge frames of the same project that follow each other within a short gap,
+    /// e.g. after imports or frequent stopping and starting. The merged frames are moved to the
+    /// trash.
+    Dedupe {
+        /// Time span to look at, e.g. "last month"
+        #[arg(default_value = "this week")]
+        timespan: Vec<String>,
+
+        /// Merge frames that start less than this many minutes after the previous one ended
+        #[arg(long, default_value_t = 2)]
+        gap: u32,
+    },
+
+    /// Confirm or discard draft frames, e.g. from `ttt import activitywatch --draft`. Drafts
+    /// don't count in reports until they are confirmed, discarded ones are moved to the trash.
+    Review {
+        /// Move all drafts to the trash without asking
+        #[arg(long)]
+        discard_all: bool,
+    },
+
+    /// Tag projects automatically when frames are created or edited, e.g. so frames with notes
+    /// like "PROJ-123" make their project a `client-a` project.
+    #[command(subcommand)]
+    Rules(RuleAction),
+
+    /// Write the tracked time to files for other people or tools.
+    #[command(subcommand)]
+    Export(ExportAction),
+
+    /// Create frames from data recorded by other tools.
+    #[command(subcommand)]
+    Import(ImportAction),
+
+    /// Mark a period as submitted, e.g. to payroll. Frames within it can no longer be added,
+    /// changed or deleted unless --force-unlock is given. Without a time span the locked
+    /// periods are listed.
+    Lock {
+        /// Period to lock, e.g. "last month"
+        timespan: Vec<String>,
+    },
+
+    /// Record days off like vacation, which are left out of `ttt analyze --show-gaps` and shown
+    /// in statistics and timesheets.
+    #[command(subcommand)]
+    Leave(LeaveAction),
+
+    /// Exchange frames with other machines through a shared folder, e.g. one synchronized by
+    /// Syncthing or Dropbox.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Sync {
+        #[arg(required = true)]
+        dir: Option<PathBuf>,
+
+        #[cfg(feature = "caldav")]
+        #[command(subcommand)]
+        target: Option<SyncTarget>,
+    },
+
+    /// Publish the tracking state to the MQTT broker from the configuration file. Start and stop
+    /// do this automatically, running it from a timer keeps the elapsed time up to date.
+    #[cfg(feature = "mqtt")]
+    Mqtt,
+
+    /// Manage the Slack or Mattermost status, which is set while tracking.
+    #[cfg(feature = "slack")]
+    #[command(subcommand)]
+    Slack(SlackAction),
+
+    /// Encrypt the database with a passphrase, or change the passphrase.
+    ///
+    /// The passphrase is read from TTT_PASSPHRASE if set.
+    #[cfg(feature = "sqlcipher")]
+    Encrypt,
+
+    /// Store the database unencrypted again.
+    #[cfg(feature = "sqlcipher")]
+    Decrypt,
+
+    /// Run a single SELECT statement on the database, e.g.
+    /// `ttt sql "SELECT name FROM projects WHERE archived"`. The database is opened read-only, so
+    /// nothing can be changed by accident.
+    Sql {
+        query: String,
+
+        /// How to print the rows
+        #[arg(long, value_enum, default_value_t = TableFormat::Table)]
+        format: TableFormat,
+    },
+
+    /// Answer JSON commands for editor plugins and scripts, one per line, e.g.
+    /// `{"command": "start", "project": "website"}`. The commands are start, stop, add, current,
+    /// frames, projects and heartbeat, which editors send to track the project of edited files.
+    Api {
+        /// Read the commands from standard input and answer on standard output
+        #[arg(long, required = true)]
+        stdin: bool,
+    },
+
+    /// Maintain the database file.
+    #[command(subcommand)]
+    Db(DbAction),
+
+    /// Collect the version, the configuration without secrets and statistics of the database
+    /// into a zip file to attach to bug reports. Frames, projects and notes are not included.
+    DebugBundle {
+        #[arg(short, long, default_value = "ttt-debug-bundle.zip")]
+        output: PathBuf,
+    },
+
+    /// Install the latest release from GitHub in place of this program, after checking its
+    /// SHA-256 checksum.
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        /// Only tell whether there is a new release
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Tools for developing ttt
+    #[command(subcommand, hide = true)]
+    Dev(DevAction),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DevAction {
+    /// Fill the database with random projects, tags and frames, e.g. for screenshots or to test
+    /// the performance with a lot of data
+    Seed {
+        /// Number of days before today to add frames to
+        #[arg(long, default_value_t = 90)]
+        days: u32,
+
+        /// Start of the random numbers, the same seed gives the same frames. Random by default.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+#[cfg(feature = "slack")]
+#[derive(Subcommand, Debug)]
+pub enum SlackAction {
+    /// Store the API token in the keyring of the operating system.
+    Login,
+
+    /// Remove the API token from the keyring.
+    Logout,
+}
+
+/// Settings of `ttt new-project` besides the name and tags.
+#[derive(Args, Debug, Default)]
+pub struct NewProjectOptions {
+    /// Archive the project right away, e.g. to record past work
+    #[arg(long)]
+    archived: bool,
+
+    /// What an hour of work is billed, e.g. 85.50
+    #[arg(long, value_parser = parse_rate)]
+    rate: Option<i32>,
+
+    /// Currency of the rate, e.g. EUR, if it differs from the one of the client
+    #[arg(long, value_parser = parse_currency)]
+    currency: Option<String>,
+
+    /// Another name to refer to the project by. Can be given several times.
+    #[arg(long = "alias")]
+    aliases: Vec<String>,
+
+    /// Client the project is done for, see `ttt new-client`
+    #[arg(long)]
+    client: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Whether to include archived objects or not
+    #[arg(
+        long,
+        num_args=0..=1,
+        default_value_t = ArchivedState::NotArchived,
+        default_missing_value="only-archived",
+        value_enum
+    )]
+    archived: ArchivedState,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ListAction {
+    Projects {
+        #[arg(long, default_value_t = false)]
+        with_tags: bool,
+
+        #[command(flatten)]
+        args: ListArgs,
+    },
+    Tags(ListArgs),
+
+    /// List clients with their projects
+    Clients,
+
+    /// List frames with their ids, oldest first. The ids are accepted by commands like
+    /// `ttt delete frame`.
+    Frames {
+        /// Only list frames within this time span, e.g. "last week". All frames by default.
+        timespan: Vec<String>,
+
+        /// Only list frames of this project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Only list frames of projects with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Only list the most recent frames
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Only include frames matching this expression, e.g.
+        /// 'project =~ "client.*" and duration > 15min'
+        #[arg(long)]
+        filter: Option<Filter>,
+
+        /// Print the frames as JSON, durations are ISO 8601 and timestamps RFC 3339
+        #[arg(long)]
+        json: bool,
+
+        /// Also show how each frame was created, e.g. with the GUI or by an import
+        #[arg(short, long, conflicts_with = "json")]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectAction {
+    /// Set the tags that are checked whenever a frame of the project is started. The tags are
+    /// added to the project, giving no tags clears the defaults.
+    SetDefaultTags { project: String, tags: Vec<String> },
+
+    /// Set how many hours a project is planned to take, giving no hours clears the estimate.
+    SetEstimate { project: String, hours: Option<f64> },
+
+    /// Compare the estimated with the tracked time of all projects that have an estimate.
+    Estimates,
+
+    /// Set what an hour of work on a project is billed, e.g. 85.50. With --from, the rate takes
+    /// effect on that day and earlier work keeps its rate, otherwise it is the rate before any
+    /// dated one. Giving no amount clears the undated rate.
+    SetRate {
+        project: String,
+
+        #[arg(value_parser = parse_rate)]
+        amount: Option<i32>,
+
+        /// First day the rate applies to, e.g. 2024-07-01
+        #[arg(long, requires = "amount")]
+        from: Option<NaiveDate>,
+    },
+
+    /// List the rates of a project and since when they apply.
+    Rates { project: String },
+
+    /// Set the currency of the rates of a project, giving no currency uses the one of its client.
+    SetCurrency {
+        project: String,
+
+        #[arg(value_parser = parse_currency)]
+        currency: Option<String>,
+    },
+
+    /// Assign a project to a client, giving no client removes it from its client.
+    SetClient {
+        project: String,
+        client: Option<String>,
+    },
+
+    /// Give a project a new name, the old name stays usable as an alias.
+    Rename { project: String, name: String },
+
+    /// Move all frames, tags and aliases of a project to another one and delete it.
+    Merge {
+        /// Project to delete
+        from: String,
+
+        /// Project that gets the frames
+        into: String,
+    },
+
+    /// Offer to archive projects that were not used for a while.
+    Prune {
+        /// Suggest projects that were not used in this many months
+        #[arg(long, default_value_t = 6)]
+        months: u32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DeleteAction {
+    /// Move a frame to the trash. It can be restored with `ttt trash restore`.
+    Frame {
+        /// Id of the frame as shown by `ttt list frames`, or any longer prefix of its uuid
+        id: String,
+    },
+}
+
+#[cfg(feature = "caldav")]
+#[derive(Subcommand, Debug)]
+pub enum SyncTarget {
+    /// Create, update and delete the events of the frames in the CalDAV calendar from the
+    /// configuration file.
+    Caldav {
+        /// Frames to publish, e.g. "this month"
+        #[arg(default_value = "this week")]
+        timespan: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportAction {
+    /// Frames as JSON, e.g. for analysis in other tools. Durations are ISO 8601 and timestamps
+    /// RFC 3339.
+    Json {
+        /// Only export frames within this time span, e.g. "last month". All frames by default.
+        timespan: Vec<String>,
+
+        /// Replace the names of projects and tags, tickets and notes by pseudonyms like
+        /// "project-3", to share the data without leaking client names. The pseudonyms are kept
+        /// in anonymization.json in the data directory, so they stay the same between exports.
+        #[arg(long)]
+        anonymize: bool,
+
+        /// Write the frames to this file instead of printing them
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Timesheet with the time per project and day of every week, ready to be signed. The
+    /// header can be configured in the [timesheet] section of the configuration file.
+    #[cfg(feature = "pdf")]
+    Pdf {
+        /// Period of the timesheet, e.g. "last month"
+        #[arg(default_value = "last week")]
+        timespan: Vec<String>,
 
-    /// Print the current project
-    Current,
+        #[arg(short, long, default_value = "timesheet.pdf")]
+        output: PathBuf,
+
+        /// Name in the header, instead of the one from the configuration file
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Client in the header, instead of the one from the configuration file
+        #[arg(long)]
+        client: Option<String>,
+
+        /// Only include frames matching this expression, e.g.
+        /// 'project =~ "client.*" and duration > 15min'
+        #[arg(long)]
+        filter: Option<Filter>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportAction {
+    /// Suggest frames for the window events of an ActivityWatch bucket export, which are added
+    /// after confirmation. The events are assigned to projects with a rules file.
+    Activitywatch {
+        /// Export created with "Export bucket" or "Export all buckets" in ActivityWatch
+        file: PathBuf,
+
+        /// Only look at events within this time span, e.g. "yesterday". All events by default.
+        timespan: Vec<String>,
+
+        /// Part of the id of the bucket with the window events
+        #[arg(long, default_value = "window")]
+        bucket: String,
+
+        /// TOML file mapping apps and window titles to projects
+        #[arg(long)]
+        rules: PathBuf,
+
+        /// What to do with frames of a project that is already tracked for most of the time
+        #[arg(long, value_enum, default_value_t = DuplicatePolicy::Skip)]
+        on_duplicate: DuplicatePolicy,
+
+        /// Add the suggestions as drafts without asking, to confirm them later with `ttt review`
+        #[arg(long)]
+        draft: bool,
+    },
+
+    /// Add the meetings of a calendar as draft frames, to confirm them with `ttt review`.
+    /// All-day, cancelled and declined events are left out.
+    Ics {
+        /// iCalendar file, or with the calendar-url feature also an http(s) URL
+        source: String,
+
+        /// Only import meetings starting within this time span
+        #[arg(default_value = "this week")]
+        timespan: Vec<String>,
+
+        /// Project to track the meetings for
+        #[arg(long, default_value = "meetings")]
+        project: String,
+
+        /// Your address in the calendar, to leave out the events you declined
+        #[arg(long)]
+        email: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BreakAction {
+    /// Stop the current frame and start a break
+    Start {
+        /// Project to take the break from, by default the tracked or last used one
+        project: Option<String>,
+    },
+
+    /// End the break and continue working on the project
+    Stop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RuleAction {
+    /// Add a rule, e.g. `ttt rules add note "^PROJ-" client-a`
+    Add {
+        /// What the pattern is matched against
+        field: RuleField,
+
+        /// Regular expression, which may match anywhere in the text
+        pattern: String,
+
+        /// Tag to add to the frame's project
+        tag: String,
+    },
+
+    /// List the rules with their ids
+    List,
+
+    /// Remove a rule by the id shown in `ttt rules list`
+    Remove { id: i32 },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LeaveAction {
+    /// Record a day off, e.g. `ttt leave add 2024-12-24 vacation`
+    Add {
+        /// Day in the format 2024-12-24
+        day: NaiveDate,
+
+        kind: LeaveKind,
+
+        /// Last day of a longer leave, weekends in between are skipped
+        #[arg(long)]
+        until: Option<NaiveDate>,
+
+        /// Description, e.g. the name of a holiday
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Remove a recorded day off
+    Remove { day: NaiveDate },
+
+    /// List the days off
+    List {
+        /// Period to list, e.g. "next month"
+        #[arg(default_value = "this year")]
+        timespan: Vec<String>,
+    },
+
+    /// Import the public holidays of a region from an iCalendar file
+    Import {
+        /// Calendar with an all-day event per holiday
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrashAction {
+    /// List all frames in the trash
+    List,
+
+    /// Restore a frame from the trash
+    Restore {
+        /// Id of the frame as shown by `ttt trash list`
+        id: String,
+    },
+
+    /// Permanently delete all frames in the trash
+    Empty,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClientAction {
+    /// Set the currency a client is billed in, giving no currency uses the one from the
+    /// configuration file.
+    SetCurrency {
+        client: String,
+
+        #[arg(value_parser = parse_currency)]
+        currency: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TaskAction {
+    /// List the open tasks
+    List {
+        /// Only list the tasks of this project
+        project: Option<String>,
+
+        /// Also list tasks that are done
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Mark a task as done, e.g. "website/checkout-bug"
+    Done { task: String },
+
+    /// Mark a task as open again
+    Reopen { task: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbAction {
+    /// Shrink the database file, e.g. after emptying the trash
+    Vacuum,
+
+    /// Show the number of rows, the file size and the time covered by the frames
+    Stats,
+
+    /// Apply pending schema migrations. Every other command does this automatically.
+    Migrate {
+        /// Only list the migrations and whether they were applied
+        #[arg(long)]
+        status: bool,
+
+        /// Only list the migrations that would be applied
+        #[arg(long, conflicts_with = "status")]
+        dry_run: bool,
+    },
+}
+
+pub fn cli_main(mut database: Database, cli: Cli, config: &Config) -> ExitCode {
+    if cli.force_unlock {
+        database.force_unlock();
+    }
+    // The frames may not be readable before the migrations are applied.
+    let auto_stop = config
+        .auto_stop
+        .filter(|_| !cli.keeps_database_unmigrated());
+    if let Some(at) = auto_stop {
+        match database.auto_stop(at) {
+            Ok(stopped) => {
+                for frame in stopped {
+                    eprintln!(
+                        "Stopped frame {} at the end of the workday ({})",
+                        frame.short_id(),
+                        at.format("%H:%M")
+                    );
+                }
+            }
+            Err(err) => eprintln!("Failed to stop the frame at the end of the workday: {err}"),
+        }
+    }
+    let prompt = Prompt::new(cli.yes, cli.no_input);
+    let needs_project = matches!(
+        cli.action,
+        None | Some(Action::Start { name: None, .. } | Action::Tag { project: None, .. })
+    );
+    if needs_project
+        && prompt.interactive
+        && database
+            .all_projects(ArchivedState::Both)
+            .expect("Database is broken")
+            .is_empty()
+        && !first_run(&mut database, config, prompt)
+    {
+        return ExitCode::FAILURE;
+    }
+    let action = match cli.action {
+        Some(action) => action,
+        None => {
+            if let Err(code) = check_interactive(prompt, MENU_QUESTION) {
+                return code;
+            }
+            match main_menu(&mut database, config) {
+                Some(action) => action,
+                None => return ExitCode::SUCCESS,
+            }
+        }
+    };
+    match action {
+        Action::Gui { autostart } => return tauri_main(database, config, autostart, None),
+        Action::Start {
+            name,
+            external_ref,
+            create,
+            unarchive,
+        } => {
+            let (name, task_name) = match name {
+                Some(name) => {
+                    let (name, task) = split_task(&mut database, name);
+                    (Some(name), task)
+                }
+                None => (None, None),
+            };
+            let mut project = match name {
+                Some(name) => {
+                    let mut selected = match database
+                        .lookup_project_by_name(&name)
+                        .expect("Error querying the database.")
+                    {
+                        Some(selected) => selected,
+                        None if create || confirm_create_project(prompt, &name) => {
+                            let project = database
+                                .create_project(&name)
+                                .expect("Error creating project");
+                            println!("Created project {name}");
+                            project
+                        }
+                        None => {
+                            eprintln!("Project {name} does not exist in this timeline ;)");
+                            return ExitCode::FAILURE;
+                        }
+                    };
+                    if let Err(code) =
+                        unarchive_project(&mut database, &mut selected, unarchive, prompt)
+                    {
+                        return code;
+                    }
+                    selected
+                }
+                None => {
+                    let possible_projects = database
+                        .all_projects(ArchivedState::NotArchived)
+                        .expect("Database is broken");
+                    if possible_projects.is_empty() {
+                        println!("Please create a project before starting a task.");
+                        return ExitCode::FAILURE;
+                    }
+                    let question = "Select the project to start";
+                    if let Err(code) = check_interactive(prompt, question) {
+                        return code;
+                    }
+                    let selected_project = Select::new(
+                        question,
+                        possible_projects.iter().map(|p| &p.name).collect(),
+                    )
+                    .raw_prompt();
+
+                    use inquire::InquireError::*;
+                    let selected_project = match selected_project {
+                        Ok(t) => t,
+                        Err(OperationCanceled | OperationInterrupted) => return ExitCode::SUCCESS,
+                        Err(err) => panic!("Failed to inquire project: {err}"),
+                    };
+
+                    let index = selected_project.index;
+                    possible_projects[index].clone()
+                }
+            };
+
+            let task = match task_name {
+                Some(name) => {
+                    match find_or_create_task(&mut database, &project, &name, create, prompt) {
+                        Some(task) => Some(task),
+                        None => return ExitCode::FAILURE,
+                    }
+                }
+                None => None,
+            };
+
+            let request = Request::Start {
+                project_id: project.id(),
+                task_id: task.as_ref().map(Task::id),
+                external_ref: external_ref.clone(),
+                source: Some(FrameSource::Cli),
+            };
+            match instance::forward(database.path(), &request) {
+                Some(Ok(tracking)) => {
+                    if let Some(stopped) = tracking.stopped {
+                        let stopped = database
+                            .frame_details(stopped.id())
+                            .expect("Database is broken");
+                        print_stopped(&stopped, config.duration_format);
+                    }
+                }
+                Some(Err(err)) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+                None => {
+                    let external_ref = external_ref.as_deref();
+                    if let Err(err) = stop_current_frame(&mut database, config.duration_format)
+                        .and_then(|_| match &task {
+                            Some(task) => database.start_task(
+                                &mut project,
+                                task,
+                                external_ref,
+                                FrameSource::Cli,
+                            ),
+                            None => database.start(&mut project, external_ref, FrameSource::Cli),
+                        })
+                    {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            match &task {
+                Some(task) => println!("Started task {}/{}", project.name, task.name),
+                None => println!("Started project {}", project.name),
+            }
+            print_today(&mut database, config.duration_format);
+            check_default_tags(&mut database, &project);
+            check_budget(&mut database, &project, config);
+            #[cfg(feature = "mqtt")]
+            warn_on_error(announce(&mut database, config));
+            #[cfg(feature = "slack")]
+            if let Some(slack) = &config.slack {
+                warn_on_error(crate::slack::set_status(slack, Some(&project.name)));
+            }
+        }
+        Action::Stop { project, all, snap } => {
+            let mut running = database.current_frames().expect("Database is broken");
+            if let Some(name) = &project {
+                let Some(project) = database
+                    .lookup_project_by_name(name)
+                    .expect("Database is broken")
+                else {
+                    eprintln!("Project {name} does not exist");
+                    return ExitCode::FAILURE;
+                };
+                running.retain(|frame| frame.project == project.id());
+                if running.is_empty() {
+                    eprintln!("Project {name} is not being tracked");
+                    return ExitCode::FAILURE;
+                }
+            } else if running.len() > 1 && !all {
+                eprintln!(
+                    "{} frames are running, name the project to stop or use --all",
+                    running.len()
+                );
+                return ExitCode::FAILURE;
+            }
+
+            let mut stopped_something = false;
+            for frame in running {
+                let request = Request::StopFrame {
+                    frame_id: frame.id(),
+                    snap_minutes: snap,
+                };
+                let stopped = match instance::forward(database.path(), &request) {
+                    Some(Ok(tracking)) => Ok(tracking.stopped),
+                    Some(Err(err)) => Err(err),
+                    None => database
+                        .stop_frame_snapped(frame.id(), snap)
+                        .map(Some)
+                        .map_err(|err| err.to_string()),
+                };
+                match stopped {
+                    Ok(Some(stopped)) => {
+                        let stopped = database
+                            .frame_details(stopped.id())
+                            .expect("Database is broken");
+                        print_stopped(&stopped, config.duration_format);
+                        stopped_something = true;
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+
+            if stopped_something {
+                print_today(&mut database, config.duration_format);
+            } else {
+                println!("Nothing to do!");
+            }
+            #[cfg(feature = "mqtt")]
+            if stopped_something {
+                warn_on_error(announce(&mut database, config));
+            }
+            #[cfg(feature = "slack")]
+            if let (true, Some(slack)) = (stopped_something, &config.slack) {
+                warn_on_error(crate::slack::set_status(slack, None));
+            }
+        }
+        Action::Break(action) => return take_break(&mut database, action, config),
+        Action::NewProject {
+            name,
+            tags,
+            options,
+        } => {
+            let (name, tags) = match name {
+                Some(name) => (name, tags),
+                None => match new_project_inquire(&mut database, prompt) {
+                    Some(answers) => answers,
+                    None => return ExitCode::FAILURE,
+                },
+            };
+            return new_project(&mut database, &name, &tags, &options);
+        }
+        Action::Analyze(options) => {
+            let span = if options.is_interactive() {
+                if let Err(code) = check_interactive(prompt, "Enter start date") {
+                    return code;
+                }
+                do_inquire_stuff().unwrap()
+            } else if !options.timespan.is_empty() {
+                match parse_timespan(&options.timespan, config) {
+                    Ok(span) => span,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else {
+                // todo: handle commandline options in detail, assuming "since_yesterday" for now
+                let end = Timestamp::now();
+                let start = Timestamp(end.0 - chrono::Duration::days(1));
+                TimeSpan::new(start, end).expect("Math broke, yesterday ended up after today ")
+            };
+
+            if options.show_gaps {
+                show_gaps(&mut database, span, &options, config.duration_format);
+            } else {
+                list_frames(
+                    &mut database,
+                    span,
+                    options.filter.as_ref(),
+                    !options.no_header,
+                    config.duration_format,
+                );
+            }
+        }
+        Action::Add {
+            project,
+            start,
+            end,
+        } => {
+            let span = match TimeSpan::new(start, end) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            if let Err(err) = add_frame(&mut database, &mut project, span, config.duration_format) {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        Action::NewTag { name } => {
+            database.create_tag(&name).expect("Error creating tag");
+            println!("Created tag {name}");
+        }
+        Action::NewClient { name, currency } => {
+            if database
+                .lookup_client_by_name(&name)
+                .expect("Database is broken")
+                .is_some()
+            {
+                eprintln!("Client {name} already exists");
+                return ExitCode::FAILURE;
+            }
+            database
+                .create_client(&name, currency.as_deref())
+                .expect("Error creating client");
+            println!("Created client {name}");
+        }
+        Action::Tag {
+            project,
+            tags,
+            unarchive,
+        } => match (project, AsRef::<[String]>::as_ref(&tags)) {
+            (None, []) => {
+                if let Err(code) = check_interactive(prompt, "Select the projects to tag") {
+                    return code;
+                }
+                tag_inquire(&mut database)
+            }
+            (Some(project), []) => {
+                if let Err(code) = check_interactive(prompt, TAG_QUESTION) {
+                    return code;
+                }
+                return tag_project_inquire(&mut database, &project, unarchive, prompt);
+            }
+            (Some(project), tags) => {
+                return tag_projects(&mut database, &project, tags, unarchive, prompt);
+            }
+            (None, _) => unreachable!(),
+        },
+        Action::Annotate {
+            tags,
+            project,
+            message,
+        } => return annotate(&mut database, &tags, project, message.as_deref()),
+        Action::IsTracking { project } => {
+            let project_id = match project {
+                Some(name) => match database
+                    .lookup_project_by_name(&name)
+                    .expect("Database is broken")
+                {
+                    Some(project) => Some(project.id()),
+                    None => {
+                        eprintln!("Project {name} does not exist");
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => None,
+            };
+            let running = database.current_frames().expect("Database is broken");
+            if running
+                .iter()
+                .any(|frame| project_id.is_none_or(|id| frame.project == id))
+            {
+                return ExitCode::SUCCESS;
+            }
+            return ExitCode::from(EXIT_IDLE);
+        }
+        Action::Current {
+            quiet,
+            elapsed_seconds,
+        } => {
+            let running = match database.current_frames() {
+                Ok(running) => running,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let Some(current) = running.last() else {
+                if !quiet && !elapsed_seconds {
+                    println!("Not tracking anything");
+                }
+                return ExitCode::from(EXIT_IDLE);
+            };
+            if quiet {
+                return ExitCode::SUCCESS;
+            }
+            if elapsed_seconds {
+                println!("{}", current.start.elapsed().num_seconds());
+                return ExitCode::SUCCESS;
+            }
+            // Several with concurrent frames, see `Config::concurrent_frames`.
+            for frame in &running {
+                let project = database
+                    .lookup_project(frame.project)
+                    .expect("Database is broken")
+                    .unwrap_or_else(|| panic!("Found no project for id {}", frame.id()));
+
+                let task = &project.name;
+                let kind = match frame.kind {
+                    FrameKind::Work => "",
+                    FrameKind::Break => " (break)",
+                    FrameKind::OnCall => " (on call)",
+                };
+                println!(
+                    "{}: {}{kind}",
+                    task,
+                    frame.start.elapsed().format_as(config.duration_format)
+                );
+                check_budget(&mut database, &project, config);
+            }
+        }
+        Action::List {
+            no_header,
+            action:
+                ListAction::Frames {
+                    timespan,
+                    project,
+                    tag,
+                    limit,
+                    json,
+                    filter,
+                    verbose,
+                },
+        } => {
+            let span = if timespan.is_empty() {
+                None
+            } else {
+                match parse_timespan(&timespan, config) {
+                    Ok(span) => Some(span),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            };
+            let selected = |p: &Project, tags: &HashMap<i32, Vec<Tag>>| {
+                project.as_ref().is_none_or(|name| &p.name == name)
+                    && tag
+                        .as_ref()
+                        .is_none_or(|name| tags[&p.id()].iter().any(|t| &t.name == name))
+            };
+
+            // Without a limit, JSON is written while the frames are read, so exports of huge
+            // histories need little memory.
+            if let (true, None, Some(span), None) = (json, limit, span, &filter) {
+                let projects = database
+                    .all_projects(ArchivedState::Both)
+                    .expect("Database is broken");
+                let tags = tags_per_project(&mut database, &projects);
+                let frames = database
+                    .frames_in_span_iter(span, ArchivedState::Both)
+                    .filter(|frame| frame.as_ref().map_or(true, |(p, _)| selected(p, &tags)));
+                if let Err(err) = print_json_frames(frames) {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+                return ExitCode::SUCCESS;
+            }
+
+            let frames = if let Some(filter) = &filter {
+                database
+                    .filtered_frames(span, filter)
+                    .expect("Database is broken")
+            } else if let Some(span) = span {
+                database
+                    .get_frames_in_span(span, ArchivedState::Both)
+                    .expect("Database is broken")
+            } else {
+                all_frames(&mut database)
+            };
+            let tags = tags_per_project(&mut database, frames.iter().map(|(p, _)| p));
+            let mut frames: Vec<_> = frames
+                .into_iter()
+                .filter(|(p, _)| selected(p, &tags))
+                .collect();
+            if let Some(limit) = limit {
+                frames.drain(..frames.len().saturating_sub(limit));
+            }
+            if json {
+                print_json_frames(frames.into_iter().map(Ok)).expect("Frames are serializable");
+            } else {
+                print_frames(&frames, !no_header, verbose, config.duration_format);
+            }
+        }
+        Action::List { no_header, action } => {
+            list(&mut database, action, !no_header).expect("Database is broken")
+        }
+        Action::Report(options) => {
+            let span = match options.span(config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let client = match &options.client {
+                Some(name) => match database
+                    .lookup_client_by_name(name)
+                    .expect("Database is broken")
+                {
+                    Some(client) => Some(client.id()),
+                    None => {
+                        eprintln!("{}", crate::error::Error::ClientNotFound(name.clone()));
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => None,
+            };
+            let of_client = |project: &Project| client.is_none() || project.client_id == client;
+            // Only templates, tickets, tasks, billing, filters, overlapping frames and spans that
+            // start or end within a day need the frames, everything else is summed up from the
+            // cached daily totals, which is much faster for long spans.
+            let (mut report, frames) = if span.is_whole_days()
+                && options.template.is_none()
+                && !options.billing
+                && options.filter.is_none()
+                && !matches!(options.by, Grouping::Ref | Grouping::Task)
+                && !database.allows_concurrent_frames()
+            {
+                let mut totals = database.daily_totals(span).expect("Database is broken");
+                totals.retain(|total| of_client(&total.project));
+                let names = group_names(
+                    &mut database,
+                    options.by,
+                    totals.iter().map(|total| &total.project),
+                );
+                let breaks = database.breaks_in_span(span).expect("Database is broken");
+                let report =
+                    Report::from_totals(span, &totals, options.by, options.attribution, &names)
+                        .with_breaks(&breaks);
+                (report, Vec::new())
+            } else {
+                let mut frames = frames_in_span(&mut database, span, options.filter.as_ref());
+                frames.retain(|(project, _)| of_client(project));
+                let names = group_names(&mut database, options.by, frames.iter().map(|(p, _)| p));
+                let report = Report::new(span, &frames, options.by, options.attribution, &names);
+                (report, frames)
+            };
+            if let Some(schedule) = &config.schedule {
+                let leave = database.leave_days(span).expect("Database is broken");
+                let budgets = database.budgets().expect("Database is broken");
+                let today = Local::now().date_naive();
+                report = report.with_schedule(schedule, &leave, today).with_budgets(
+                    &budgets,
+                    today,
+                    config.week_start.unwrap_or(Weekday::Mon),
+                );
+            }
+            if options.billing {
+                let rates = database.rates().expect("Database is broken");
+                let currencies = database
+                    .currencies(config.currency.as_deref())
+                    .expect("Database is broken");
+                report = report.with_billing(&frames, &rates, &currencies, &config.multipliers);
+            }
+            let text = if let Some(template) = &options.template {
+                let rendered = std::fs::read_to_string(template)
+                    .map_err(crate::error::Error::from)
+                    .and_then(|template| {
+                        crate::template::render(&template, &report, &frames, config.duration_format)
+                    });
+                match rendered {
+                    Ok(text) => text,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else {
+                match options.format() {
+                    ReportFormat::Text => report.to_text(config.duration_format),
+                    ReportFormat::Markdown => report.to_markdown(config.duration_format),
+                    ReportFormat::Email => {
+                        report.to_email(options.to.as_deref(), config.duration_format)
+                    }
+                    ReportFormat::Json => report.to_json(),
+                    ReportFormat::Html => report.to_html(config.duration_format),
+                }
+            };
+            match &options.output {
+                Some(path) => {
+                    if let Err(err) = std::fs::write(path, text) {
+                        eprintln!("Failed to write {}: {err}", path.display());
+                        return ExitCode::FAILURE;
+                    }
+                }
+                None => print!("{text}"),
+            }
+        }
+        Action::Balance => {
+            let Some(schedule) = &config.schedule else {
+                eprintln!(
+                    "No working hours configured, add a [schedule] to {}",
+                    Config::path().display()
+                );
+                return ExitCode::FAILURE;
+            };
+            show_balance(&mut database, schedule, config.duration_format);
+        }
+        Action::Stats { timespan } => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let frames = database
+                .get_frames_in_span(span, ArchivedState::Both)
+                .expect("Database is broken");
+            let tags = tags_per_project(&mut database, frames.iter().map(|(p, _)| p));
+            let leave = database.leave_days(span).expect("Database is broken");
+            print!(
+                "{}",
+                Statistics::new(span, &frames, &tags, &leave).to_text(config.duration_format)
+            );
+        }
+        Action::Compare {
+            current,
+            previous,
+            by,
+            json,
+        } => {
+            let mut reports = Vec::new();
+            for timespan in [current, previous] {
+                let span = match parse_timespan(&[timespan], config) {
+                    Ok(span) => span,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                };
+                let frames = frames_in_span(&mut database, span, None);
+                let names = group_names(&mut database, by, frames.iter().map(|(p, _)| p));
+                reports.push(Report::new(span, &frames, by, Attribution::Full, &names));
+            }
+            let comparison = Comparison::new(&reports[0], &reports[1]);
+            if json {
+                print!("{}", comparison.to_json());
+            } else {
+                comparison.to_table(config.duration_format).print(true);
+            }
+        }
+        Action::Trend {
+            window,
+            last,
+            projects,
+            format,
+        } => {
+            let mut names = Vec::new();
+            for name in projects {
+                match database
+                    .lookup_project_by_name(&name)
+                    .expect("Database is broken")
+                {
+                    Some(project) => names.push(project.name),
+                    None => {
+                        eprintln!("Project {name} does not exist");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            let week_start = config.week_start.unwrap_or(chrono::Weekday::Mon);
+            let windows = window.last(last as usize, Local::now().date_naive(), week_start);
+            let span = TimeSpan::new(windows[0].start(), windows[windows.len() - 1].end())
+                .expect("Windows end after they start");
+            let totals = database.daily_totals(span).expect("Database is broken");
+            let trend = Trend::new(windows, &totals, names);
+            match format {
+                TableFormat::Table => {
+                    let columns = trend.header().into_iter().enumerate().map(|(index, name)| {
+                        (
+                            name,
+                            if index == 0 {
+                                Align::Left
+                            } else {
+                                Align::Right
+                            },
+                        )
+                    });
+                    let mut table = Table::with_columns(columns.collect());
+                    for row in trend.rows(Some(config.duration_format)) {
+                        table.add_row(row);
+                    }
+                    table.print(true);
+                }
+                TableFormat::Csv => {
+                    println!("{}", csv_line(trend.header().into_iter()));
+                    for row in trend.rows(None) {
+                        println!("{}", csv_line(row.into_iter()));
+                    }
+                }
+                TableFormat::Json => print!("{}", trend.to_json()),
+            }
+        }
+        Action::Project(ProjectAction::SetDefaultTags { project, tags }) => {
+            let Some(project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let mut defaults = Vec::new();
+            for name in &tags {
+                let Some(tag) = database
+                    .lookup_tag_by_name(name)
+                    .expect("Database is broken")
+                else {
+                    eprintln!("Tag {name} does not exist");
+                    return ExitCode::FAILURE;
+                };
+                defaults.push(tag);
+            }
+            database
+                .set_default_tags(&project, &defaults)
+                .expect("Database is broken");
+            if tags.is_empty() {
+                println!("Cleared the default tags of {}", project.name);
+            } else {
+                println!("Default tags of {}: {}", project.name, tags.join(", "));
+            }
+        }
+        Action::Project(ProjectAction::SetEstimate { project, hours }) => {
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let minutes = match hours {
+                Some(hours) if !(0.0..=f64::from(i32::MAX) / 60.0).contains(&hours) => {
+                    eprintln!("Invalid estimate: {hours} hours");
+                    return ExitCode::FAILURE;
+                }
+                Some(hours) => Some((hours * 60.0).round() as i32),
+                None => None,
+            };
+            database
+                .set_estimate(&mut project, minutes)
+                .expect("Database is broken");
+            match project.estimate() {
+                Some(estimate) => println!(
+                    "Estimated {} for {}",
+                    estimate.format_as(config.duration_format),
+                    project.name
+                ),
+                None => println!("Cleared the estimate of {}", project.name),
+            }
+        }
+        Action::Project(ProjectAction::SetRate {
+            project,
+            amount,
+            from,
+        }) => {
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let currency = database
+                .currencies(config.currency.as_deref())
+                .expect("Database is broken")
+                .remove(&project.id());
+            let money = |cents: i32| Money::new(cents.into(), currency.as_deref());
+            match (from, amount) {
+                (Some(day), Some(cents)) => {
+                    database
+                        .add_rate(&project, day, cents)
+                        .expect("Database is broken");
+                    println!(
+                        "{} is billed {} per hour from {day} on",
+                        project.name,
+                        money(cents)
+                    );
+                }
+                (_, amount) => {
+                    database
+                        .set_hourly_rate(&mut project, amount)
+                        .expect("Database is broken");
+                    match amount {
+                        Some(cents) => {
+                            println!("{} is billed {} per hour", project.name, money(cents))
+                        }
+                        None => println!("Cleared the rate of {}", project.name),
+                    }
+                }
+            }
+        }
+        Action::Project(ProjectAction::Rates { project }) => {
+            let Some(project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let rates = database
+                .rates()
+                .expect("Database is broken")
+                .remove(&project.id())
+                .unwrap_or_default();
+            let currency = database
+                .currencies(config.currency.as_deref())
+                .expect("Database is broken")
+                .remove(&project.id());
+            let money = |cents: i32| Money::new(cents.into(), currency.as_deref()).to_string();
+            let mut table = Table::new(&[("From", Align::Left), ("Rate", Align::Right)]);
+            if let Some(cents) = project.hourly_rate_cents {
+                table.add_row(vec!["-".to_owned(), money(cents)]);
+            }
+            for rate in rates {
+                table.add_row(vec![rate.effective_from.to_string(), money(rate.cents)]);
+            }
+            table.print(true);
+        }
+        Action::Project(ProjectAction::SetCurrency { project, currency }) => {
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            database
+                .set_currency(&mut project, currency.as_deref())
+                .expect("Database is broken");
+            match database
+                .currencies(config.currency.as_deref())
+                .expect("Database is broken")
+                .remove(&project.id())
+            {
+                Some(currency) => println!("{} is billed in {currency}", project.name),
+                None => println!("{} has no currency", project.name),
+            }
+        }
+        Action::Project(ProjectAction::SetClient { project, client }) => {
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let client = match client {
+                Some(name) => match database
+                    .lookup_client_by_name(&name)
+                    .expect("Database is broken")
+                {
+                    Some(client) => Some(client),
+                    None => {
+                        eprintln!("{}", crate::error::Error::ClientNotFound(name));
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => None,
+            };
+            database
+                .set_client(&mut project, client.as_ref())
+                .expect("Database is broken");
+            match client {
+                Some(client) => println!("{} is done for {}", project.name, client.name),
+                None => println!("{} has no client anymore", project.name),
+            }
+        }
+        Action::Project(ProjectAction::Estimates) => {
+            let projects = database
+                .all_projects(ArchivedState::Both)
+                .expect("Database is broken");
+            let frames = database
+                .all_frames(ArchivedState::Both)
+                .expect("Database is broken");
+            for estimate in Estimate::compare(&projects, &frames) {
+                let deviation = estimate
+                    .deviation()
+                    .map_or_else(String::new, |deviation| format!(" ({deviation:+}%)"));
+                println!(
+                    "{}: {} of {} estimated{deviation}",
+                    estimate.project,
+                    estimate.tracked.format_as(config.duration_format),
+                    estimate.estimate.format_as(config.duration_format)
+                );
+            }
+        }
+        Action::Project(ProjectAction::Rename { project, name }) => {
+            let Some(mut project) = database
+                .lookup_project_by_name(&project)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {project} does not exist");
+                return ExitCode::FAILURE;
+            };
+            let old_name = project.name.clone();
+            if let Err(err) = database.rename_project(&mut project, &name) {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+            println!("Renamed project {old_name} to {name}");
+        }
+        Action::Project(ProjectAction::Merge { from, into }) => {
+            let mut lookup = |name: &str| {
+                let project = database
+                    .lookup_project_by_name(name)
+                    .expect("Database is broken");
+                if project.is_none() {
+                    eprintln!("Project {name} does not exist");
+                }
+                project
+            };
+            let (Some(from), Some(into)) = (lookup(&from), lookup(&into)) else {
+                return ExitCode::FAILURE;
+            };
+            let question = format!(
+                "Move all frames of {} to {} and delete {}?",
+                from.name, into.name, from.name
+            );
+            if let Err(code) = confirm(prompt, &question, true) {
+                return code;
+            }
+            if let Err(err) = database.merge_projects(&from, &into) {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+            println!("Merged project {} into {}", from.name, into.name);
+        }
+        Action::Project(ProjectAction::Prune { months }) => {
+            return prune_projects(&mut database, months, prompt)
+        }
+        Action::Delete(DeleteAction::Frame { id }) => {
+            let frame = match database.resolve_frame(&id) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let question = format!(
+                "Move frame {} to the trash?",
+                describe_frame(&mut database, &frame)
+            );
+            if let Err(code) = confirm(prompt, &question, true) {
+                return code;
+            }
+            match database.delete_frame(frame.id()) {
+                Ok(frame) => println!("Moved frame {} to the trash", frame.short_id()),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Action::Mark { id, kind } => {
+            let marked = database
+                .resolve_frame(&id)
+                .and_then(|frame| database.set_frame_kind(frame.id(), kind));
+            match marked {
+                Ok(frame) => println!("Marked frame {} as {kind}", frame.short_id()),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Action::Trash(action) => return trash(&mut database, action, prompt),
+        Action::Dedupe { timespan, gap } => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            return dedupe(&mut database, span, gap, config.duration_format, prompt);
+        }
+        Action::Review { discard_all } => {
+            return review(&mut database, discard_all, config.duration_format, prompt)
+        }
+        Action::Sql { query, format } => return sql(&mut database, &query, format),
+        Action::Api { stdin: _ } => {
+            let stdin = std::io::stdin().lock();
+            let stdout = std::io::stdout().lock();
+            if let Err(err) = crate::api::serve(&mut database, config, stdin, stdout) {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        Action::Db(action) => return db(&mut database, action),
+        Action::DebugBundle { output } => return debug_bundle(&mut database, &output),
+        #[cfg(feature = "self-update")]
+        Action::SelfUpdate { check } => return self_update(check),
+        Action::Dev(DevAction::Seed { days, seed }) => {
+            let seed =
+                seed.unwrap_or_else(|| Local::now().timestamp_nanos_opt().unwrap_or(1) as u64);
+            match crate::seed::seed(&mut database, days, seed) {
+                Ok(seeded) => println!(
+                    "Added {} projects and {} frames (seed {seed})",
+                    seeded.projects, seeded.frames
+                ),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Action::Task(action) => return task(&mut database, action),
+        Action::Budget {
+            project,
+            limit,
+            per,
+            clear,
+        } => return budget(&mut database, project, limit, per, clear, config),
+        Action::Client(ClientAction::SetCurrency { client, currency }) => {
+            let Some(mut client) = database
+                .lookup_client_by_name(&client)
+                .expect("Database is broken")
+            else {
+                eprintln!("{}", crate::error::Error::ClientNotFound(client));
+                return ExitCode::FAILURE;
+            };
+            database
+                .set_client_currency(&mut client, currency.as_deref())
+                .expect("Database is broken");
+            match client.currency.as_ref().or(config.currency.as_ref()) {
+                Some(currency) => println!("{} is billed in {currency}", client.name),
+                None => println!("{} has no currency", client.name),
+            }
+        }
+        Action::Leave(action) => return leave(&mut database, action, config, prompt),
+        Action::Rules(action) => return rules(&mut database, action),
+        Action::Lock { timespan } if timespan.is_empty() => {
+            for period in database.locked_periods().expect("Database is broken") {
+                println!(
+                    "{} (locked {})",
+                    crate::report::describe_days(&period.span()),
+                    period.locked_at.to_local().format(TIMESTAMP_FORMAT)
+                );
+            }
+        }
+        Action::Lock { timespan } => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            database.lock(span).expect("Database is broken");
+            println!("Locked {}", crate::report::describe_days(&span));
+        }
+        Action::Export(ExportAction::Json {
+            timespan,
+            anonymize,
+            output,
+        }) => {
+            let frames = if timespan.is_empty() {
+                all_frames(&mut database)
+            } else {
+                match parse_timespan(&timespan, config) {
+                    Ok(span) => frames_in_span(&mut database, span, None),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            };
+            let tags = tags_per_project(&mut database, frames.iter().map(|(p, _)| p));
+            let json = if anonymize {
+                let path = Pseudonyms::path();
+                let mut pseudonyms = match Pseudonyms::load(&path) {
+                    Ok(pseudonyms) => pseudonyms,
+                    Err(err) => {
+                        eprintln!("Failed to read {}: {err}", path.display());
+                        return ExitCode::FAILURE;
+                    }
+                };
+                let json = crate::export::to_json(&frames, &tags, Some(&mut pseudonyms));
+                if let Err(err) = pseudonyms.save(&path) {
+                    eprintln!("Failed to write {}: {err}", path.display());
+                    return ExitCode::FAILURE;
+                }
+                json
+            } else {
+                crate::export::to_json(&frames, &tags, None)
+            };
+            match output {
+                Some(output) => {
+                    if let Err(err) = std::fs::write(&output, json) {
+                        eprintln!("Failed to write {}: {err}", output.display());
+                        return ExitCode::FAILURE;
+                    }
+                    println!("Exported {} frames to {}", frames.len(), output.display());
+                }
+                None => print!("{json}"),
+            }
+        }
+        #[cfg(feature = "pdf")]
+        Action::Export(ExportAction::Pdf {
+            timespan,
+            output,
+            name,
+            client,
+            filter,
+        }) => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let frames = frames_in_span(&mut database, span, filter.as_ref());
+            let header = crate::timesheet::TimesheetConfig {
+                name: name.or_else(|| config.timesheet.name.clone()),
+                client: client.or_else(|| config.timesheet.client.clone()),
+            };
+            let leave = database.leave_days(span).expect("Database is broken");
+            let pdf = crate::timesheet::Timesheet::new(span, &frames, &leave)
+                .to_pdf(&header, config.duration_format);
+            if let Err(err) = std::fs::write(&output, pdf) {
+                eprintln!("Failed to write {}: {err}", output.display());
+                return ExitCode::FAILURE;
+            }
+            println!("Wrote the timesheet to {}", output.display());
+        }
+        Action::Import(ImportAction::Activitywatch {
+            file,
+            timespan,
+            bucket,
+            rules,
+            on_duplicate,
+            draft,
+        }) => {
+            let span = if timespan.is_empty() {
+                None
+            } else {
+                match parse_timespan(&timespan, config) {
+                    Ok(span) => Some(span),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            };
+            let suggestions = std::fs::read_to_string(&file)
+                .map_err(crate::error::Error::from)
+                .and_then(|text| crate::activitywatch::Export::parse(&text))
+                .and_then(|export| {
+                    let rules =
+                        crate::activitywatch::Rules::parse(&std::fs::read_to_string(&rules)?)?;
+                    crate::activitywatch::suggest(&export, &bucket, &rules, span)
+                });
+            match suggestions {
+                Ok(suggestions) => {
+                    return import_suggestions(
+                        &mut database,
+                        suggestions,
+                        on_duplicate,
+                        draft,
+                        config.duration_format,
+                        prompt,
+                    )
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Action::Import(ImportAction::Ics {
+            source,
+            timespan,
+            project,
+            email,
+        }) => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            return import_ics(&mut database, &source, span, &project, email.as_deref());
+        }
+        #[cfg(feature = "caldav")]
+        Action::Sync {
+            target: Some(SyncTarget::Caldav { timespan }),
+            ..
+        } => {
+            let Some(caldav) = &config.caldav else {
+                eprintln!("No [caldav] section in {}", Config::path().display());
+                return ExitCode::FAILURE;
+            };
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let frames = database
+                .get_frames_in_span(span, ArchivedState::Both)
+                .expect("Database is broken");
+            let trashed: Vec<_> = database
+                .trashed_frames()
+                .expect("Database is broken")
+                .into_iter()
+                .map(|(_, frame)| frame)
+                .filter(|frame| frame.start < span.end() && frame.end >= Some(span.start()))
+                .collect();
+            match crate::caldav::push(caldav, &frames, &trashed) {
+                Ok(summary) => println!(
+                    "Published {} and removed {} events",
+                    summary.pushed, summary.deleted
+                ),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        Action::Sync { dir, .. } => {
+            let dir = dir.expect("The directory is required by clap");
+            let summary = match crate::sync::sync(&mut database, &dir) {
+                Ok(summary) => summary,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            for frame in &summary.resolved {
+                println!(
+                    "Frame {} overlapped with a later frame and now ends at {}",
+                    frame.short_id(),
+                    frame.end.unwrap().0
+                );
+            }
+            println!(
+                "Imported {} and exported {} frames",
+                summary.imported, summary.exported
+            );
+        }
+        #[cfg(feature = "mqtt")]
+        Action::Mqtt => {
+            if config.mqtt.is_none() {
+                eprintln!("No [mqtt] section in {}", Config::path().display());
+                return ExitCode::FAILURE;
+            }
+            if let Err(err) = announce(&mut database, config) {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        #[cfg(feature = "slack")]
+        Action::Slack(action) => {
+            let result = match action {
+                SlackAction::Login => prompt
+                    .check_interactive("API token:")
+                    .map_err(|e| crate::error::Error::Slack(e.to_string()))
+                    .and_then(|_| {
+                        inquire::Password::new("API token:")
+                            .prompt()
+                            .map_err(|e| crate::error::Error::Slack(e.to_string()))
+                    })
+                    .and_then(|token| crate::slack::login(&token)),
+                SlackAction::Logout => crate::slack::logout(),
+            };
+            if let Err(err) = result {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+        #[cfg(feature = "sqlcipher")]
+        action @ (Action::Encrypt | Action::Decrypt) => {
+            let result = if matches!(action, Action::Encrypt) {
+                crate::encryption::new_passphrase().and_then(|p| database.encrypt(&p))
+            } else {
+                database.decrypt()
+            };
+            match result {
+                Ok(backup) => println!(
+                    "Converted the database, the old file was kept at {}",
+                    backup.display()
+                ),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Question of the [`main_menu`].
+const MENU_QUESTION: &str = "What do you want to do?";
+
+/// Guide through the setup of a fresh database: create the first project and choose the first
+/// day of the week. Returns false if the setup was cancelled.
+fn first_run(db: &mut Database, config: &Config, prompt: Prompt) -> bool {
+    println!("Welcome to ttt! There are no projects yet, let's create the first one.");
+    let Some((name, tags)) = new_project_inquire(db, prompt) else {
+        return false;
+    };
+    if new_project(db, &name, &tags, &NewProjectOptions::default()) != ExitCode::SUCCESS {
+        return false;
+    }
+
+    if config.week_start.is_none() {
+        let mut days = vec![Weekday::Mon];
+        while days.len() < 7 {
+            days.push(days[days.len() - 1].succ());
+        }
+        let names = days
+            .iter()
+            .map(|day| crate::stats::weekday_name(*day))
+            .collect();
+        // Escape keeps weeks starting on monday.
+        if let Ok(choice) = Select::new("First day of the week:", names).raw_prompt() {
+            match Config::save_week_start(days[choice.index]) {
+                Ok(()) => println!(
+                    "Saved the first day of the week to {}",
+                    Config::path().display()
+                ),
+                Err(err) => eprintln!("Failed to save the first day of the week: {err}"),
+            }
+        }
+    }
+
+    println!(
+        "Window events recorded by ActivityWatch can be added with `ttt import activitywatch`."
+    );
+    true
+}
+
+/// Show what is being tracked and let the user pick one of the common actions.
+fn main_menu(db: &mut Database, config: &Config) -> Option<Action> {
+    match db.current_frame() {
+        Ok(current) => {
+            let project = db
+                .lookup_project(current.project)
+                .expect("Database is broken")
+                .unwrap_or_else(|| panic!("Found no project for id {}", current.id()));
+            let elapsed = current.start.elapsed().format_as(config.duration_format);
+            match current.kind {
+                FrameKind::Break => println!("On a break from {} for {elapsed}", project.name),
+                _ => println!("Tracking {} for {elapsed}", project.name),
+            }
+        }
+        Err(crate::error::Error::NoActiveFrame) => println!("Not tracking anything"),
+        Err(err) => panic!("Database is broken: {err}"),
+    }
+
+    let choices = vec!["Start", "Stop", "Analyze", "Tag", "Quit"];
+    // Escape quits as well.
+    let choice = Select::new(MENU_QUESTION, choices).raw_prompt().ok()?;
+    match choice.value {
+        "Start" => Some(Action::Start {
+            name: None,
+            external_ref: None,
+            create: false,
+            unarchive: false,
+        }),
+        "Stop" => Some(Action::Stop {
+            project: None,
+            all: true,
+            snap: None,
+        }),
+        "Analyze" => Some(Action::Analyze(AnalyzeOptions::parse_from(["analyze"]))),
+        "Tag" => Some(Action::Tag {
+            project: None,
+            tags: Vec::new(),
+            unarchive: false,
+        }),
+        _ => None,
+    }
+}
+
+fn do_inquire_stuff() -> Result<TimeSpan, Box<dyn Error>> {
+    let begin = DateSelect::new("Enter start date");
+    let begin = begin.prompt()?;
+    let end = DateSelect::new("Enter end date").with_min_date(begin);
+    let end = end.prompt()?;
+
+    let precise_mode = Confirm::new("Do you want to enter start/end times?").prompt()?;
+
+    let (start_time, end_time) = if precise_mode {
+        let start_time: chrono::naive::NaiveTime = CustomType::new("Enter start time").prompt()?;
+        let end_time: chrono::naive::NaiveTime = CustomType::new("Enter end time")
+            .with_parser(&|text| {
+                let time = text.parse().map_err(|_| ())?;
+                if end == begin && time < start_time {
+                    return Err(());
+                }
+                Ok(time)
+            })
+            .with_error_message(&format!("Enter a valid time that's after {start_time}!"))
+            .prompt()?;
+        (start_time, end_time)
+    } else {
+        use chrono::NaiveTime;
+        (
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
+        )
+    };
+
+    let begin = Timestamp::from_naive(begin.and_time(start_time));
+    let end = Timestamp::from_naive(end.and_time(end_time));
+    Ok(TimeSpan::new(begin, end)?)
+}
+
+/// Parse a time span like "last week" relative to now.
+fn parse_timespan(words: &[impl AsRef<str>], config: &Config) -> Result<TimeSpan, Diagnostic> {
+    let context = timespan_parser::Context {
+        now: Timestamp::now(),
+        locales: config.locales.clone(),
+        date_order: config.date_order,
+        timezone: chrono::Local,
+        week_start: config.week_start.unwrap_or(chrono::Weekday::Mon),
+    };
+    timespan_parser::parse(words, &context).map_err(|err| err.annotate(words))
+}
+
+fn prune_projects(db: &mut Database, months: u32, prompt: Prompt) -> ExitCode {
+    let cutoff = Timestamp::now() - chrono::Months::new(months);
+    let stale = db.stale_projects(cutoff).expect("Database is broken");
+    if stale.is_empty() {
+        println!("No projects were unused for {months} months");
+        return ExitCode::SUCCESS;
+    }
+    let question = "Select the projects to archive";
+    if let Err(code) = check_interactive(prompt, question) {
+        return code;
+    }
+
+    let descriptions: Vec<_> = stale
+        .iter()
+        .map(|p| {
+            format!(
+                "{} (last used {})",
+                p.name,
+                p.last_access_time.0.date_naive()
+            )
+        })
+        .collect();
+    let all: Vec<_> = (0..stale.len()).collect();
+    let Ok(selected) = MultiSelect::new(question, descriptions)
+        .with_default(&all)
+        .raw_prompt()
+    else {
+        return ExitCode::SUCCESS;
+    };
+
+    let mut selected: Vec<_> = selected
+        .into_iter()
+        .map(|item| stale[item.index].clone())
+        .collect();
+    db.archive_projects(&mut selected)
+        .expect("Database is broken");
+    println!("Archived {} projects", selected.len());
+    ExitCode::SUCCESS
+}
+
+/// Report a failed side effect without failing the command itself.
+#[cfg(any(feature = "mqtt", feature = "slack"))]
+fn warn_on_error(result: crate::error::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("Warning: {err}");
+    }
+}
+
+/// Publish the tracking state if MQTT is configured.
+#[cfg(feature = "mqtt")]
+fn announce(db: &mut Database, config: &Config) -> crate::error::Result<()> {
+    let Some(mqtt) = &config.mqtt else {
+        return Ok(());
+    };
+    let current = match db.current_frame() {
+        Ok(frame) => {
+            let project = db
+                .lookup_project(frame.project)?
+                .unwrap_or_else(|| panic!("Found no project for id {}", frame.id()));
+            Some((project, frame))
+        }
+        Err(crate::error::Error::NoActiveFrame) => None,
+        Err(err) => return Err(err),
+    };
+    crate::mqtt::publish(db, mqtt, current.as_ref().map(|(p, f)| (p, f)))
+}
+
+/// Look up the tags of all `projects`, by project id.
+fn tags_per_project<'a>(
+    db: &mut Database,
+    projects: impl IntoIterator<Item = &'a Project>,
+) -> HashMap<i32, Vec<Tag>> {
+    let mut tags = HashMap::new();
+    for project in projects {
+        tags.entry(project.id()).or_insert_with(|| {
+            db.lookup_tags_for_project(project.id())
+                .expect("Database is broken")
+        });
+    }
+    tags
+}
+
+/// Look up the names `by` needs for the groups of `projects`, see [`GroupNames`].
+fn group_names<'a>(
+    db: &mut Database,
+    by: Grouping,
+    projects: impl IntoIterator<Item = &'a Project>,
+) -> GroupNames {
+    match by {
+        Grouping::Tag => GroupNames {
+            tags: tags_per_project(db, projects),
+            ..Default::default()
+        },
+        Grouping::Task => GroupNames {
+            tasks: db
+                .tasks(None)
+                .expect("Database is broken")
+                .into_iter()
+                .map(|task| (task.id(), task))
+                .collect(),
+            ..Default::default()
+        },
+        Grouping::Client => GroupNames {
+            clients: db
+                .clients()
+                .expect("Database is broken")
+                .into_iter()
+                .map(|client| (client.id(), client))
+                .collect(),
+            ..Default::default()
+        },
+        Grouping::Project | Grouping::Ref => GroupNames::default(),
+    }
+}
+
+/// Warn when most or all of the budget of `project` is used, see [`budget`].
+fn check_budget(db: &mut Database, project: &Project, config: &Config) {
+    let currencies = db
+        .currencies(config.currency.as_deref())
+        .expect("Database is broken");
+    let currency = currencies.get(&project.id()).map(String::as_str);
+    let week_start = config.week_start.unwrap_or(Weekday::Mon);
+    let used = budget::check(db, project, currency, week_start).expect("Database is broken");
+    if let Some(warning) = used.and_then(|used| used.warning(config.duration_format)) {
+        eprintln!("{warning}");
+    }
+}
+
+/// Print the time tracked today per project, e.g. "Total tracked today: 5h 12min (website 3h,
+/// client-x 2h 12min)". Uses the cached daily totals, so it stays cheap to do on every start and
+/// stop.
+fn print_today(db: &mut Database, durations: DurationFormat) {
+    let today = Local::now().date_naive();
+    let span = TimeSpan::new(
+        Timestamp::from_naive(today.and_time(NaiveTime::MIN)),
+        Timestamp::from_naive(today.and_time(NaiveTime::MIN) + chrono::Duration::days(1)),
+    )
+    .expect("A day is not empty");
+    let mut totals = db.daily_totals(span).expect("Database is broken");
+    if totals.is_empty() {
+        return;
+    }
+    totals.sort_by_key(|total| std::cmp::Reverse(total.duration));
+    let total: chrono::Duration = totals.iter().map(|total| total.duration).sum();
+    let projects: Vec<_> = totals
+        .iter()
+        .map(|total| {
+            format!(
+                "{} {}",
+                total.project.name,
+                total.duration.format_as(durations)
+            )
+        })
+        .collect();
+    println!(
+        "Total tracked today: {} ({})",
+        total.format_as(durations),
+        projects.join(", ")
+    );
+}
+
+/// Warn about default tags of `project` that can no longer be used.
+fn check_default_tags(db: &mut Database, project: &Project) {
+    let defaults = db.default_tags(project.id()).expect("Database is broken");
+    for tag in defaults.iter().filter(|tag| tag.archived) {
+        eprintln!(
+            "Warning: default tag {} of {} is archived",
+            tag.name, project.name
+        );
+    }
+}
+
+/// Stop the current frame before starting another one, see [`Database::stop_before_start`].
+fn stop_current_frame(
+    db: &mut Database,
+    durations: DurationFormat,
+) -> crate::error::Result<Option<Frame>> {
+    if db.allows_concurrent_frames() {
+        return Ok(None);
+    }
+    let Some(stopped) = db.stop_detailed()? else {
+        return Ok(None);
+    };
+    print_stopped(&stopped, durations);
+    Ok(Some(stopped.frame))
+}
+
+/// Print a summary like "Stopped website (+billable) after 1h 23min: fixed login bug".
+fn print_stopped(stopped: &FrameDetails, durations: DurationFormat) {
+    let FrameDetails {
+        frame,
+        project,
+        tags,
+    } = stopped;
+    let mut summary = format!("Stopped {}", project.name);
+    if !tags.is_empty() {
+        let tags: Vec<_> = tags.iter().map(|tag| format!("+{}", tag.name)).collect();
+        summary += &format!(" ({})", tags.join(" "));
+    }
+    summary += &format!(" after {}", frame.duration().format_as(durations));
+    if let Some(note) = &frame.note {
+        summary += &format!(": {note}");
+    }
+    println!("{summary}");
+}
+
+fn list_frames(
+    db: &mut Database,
+    span: TimeSpan,
+    filter: Option<&Filter>,
+    header: bool,
+    durations: DurationFormat,
+) {
+    let data = frames_in_span(db, span, filter);
+    print_frames(&data, header, false, durations);
+}
+
+/// Every frame with its project.
+fn all_frames(db: &mut Database) -> Vec<(Project, Frame)> {
+    let projects: HashMap<_, _> = db
+        .all_projects(ArchivedState::Both)
+        .expect("Database is broken")
+        .into_iter()
+        .map(|p| (p.id(), p))
+        .collect();
+    db.all_frames(ArchivedState::Both)
+        .expect("Database is broken")
+        .into_iter()
+        .map(|frame| (projects[&frame.project].clone(), frame))
+        .collect()
+}
+
+/// The frames overlapping `span`, only those matching `filter` if given.
+fn frames_in_span(
+    db: &mut Database,
+    span: TimeSpan,
+    filter: Option<&Filter>,
+) -> Vec<(Project, Frame)> {
+    match filter {
+        Some(filter) => db.filtered_frames(Some(span), filter),
+        None => db.get_frames_in_span(span, ArchivedState::Both),
+    }
+    .expect("Database is broken")
+}
+
+fn show_gaps(
+    db: &mut Database,
+    span: TimeSpan,
+    options: &AnalyzeOptions,
+    durations: DurationFormat,
+) {
+    let frames = db
+        .get_frames_in_span(span, ArchivedState::Both)
+        .expect("Database is broken");
+    let hours = WorkingHours {
+        start: options.work_start,
+        end: options.work_end,
+    };
+    let min_length = chrono::Duration::minutes(options.min_gap.into());
+    let days_off: Vec<_> = db
+        .leave_days(span)
+        .expect("Database is broken")
+        .into_iter()
+        .map(|leave| leave.day)
+        .collect();
+    let gaps = find_gaps(
+        span,
+        &frames,
+        hours,
+        &days_off,
+        min_length,
+        Timestamp::now(),
+    );
+
+    if gaps.is_empty() {
+        println!("No untracked time within working hours");
+        return;
+    }
+    let descriptions: Vec<_> = gaps
+        .iter()
+        .map(|gap| {
+            format!(
+                "{} -> {} ({})",
+                gap.start().0.format(TIMESTAMP_FORMAT),
+                gap.end().0.format("%H:%M"),
+                (gap.end().0 - gap.start().0).format_as(durations)
+            )
+        })
+        .collect();
+
+    if !options.is_interactive() {
+        for description in &descriptions {
+            println!("{description}");
+        }
+        println!("Fill a gap with `ttt add <project> --start <time> --end <time>`");
+        return;
+    }
+
+    // Escape skips filling a gap.
+    let Ok(selected) = Select::new("Select a gap to fill", descriptions).raw_prompt() else {
+        return;
+    };
+    let projects = db
+        .all_projects(ArchivedState::NotArchived)
+        .expect("Database is broken");
+    let Ok(project) = Select::new(
+        "Select the project to book the gap on",
+        projects.iter().map(|p| &p.name).collect(),
+    )
+    .raw_prompt() else {
+        return;
+    };
+    let mut project = projects[project.index].clone();
+    if let Err(err) = add_frame(db, &mut project, gaps[selected.index], durations) {
+        eprintln!("{err}");
+    }
+}
+
+fn add_frame(
+    db: &mut Database,
+    project: &mut Project,
+    span: TimeSpan,
+    durations: DurationFormat,
+) -> crate::error::Result<()> {
+    let frame = db.add_frame(project, span, FrameSource::Cli)?;
+    println!(
+        "Added {} to {} as frame {}",
+        (span.end().0 - span.start().0).format_as(durations),
+        project.name,
+        frame.short_id()
+    );
+    Ok(())
+}
+
+/// Exit code of `ttt current` when no frame is running, and of `ttt is-tracking` when the
+/// project is not tracked.
+const EXIT_IDLE: u8 = 3;
+
+/// Format in which timestamps are printed and accepted on the command line.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Parse a local time like "2024-03-04 12:30".
+fn parse_timestamp(text: &str) -> Result<Timestamp, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(text, TIMESTAMP_FORMAT).map(Timestamp::from_naive)
+}
+
+/// Let the user pick which of the suggested frames to add. Suggestions overlapping already
+/// tracked frames are not selected initially, unless they duplicate a frame of the same project,
+/// which is handled by `on_duplicate`.
+fn import_suggestions(
+    db: &mut Database,
+    suggestions: Vec<crate::activitywatch::Suggestion>,
+    on_duplicate: DuplicatePolicy,
+    draft: bool,
+    durations: DurationFormat,
+    prompt: Prompt,
+) -> ExitCode {
+    let (Some(start), Some(end)) = (
+        suggestions.iter().map(|s| s.start).min(),
+        suggestions.iter().map(|s| s.end).max(),
+    ) else {
+        println!("No events matched the rules");
+        return ExitCode::SUCCESS;
+    };
+
+    let mut projects = HashMap::new();
+    for suggestion in &suggestions {
+        if projects.contains_key(&suggestion.project) {
+            continue;
+        }
+        match db
+            .lookup_project_by_name(&suggestion.project)
+            .expect("Database is broken")
+        {
+            Some(project) => projects.insert(suggestion.project.clone(), project),
+            None => {
+                eprintln!("Project {} does not exist", suggestion.project);
+                return ExitCode::FAILURE;
+            }
+        };
+    }
+
+    let span = TimeSpan::new(start, end).expect("Suggestions are not empty");
+    let tracked = db
+        .get_frames_in_span(span, ArchivedState::Both)
+        .expect("Database is broken");
+    let now = Timestamp::now();
+    let duplicates = |suggestion: &crate::activitywatch::Suggestion| {
+        tracked.iter().any(|(project, frame)| {
+            project.name == suggestion.project
+                && is_duplicate(
+                    (suggestion.start, suggestion.end),
+                    (frame.start, frame.end.unwrap_or(now)),
+                )
+        })
+    };
+    let overlaps = |suggestion: &crate::activitywatch::Suggestion| {
+        !duplicates(suggestion)
+            && tracked.iter().any(|(_, frame)| {
+                frame.start < suggestion.end && frame.end.is_none_or(|end| end > suggestion.start)
+            })
+    };
+
+    let describe = |s: &crate::activitywatch::Suggestion| {
+        format!(
+            "{} - {}  {} ({})",
+            s.start.to_local().format(TIMESTAMP_FORMAT),
+            s.end.to_local().format("%H:%M"),
+            s.project,
+            s.duration().format_as(durations),
+        )
+    };
+    let options: Vec<_> = suggestions
+        .iter()
+        .map(|s| {
+            if duplicates(s) {
+                format!("{}, already tracked", describe(s))
+            } else if overlaps(s) {
+                format!("{}, overlaps a frame", describe(s))
+            } else {
+                describe(s)
+            }
+        })
+        .collect();
+    let defaults: Vec<_> = (0..suggestions.len())
+        .filter(|&i| !overlaps(&suggestions[i]))
+        .collect();
+    // With --yes or --draft, the suggestions that would be selected initially are added.
+    let question = "Select the frames to add";
+    let selected = if prompt.assume_yes || draft {
+        defaults
+    } else {
+        if let Err(err) = prompt.check_interactive(question) {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+        let selected = MultiSelect::new(question, options)
+            .with_default(&defaults)
+            .raw_prompt();
+
+        use inquire::InquireError::*;
+        match selected {
+            Ok(selected) => selected.into_iter().map(|option| option.index).collect(),
+            Err(OperationCanceled | OperationInterrupted) => return ExitCode::SUCCESS,
+            Err(err) => panic!("Failed to inquire frames: {err}"),
+        }
+    };
+
+    let uuids: Vec<_> = selected
+        .iter()
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect();
+    let frames = selected
+        .iter()
+        .zip(&uuids)
+        .map(|(&index, uuid)| {
+            let suggestion = &suggestions[index];
+            NewFrame {
+                project: projects[&suggestion.project].id(),
+                start: &suggestion.start,
+                end: Some(&suggestion.end),
+                deleted_at: None,
+                uuid,
+                updated_at: &now,
+                external_ref: None,
+                note: None,
+                source: Some(FrameSource::Import),
+                draft,
+                kind: FrameKind::Work,
+            }
+        })
+        .collect();
+    // Importing the same events again adds nothing.
+    match db.import_frames(frames, on_duplicate) {
+        Ok(summary) => {
+            if draft {
+                println!(
+                    "Added {} draft frames, review them with `ttt review`",
+                    summary.inserted
+                );
+            } else {
+                println!("Added {} frames", summary.inserted);
+            }
+            for frame in &summary.merged {
+                println!(
+                    "Merged duplicates into frame {}, now {}",
+                    frame.short_id(),
+                    frame.duration().format_as(durations)
+                );
+            }
+            if !summary.skipped.is_empty() {
+                println!("Skipped {} duplicates:", summary.skipped.len());
+                for index in summary.skipped {
+                    println!("  {}", describe(&suggestions[selected[index]]));
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Offer to replace the damaged database file at `path` by its latest backup or by an empty
+/// database, see [`crate::database::replace_damaged`]. The damaged file is kept.
+///
+/// Returns whether the file was replaced.
+pub fn recover_damaged(path: &Path, prompt: Prompt) -> bool {
+    let backup = crate::database::latest_backup(path);
+    let question = "How do you want to continue?";
+    if let Err(err) = prompt.check_interactive(question) {
+        eprintln!("{err}");
+        return false;
+    }
+    let mut options = Vec::new();
+    if let Some(backup) = &backup {
+        let name = backup.file_stem().unwrap_or_default().to_string_lossy();
+        options.push(format!("Restore the backup of {name}"));
+    }
+    options.push("Start over with an empty database".to_owned());
+    options.push("Quit".to_owned());
+    let Ok(choice) = Select::new(question, options).raw_prompt() else {
+        return false;
+    };
+    let backup = match (choice.index, &backup) {
+        (0, Some(backup)) => Some(backup.as_path()),
+        (1, Some(_)) | (0, None) => None,
+        _ => return false,
+    };
+    match crate::database::replace_damaged(path, backup) {
+        Ok(damaged) => {
+            println!("The damaged database was moved to {}", damaged.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            false
+        }
+    }
+}
+
+/// Ask `question` before a destructive change. `Err` holds the exit code if the command must
+/// stop, which is a success if the user declined.
+fn confirm(prompt: Prompt, question: &str, default: bool) -> Result<(), ExitCode> {
+    match prompt.confirm(question, default) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ExitCode::SUCCESS),
+        Err(err) => {
+            eprintln!("{err}");
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Fail with an error message if `question` cannot be asked, see [`Prompt::check_interactive`].
+fn check_interactive(prompt: Prompt, question: &str) -> Result<(), ExitCode> {
+    prompt.check_interactive(question).map_err(|err| {
+        eprintln!("{err}");
+        ExitCode::FAILURE
+    })
+}
+
+/// Let the user pick which groups of frames within `span` to merge, see
+/// [`Database::mergeable_frames`]. With --yes, all of them are merged.
+fn dedupe(
+    db: &mut Database,
+    span: TimeSpan,
+    gap: u32,
+    durations: DurationFormat,
+    prompt: Prompt,
+) -> ExitCode {
+    let groups = db
+        .mergeable_frames(span, chrono::Duration::minutes(gap.into()))
+        .expect("Database is broken");
+    if groups.is_empty() {
+        println!("No frames to merge");
+        return ExitCode::SUCCESS;
+    }
+
+    let options: Vec<_> = groups
+        .iter()
+        .map(|group| {
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            let end = last.end.map_or_else(
+                || "now".to_owned(),
+                |end| end.to_local().format("%H:%M").to_string(),
+            );
+            let tracked = group
+                .iter()
+                .map(Frame::duration)
+                .fold(chrono::Duration::zero(), |total, duration| total + duration);
+            let project = db
+                .lookup_project(first.project)
+                .expect("Database is broken")
+                .map_or_else(|| "unknown project".to_owned(), |project| project.name);
+            format!(
+                "{} - {end}  {project} ({} frames, {})",
+                first.start.to_local().format(TIMESTAMP_FORMAT),
+                group.len(),
+                tracked.format_as(durations)
+            )
+        })
+        .collect();
+    let question = "Select the frames to merge";
+    let selected: Vec<_> = if prompt.assume_yes {
+        (0..groups.len()).collect()
+    } else {
+        if let Err(code) = check_interactive(prompt, question) {
+            return code;
+        }
+        let all: Vec<_> = (0..groups.len()).collect();
+        let Ok(selected) = MultiSelect::new(question, options)
+            .with_default(&all)
+            .raw_prompt()
+        else {
+            return ExitCode::SUCCESS;
+        };
+        selected.into_iter().map(|option| option.index).collect()
+    };
+
+    for index in selected {
+        let group = &groups[index];
+        match db.merge_frames(group) {
+            Ok(frame) => println!(
+                "Merged {} frames into frame {}, now {}",
+                group.len(),
+                frame.short_id(),
+                frame.duration().format_as(durations)
+            ),
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Add the meetings of the calendar at `source` within `span` as draft frames of `project`, with
+/// their summary as note.
+fn import_ics(
+    db: &mut Database,
+    source: &str,
+    span: TimeSpan,
+    project: &str,
+    email: Option<&str>,
+) -> ExitCode {
+    let Some(project) = db
+        .lookup_project_by_name(project)
+        .expect("Database is broken")
+    else {
+        eprintln!("Project {project} does not exist, choose another one with --project");
+        return ExitCode::FAILURE;
+    };
+    let meetings = match read_calendar(source)
+        .and_then(|ics| crate::calendar::parse_meetings(&ics, span, email))
+    {
+        Ok(meetings) => meetings,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if meetings.is_empty() {
+        println!("No meetings in the calendar within the time span");
+        return ExitCode::SUCCESS;
+    }
+
+    let now = Timestamp::now();
+    let times: Vec<_> = meetings
+        .iter()
+        .map(|meeting| (meeting.span.start(), meeting.span.end()))
+        .collect();
+    let uuids: Vec<_> = meetings
+        .iter()
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect();
+    let frames = times
+        .iter()
+        .zip(&uuids)
+        .zip(&meetings)
+        .map(|(((start, end), uuid), meeting)| NewFrame {
+            project: project.id(),
+            start,
+            end: Some(end),
+            deleted_at: None,
+            uuid,
+            updated_at: &now,
+            external_ref: None,
+            note: meeting.summary.as_deref(),
+            source: Some(FrameSource::Import),
+            draft: true,
+            kind: FrameKind::Work,
+        })
+        .collect();
+    // Importing the same calendar again skips the meetings that are already there.
+    match db.import_frames(frames, DuplicatePolicy::Skip) {
+        Ok(summary) => {
+            for (index, meeting) in meetings.iter().enumerate() {
+                if !summary.skipped.contains(&index) {
+                    println!(
+                        "{} - {}  {}",
+                        meeting.span.start().to_local().format(TIMESTAMP_FORMAT),
+                        meeting.span.end().to_local().format("%H:%M"),
+                        meeting.summary.as_deref().unwrap_or("")
+                    );
+                }
+            }
+            println!(
+                "Added {} meetings as draft frames of {}, review them with `ttt review`",
+                summary.inserted, project.name
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The text of a calendar file, or of a calendar URL with the calendar-url feature.
+fn read_calendar(source: &str) -> crate::error::Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        #[cfg(feature = "calendar-url")]
+        return ureq::get(source)
+            .call()
+            .map_err(|err| crate::error::Error::InvalidImport(err.to_string()))?
+            .into_string()
+            .map_err(Into::into);
+        #[cfg(not(feature = "calendar-url"))]
+        return Err(crate::error::Error::InvalidImport(
+            "Downloading calendars needs the calendar-url feature, download the file instead"
+                .to_owned(),
+        ));
+    }
+    Ok(std::fs::read_to_string(source)?)
+}
+
+/// Let the user pick which drafts to confirm, the others are moved to the trash. With --yes, all
+/// of them are confirmed.
+fn review(
+    db: &mut Database,
+    discard_all: bool,
+    durations: DurationFormat,
+    prompt: Prompt,
+) -> ExitCode {
+    let drafts = db.drafts().expect("Database is broken");
+    if drafts.is_empty() {
+        println!("No drafts to review");
+        return ExitCode::SUCCESS;
+    }
+
+    let question = "Select the drafts to confirm, the others are moved to the trash";
+    let selected: Vec<_> = if discard_all {
+        Vec::new()
+    } else if prompt.assume_yes {
+        (0..drafts.len()).collect()
+    } else {
+        if let Err(code) = check_interactive(prompt, question) {
+            return code;
+        }
+        let options: Vec<_> = drafts
+            .iter()
+            .map(|(project, frame)| {
+                let end = frame.end.map_or_else(
+                    || "now".to_owned(),
+                    |end| end.to_local().format("%H:%M").to_string(),
+                );
+                format!(
+                    "{} - {end}  {} ({})",
+                    frame.start.to_local().format(TIMESTAMP_FORMAT),
+                    project.name,
+                    frame.duration().format_as(durations)
+                )
+            })
+            .collect();
+        let all: Vec<_> = (0..drafts.len()).collect();
+        let Ok(selected) = MultiSelect::new(question, options)
+            .with_default(&all)
+            .raw_prompt()
+        else {
+            return ExitCode::SUCCESS;
+        };
+        selected.into_iter().map(|option| option.index).collect()
+    };
+
+    let confirmed: Vec<_> = selected.iter().map(|&index| drafts[index].1.id()).collect();
+    let discarded: Vec<_> = drafts
+        .iter()
+        .map(|(_, frame)| frame.id())
+        .filter(|id| !confirmed.contains(id))
+        .collect();
+    let result = db.review_drafts(&confirmed, &discarded);
+    match result {
+        Ok((confirmed, discarded)) => {
+            if confirmed > 0 {
+                println!("Confirmed {confirmed} drafts");
+            }
+            if discarded > 0 {
+                println!("Moved {discarded} drafts to the trash");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Describe a frame for confirmations, e.g. "1a2b3c4 (website, 2024-03-04 09:00 - 10:30)".
+fn describe_frame(db: &mut Database, frame: &Frame) -> String {
+    let project = db
+        .lookup_project(frame.project)
+        .expect("Database is broken")
+        .map_or_else(|| "unknown project".to_owned(), |project| project.name);
+    let end = frame.end.map_or_else(
+        || "now".to_owned(),
+        |end| end.to_local().format("%H:%M").to_string(),
+    );
+    format!(
+        "{} ({project}, {} - {end})",
+        frame.short_id(),
+        frame.start.to_local().format(TIMESTAMP_FORMAT)
+    )
+}
+
+fn trash(db: &mut Database, action: TrashAction, prompt: Prompt) -> ExitCode {
+    match action {
+        TrashAction::List => {
+            let trashed = db.trashed_frames().expect("Database is broken");
+            for (project, frame) in trashed {
+                let end = frame
+                    .end
+                    .map_or_else(|| "now".to_owned(), |end| end.0.to_string());
+                println!(
+                    "{}: {}: {} -> {} (deleted {})",
+                    frame.short_id(),
+                    project.name,
+                    frame.start.0,
+                    end,
+                    frame.deleted_at.unwrap().0
+                );
+            }
+        }
+        TrashAction::Restore { id } => {
+            let frame = match db.resolve_frame(&id) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let question = format!("Restore frame {}?", describe_frame(db, &frame));
+            if let Err(code) = confirm(prompt, &question, true) {
+                return code;
+            }
+            match db.restore_frame(frame.id()) {
+                Ok(frame) => println!("Restored frame {}", frame.short_id()),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        TrashAction::Empty => {
+            let count = db.trashed_frames().expect("Database is broken").len();
+            if count == 0 {
+                println!("Nothing to do!");
+                return ExitCode::SUCCESS;
+            }
+
+            let question = format!("Permanently delete {count} frames?");
+            if let Err(code) = confirm(prompt, &question, false) {
+                return code;
+            }
+
+            let deleted = db.empty_trash().expect("Database is broken");
+            println!("Permanently deleted {deleted} frames");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Handle `ttt budget`: set or clear the budget of `project`, or show how much of the budgets
+/// is used.
+fn budget(
+    db: &mut Database,
+    project: Option<String>,
+    limit: Option<BudgetLimit>,
+    period: BudgetPeriod,
+    clear: bool,
+    config: &Config,
+) -> ExitCode {
+    let project = match project {
+        Some(name) => match db
+            .lookup_project_by_name(&name)
+            .expect("Database is broken")
+        {
+            Some(project) => Some(project),
+            None => {
+                eprintln!("Project {name} does not exist");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    if let (Some(project), true) = (&project, clear) {
+        if db.remove_budget(project).expect("Database is broken") {
+            println!("Removed the budget of {}", project.name);
+        } else {
+            println!("{} has no budget", project.name);
+        }
+        return ExitCode::SUCCESS;
+    }
+    if let (Some(project), Some(limit)) = (&project, limit) {
+        let (minutes, cents) = match limit {
+            BudgetLimit::Minutes(minutes) => (Some(minutes), None),
+            BudgetLimit::Cents(cents) => (None, Some(cents)),
+        };
+        let budget = Budget {
+            project_id: project.id(),
+            minutes,
+            cents,
+            period,
+        };
+        db.set_budget(&budget).expect("Database is broken");
+    }
+
+    let budgets = match &project {
+        Some(project) => vec![project.clone()],
+        None => db
+            .budgets()
+            .expect("Database is broken")
+            .into_iter()
+            .map(|(project, _)| project)
+            .collect(),
+    };
+    let currencies = db
+        .currencies(config.currency.as_deref())
+        .expect("Database is broken");
+    let durations = config.duration_format;
+    let mut table = Table::new(&[
+        ("Project", Align::Left),
+        ("Budget", Align::Right),
+        ("Used", Align::Right),
+        ("Share", Align::Right),
+    ]);
+    let week_start = config.week_start.unwrap_or(Weekday::Mon);
+    for project in budgets {
+        let currency = currencies.get(&project.id()).map(String::as_str);
+        let Some(used) =
+            budget::check(db, &project, currency, week_start).expect("Database is broken")
+        else {
+            println!("{} has no budget", project.name);
+            return ExitCode::SUCCESS;
+        };
+        table.add_row(vec![
+            project.name,
+            used.limit_text(durations),
+            used.used_text(durations),
+            format!("{}%", used.percentage()),
+        ]);
+    }
+    table.print(true);
+    ExitCode::SUCCESS
+}
+
+fn task(db: &mut Database, action: TaskAction) -> ExitCode {
+    match action {
+        TaskAction::List { project, all } => {
+            let project_id = match project {
+                Some(name) => match db
+                    .lookup_project_by_name(&name)
+                    .expect("Database is broken")
+                {
+                    Some(project) => Some(project.id()),
+                    None => {
+                        eprintln!("Project {name} does not exist");
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => None,
+            };
+            let projects: HashMap<_, _> = db
+                .all_projects(ArchivedState::Both)
+                .expect("Database is broken")
+                .into_iter()
+                .map(|project| (project.id(), project.name))
+                .collect();
+            let mut table = Table::new(&[("Task", Align::Left), ("State", Align::Left)]);
+            for task in db.tasks(project_id).expect("Database is broken") {
+                if task.done && !all {
+                    continue;
+                }
+                let state = if task.done { "done" } else { "open" };
+                table.add_row(vec![
+                    format!("{}/{}", projects[&task.project_id], task.name),
+                    state.to_owned(),
+                ]);
+            }
+            table.print(true);
+        }
+        TaskAction::Done { task: ref name } | TaskAction::Reopen { task: ref name } => {
+            let done = matches!(action, TaskAction::Done { .. });
+            let task = match resolve_task(db, name) {
+                Ok(task) => task,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            db.set_task_done(&task, done).expect("Database is broken");
+            if done {
+                println!("Marked task {name} as done");
+            } else {
+                println!("Reopened task {name}");
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Find the task named like "project/task".
+fn resolve_task(db: &mut Database, name: &str) -> crate::error::Result<Task> {
+    let not_found = || crate::error::Error::TaskNotFound(name.to_owned());
+    let (project, task) = name.rsplit_once('/').ok_or_else(not_found)?;
+    let project = db
+        .lookup_project_by_name(project)?
+        .ok_or_else(|| crate::error::Error::ProjectNotFound(project.to_owned()))?;
+    db.lookup_task(project.id(), task)?.ok_or_else(not_found)
+}
+
+fn db(db: &mut Database, action: DbAction) -> ExitCode {
+    match action {
+        DbAction::Vacuum => {
+            let before = database_size(db);
+            if let Err(err) = db.vacuum() {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+            match (before, database_size(db)) {
+                (Some(before), Some(after)) => println!(
+                    "Shrunk the database from {} to {}",
+                    format_size(before),
+                    format_size(after)
+                ),
+                _ => println!("Vacuumed the database"),
+            }
+        }
+        DbAction::Stats => match describe_database(db) {
+            Ok((summary, table)) => {
+                print!("{summary}");
+                table.print(true);
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        DbAction::Migrate { status: true, .. } => {
+            let migrations = match db.migrations() {
+                Ok(migrations) => migrations,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let mut table = Table::new(&[("Migration", Align::Left), ("State", Align::Left)]);
+            for (name, applied) in migrations {
+                let state = if applied { "applied" } else { "pending" };
+                table.add_row(vec![name, state.to_owned()]);
+            }
+            table.print(true);
+        }
+        DbAction::Migrate { dry_run: true, .. } => {
+            let pending: Vec<_> = match db.migrations() {
+                Ok(migrations) => migrations
+                    .into_iter()
+                    .filter(|(_, applied)| !applied)
+                    .map(|(name, _)| name)
+                    .collect(),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            if pending.is_empty() {
+                println!("The database is up to date");
+            }
+            for name in pending {
+                println!("Would apply {name}");
+            }
+        }
+        DbAction::Migrate { .. } => match db.migrate() {
+            Ok(applied) if applied.is_empty() => println!("The database is up to date"),
+            Ok(applied) => {
+                for name in applied {
+                    println!("Applied {name}");
+                }
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+    ExitCode::SUCCESS
+}
+
+/// File, size and time covered by the frames, followed by a table with the rows of each table
+/// of the database, see `ttt db stats`.
+fn describe_database(db: &mut Database) -> crate::error::Result<(String, Table)> {
+    let stats = db.stats()?;
+    let mut summary = String::new();
+    if let Some(path) = db.path() {
+        summary += &format!("File: {}\n", path.display());
+    }
+    if let Some(size) = database_size(db) {
+        summary += &format!("Size: {}\n", format_size(size));
+    }
+    summary += &match (stats.first_frame, stats.last_frame) {
+        (Some(first), Some(last)) => format!(
+            "Frames: {} - {}\n\n",
+            first.to_local().format(TIMESTAMP_FORMAT),
+            last.to_local().format(TIMESTAMP_FORMAT)
+        ),
+        _ => "Frames: none\n\n".to_owned(),
+    };
+
+    let mut table = Table::new(&[("Table", Align::Left), ("Rows", Align::Right)]);
+    for (name, rows) in stats.rows {
+        table.add_row(vec![name.to_owned(), rows.to_string()]);
+    }
+    Ok((summary, table))
+}
+
+/// Handle `ttt debug-bundle`.
+fn debug_bundle(db: &mut Database, output: &Path) -> ExitCode {
+    let config = match std::fs::read_to_string(Config::path()) {
+        Ok(text) => crate::bundle::redact_config(&text),
+        Err(err) => format!("# {}: {err}\n", Config::path().display()),
+    };
+    let mut migrations = Table::new(&[("Migration", Align::Left), ("State", Align::Left)]);
+    match db.migrations() {
+        Ok(applied) => {
+            for (name, applied) in applied {
+                let state = if applied { "applied" } else { "pending" };
+                migrations.add_row(vec![name, state.to_owned()]);
+            }
+        }
+        Err(err) => migrations.add_row(vec![err.to_string(), "failed".to_owned()]),
+    }
+    // Pending migrations can leave tables missing, the bundle is most useful then.
+    let database = match describe_database(db) {
+        Ok((summary, table)) => summary + &table.render(true, false),
+        Err(err) => format!("Failed to read the database: {err}\n"),
+    };
+    let files = [
+        ("version.txt", crate::bundle::version().into_bytes()),
+        ("config.toml", config.into_bytes()),
+        ("database.txt", database.into_bytes()),
+        (
+            "migrations.txt",
+            migrations.render(true, false).into_bytes(),
+        ),
+    ];
+    if let Err(err) = std::fs::write(output, crate::bundle::zip(&files)) {
+        eprintln!("Failed to write {}: {err}", output.display());
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "Wrote {}, please check that it holds nothing private before attaching it to an issue",
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Handle `ttt self-update`.
+#[cfg(feature = "self-update")]
+fn self_update(check: bool) -> ExitCode {
+    use crate::update::{install, latest_release, CURRENT};
+
+    let release = match latest_release(std::time::Duration::from_secs(30)) {
+        Ok(release) => release,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let version = release.version();
+    if !release.is_newer() {
+        println!("ttt {CURRENT} is the latest version");
+    } else if check {
+        println!("ttt {version} is available, run `ttt self-update` to install it");
+    } else if let Err(err) = install(&release) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    } else {
+        println!("Updated ttt from {CURRENT} to {version}");
+    }
+    ExitCode::SUCCESS
+}
+
+/// Bytes taken by the database file and its write ahead log, `None` for in memory databases.
+fn database_size(db: &Database) -> Option<u64> {
+    let path = db.path()?;
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    let size = |path: &Path| std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    Some(size(path) + size(Path::new(&wal)))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["bytes", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} bytes")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Print the overtime per week up to yesterday, today is shown separately as it is not over.
+fn show_balance(db: &mut Database, schedule: &Schedule, durations: DurationFormat) {
+    let today = Local::now().date_naive();
+    let first_frame = db
+        .all_frames(ArchivedState::Both)
+        .expect("Database is broken")
+        .iter()
+        .map(|frame| frame.start.0.date_naive())
+        .min();
+    let since = schedule.since.or(first_frame).unwrap_or(today).min(today);
+
+    let span = TimeSpan::new(
+        Timestamp::from_naive(since.and_time(NaiveTime::MIN)),
+        Timestamp::from_naive(today.and_time(NaiveTime::MIN) + chrono::Duration::days(1)),
+    )
+    .expect("Span covers at least today");
+    let leave = db.leave_days(span).expect("Database is broken");
+    let worked = Report::per_project(db, span)
+        .expect("Database is broken")
+        .days;
+    let mut days = schedule.balance(since, today, &worked, &leave);
+    let today = days.pop().expect("Balance contains today");
+
+    let mut table = Table::new(&[
+        ("Week of", Align::Left),
+        ("Worked", Align::Right),
+        ("Expected", Align::Right),
+        ("Overtime", Align::Right),
+        ("Balance", Align::Right),
+    ]);
+    let mut balance = chrono::Duration::zero();
+    for week in crate::schedule::weeks(&days) {
+        balance = balance + week.difference();
+        table.add_row(vec![
+            week.day.to_string(),
+            week.worked.format_as(durations),
+            week.expected.format_as(durations),
+            format_difference(week.difference(), durations),
+            format_difference(balance, durations),
+        ]);
+    }
+    table.print(true);
+    println!(
+        "Flex time since {since}: {}",
+        format_difference(balance, durations)
+    );
+    println!(
+        "Today: {} of {}",
+        today.worked.format_as(durations),
+        today.expected.format_as(durations)
+    );
+}
+
+/// Start or end a break, see [`FrameKind::Break`]. Ending it continues work on the project the
+/// break was taken from.
+fn take_break(db: &mut Database, action: BreakAction, config: &Config) -> ExitCode {
+    let current = match db.current_frame() {
+        Ok(current) => Some(current),
+        Err(crate::error::Error::NoActiveFrame) => None,
+        Err(err) => panic!("Database is broken: {err}"),
+    };
+    let durations = config.duration_format;
+    match action {
+        BreakAction::Start { project } => {
+            if let Some(current) = current.as_ref().filter(|c| c.kind == FrameKind::Break) {
+                eprintln!(
+                    "Already on a break since {}",
+                    current.start.to_local().format("%H:%M")
+                );
+                return ExitCode::FAILURE;
+            }
+            let project = match project {
+                Some(name) => db.lookup_project_by_name(&name),
+                None => match &current {
+                    Some(current) => db.lookup_project(current.project),
+                    None => db
+                        .all_projects(ArchivedState::NotArchived)
+                        .map(|mut projects| projects.pop()),
+                },
+            };
+            let Some(mut project) = project.expect("Database is broken") else {
+                eprintln!("No project to take a break from, name one");
+                return ExitCode::FAILURE;
+            };
+            let stopped = match db.start_break(&mut project, FrameSource::Cli) {
+                Ok((stopped, _)) => stopped,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            for frame in stopped {
+                let stopped = db.frame_details(frame.id()).expect("Database is broken");
+                print_stopped(&stopped, durations);
+            }
+            println!("Started a break from {}", project.name);
+        }
+        BreakAction::Stop => {
+            let Some(current) = current.filter(|c| c.kind == FrameKind::Break) else {
+                eprintln!("Not on a break");
+                return ExitCode::FAILURE;
+            };
+            let project = db
+                .lookup_project(current.project)
+                .expect("Database is broken")
+                .unwrap_or_else(|| panic!("Found no project for id {}", current.id()));
+            match db.end_break(current.id(), FrameSource::Cli) {
+                Ok((stopped, _)) => println!(
+                    "Back to {} after a break of {}",
+                    project.name,
+                    stopped.duration().format_as(durations)
+                ),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    print_today(db, durations);
+    ExitCode::SUCCESS
+}
+
+fn rules(db: &mut Database, action: RuleAction) -> ExitCode {
+    match action {
+        RuleAction::Add {
+            field,
+            pattern,
+            tag,
+        } => {
+            let Some(tag) = db.lookup_tag_by_name(&tag).expect("Database is broken") else {
+                eprintln!("Tag {tag} does not exist");
+                return ExitCode::FAILURE;
+            };
+            match db.add_rule(field, &pattern, &tag) {
+                Ok(rule) => println!(
+                    "Added rule {}: {field} =~ \"{pattern}\" adds {}",
+                    rule.id(),
+                    tag.name
+                ),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        RuleAction::List => {
+            for (rule, tag) in db.rules().expect("Database is broken") {
+                println!(
+                    "{}: {} =~ \"{}\" adds {}",
+                    rule.id(),
+                    rule.field,
+                    rule.pattern,
+                    tag.name
+                );
+            }
+        }
+        RuleAction::Remove { id } => {
+            if !db.remove_rule(id).expect("Database is broken") {
+                eprintln!("No rule with id {id}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn leave(db: &mut Database, action: LeaveAction, config: &Config, prompt: Prompt) -> ExitCode {
+    match action {
+        LeaveAction::Add {
+            day,
+            kind,
+            until,
+            name,
+        } => {
+            let days: Vec<_> = match until {
+                Some(until) => day
+                    .iter_days()
+                    .take_while(|d| *d <= until)
+                    .filter(|d| !matches!(d.weekday(), Weekday::Sat | Weekday::Sun))
+                    .collect(),
+                None => vec![day],
+            };
+            for day in &days {
+                let leave = LeaveDay {
+                    day: *day,
+                    kind,
+                    name: name.clone(),
+                };
+                db.add_leave(&leave).expect("Database is broken");
+            }
+            match days.len() {
+                1 => println!("Recorded {day} as {kind}"),
+                count => println!("Recorded {count} days of {kind}"),
+            }
+        }
+        LeaveAction::Remove { day } => {
+            if !db.remove_leave(day).expect("Database is broken") {
+                eprintln!("No day off recorded on {day}");
+                return ExitCode::FAILURE;
+            }
+        }
+        LeaveAction::List { timespan } => {
+            let span = match parse_timespan(&timespan, config) {
+                Ok(span) => span,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            for leave in db.leave_days(span).expect("Database is broken") {
+                match leave.name {
+                    Some(name) => println!(
+                        "{}: {} ({name})",
+                        leave.day.format("%a %Y-%m-%d"),
+                        leave.kind
+                    ),
+                    None => println!("{}: {}", leave.day.format("%a %Y-%m-%d"), leave.kind),
+                }
+            }
+        }
+        LeaveAction::Import { file } => {
+            let holidays = match std::fs::read_to_string(&file)
+                .map_err(crate::error::Error::from)
+                .and_then(|text| crate::leave::parse_holidays(&text))
+            {
+                Ok(holidays) => holidays,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let question = format!(
+                "Import {} holidays, replacing days off recorded on the same days?",
+                holidays.len()
+            );
+            if let Err(code) = confirm(prompt, &question, true) {
+                return code;
+            }
+            for holiday in &holidays {
+                db.add_leave(holiday).expect("Database is broken");
+            }
+            println!("Imported {} holidays", holidays.len());
+        }
+    }
+    ExitCode::SUCCESS
+}
 
-    /// Add a project
-    NewProject { name: String },
+fn new_project(
+    db: &mut Database,
+    name: &str,
+    tag_names: &[String],
+    options: &NewProjectOptions,
+) -> ExitCode {
+    let client = match &options.client {
+        Some(name) => match db.lookup_client_by_name(name).expect("Database is broken") {
+            Some(client) => Some(client),
+            None => {
+                eprintln!("{}", crate::error::Error::ClientNotFound(name.to_owned()));
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let mut project = match db.create_project_with_aliases(name, &options.aliases) {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    /// Add a tag
-    NewTag { name: String },
+    let mut tags = Vec::new();
+    for tag in tag_names {
+        match db.lookup_tag_by_name(tag).expect("Database is broken") {
+            Some(tag) => tags.push(tag),
+            None => {
+                tags.push(db.create_tag(tag).expect("Error creating tag"));
+                println!("Created tag {tag}");
+            }
+        }
+    }
 
-    /// Tag projects interactively
-    Tag {
-        project: Option<String>,
-        tags: Vec<String>,
-    },
+    if !tags.is_empty() {
+        db.tag_projects(tags, vec![project.clone()])
+            .expect("Could not tag projects.");
+    }
+    if options.archived {
+        db.archive_projects(std::slice::from_mut(&mut project))
+            .expect("Database is broken");
+    }
+    if options.rate.is_some() {
+        db.set_hourly_rate(&mut project, options.rate)
+            .expect("Database is broken");
+    }
+    if options.currency.is_some() {
+        db.set_currency(&mut project, options.currency.as_deref())
+            .expect("Database is broken");
+    }
+    if client.is_some() {
+        db.set_client(&mut project, client.as_ref())
+            .expect("Database is broken");
+    }
+    println!("Created project {name}");
+    ExitCode::SUCCESS
+}
 
-    /// Analyze activities performed in a time frame
-    Analyze(AnalyzeOptions),
+/// Ask for the name and tags of a new project, `None` if the user gave up.
+fn new_project_inquire(database: &mut Database, prompt: Prompt) -> Option<(String, Vec<String>)> {
+    let question = "Name of the project:";
+    if let Err(err) = prompt.check_interactive(question) {
+        eprintln!("{err}");
+        return None;
+    }
+    let name = Text::new(question)
+        .with_validator(|name: &str| {
+            Ok(if name.trim().is_empty() {
+                Validation::Invalid("The name must not be empty".into())
+            } else {
+                Validation::Valid
+            })
+        })
+        .prompt()
+        .ok()?;
 
-    /// List available projects or tags.
-    #[command(subcommand)]
-    List(ListAction),
+    let tags = database
+        .all_tags(ArchivedState::NotArchived)
+        .expect("Database is broken");
+    if tags.is_empty() {
+        return Some((name.trim().to_owned(), Vec::new()));
+    }
+    let selected = MultiSelect::new(
+        "Select the tags of the project:",
+        tags.into_iter().map(|tag| tag.name).collect(),
+    )
+    .prompt()
+    .ok()?;
+    Some((name.trim().to_owned(), selected))
 }
 
-#[derive(Args, Debug)]
-pub struct ListArgs {
-    /// Whether to include archived objects or not
-    #[arg(
-        long,
-        num_args=0..=1,
-        default_value_t = ArchivedState::NotArchived,
-        default_missing_value="only-archived",
-        value_enum
-    )]
-    archived: ArchivedState,
+/// Ask whether `what`, e.g. "Project website", should be unarchived to continue. Does not ask
+/// if `unarchive` or `--yes` is given, and never when not interactive.
+fn confirm_unarchive(prompt: Prompt, what: &str, unarchive: bool) -> bool {
+    unarchive
+        || prompt
+            .confirm(
+                &format!("{what} is archived, unarchive it and continue?"),
+                true,
+            )
+            .unwrap_or(false)
 }
 
-#[derive(Subcommand, Debug)]
-pub enum ListAction {
-    Projects {
-        #[arg(long, default_value_t = false)]
-        with_tags: bool,
-
-        #[command(flatten)]
-        args: ListArgs,
-    },
-    Tags(ListArgs),
+/// Unarchive `project` if it is archived and the user agrees, see [`confirm_unarchive`].
+fn unarchive_project(
+    database: &mut Database,
+    project: &mut Project,
+    unarchive: bool,
+    prompt: Prompt,
+) -> Result<(), ExitCode> {
+    if !project.archived {
+        return Ok(());
+    }
+    if !confirm_unarchive(prompt, &format!("Project {}", project.name), unarchive) {
+        eprintln!(
+            "Project {} is archived, use --unarchive to use it again.",
+            project.name
+        );
+        return Err(ExitCode::FAILURE);
+    }
+    project.archived = false;
+    database
+        .write_projects([&mut *project])
+        .expect("Database is broken");
+    println!("Unarchived project {}", project.name);
+    Ok(())
 }
 
-pub fn cli_main(mut database: Database, cli: Cli) -> ExitCode {
-    match cli.action.unwrap() {
-        Action::Start { name } => {
-            let mut project = match name {
-                Some(name) => {
-                    let Some(selected) = database
-                        .lookup_project_by_name(&name)
-                        .expect("Error querying the database.")
-                    else {
-                        eprintln!("Project {name} does not exist in this timeline ;)");
-                        return ExitCode::FAILURE;
-                    };
-                    if selected.archived {
-                        eprintln!("Project {name} is archived. Please remove the archived flag.");
-                        return ExitCode::FAILURE;
-                    }
-                    selected
-                }
-                None => {
-                    let possible_projects = database
-                        .all_projects(ArchivedState::NotArchived)
-                        .expect("Database is broken");
-                    if possible_projects.is_empty() {
-                        println!("Please create a project before starting a task.");
-                        return ExitCode::FAILURE;
-                    }
-                    let selected_project = Select::new(
-                        "Select the project to start",
-                        possible_projects.iter().map(|p| &p.name).collect(),
-                    )
-                    .raw_prompt();
-
-                    use inquire::InquireError::*;
-                    let selected_project = match selected_project {
-                        Ok(t) => t,
-                        Err(OperationCanceled | OperationInterrupted) => return ExitCode::SUCCESS,
-                        Err(err) => panic!("Failed to inquire project: {err}"),
-                    };
+/// Split "project/task" into its parts, unless a project has the whole name.
+fn split_task(db: &mut Database, name: String) -> (String, Option<String>) {
+    match name.rsplit_once('/') {
+        Some((project, task))
+            if !project.is_empty()
+                && !task.is_empty()
+                && db
+                    .lookup_project_by_name(&name)
+                    .expect("Database is broken")
+                    .is_none() =>
+        {
+            (project.to_owned(), Some(task.to_owned()))
+        }
+        _ => (name, None),
+    }
+}
 
-                    let index = selected_project.index;
-                    possible_projects[index].clone()
-                }
-            };
+/// The task `name` of `project`, created if missing and reopened if done. `None` if the user did
+/// not want to create it.
+fn find_or_create_task(
+    db: &mut Database,
+    project: &Project,
+    name: &str,
+    create: bool,
+    prompt: Prompt,
+) -> Option<Task> {
+    let full_name = format!("{}/{name}", project.name);
+    match db
+        .lookup_task(project.id(), name)
+        .expect("Database is broken")
+    {
+        Some(task) if task.done => {
+            let task = db.set_task_done(&task, false).expect("Database is broken");
+            println!("Reopened task {full_name}");
+            Some(task)
+        }
+        Some(task) => Some(task),
+        None if create || confirm_create_task(prompt, &full_name) => {
+            let task = db.create_task(project, name).expect("Database is broken");
+            println!("Created task {full_name}");
+            Some(task)
+        }
+        None => {
+            eprintln!("Task {full_name} does not exist, use --create to create it");
+            None
+        }
+    }
+}
 
-            let _ = stop_current_frame(&mut database);
+fn confirm_create_task(prompt: Prompt, name: &str) -> bool {
+    if !prompt.interactive {
+        return false;
+    }
+    Confirm::new(&format!("Task {name} does not exist, create it?"))
+        .with_default(true)
+        .with_help_message("Use --create to skip this question")
+        .prompt()
+        .unwrap_or(false)
+}
 
-            database
-                .start(&mut project)
-                .expect("Failed to start project");
-            println!("Started project {}", project.name);
+/// Handle `ttt sql`.
+fn sql(db: &mut Database, query: &str, format: TableFormat) -> ExitCode {
+    let result = match db.query_read_only(query) {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
         }
-        Action::Stop => {
-            let stopped_something = stop_current_frame(&mut database).is_some();
-
-            if !stopped_something {
-                println!("Nothing to do!");
+    };
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+    match format {
+        TableFormat::Table => {
+            let mut table = Table::with_columns(
+                result
+                    .columns
+                    .iter()
+                    .map(|column| (column.clone(), Align::Left))
+                    .collect(),
+            );
+            for row in &result.rows {
+                table.add_row(row.iter().map(text).collect());
             }
+            table.print(true);
         }
-        Action::NewProject { name } => {
-            database
-                .create_project(&name)
-                .expect("Error creating project");
-            println!("Created project {name}");
+        TableFormat::Csv => {
+            println!("{}", csv_line(result.columns.iter().cloned()));
+            for row in &result.rows {
+                println!("{}", csv_line(row.iter().map(text)));
+            }
         }
-        Action::Analyze(options) => {
-            let span = if options.is_interactive() {
-                do_inquire_stuff().unwrap()
+        TableFormat::Json => {
+            let rows: Vec<serde_json::Map<_, _>> = result
+                .rows
+                .into_iter()
+                .map(|row| result.columns.iter().cloned().zip(row).collect())
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&rows).expect("Rows are serializable")
+            );
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Join `cells` with commas, quoting those that contain commas, quotes or line breaks.
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
             } else {
-                // todo: handle commandline options in detail, assuming "since_yesterday" for now
-                let end = Timestamp::now();
-                let start = Timestamp(end.0 - chrono::Duration::days(1));
-                TimeSpan::new(start, end).expect("Math broke, yesterday ended up after today ")
-            };
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
 
-            list_frames(&mut database, span);
-        }
-        Action::NewTag { name } => {
-            database.create_tag(&name).expect("Error creating tag");
-            println!("Created tag {name}");
+/// Handle `ttt annotate`. Everything is looked up before the frame is changed, so a typo changes
+/// nothing.
+fn annotate(
+    db: &mut Database,
+    tag_names: &[String],
+    project: Option<String>,
+    message: Option<&str>,
+) -> ExitCode {
+    let frame = match db.current_frame() {
+        Ok(frame) => frame,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
         }
-        Action::Tag { project, tags } => match (project, AsRef::<[String]>::as_ref(&tags)) {
-            (None, []) => tag_inquire(&mut database),
-            (Some(project), []) => tag_project_inquire(&mut database, &project),
-            (Some(project), tags) => tag_projects(&mut database, &project, tags),
-            (None, _) => unreachable!(),
-        },
-        Action::Current => {
-            let Ok(current) = database.current_frame() else {
+    };
+    let (project, task) = match project {
+        Some(name) => {
+            let (name, task_name) = split_task(db, name);
+            let Some(project) = db
+                .lookup_project_by_name(&name)
+                .expect("Database is broken")
+            else {
+                eprintln!("Project {name} does not exist");
                 return ExitCode::FAILURE;
             };
-            let project = database
-                .lookup_project(current.project)
-                .expect("Database is broken")
-                .unwrap_or_else(|| panic!("Found no project for id {}", current.id()));
+            let task = match task_name {
+                Some(task_name) => {
+                    match db
+                        .lookup_task(project.id(), &task_name)
+                        .expect("Database is broken")
+                    {
+                        Some(task) => Some(task),
+                        None => {
+                            eprintln!("Task {name}/{task_name} does not exist");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                None => None,
+            };
+            (Some(project), task)
+        }
+        None => (None, None),
+    };
+    let mut tags = Vec::new();
+    for name in tag_names {
+        let Some(tag) = db.lookup_tag_by_name(name).expect("Database is broken") else {
+            eprintln!("Tag {name} does not exist");
+            return ExitCode::FAILURE;
+        };
+        tags.push(tag);
+    }
+    if project.is_none() && tags.is_empty() && message.is_none() {
+        eprintln!("Nothing to change, give a project, tags or a message");
+        return ExitCode::FAILURE;
+    }
 
-            let task = &project.name;
-            println!("{}: {}", task, current.start.elapsed().format());
+    let frame = match db.amend_frame(frame.id(), project.as_ref(), task.as_ref(), message) {
+        Ok(frame) => frame,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
         }
-        Action::List(action) => list(&mut database, action).expect("Database is broken"),
+    };
+    let project = db
+        .lookup_project(frame.project)
+        .expect("Database is broken")
+        .expect("Frames belong to a project");
+    if !tags.is_empty() {
+        db.tag_projects(tags, vec![project.clone()])
+            .expect("Could not tag projects.");
     }
+    println!("Amended the running frame of {}", project.name);
     ExitCode::SUCCESS
 }
 
-fn do_inquire_stuff() -> Result<TimeSpan, Box<dyn Error>> {
-    let begin = DateSelect::new("Enter start date");
-    let begin = begin.prompt()?;
-    let end = DateSelect::new("Enter end date").with_min_date(begin);
-    let end = end.prompt()?;
-
-    let precise_mode = Confirm::new("Do you want to enter start/end times?").prompt()?;
-
-    let (start_time, end_time) = if precise_mode {
-        let start_time: chrono::naive::NaiveTime = CustomType::new("Enter start time").prompt()?;
-        let end_time: chrono::naive::NaiveTime = CustomType::new("Enter end time")
-            .with_parser(&|text| {
-                let time = text.parse().map_err(|_| ())?;
-                if end == begin && time < start_time {
-                    return Err(());
-                }
-                Ok(time)
-            })
-            .with_error_message(&format!("Enter a valid time that's after {start_time}!"))
-            .prompt()?;
-        (start_time, end_time)
-    } else {
-        use chrono::NaiveTime;
-        (
-            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-            NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
-        )
-    };
+/// Parse a tag to add like "+review" into its name.
+fn parse_added_tag(text: &str) -> Result<String, String> {
+    match text.strip_prefix('+') {
+        Some(name) if !name.is_empty() => Ok(name.to_owned()),
+        _ => Err(format!("{text} is not a tag to add, e.g. +review")),
+    }
+}
 
-    let begin = Timestamp::from_naive(begin.and_time(start_time));
-    let end = Timestamp::from_naive(end.and_time(end_time));
-    Ok(TimeSpan::new(begin, end)?)
+/// Ask whether the unknown project `name` should be created, never when not interactive.
+fn confirm_create_project(prompt: Prompt, name: &str) -> bool {
+    if !prompt.interactive {
+        return false;
+    }
+    Confirm::new(&format!("Project {name} does not exist, create it?"))
+        .with_default(true)
+        .with_help_message("Use --create to skip this question")
+        .prompt()
+        .unwrap_or(false)
 }
 
-fn stop_current_frame(db: &mut Database) -> Option<Frame> {
-    if let Some(current) = db.stop().expect("Database is broken") {
-        let duration = current.end.unwrap().0 - current.start.0;
-        let project = db
-            .lookup_project(current.project)
-            .expect("Database is broken")
-            .unwrap();
+/// Limit of a budget, see `ttt budget`.
+#[derive(Debug, Clone, Copy)]
+pub enum BudgetLimit {
+    Minutes(i32),
+    Cents(i32),
+}
 
-        println!(
-            "Tracked time for Task {}: {}",
-            project.name,
-            duration.format()
-        );
+/// Parse hours like "40h" or an amount of money like "2000" into a budget limit.
+fn parse_budget(text: &str) -> Result<BudgetLimit, String> {
+    let Some(hours) = text.strip_suffix('h') else {
+        return parse_rate(text).map(BudgetLimit::Cents);
+    };
+    let hours: f64 = hours
+        .parse()
+        .map_err(|_| format!("{text} is not a number of hours, e.g. 40h"))?;
+    if !(0.0..=f64::from(i32::MAX) / 60.0).contains(&hours) {
+        return Err(format!("Invalid budget: {text}"));
+    }
+    Ok(BudgetLimit::Minutes((hours * 60.0).round() as i32))
+}
 
-        Some(current)
+/// Parse an ISO 4217 currency code like "eur" into its uppercase form.
+fn parse_currency(text: &str) -> Result<String, String> {
+    if text.len() == 3 && text.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(text.to_ascii_uppercase())
     } else {
-        None
+        Err(format!("{text} is not a currency code, e.g. EUR"))
     }
 }
 
-fn list_frames(db: &mut Database, span: TimeSpan) {
-    let data = db
-        .get_frames_in_span(span, ArchivedState::Both)
-        .expect("Database is broken");
-
-    for (project, frame) in data {
-        if let Some(end) = frame.end {
-            println!(
-                "{}: {} -> {} ({})",
-                project.name,
-                frame.start.0,
-                end.0,
-                (end.0 - frame.start.0).format()
-            );
-        } else {
-            println!(
-                "{}: {} -> now ({})",
-                project.name,
-                frame.start.0,
-                frame.start.elapsed().format()
-            );
-        }
+/// Parse an amount of money like "85.50" into cents.
+fn parse_rate(text: &str) -> Result<i32, String> {
+    let amount: f64 = text
+        .parse()
+        .map_err(|_| format!("{text} is not an amount, e.g. 85.50"))?;
+    if !(0.0..=f64::from(i32::MAX) / 100.0).contains(&amount) {
+        return Err(format!("Invalid rate: {text}"));
     }
+    Ok((amount * 100.0).round() as i32)
 }
 
+const TAG_QUESTION: &str = "Select the tags to apply to selected projects.";
+
 fn min_select_validator(input: &[ListOption<&&String>]) -> Result<Validation, CustomUserError> {
     if input.is_empty() {
         Ok(Validation::Invalid("Select at least one element".into()))
@@ -283,59 +4352,70 @@ fn min_select_validator(input: &[ListOption<&&String>]) -> Result<Validation, Cu
     }
 }
 
-fn tag_projects(database: &mut Database, project_name: &str, tag_names: &[String]) {
-    let Some(selected_project) = database
+fn tag_projects(
+    database: &mut Database,
+    project_name: &str,
+    tag_names: &[String],
+    unarchive: bool,
+    prompt: Prompt,
+) -> ExitCode {
+    let Some(mut selected_project) = database
         .lookup_project_by_name(project_name)
         .expect("Database is broken")
     else {
         eprintln!("Project {project_name} seems to be missing from the database. Please add it before using it.");
-        std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                               // added.
+        return ExitCode::FAILURE;
     };
-
-    if selected_project.archived {
-        eprintln!(
-            "Project {project_name} is archived. Please unarchive the project before using it."
-        );
-        std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                               // added.
+    if let Err(code) = unarchive_project(database, &mut selected_project, unarchive, prompt) {
+        return code;
     }
 
-    let tags: Vec<_> = tag_names.iter().map(|tag| {
-        let Some(selected_tag) = database.lookup_tag_by_name(tag).expect("Database is broken") else {
-            eprintln!("Tag {tag} seems to be missing from the database. Please add it before using it.");
-            std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                                   // added.
+    let mut tags = Vec::new();
+    for tag in tag_names {
+        let Some(mut selected_tag) = database
+            .lookup_tag_by_name(tag)
+            .expect("Database is broken")
+        else {
+            eprintln!(
+                "Tag {tag} seems to be missing from the database. Please add it before using it."
+            );
+            return ExitCode::FAILURE;
         };
-
         if selected_tag.archived {
-            eprintln!("Tag {tag} is archived. Please unarchive the tag before using it.");
-            std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                                   // added.
+            if !confirm_unarchive(prompt, &format!("Tag {tag}"), unarchive) {
+                eprintln!("Tag {tag} is archived, use --unarchive to use it again.");
+                return ExitCode::FAILURE;
+            }
+            selected_tag.archived = false;
+            database
+                .write_tags([&mut selected_tag])
+                .expect("Database is broken");
+            println!("Unarchived tag {tag}");
         }
-        selected_tag
-
-    }).collect();
+        tags.push(selected_tag);
+    }
 
     database
         .tag_projects(tags, vec![selected_project])
         .expect("Could not tag projects.");
+    ExitCode::SUCCESS
 }
 
-fn tag_project_inquire(database: &mut Database, project: &str) {
-    let Some(selected_project) = database
+fn tag_project_inquire(
+    database: &mut Database,
+    project: &str,
+    unarchive: bool,
+    prompt: Prompt,
+) -> ExitCode {
+    let Some(mut selected_project) = database
         .lookup_project_by_name(project)
         .expect("Database is broken")
     else {
         eprintln!("Project {project} seems to be missing from the database. Please add it before using it.");
-        std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                               // added.
+        return ExitCode::FAILURE;
     };
-
-    if selected_project.archived {
-        eprintln!("Project {project} is archived. Please unarchive the project before using it.");
-        std::process::exit(1); // TODO: Change this to ExitCode::FAILURE if casting support is
-                               // added.
+    if let Err(code) = unarchive_project(database, &mut selected_project, unarchive, prompt) {
+        return code;
     }
 
     let mut possible_tags = database
@@ -343,11 +4423,11 @@ fn tag_project_inquire(database: &mut Database, project: &str) {
         .expect("Database is broken");
     if possible_tags.is_empty() {
         println!("Please create a tag before tagging.");
-        return;
+        return ExitCode::SUCCESS;
     }
 
     let selected_tags: Vec<_> = MultiSelect::new(
-        "Select the tags to apply to selected projects.",
+        TAG_QUESTION,
         possible_tags.iter().map(|p| &p.name).collect(),
     )
     .with_validator(min_select_validator)
@@ -363,6 +4443,7 @@ fn tag_project_inquire(database: &mut Database, project: &str) {
             vec![selected_project],
         )
         .expect("Could not tag projects.");
+    ExitCode::SUCCESS
 }
 
 fn tag_inquire(database: &mut Database) {
@@ -394,7 +4475,7 @@ fn tag_inquire(database: &mut Database) {
     .collect();
 
     let selected_tags: Vec<_> = MultiSelect::new(
-        "Select the tags to apply to selected projects.",
+        TAG_QUESTION,
         possible_tags.iter().map(|p| &p.name).collect(),
     )
     .with_validator(min_select_validator)
@@ -412,42 +4493,155 @@ fn tag_inquire(database: &mut Database) {
         .expect("Could not tag projects.");
 }
 
-fn list(db: &mut Database, action: ListAction) -> crate::error::Result<()> {
-    let to_print: Vec<_> = match action {
-        ListAction::Projects { args, with_tags } => db
-            .all_projects(args.archived)?
-            .into_iter()
-            .map(|p| {
-                if with_tags {
-                    let tags = db
-                        .lookup_tags_for_project(p.id())
-                        .expect("Database is broken");
-                    let tags: Vec<_> = tags.into_iter().map(|t| format!("+{}", t.name)).collect();
-                    let tags = tags.join(" ");
-                    if tags.is_empty() {
-                        p.name
-                    } else {
-                        format!("{} {}", p.name, tags)
-                    }
-                } else {
-                    p.name
-                }
-            })
-            .collect(),
-        ListAction::Tags(args) => db
-            .all_tags(args.archived)?
-            .into_iter()
-            .map(|t| t.name)
-            .collect(),
+fn list(db: &mut Database, action: ListAction, header: bool) -> crate::error::Result<()> {
+    let table = match action {
+        ListAction::Projects {
+            args,
+            with_tags: false,
+        } => {
+            let mut table = Table::new(&[("Project", Align::Left)]);
+            for p in db.all_projects(args.archived)? {
+                table.add_row(vec![p.name]);
+            }
+            table
+        }
+        ListAction::Projects {
+            args,
+            with_tags: true,
+        } => {
+            let mut table = Table::new(&[("Project", Align::Left), ("Tags", Align::Left)]);
+            for p in db.all_projects(args.archived)? {
+                let tags: Vec<_> = db
+                    .lookup_tags_for_project(p.id())?
+                    .into_iter()
+                    .map(|t| format!("+{}", t.name))
+                    .collect();
+                table.add_row(vec![p.name, tags.join(" ")]);
+            }
+            table
+        }
+        ListAction::Tags(args) => {
+            let mut table = Table::new(&[("Tag", Align::Left)]);
+            for t in db.all_tags(args.archived)? {
+                table.add_row(vec![t.name]);
+            }
+            table
+        }
+        ListAction::Clients => {
+            let projects = db.all_projects(ArchivedState::Both)?;
+            let mut table = Table::new(&[
+                ("Client", Align::Left),
+                ("Currency", Align::Left),
+                ("Projects", Align::Left),
+            ]);
+            for client in db.clients()? {
+                let names: Vec<_> = projects
+                    .iter()
+                    .filter(|p| p.client_id == Some(client.id()))
+                    .map(|p| p.name.as_str())
+                    .collect();
+                table.add_row(vec![
+                    client.name,
+                    client.currency.unwrap_or_default(),
+                    names.join(", "),
+                ]);
+            }
+            table
+        }
+        ListAction::Frames { .. } => unreachable!("Frames are listed by `cli_main`"),
     };
+    table.print(header);
 
-    for item in to_print {
-        println!("{item}");
-    }
+    Ok(())
+}
+
+/// A frame in the output of `ttt list frames --json`.
+#[derive(Serialize)]
+struct JsonFrame<'a> {
+    id: &'a str,
+    uuid: &'a str,
+    project: &'a str,
+    project_uuid: &'a str,
+    start: Timestamp,
+    end: Option<Timestamp>,
+    #[serde(serialize_with = "serialize_duration")]
+    duration: chrono::Duration,
+    external_ref: Option<&'a str>,
+    note: Option<&'a str>,
+}
 
+/// Print `frames` as JSON array, each one as soon as it is read.
+fn print_json_frames(
+    frames: impl Iterator<Item = crate::error::Result<(Project, Frame)>>,
+) -> crate::error::Result<()> {
+    let mut out = std::io::stdout().lock();
+    let mut serializer = serde_json::Serializer::pretty(&mut out);
+    let mut array = serializer
+        .serialize_seq(None)
+        .map_err(std::io::Error::from)?;
+    for frame in frames {
+        let (project, frame) = frame?;
+        array
+            .serialize_element(&JsonFrame {
+                id: frame.short_id(),
+                uuid: frame.uuid(),
+                project: &project.name,
+                project_uuid: project.uuid(),
+                start: frame.start,
+                end: frame.end,
+                duration: frame.duration(),
+                external_ref: frame.external_ref.as_deref(),
+                note: frame.note.as_deref(),
+            })
+            .map_err(std::io::Error::from)?;
+    }
+    array.end().map_err(std::io::Error::from)?;
+    writeln!(out)?;
     Ok(())
 }
 
+/// Print the frames as a table, with `verbose` also how they were created.
+fn print_frames(
+    frames: &[(Project, Frame)],
+    header: bool,
+    verbose: bool,
+    durations: DurationFormat,
+) {
+    let mut columns = vec![
+        ("Id", Align::Left),
+        ("Project", Align::Left),
+        ("Start", Align::Left),
+        ("End", Align::Left),
+        ("Duration", Align::Right),
+        ("Ref", Align::Left),
+    ];
+    if verbose {
+        columns.push(("Kind", Align::Left));
+        columns.push(("Source", Align::Left));
+    }
+    let mut table = Table::new(&columns);
+    for (project, frame) in frames {
+        let end = frame.end.map_or_else(
+            || "now".to_owned(),
+            |end| end.0.format(TIMESTAMP_FORMAT).to_string(),
+        );
+        let mut row = vec![
+            frame.short_id().to_owned(),
+            project.name.clone(),
+            frame.start.0.format(TIMESTAMP_FORMAT).to_string(),
+            end,
+            frame.duration().format_as(durations),
+            frame.external_ref.clone().unwrap_or_default(),
+        ];
+        if verbose {
+            row.push(frame.kind.name().to_owned());
+            row.push(frame.source.map_or("", FrameSource::name).to_owned());
+        }
+        table.add_row(row);
+    }
+    table.print(header);
+}
+
 fn pick<T>(items: &mut Vec<T>, idxs: &[usize]) -> Vec<T> {
     // Move the items into a vector of Option<T> we can remove items from
     // without reordering.
@@ -0,0 +1,194 @@
+//! User configuration, read from `config.toml` in the configuration directory, e.g.
+//! `~/.config/ttt/config.toml` on Linux.
+//!
+//! ```toml
+//! # Also understand German and French month and weekday names, e.g. "ttt report März"
+//! locales = ["de", "fr"]
+//!
+//! # Read "3.4." as march 4th instead of april 3rd
+//! date_order = "month-first"
+//!
+//! # Let "this week" start on sunday
+//! week_start = "sunday"
+//!
+//! # Print durations as "1:23" instead of "1h 23min"
+//! duration_format = "clock"
+//!
+//! # Stop frames still running at 18:30, also in the GUI
+//! auto_stop = "18:30"
+//!
+//! # Currency of rates when neither the project nor its client has one
+//! currency = "EUR"
+//! ```
+
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use chrono::{NaiveTime, Weekday};
+
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+use crate::{
+    duration::DurationFormat,
+    error::{Error, Result},
+    locale::Locale,
+    timespan_parser::DateOrder,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Languages of month and weekday names in time spans, English is always understood.
+    pub locales: Vec<Locale>,
+
+    /// Order of day and month in dates like "3.4." in time spans.
+    pub date_order: DateOrder,
+
+    /// First day of the week in time spans like "last week", monday if not set.
+    pub week_start: Option<Weekday>,
+
+    /// How durations are printed, can be overridden with `--duration-format`.
+    pub duration_format: DurationFormat,
+
+    /// End of the workday, frames still running then are stopped at that time. Checked every
+    /// minute by the GUI and by each command on the command line.
+    pub auto_stop: Option<NaiveTime>,
+
+    /// ISO 4217 code of the currency of projects and clients without one, e.g. "EUR".
+    pub currency: Option<String>,
+
+    /// Let several frames run at once, see [`crate::database::Database::allow_concurrent_frames`].
+    pub concurrent_frames: bool,
+
+    /// Expected working hours, see [`crate::schedule`].
+    pub schedule: Option<crate::schedule::Schedule>,
+
+    /// Notifications when nothing is tracked, see [`crate::reminder`].
+    pub reminder: Option<crate::reminder::ReminderConfig>,
+
+    /// Projects of the files edited in editors, see [`crate::heartbeat`].
+    pub heartbeat: Vec<crate::heartbeat::HeartbeatRule>,
+
+    /// Broker to announce the tracking state to, see [`crate::mqtt`].
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<crate::mqtt::MqttConfig>,
+
+    /// Chat status to show while tracking, see [`crate::slack`].
+    #[cfg(feature = "slack")]
+    pub slack: Option<crate::slack::SlackConfig>,
+
+    /// Higher pay for overtime and time on call, see [`crate::report::Multipliers`].
+    pub multipliers: crate::report::Multipliers,
+
+    /// Calendar to publish frames to, see [`crate::caldav`].
+    #[cfg(feature = "caldav")]
+    pub caldav: Option<crate::caldav::CalDavConfig>,
+
+    /// Header of PDF timesheets, see [`crate::timesheet`].
+    #[cfg(feature = "pdf")]
+    pub timesheet: crate::timesheet::TimesheetConfig,
+
+    /// Notice about new releases, see [`crate::update`].
+    #[cfg(feature = "self-update")]
+    pub update: crate::update::UpdateConfig,
+}
+
+impl Config {
+    /// Read the configuration file, a missing file results in the default configuration.
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text)
+                .map_err(|e| Error::InvalidConfig(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn path() -> PathBuf {
+        let dirs = ProjectDirs::from("", "", "ttt").expect("Failed to get base directory paths!");
+        dirs.config_dir().join("config.toml")
+    }
+
+    /// Set `week_start` in the configuration file, keeping the rest of the file as it is. Only
+    /// meant for files that do not set it yet.
+    pub fn save_week_start(week_start: Weekday) -> Result<()> {
+        let path = Self::path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, with_week_start(&text, week_start))?;
+        Ok(())
+    }
+
+    fn parse(text: &str) -> std::result::Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+}
+
+/// `text` with a `week_start` line added at the top, where it cannot end up in a table.
+fn with_week_start(text: &str, week_start: Weekday) -> String {
+    let name = crate::stats::weekday_name(week_start).to_lowercase();
+    format!("week_start = \"{name}\"\n{text}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        assert!(Config::parse("").unwrap().locales.is_empty());
+        assert_eq!(
+            Config::parse("locales = [\"de\", \"es\"]").unwrap().locales,
+            vec![Locale::De, Locale::Es]
+        );
+        assert!(Config::parse("locales = [\"klingon\"]").is_err());
+        assert!(Config::parse("locale = [\"de\"]").is_err());
+        assert_eq!(
+            Config::parse("duration_format = \"decimal\"")
+                .unwrap()
+                .duration_format,
+            DurationFormat::Decimal
+        );
+        let schedule = Config::parse("[schedule]\nmonday = 8\nfriday = 6.5")
+            .unwrap()
+            .schedule
+            .unwrap();
+        assert_eq!(schedule.friday, 6.5);
+        assert!(Config::parse("[schedule]\nmon = 8").is_err());
+        assert_eq!(
+            Config::parse("auto_stop = \"18:30\"").unwrap().auto_stop,
+            NaiveTime::from_hms_opt(18, 30, 0)
+        );
+        assert!(Config::parse("auto_stop = \"6pm\"").is_err());
+        assert_eq!(
+            Config::parse("date_order = \"month-first\"")
+                .unwrap()
+                .date_order,
+            DateOrder::MonthFirst
+        );
+        assert_eq!(
+            Config::parse("week_start = \"sunday\"").unwrap().week_start,
+            Some(Weekday::Sun)
+        );
+        assert!(Config::parse("week_start = \"someday\"").is_err());
+    }
+
+    #[test]
+    fn test_with_week_start() {
+        let text = "currency = \"EUR\"\n\n[schedule]\nmonday = 8\n";
+        let config = Config::parse(&with_week_start(text, Weekday::Sun)).unwrap();
+        assert_eq!(config.week_start, Some(Weekday::Sun));
+        assert_eq!(config.currency.as_deref(), Some("EUR"));
+        assert!(config.schedule.is_some());
+
+        let config = Config::parse(&with_week_start("", Weekday::Tue)).unwrap();
+        assert_eq!(config.week_start, Some(Weekday::Tue));
+    }
+}
@@ -1,15 +1,43 @@
+use chrono::NaiveTime;
 use clap::ValueEnum;
-use diesel::{prelude::*, SqliteConnection};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+use diesel::{
+    connection::SimpleConnection,
+    dsl::sql,
+    migration::MigrationSource,
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DieselError},
+    sql_types::{Bool, Text},
+    SqliteConnection,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use directories::ProjectDirs;
 use dotenvy::dotenv;
 use itertools::iproduct;
-use std::{env, fs::create_dir_all};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use typeshare::typeshare;
+use uuid::Uuid;
 
 use crate::{
     error::{Error, Result},
-    model::{Frame, NewFrame, NewProject, NewTag, Project, Tag, TagProject, TimeSpan, Timestamp},
-    schema::{frames, projects, tags, tags_per_project},
+    filter::Filter,
+    model::{
+        Budget, Client, DailyTotal, Frame, FrameChanges, FrameDetails, FrameKind, FrameSource,
+        LeaveDay, LockedPeriod, NewClient, NewFrame, NewLockedPeriod, NewProject, NewTag, NewTask,
+        Project, ProjectAlias, Rate, Rule, RuleField, Tag, TagProject, Task, TimeSpan, Timestamp,
+    },
+    schema::{
+        budgets, clients, daily_totals, frames, leave_days, locked_periods, project_aliases,
+        projects, rates, rules, settings, tags, tags_per_project, tasks,
+    },
 };
 
 macro_rules! query_table {
@@ -29,40 +57,596 @@ macro_rules! query_table {
 
 pub struct Database {
     connection: SqliteConnection,
+
+    /// File the database is stored in, `None` for in memory databases.
+    path: Option<PathBuf>,
+
+    /// Whether frames in locked periods may be changed, see [`Database::force_unlock`].
+    force_unlock: bool,
+
+    /// Whether several frames may run at once, see [`Database::allow_concurrent_frames`].
+    concurrent_frames: bool,
 }
 
 impl Database {
-    pub fn new() -> Result<Self> {
-        let connection = establish_connection()?;
-        Ok(Self { connection })
+    pub fn new(target: ConnectionTarget) -> Result<Self> {
+        let path = target.path();
+        let connection = establish_connection(target)?;
+        Ok(Self {
+            connection,
+            path,
+            force_unlock: false,
+            concurrent_frames: false,
+        })
     }
 
-    pub fn current_frame(&mut self) -> Result<Frame> {
+    /// Encrypt the database file with `passphrase`, or change the passphrase of an already
+    /// encrypted database.
+    ///
+    /// The previous database file is kept, its new location is returned.
+    #[cfg(feature = "sqlcipher")]
+    pub fn encrypt(self, passphrase: &str) -> Result<PathBuf> {
+        self.convert(passphrase)
+    }
+
+    /// Store the database unencrypted again.
+    ///
+    /// The encrypted database file is kept, its new location is returned.
+    #[cfg(feature = "sqlcipher")]
+    pub fn decrypt(self) -> Result<PathBuf> {
+        self.convert("")
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    fn convert(mut self, passphrase: &str) -> Result<PathBuf> {
+        let path = self
+            .path
+            .clone()
+            .expect("In memory databases can't be encrypted");
+        let converted = path.with_extension("converted");
+        let backup = path.with_extension("backup");
+
+        crate::encryption::export(&mut self.connection, &converted, passphrase)?;
+        // Close the connection so the write ahead log is merged into the old file.
+        drop(self);
+
+        std::fs::rename(&path, &backup)?;
+        std::fs::rename(&converted, &path)?;
+        Ok(backup)
+    }
+
+    /// Open the database without applying pending migrations, only to inspect them with
+    /// [`Database::migrations`] or apply them with [`Database::migrate`].
+    pub fn new_unmigrated(target: ConnectionTarget) -> Result<Self> {
+        let path = target.path();
+        let connection = connect(target)?;
+        Ok(Self {
+            connection,
+            path,
+            force_unlock: false,
+            concurrent_frames: false,
+        })
+    }
+
+    /// Names of all migrations known to this version of ttt, and whether they were applied.
+    pub fn migrations(&mut self) -> Result<Vec<(String, bool)>> {
+        let failed =
+            |err: Box<dyn std::error::Error + Send + Sync>| Error::Migration(err.to_string());
+        let applied = self.connection.applied_migrations().map_err(failed)?;
+        let known =
+            MigrationSource::<diesel::sqlite::Sqlite>::migrations(&MIGRATIONS).map_err(failed)?;
+        Ok(known
+            .iter()
+            .map(|migration| {
+                let name = migration.name();
+                (name.to_string(), applied.contains(&name.version()))
+            })
+            .collect())
+    }
+
+    /// Apply all pending migrations, returns their names. See [`run_migrations`] for what
+    /// happens if one fails.
+    pub fn migrate(&mut self) -> Result<Vec<String>> {
+        run_migrations(&mut self.connection, self.path.as_deref())
+    }
+
+    /// Copy the database into [`backup_dir`] unless that was already done today, and remove all
+    /// but the newest [`BACKUPS_KEPT`] backups. In memory databases are not backed up.
+    ///
+    /// Returns the new backup.
+    pub fn back_up_daily(&mut self) -> Result<Option<PathBuf>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(None);
+        };
+        let dir = backup_dir(&path);
+        let backup = dir.join(format!(
+            "{}.sqlite",
+            chrono::Local::now().format("%Y-%m-%d")
+        ));
+        if backup.exists() {
+            return Ok(None);
+        }
+        create_dir_all(&dir)?;
+        self.back_up(&backup)?;
+
+        let backups = backups(&path);
+        for old in &backups[..backups.len().saturating_sub(BACKUPS_KEPT)] {
+            std::fs::remove_file(old)?;
+        }
+        Ok(Some(backup))
+    }
+
+    /// Write a consistent copy of the database to the new file `target`, which can be opened
+    /// like the original.
+    pub fn back_up(&mut self, target: &Path) -> Result<()> {
+        back_up(&mut self.connection, target)
+    }
+
+    /// Rebuild the database file without unused space, e.g. after emptying the trash.
+    pub fn vacuum(&mut self) -> Result<()> {
+        self.connection.batch_execute("VACUUM;")?;
+        // Move everything from the write ahead log into the database file, so both shrink.
+        if self.path.is_some() {
+            self.connection
+                .batch_execute("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        }
+        Ok(())
+    }
+
+    /// What is stored in the database, see `ttt db stats`.
+    pub fn stats(&mut self) -> Result<DatabaseStats> {
+        let con = &mut self.connection;
+        let rows = vec![
+            ("frames", frames::table.count().get_result(con)?),
+            ("projects", projects::table.count().get_result(con)?),
+            ("tags", tags::table.count().get_result(con)?),
+            ("tasks", tasks::table.count().get_result(con)?),
+            ("clients", clients::table.count().get_result(con)?),
+            ("rates", rates::table.count().get_result(con)?),
+            ("budgets", budgets::table.count().get_result(con)?),
+            ("rules", rules::table.count().get_result(con)?),
+            (
+                "project aliases",
+                project_aliases::table.count().get_result(con)?,
+            ),
+            (
+                "locked periods",
+                locked_periods::table.count().get_result(con)?,
+            ),
+            ("leave days", leave_days::table.count().get_result(con)?),
+            ("settings", settings::table.count().get_result(con)?),
+        ];
+        let (first_frame, last_start, last_end) = frames::table
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .select((
+                diesel::dsl::min(frames::start),
+                diesel::dsl::max(frames::start),
+                diesel::dsl::max(frames::end),
+            ))
+            .get_result::<(Option<Timestamp>, Option<Timestamp>, Option<Timestamp>)>(con)?;
+        Ok(DatabaseStats {
+            rows,
+            first_frame,
+            last_frame: last_start.max(last_end),
+        })
+    }
+
+    /// Run a SELECT statement from the user, see `ttt sql`, further statements are ignored. File
+    /// databases are queried through a separate read-only connection, so the statement cannot
+    /// change anything.
+    pub fn query_read_only(&mut self, sql: &str) -> Result<QueryResult> {
+        let sql = sql.trim().trim_end_matches(';');
+        match &self.path {
+            Some(path) => query_rows(&mut connect_read_only(path)?, sql),
+            // Only used by tests, a second connection would open another, empty database.
+            None => query_rows(&mut self.connection, sql),
+        }
+    }
+
+    /// Open a fresh database that only lives in memory, with all migrations applied.
+    /// Mainly useful for tests.
+    #[allow(dead_code)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::new(ConnectionTarget::InMemory)
+    }
+
+    /// Add a frame of `project` from `start` to `end` for each of `spans`, running if it has no
+    /// end.
+    #[cfg(test)]
+    pub fn add_test_frames(
+        &mut self,
+        spans: &[(&Project, Timestamp, Option<Timestamp>)],
+    ) -> Result<()> {
+        for (project, start, end) in spans {
+            let uuid = Uuid::new_v4().to_string();
+            let frame = NewFrame {
+                project: Project::id(project),
+                start,
+                end: end.as_ref(),
+                deleted_at: None,
+                uuid: &uuid,
+                updated_at: start,
+                external_ref: None,
+                note: None,
+                source: None,
+                draft: false,
+                kind: FrameKind::Work,
+            };
+            self.merge_frame(&frame, None)?;
+        }
+        Ok(())
+    }
+
+    /// File the database is stored in, `None` for in memory databases.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Allow changes to frames within locked periods.
+    pub fn force_unlock(&mut self) {
+        self.force_unlock = true;
+    }
+
+    /// Allow several frames to run at once, e.g. for a meeting during on-call duty. Starting a
+    /// frame no longer stops the running ones, and overlapping frames are kept by
+    /// [`Database::resolve_overlaps`]. Only one frame per project may run.
+    pub fn allow_concurrent_frames(&mut self) {
+        self.concurrent_frames = true;
+    }
+
+    pub fn allows_concurrent_frames(&self) -> bool {
+        self.concurrent_frames
+    }
+
+    /// Lock `span`, so frames within it can no longer be added, changed or deleted.
+    pub fn lock(&mut self, span: TimeSpan) -> Result<LockedPeriod> {
+        let (start, end, now) = (span.start(), span.end(), Timestamp::now());
+        let period = NewLockedPeriod {
+            start: &start,
+            end: &end,
+            locked_at: &now,
+        };
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(locked_periods::table)
+                .values(&period)
+                .get_result(con)?)
+        })
+    }
+
+    /// All locked periods, the earliest first.
+    pub fn locked_periods(&mut self) -> Result<Vec<LockedPeriod>> {
+        Ok(locked_periods::table
+            .order_by(locked_periods::start)
+            .load(&mut self.connection)?)
+    }
+
+    /// Record a day off, replacing what was recorded for the same day before.
+    pub fn add_leave(&mut self, leave: &LeaveDay) -> Result<()> {
+        diesel::replace_into(leave_days::table)
+            .values(leave)
+            .execute(&mut self.connection)?;
+        Ok(())
+    }
+
+    /// Remove the day off on `day`, returns whether there was one.
+    pub fn remove_leave(&mut self, day: chrono::NaiveDate) -> Result<bool> {
+        let removed = diesel::delete(leave_days::table.filter(leave_days::day.eq(day)))
+            .execute(&mut self.connection)?;
+        Ok(removed > 0)
+    }
+
+    /// The days off that fall into `span`, the earliest first.
+    pub fn leave_days(&mut self, span: TimeSpan) -> Result<Vec<LeaveDay>> {
+        let first = span.start().0.date_naive();
+        // The end of the span is exclusive.
+        let last = (span.end().0 - chrono::Duration::nanoseconds(1)).date_naive();
+        Ok(leave_days::table
+            .filter(leave_days::day.between(first, last))
+            .order_by(leave_days::day)
+            .load(&mut self.connection)?)
+    }
+
+    /// Time tracked per project and day within the days of `span`, ordered by day. Closed frames
+    /// are summed up in the `daily_totals` table, which database triggers update whenever a frame
+    /// changes, so this stays fast for years of frames. Like in reports, frames crossing midnight
+    /// count for each day with the part on it, the running one up to now. Breaks are left out.
+    pub fn daily_totals(&mut self, span: TimeSpan) -> Result<Vec<DailyTotal>> {
+        let first = span.start().0.date_naive();
+        // The end of the span is exclusive.
+        let last = (span.end().0 - chrono::Duration::nanoseconds(1)).date_naive();
+        let mut totals = BTreeMap::<(chrono::NaiveDate, i32), DailyTotal>::new();
+        let mut add = |project: &Project, day, duration| {
+            if !(first..=last).contains(&day) {
+                return;
+            }
+            let total = totals
+                .entry((day, project.id()))
+                .or_insert_with(|| DailyTotal {
+                    project: project.clone(),
+                    day,
+                    duration: chrono::Duration::zero(),
+                });
+            total.duration = total.duration + duration;
+        };
+
+        let cached = daily_totals::table
+            .inner_join(projects::table)
+            .filter(daily_totals::day.between(first, last))
+            .select((
+                projects::all_columns,
+                daily_totals::day,
+                daily_totals::milliseconds,
+            ))
+            .load::<(Project, chrono::NaiveDate, i64)>(&mut self.connection)?;
+        for (project, day, milliseconds) in cached {
+            add(&project, day, chrono::Duration::milliseconds(milliseconds));
+        }
+
+        // The table counts frames fully for the local day they start on. Few cross midnight, so they
+        // are moved to the days they cover here.
+        let crossing = frames::table
+            .inner_join(projects::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .filter(frames::kind.ne(FrameKind::Break))
+            .filter(frames::start.lt(span.end()))
+            .filter(frames::end.gt(span.start()))
+            .filter(sql::<Bool>(
+                "date(frames.start, 'localtime') <> date(frames.end, 'localtime')",
+            ))
+            .load::<(Project, Frame)>(&mut self.connection)?;
+        for (project, frame) in crossing {
+            add(&project, frame.start.0.date_naive(), -frame.duration());
+            for day in frame.span().into_iter().flat_map(TimeSpan::split_by_day) {
+                add(&project, day.start().0.date_naive(), day.duration());
+            }
+        }
+
+        for running in self.current_frames()? {
+            if running.kind == FrameKind::Break {
+                continue;
+            }
+            let project = self
+                .lookup_project(running.project)?
+                .ok_or_else(|| Error::ProjectNotFound(running.project.to_string()))?;
+            for day in running.span().into_iter().flat_map(TimeSpan::split_by_day) {
+                add(&project, day.start().0.date_naive(), day.duration());
+            }
+        }
+        Ok(totals
+            .into_values()
+            .filter(|total| !total.duration.is_zero())
+            .collect())
+    }
+
+    /// Fail if the time from `start` to `end` overlaps a locked period. Sync is not checked, as
+    /// other machines would otherwise never agree on the frames.
+    fn check_unlocked(&mut self, start: Timestamp, end: Timestamp) -> Result<()> {
+        if self.force_unlock {
+            return Ok(());
+        }
+        Self::check_unlocked_impl(&mut self.connection, start, end)
+    }
+
+    fn check_unlocked_impl(
+        connection: &mut SqliteConnection,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<()> {
+        let locked = locked_periods::table
+            .filter(locked_periods::start.lt(end))
+            .filter(locked_periods::end.gt(start))
+            .first::<LockedPeriod>(connection)
+            .optional()?;
+        match locked {
+            Some(period) => Err(Error::PeriodLocked(period.span())),
+            None => Ok(()),
+        }
+    }
+
+    /// All running frames, the most recently started one last. There is at most one unless
+    /// [`Database::allow_concurrent_frames`] was used, or another machine ran a frame at the same
+    /// time, see `ttt sync`.
+    pub fn current_frames(&mut self) -> Result<Vec<Frame>> {
+        Self::current_frames_impl(&mut self.connection)
+    }
+
+    fn current_frames_impl(connection: &mut SqliteConnection) -> Result<Vec<Frame>> {
         use crate::schema::frames::dsl::*;
-        let mut current = frames
+        Ok(frames
             .filter(end.is_null())
-            .load::<Frame>(&mut self.connection)?;
-        current.pop().ok_or(Error::NoActiveFrame)
+            .filter(deleted_at.is_null())
+            .filter(draft.eq(false))
+            .order_by((start, id))
+            .load::<Frame>(connection)?)
+    }
+
+    /// The most recently started of the [`Database::current_frames`].
+    pub fn current_frame(&mut self) -> Result<Frame> {
+        self.current_frames()?.pop().ok_or(Error::NoActiveFrame)
+    }
+
+    /// Start a new frame for the given project, optionally referencing an issue or ticket.
+    /// `source` is recorded as where the frame was started from.
+    ///
+    /// Fails if a frame is running already, or with concurrent frames if one of the same project
+    /// is running.
+    pub fn start(
+        &mut self,
+        project: &mut Project,
+        external_ref: Option<&str>,
+        source: FrameSource,
+    ) -> Result<Frame> {
+        self.start_frame(project, None, external_ref, FrameKind::Work, source)
+    }
+
+    /// Start a break, which is tracked for `project` but does not count as working time, see
+    /// [`FrameKind::Break`]. The running frames are stopped in the same transaction, also with
+    /// concurrent frames, so none of them counts the break as work.
+    ///
+    /// Returns the stopped frames and the break. Fails if a break is running already.
+    pub fn start_break(
+        &mut self,
+        project: &mut Project,
+        source: FrameSource,
+    ) -> Result<(Vec<Frame>, Frame)> {
+        let now = Timestamp::now();
+        let force_unlock = self.force_unlock;
+        write_transaction(&mut self.connection, |con| {
+            let running = Self::current_frames_impl(con)?;
+            if let Some(existing) = running.iter().find(|frame| frame.kind == FrameKind::Break) {
+                return Err(Error::AlreadyTracking(Box::new(existing.clone())));
+            }
+            let mut stopped = Vec::new();
+            for mut frame in running {
+                if !force_unlock {
+                    Self::check_unlocked_impl(con, frame.start, now)?;
+                }
+                frame.end = Some(now);
+                frame.updated_at = now;
+                Self::update_frame_impl(con, &frame)?;
+                stopped.push(frame);
+            }
+            if !force_unlock {
+                Self::check_unlocked_impl(con, now, now + chrono::Duration::seconds(1))?;
+            }
+            let started = Self::insert_running_frame_impl(
+                con,
+                project,
+                None,
+                None,
+                FrameKind::Break,
+                source,
+            )?;
+            Ok((stopped, started))
+        })
+    }
+
+    /// Stop the running break with the given id and continue work on its project, both in one
+    /// transaction.
+    ///
+    /// Returns the stopped break and the new frame. Fails like [`Database::start`] if another
+    /// frame is in the way.
+    pub fn end_break(&mut self, break_id: i32, source: FrameSource) -> Result<(Frame, Frame)> {
+        let now = Timestamp::now();
+        let force_unlock = self.force_unlock;
+        let concurrent = self.concurrent_frames;
+        write_transaction(&mut self.connection, |con| {
+            let mut ended = Self::current_frames_impl(con)?
+                .into_iter()
+                .find(|frame| frame.id() == break_id && frame.kind == FrameKind::Break)
+                .ok_or(Error::FrameNotFound(break_id))?;
+            if !force_unlock {
+                Self::check_unlocked_impl(con, ended.start, now + chrono::Duration::seconds(1))?;
+            }
+            ended.end = Some(now);
+            ended.updated_at = now;
+            Self::update_frame_impl(con, &ended)?;
+
+            let mut project: Project = projects::table.find(ended.project).first(con)?;
+            Self::check_not_tracking_impl(con, concurrent, &project)?;
+            let started = Self::insert_running_frame_impl(
+                con,
+                &mut project,
+                None,
+                None,
+                FrameKind::Work,
+                source,
+            )?;
+            Ok((ended, started))
+        })
+    }
+
+    /// Like [`Database::start`], but tracked against a task of the project.
+    pub fn start_task(
+        &mut self,
+        project: &mut Project,
+        task: &Task,
+        external_ref: Option<&str>,
+        source: FrameSource,
+    ) -> Result<Frame> {
+        if task.project_id != project.id() {
+            return Err(Error::TaskNotFound(format!(
+                "{}/{}",
+                project.name, task.name
+            )));
+        }
+        self.start_frame(
+            project,
+            Some(task.id()),
+            external_ref,
+            FrameKind::Work,
+            source,
+        )
+    }
+
+    fn start_frame(
+        &mut self,
+        project: &mut Project,
+        task_id: Option<i32>,
+        external_ref: Option<&str>,
+        kind: FrameKind,
+        source: FrameSource,
+    ) -> Result<Frame> {
+        Self::check_not_tracking_impl(&mut self.connection, self.concurrent_frames, project)?;
+        let now = Timestamp::now();
+        self.check_unlocked(now, now + chrono::Duration::seconds(1))?;
+        write_transaction(&mut self.connection, |con| {
+            Self::insert_running_frame_impl(con, project, task_id, external_ref, kind, source)
+        })
     }
 
-    /// Start a new frame for the given project.
-    pub fn start(&mut self, project: &mut Project) -> Result<Frame> {
-        if let Ok(existing) = self.current_frame() {
-            return Err(Error::AlreadyTracking(existing));
+    /// Fail if a frame is running, or with `concurrent` frames if one of `project` is.
+    fn check_not_tracking_impl(
+        connection: &mut SqliteConnection,
+        concurrent: bool,
+        project: &Project,
+    ) -> Result<()> {
+        let running = Self::current_frames_impl(connection)?;
+        let blocking = match concurrent {
+            true => running
+                .into_iter()
+                .find(|frame| frame.project == project.id()),
+            false => running.into_iter().last(),
+        };
+        match blocking {
+            Some(existing) => Err(Error::AlreadyTracking(Box::new(existing))),
+            None => Ok(()),
         }
+    }
 
+    /// Insert a frame of `project` that starts now and touch the project.
+    fn insert_running_frame_impl(
+        connection: &mut SqliteConnection,
+        project: &mut Project,
+        task_id: Option<i32>,
+        external_ref: Option<&str>,
+        kind: FrameKind,
+        source: FrameSource,
+    ) -> Result<Frame> {
         let now = Timestamp::now();
+        let uuid = Uuid::new_v4().to_string();
         let frame = NewFrame {
             project: project.id(),
             start: &now,
             end: None,
+            deleted_at: None,
+            uuid: &uuid,
+            updated_at: &now,
+            external_ref,
+            note: None,
+            source: Some(source),
+            draft: false,
+            kind,
         };
-        self.connection.transaction(|con| {
-            Self::write_projects_impl(con, std::iter::once(project))?;
-            Ok(diesel::insert_into(frames::table)
-                .values(&frame)
-                .get_result(con)?)
-        })
+        Self::write_projects_impl(connection, std::iter::once(&mut *project))?;
+        let frame = diesel::insert_into(frames::table)
+            .values((&frame, frames::task_id.eq(task_id)))
+            .get_result(connection)?;
+        Self::apply_rules_to_frame(connection, &frame)?;
+        Ok(frame)
     }
 
     /// Stop the currently running frame, if any.
@@ -70,23 +654,230 @@ impl Database {
     ///
     /// Returns the stopped frame if it was stopped or None in case no frame was active.
     ///
-    /// ```no_run
+    /// ```
     /// # use ttt::database::Database;
-    /// let mut db = Database::new().unwrap();
+    /// let mut db = Database::open_in_memory().unwrap();
     /// assert!(db.stop().unwrap().is_none());
     /// ```
+    ///
+    /// With concurrent frames, the one started last is stopped, see [`Database::stop_frame`] to
+    /// choose another one.
     pub fn stop(&mut self) -> Result<Option<Frame>> {
-        let mut frame = match self.current_frame() {
-            Ok(frame) => frame,
-            Err(Error::NoActiveFrame) => return Ok(None),
-            Err(e) => return Err(e),
-        };
+        match self.current_frame() {
+            Ok(frame) => Ok(Some(self.stop_frame(frame.id())?)),
+            Err(Error::NoActiveFrame) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [`Database::stop`], but also returns the project and tags of the stopped frame, e.g.
+    /// to print a summary.
+    pub fn stop_detailed(&mut self) -> Result<Option<FrameDetails>> {
+        match self.stop()? {
+            Some(frame) => Ok(Some(self.frame_details(frame.id())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The frame with the given id together with its project and the project's tags, sorted by
+    /// name, all loaded in one query.
+    pub fn frame_details(&mut self, frame_id: i32) -> Result<FrameDetails> {
+        let rows: Vec<(Frame, Project, Option<Tag>)> = frames::table
+            .inner_join(projects::table)
+            .left_join(tags_per_project::table.on(tags_per_project::project_id.eq(projects::id)))
+            .left_join(tags::table.on(tags::id.eq(tags_per_project::tag_id)))
+            .filter(frames::id.eq(frame_id))
+            .order_by(tags::name)
+            .select((
+                frames::all_columns,
+                projects::all_columns,
+                tags::all_columns.nullable(),
+            ))
+            .load(&mut self.connection)?;
+        let mut rows = rows.into_iter();
+        let (frame, project, tag) = rows.next().ok_or(Error::FrameNotFound(frame_id))?;
+        let tags = tag
+            .into_iter()
+            .chain(rows.filter_map(|(_, _, tag)| tag))
+            .collect();
+        Ok(FrameDetails {
+            frame,
+            project,
+            tags,
+        })
+    }
+
+    /// Stop the running frame with the given id.
+    pub fn stop_frame(&mut self, frame_id: i32) -> Result<Frame> {
+        self.stop_frame_snapped(frame_id, None)
+    }
+
+    /// Like [`Database::stop_frame`], but with `snap_minutes` the end is moved back to the last
+    /// whole multiple of that many minutes, see [`Timestamp::round_down`], so a short break
+    /// before stopping is not counted. Frames that would end before they started are stopped
+    /// now.
+    pub fn stop_frame_snapped(
+        &mut self,
+        frame_id: i32,
+        snap_minutes: Option<u32>,
+    ) -> Result<Frame> {
+        let mut frame = self
+            .current_frames()?
+            .into_iter()
+            .find(|frame| frame.id() == frame_id)
+            .ok_or(Error::FrameNotFound(frame_id))?;
 
         let now = Timestamp::now();
-        frame.end = Some(now);
+        let end = snap_minutes
+            .map(|minutes| now.round_down(minutes))
+            .filter(|end| *end > frame.start)
+            .unwrap_or(now);
+        self.check_unlocked(frame.start, end)?;
+        frame.end = Some(end);
+        frame.updated_at = now;
         self.update_frame(&frame)?;
 
-        Ok(Some(frame))
+        Ok(frame)
+    }
+
+    /// Change the running frame with the given id without stopping it. Only the given fields are
+    /// written: it is moved to `project`, tracked against `task`, and `note` is appended to its
+    /// note. Moving it to another project takes it off its task, unless a task of the new project
+    /// is given.
+    ///
+    /// # Errors
+    /// Fails if the task belongs to another project, or with concurrent frames if a frame of
+    /// `project` is running already.
+    pub fn amend_frame(
+        &mut self,
+        frame_id: i32,
+        project: Option<&Project>,
+        task: Option<&Task>,
+        note: Option<&str>,
+    ) -> Result<Frame> {
+        let running = self.current_frames()?;
+        let frame = running
+            .iter()
+            .find(|frame| Frame::id(frame) == frame_id)
+            .ok_or(Error::FrameNotFound(frame_id))?;
+        let project_id = project.map_or(frame.project, Project::id);
+        if let Some(task) = task {
+            if task.project_id != project_id {
+                return Err(Error::TaskNotFound(task.name.clone()));
+            }
+        }
+        if let Some(other) = running
+            .iter()
+            .find(|other| Frame::id(other) != frame_id && other.project == project_id)
+        {
+            return Err(Error::AlreadyTracking(Box::new(other.clone())));
+        }
+
+        let now = Timestamp::now();
+        self.check_unlocked(frame.start, now)?;
+        let moved = project_id != frame.project;
+        let task_id = match task {
+            Some(task) => Some(Some(task.id())),
+            None if moved => Some(None),
+            None => None,
+        };
+        let note = note.map(|note| match &frame.note {
+            Some(previous) => format!("{previous}; {note}"),
+            None => note.to_owned(),
+        });
+        let changes = FrameChanges {
+            project: moved.then_some(project_id),
+            task_id,
+            note: note.as_deref(),
+            updated_at: &now,
+        };
+        write_transaction(&mut self.connection, |con| {
+            let frame = diesel::update(frames::table.find(frame_id))
+                .filter(frames::end.is_null())
+                .set(&changes)
+                .get_result(con)?;
+            Self::apply_rules_to_frame(con, &frame)?;
+            diesel::update(projects::table.find(project_id))
+                .set(projects::last_access_time.eq(now))
+                .execute(con)?;
+            Ok(frame)
+        })
+    }
+
+    /// Stop the running frame to make room for a new one, nothing is stopped with concurrent
+    /// frames.
+    pub fn stop_before_start(&mut self) -> Result<Option<Frame>> {
+        if self.concurrent_frames {
+            return Ok(None);
+        }
+        self.stop()
+    }
+
+    /// Stop running frames at the first `at` after their start once that time has passed, so a
+    /// frame forgotten at the end of the workday does not run overnight. The frames get a note
+    /// saying so.
+    ///
+    /// Returns the stopped frames.
+    pub fn auto_stop(&mut self, at: NaiveTime) -> Result<Vec<Frame>> {
+        let now = Timestamp::now();
+        let mut stopped = Vec::new();
+        for mut frame in self.current_frames()? {
+            let start = frame.start.to_naive();
+            let mut end = start.date().and_time(at);
+            if end <= start {
+                end += chrono::Duration::days(1);
+            }
+            let end = Timestamp::from_naive(end);
+            if end > now {
+                continue;
+            }
+            self.check_unlocked(frame.start, end)?;
+            frame.end = Some(end);
+            frame.updated_at = now;
+            let note = format!("Stopped automatically at {}", at.format("%H:%M"));
+            frame.note = Some(match frame.note {
+                Some(previous) => format!("{previous}; {note}"),
+                None => note,
+            });
+            self.update_frame(&frame)?;
+            stopped.push(frame);
+        }
+        Ok(stopped)
+    }
+
+    /// Add an already finished frame, e.g. for time that was forgotten to be tracked, added
+    /// from `source`.
+    pub fn add_frame(
+        &mut self,
+        project: &mut Project,
+        span: TimeSpan,
+        source: FrameSource,
+    ) -> Result<Frame> {
+        let (start, end) = (span.start(), span.end());
+        self.check_unlocked(start, end)?;
+        let now = Timestamp::now();
+        let uuid = Uuid::new_v4().to_string();
+        let frame = NewFrame {
+            project: project.id(),
+            start: &start,
+            end: Some(&end),
+            deleted_at: None,
+            uuid: &uuid,
+            updated_at: &now,
+            external_ref: None,
+            note: None,
+            source: Some(source),
+            draft: false,
+            kind: FrameKind::Work,
+        };
+        write_transaction(&mut self.connection, |con| {
+            Self::write_projects_impl(con, std::iter::once(&mut *project))?;
+            let frame = diesel::insert_into(frames::table)
+                .values(&frame)
+                .get_result(con)?;
+            Self::apply_rules_to_frame(con, &frame)?;
+            Ok(frame)
+        })
     }
 
     /// Search the project for the given id. Return None if no project belongs to that id.
@@ -127,16 +918,35 @@ impl Database {
                     .inner_join(frames::table)
                     .select(frames::all_columns)
                     .filter(projects::archived.eq(matches!(state, ArchivedState::OnlyArchived)))
+                    .filter(frames::deleted_at.is_null())
+                    .filter(frames::draft.eq(false))
                     .order_by(frames::start)
                     .load::<Frame>(&mut self.connection)?)
             }
 
             ArchivedState::Both => Ok(frames::table
+                .filter(frames::deleted_at.is_null())
+                .filter(frames::draft.eq(false))
                 .order_by(frames::start)
                 .load::<Frame>(&mut self.connection)?),
         }
     }
 
+    /// The breaks overlapping `span`, including a running one, which
+    /// [`Database::daily_totals`] leaves out.
+    pub fn breaks_in_span(&mut self, span: TimeSpan) -> Result<Vec<(Project, Frame)>> {
+        Ok(frames::table
+            .inner_join(projects::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::kind.eq(FrameKind::Break))
+            .filter(frames::end.gt(span.start()).or(frames::end.is_null()))
+            .filter(frames::start.lt(span.end()))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .order_by(frames::start)
+            .load(&mut self.connection)?)
+    }
+
     pub fn get_frames_in_span(
         &mut self,
         span: TimeSpan,
@@ -151,6 +961,8 @@ impl Database {
                     .filter(frames::end.ge(span.start()))
                     .or_filter(frames::end.is_null())
                     .filter(frames::start.lt(span.end()))
+                    .filter(frames::deleted_at.is_null())
+                    .filter(frames::draft.eq(false))
                     .order_by(frames::start)
                     .load::<(Project, Frame)>(&mut self.connection)?)
             }
@@ -161,80 +973,724 @@ impl Database {
                 .filter(frames::end.ge(span.start()))
                 .or_filter(frames::end.is_null())
                 .filter(frames::start.lt(span.end()))
+                .filter(frames::deleted_at.is_null())
+                .filter(frames::draft.eq(false))
                 .order_by(frames::start)
                 .load::<(Project, Frame)>(&mut self.connection)?),
         }
     }
 
-    /// Write the given projects into the database.
-    #[allow(dead_code)]
-    pub fn write_projects<'a>(
+    /// The frames of the project with the id `project_id` overlapping `span`, ordered by start.
+    pub fn project_frames_in_span(
         &mut self,
-        items: impl IntoIterator<Item = &'a mut Project>,
-    ) -> Result<()> {
-        Self::write_projects_impl(&mut self.connection, items)
+        project_id: i32,
+        span: TimeSpan,
+    ) -> Result<Vec<Frame>> {
+        Ok(frames::table
+            .filter(frames::project.eq(project_id))
+            .filter(frames::end.ge(span.start()).or(frames::end.is_null()))
+            .filter(frames::start.lt(span.end()))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .order_by(frames::start)
+            .load(&mut self.connection)?)
     }
 
-    fn write_projects_impl<'a>(
-        connection: &mut SqliteConnection,
-        items: impl IntoIterator<Item = &'a mut Project>,
-    ) -> Result<()> {
-        connection.transaction(|connection| {
-            use crate::schema::projects::dsl::*;
-            let now = Timestamp::now();
-            for item in items {
-                item.last_access_time = now;
-                diesel::insert_into(projects)
-                    .values(&*item)
-                    .on_conflict(id)
-                    .do_update()
-                    .set(&*item)
-                    .execute(connection)?;
-            }
-            Ok(())
-        })
+    /// Return the frames selected by `filter`, within `span` if given, together with their
+    /// project, ordered by start.
+    pub fn filtered_frames(
+        &mut self,
+        span: Option<TimeSpan>,
+        filter: &Filter,
+    ) -> Result<Vec<(Project, Frame)>> {
+        let mut query = frames::table
+            .inner_join(projects::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .filter(filter.to_sql())
+            .order_by(frames::start)
+            .into_boxed();
+        if let Some(span) = span {
+            query = query
+                .filter(frames::end.ge(span.start()).or(frames::end.is_null()))
+                .filter(frames::start.lt(span.end()));
+        }
+        Ok(query.load::<(Project, Frame)>(&mut self.connection)?)
     }
 
-    /// Create a new tag and return it.
-    pub fn create_tag(&mut self, name: impl AsRef<str>) -> Result<Tag> {
-        let new_tag = NewTag {
-            name: name.as_ref(),
-            last_access_time: &Timestamp::now(),
-        };
-        Ok(diesel::insert_into(tags::table)
-            .values(&new_tag)
-            .get_result(&mut self.connection)?)
+    /// Like [`Database::get_frames_in_span`], but the frames are loaded in chunks while iterating,
+    /// so even exports of huge histories need little memory.
+    pub fn frames_in_span_iter(
+        &mut self,
+        span: TimeSpan,
+        include_archived: ArchivedState,
+    ) -> FramesInSpan<'_> {
+        FramesInSpan {
+            database: self,
+            span,
+            include_archived,
+            chunk_size: FRAME_CHUNK_SIZE,
+            last: None,
+            chunk: Vec::new().into_iter(),
+            done: false,
+        }
     }
 
-    /// Create a new project and return it.
-    pub fn create_project(&mut self, name: impl AsRef<str>) -> Result<Project> {
-        let new_project = NewProject {
-            name: name.as_ref(),
-            last_access_time: &Timestamp::now(),
+    /// The next `limit` frames of [`FramesInSpan`], ordered by start and id, after the frame with
+    /// the start and id `after`.
+    fn frames_in_span_chunk(
+        &mut self,
+        span: TimeSpan,
+        include_archived: ArchivedState,
+        after: Option<(Timestamp, i32)>,
+        limit: i64,
+    ) -> Result<Vec<(Project, Frame)>> {
+        let mut query = frames::table
+            .inner_join(projects::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::end.ge(span.start()).or(frames::end.is_null()))
+            .filter(frames::start.lt(span.end()))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .order_by((frames::start, frames::id))
+            .limit(limit)
+            .into_boxed();
+        if let Some((start, id)) = after {
+            query = query.filter(
+                frames::start
+                    .gt(start)
+                    .or(frames::start.eq(start).and(frames::id.gt(id))),
+            );
+        }
+        query = match include_archived {
+            ArchivedState::NotArchived => query.filter(projects::archived.eq(false)),
+            ArchivedState::OnlyArchived => query.filter(projects::archived.eq(true)),
+            ArchivedState::Both => query,
         };
-        Ok(diesel::insert_into(projects::table)
-            .values(&new_project)
-            .get_result(&mut self.connection)?)
+        Ok(query.load(&mut self.connection)?)
     }
 
-    /// Write the given tags to the database.
-    /// This function acts as a transaction, the database is only modified if all tags can be
-    /// written successfully.
-    #[allow(dead_code)]
-    pub fn write_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a mut Tag>) -> Result<()> {
-        Self::write_tags_impl(&mut self.connection, tags)
+    /// Move the frame with the given id to the trash.
+    /// Returns the deleted frame.
+    pub fn delete_frame(&mut self, frame_id: i32) -> Result<Frame> {
+        if let Some(frame) = frames::table
+            .find(frame_id)
+            .get_result::<Frame>(&mut self.connection)
+            .optional()?
+        {
+            self.check_unlocked(frame.start, frame.end.unwrap_or_else(Timestamp::now))?;
+        }
+        let now = Timestamp::now();
+        write_transaction(&mut self.connection, |con| {
+            diesel::update(frames::table)
+                .filter(frames::id.eq(frame_id))
+                .filter(frames::deleted_at.is_null())
+                .set((frames::deleted_at.eq(now), frames::updated_at.eq(now)))
+                .get_result(con)
+                .optional()?
+                .ok_or(Error::FrameNotFound(frame_id))
+        })
     }
 
-    fn write_tags_impl<'a>(
-        connection: &mut SqliteConnection,
-        items: impl IntoIterator<Item = &'a mut Tag>,
-    ) -> Result<()> {
-        connection.transaction(|connection| {
-            use crate::schema::tags::dsl::*;
-            let now = Timestamp::now();
-            for item in items {
-                item.last_access_time = now;
-                diesel::insert_into(tags)
+    /// Change whether a frame is work, a break or on-call time, see `ttt mark`.
+    pub fn set_frame_kind(&mut self, frame_id: i32, kind: FrameKind) -> Result<Frame> {
+        let frame = frames::table
+            .find(frame_id)
+            .filter(frames::deleted_at.is_null())
+            .get_result::<Frame>(&mut self.connection)
+            .optional()?
+            .ok_or(Error::FrameNotFound(frame_id))?;
+        let now = Timestamp::now();
+        self.check_unlocked(frame.start, frame.end.unwrap_or(now))?;
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::update(frames::table.find(frame_id))
+                .set((frames::kind.eq(kind), frames::updated_at.eq(now)))
+                .get_result(con)?)
+        })
+    }
+
+    /// Groups of frames within `span` that could be merged into one, see `ttt dedupe`: frames of
+    /// the same project, task, ref and kind, each starting less than `gap` after the previous one
+    /// ended. A frame of another kind in between, like a break, ends the group. Groups are ordered
+    /// by their start.
+    pub fn mergeable_frames(
+        &mut self,
+        span: TimeSpan,
+        gap: chrono::Duration,
+    ) -> Result<Vec<Vec<Frame>>> {
+        let frames: Vec<Frame> = frames::table
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::draft.eq(false))
+            .filter(frames::end.ge(span.start()).or(frames::end.is_null()))
+            .filter(frames::start.lt(span.end()))
+            .order_by((frames::start, frames::id))
+            .load(&mut self.connection)?;
+
+        // Project, task, ref and kind
+        type Key = (i32, Option<i32>, Option<String>, FrameKind);
+        let mut open: HashMap<Key, Vec<Frame>> = HashMap::new();
+        let mut groups = Vec::new();
+        for frame in frames {
+            for (_, group) in open.iter_mut().filter(|((project, .., kind), group)| {
+                *project == frame.project && *kind != frame.kind && !group.is_empty()
+            }) {
+                groups.push(std::mem::take(group));
+            }
+            let key = (
+                frame.project,
+                frame.task_id,
+                frame.external_ref.clone(),
+                frame.kind,
+            );
+            let group = open.entry(key).or_default();
+            let follows = group
+                .last()
+                .and_then(|previous| previous.end)
+                .is_some_and(|end| frame.start.0 - end.0 < gap);
+            if !follows && !group.is_empty() {
+                groups.push(std::mem::take(group));
+            }
+            group.push(frame);
+        }
+        groups.extend(open.into_values());
+        groups.retain(|group| group.len() > 1);
+        groups.sort_by_key(|group| group[0].start);
+        Ok(groups)
+    }
+
+    /// Merge `frames` into the first of them, which then lasts until the last one ends. The
+    /// notes are joined and the other frames moved to the trash. If one of them is running, the
+    /// merged frame keeps running.
+    ///
+    /// Returns the merged frame.
+    ///
+    /// # Panics
+    /// Panics if `frames` is empty.
+    pub fn merge_frames(&mut self, frames: &[Frame]) -> Result<Frame> {
+        let (first, rest) = frames.split_first().expect("There are frames to merge");
+        let now = Timestamp::now();
+        let end = frames.iter().try_fold(first.start, |latest, frame| {
+            frame.end.map(|end| latest.max(end))
+        });
+        self.check_unlocked(first.start, end.unwrap_or(now))?;
+        let notes: Vec<_> = frames
+            .iter()
+            .filter_map(|frame| frame.note.as_deref())
+            .collect();
+        let note = (!notes.is_empty()).then(|| notes.join("; "));
+        write_transaction(&mut self.connection, |con| {
+            for frame in rest {
+                diesel::update(frames::table.find(frame.id()))
+                    .filter(frames::deleted_at.is_null())
+                    .set((frames::deleted_at.eq(now), frames::updated_at.eq(now)))
+                    .execute(con)?;
+            }
+            diesel::update(frames::table.find(first.id()))
+                .set((
+                    frames::end.eq(end),
+                    frames::note.eq(&note),
+                    frames::updated_at.eq(now),
+                ))
+                .get_result(con)
+                .optional()?
+                .ok_or(Error::FrameNotFound(first.id()))
+        })
+    }
+
+    /// Find the frame whose uuid starts with `id`, usually a [`Frame::short_id`]. Frames in the
+    /// trash are included.
+    ///
+    /// # Errors
+    /// Fails if no frame or more than one frame matches.
+    pub fn resolve_frame(&mut self, id: &str) -> Result<Frame> {
+        let id = id.to_lowercase();
+        // Also keeps LIKE wildcards out of the pattern.
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(Error::UnknownFrameId(id));
+        }
+        let mut candidates: Vec<Frame> = frames::table
+            .filter(frames::uuid.like(format!("{id}%")))
+            .limit(2)
+            .load(&mut self.connection)?;
+        match candidates.len() {
+            0 => Err(Error::UnknownFrameId(id)),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(Error::AmbiguousFrameId(id)),
+        }
+    }
+
+    /// Return all frames in the trash together with their project, most recently deleted last.
+    pub fn trashed_frames(&mut self) -> Result<Vec<(Project, Frame)>> {
+        Ok(frames::table
+            .inner_join(projects::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::deleted_at.is_not_null())
+            .order_by(frames::deleted_at)
+            .load::<(Project, Frame)>(&mut self.connection)?)
+    }
+
+    /// Take the frame with the given id out of the trash.
+    ///
+    /// Restoring a frame that was still running fails if another frame is active in the meantime.
+    pub fn restore_frame(&mut self, frame_id: i32) -> Result<Frame> {
+        let frame: Frame = frames::table
+            .filter(frames::id.eq(frame_id))
+            .filter(frames::deleted_at.is_not_null())
+            .get_result(&mut self.connection)
+            .optional()?
+            .ok_or(Error::FrameNotFound(frame_id))?;
+        self.check_unlocked(frame.start, frame.end.unwrap_or_else(Timestamp::now))?;
+
+        if frame.end.is_none() && !self.concurrent_frames {
+            if let Ok(existing) = self.current_frame() {
+                return Err(Error::AlreadyTracking(Box::new(existing)));
+            }
+        }
+
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::update(&frame)
+                .set((
+                    frames::deleted_at.eq(None::<Timestamp>),
+                    frames::updated_at.eq(Timestamp::now()),
+                ))
+                .get_result(con)?)
+        })
+    }
+
+    /// Permanently delete all frames in the trash.
+    /// Returns the number of deleted frames.
+    pub fn empty_trash(&mut self) -> Result<usize> {
+        write_transaction(&mut self.connection, |con| {
+            Ok(
+                diesel::delete(frames::table.filter(frames::deleted_at.is_not_null()))
+                    .execute(con)?,
+            )
+        })
+    }
+
+    /// Frames waiting to be confirmed or discarded with `ttt review`, oldest first.
+    pub fn drafts(&mut self) -> Result<Vec<(Project, Frame)>> {
+        Ok(projects::table
+            .inner_join(frames::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::draft.eq(true))
+            .filter(frames::deleted_at.is_null())
+            .order_by(frames::start)
+            .load(&mut self.connection)?)
+    }
+
+    /// Turn the drafts in `confirm` into regular frames, which count in reports, and move the
+    /// drafts in `discard` to the trash, see `ttt review`. Nothing changes if any of them is
+    /// within a locked period.
+    ///
+    /// Returns the number of confirmed and discarded drafts.
+    pub fn review_drafts(&mut self, confirm: &[i32], discard: &[i32]) -> Result<(usize, usize)> {
+        let now = Timestamp::now();
+        let force_unlock = self.force_unlock;
+        write_transaction(&mut self.connection, |con| {
+            let drafts: Vec<Frame> = frames::table
+                .filter(frames::id.eq_any([confirm, discard].concat()))
+                .filter(frames::draft.eq(true))
+                .filter(frames::deleted_at.is_null())
+                .load(con)?;
+            if !force_unlock {
+                for draft in &drafts {
+                    Self::check_unlocked_impl(con, draft.start, draft.end.unwrap_or(now))?;
+                }
+            }
+            let (confirmed, discarded): (Vec<_>, Vec<_>) = drafts
+                .iter()
+                .partition(|draft| confirm.contains(&Frame::id(draft)));
+            for draft in &confirmed {
+                Self::apply_rules_to_frame(con, draft)?;
+            }
+            let confirmed = diesel::update(frames::table)
+                .filter(frames::id.eq_any(confirmed.into_iter().map(Frame::id)))
+                .set((frames::draft.eq(false), frames::updated_at.eq(now)))
+                .execute(con)?;
+            let discarded = diesel::update(frames::table)
+                .filter(frames::id.eq_any(discarded.into_iter().map(Frame::id)))
+                .set((frames::deleted_at.eq(now), frames::updated_at.eq(now)))
+                .execute(con)?;
+            Ok((confirmed, discarded))
+        })
+    }
+
+    /// Return all frames, including those in the trash, which were changed after `since`. Drafts
+    /// are left out until they are confirmed.
+    pub fn frames_changed_since(
+        &mut self,
+        since: Option<Timestamp>,
+    ) -> Result<Vec<(Project, Frame)>> {
+        let mut query = projects::table
+            .inner_join(frames::table)
+            .select((projects::all_columns, frames::all_columns))
+            .filter(frames::draft.eq(false))
+            .into_boxed();
+        if let Some(since) = since {
+            query = query.filter(frames::updated_at.gt(since));
+        }
+        Ok(query
+            .order_by(frames::updated_at)
+            .load::<(Project, Frame)>(&mut self.connection)?)
+    }
+
+    /// Insert a frame which was created somewhere else, e.g. on another machine, tracked against
+    /// the task `task_id`.
+    ///
+    /// If a frame with the same uuid already exists it is only overwritten if the given frame
+    /// was updated more recently. Returns whether the database was changed.
+    pub fn merge_frame(&mut self, frame: &NewFrame, task_id: Option<i32>) -> Result<bool> {
+        write_transaction(&mut self.connection, |con| {
+            let existing: Option<Frame> = frames::table
+                .filter(frames::uuid.eq(frame.uuid))
+                .get_result(con)
+                .optional()?;
+
+            match existing {
+                None => {
+                    diesel::insert_into(frames::table)
+                        .values((frame, frames::task_id.eq(task_id)))
+                        .execute(con)?;
+                    Ok(true)
+                }
+                Some(existing) if existing.updated_at < *frame.updated_at => {
+                    diesel::update(&existing)
+                        .set((
+                            frames::project.eq(frame.project),
+                            frames::start.eq(frame.start),
+                            frames::end.eq(frame.end),
+                            frames::deleted_at.eq(frame.deleted_at),
+                            frames::updated_at.eq(frame.updated_at),
+                            frames::external_ref.eq(frame.external_ref),
+                            frames::note.eq(frame.note),
+                            frames::task_id.eq(task_id),
+                            frames::kind.eq(frame.kind),
+                        ))
+                        .execute(con)?;
+                    Ok(true)
+                }
+                Some(_) => Ok(false),
+            }
+        })
+    }
+
+    /// Insert many frames at once, e.g. from an importer, with multi-row inserts in a single
+    /// transaction. With `skip_duplicates`, frames are left out if a stored or earlier frame has
+    /// the same uuid, or the same project, start and end. Nothing is inserted if any frame is
+    /// within a locked period.
+    ///
+    /// Returns the number of inserted frames.
+    pub fn insert_frames_bulk(
+        &mut self,
+        frames: Vec<NewFrame>,
+        skip_duplicates: bool,
+    ) -> Result<usize> {
+        if !self.force_unlock {
+            let locked = self.locked_periods()?;
+            for frame in &frames {
+                let end = frame.end.copied().unwrap_or_else(Timestamp::now);
+                let overlapping = locked
+                    .iter()
+                    .find(|period| period.start < end && period.end > *frame.start);
+                if let Some(period) = overlapping {
+                    return Err(Error::PeriodLocked(period.span()));
+                }
+            }
+        }
+
+        write_transaction(&mut self.connection, |con| {
+            let mut uuids = HashSet::new();
+            let mut keys = BTreeSet::new();
+            let mut inserted = 0;
+            for chunk in frames.chunks(BULK_INSERT_CHUNK_SIZE) {
+                let mut chunk: Vec<_> = chunk.iter().collect();
+                if skip_duplicates {
+                    uuids.extend(
+                        frames::table
+                            .select(frames::uuid)
+                            .filter(frames::uuid.eq_any(chunk.iter().map(|frame| frame.uuid)))
+                            .load::<String>(con)?,
+                    );
+                    let first = chunk.iter().map(|frame| *frame.start).min();
+                    let last = chunk.iter().map(|frame| *frame.start).max();
+                    if let (Some(first), Some(last)) = (first, last) {
+                        keys.extend(
+                            frames::table
+                                .select((
+                                    frames::project,
+                                    frames::start,
+                                    frames::end,
+                                    frames::draft,
+                                ))
+                                .filter(frames::start.between(first, last))
+                                .filter(frames::deleted_at.is_null())
+                                .load::<(i32, Timestamp, Option<Timestamp>, bool)>(con)?,
+                        );
+                    }
+                    // Drafts are duplicates of any frame, other frames only of frames that are
+                    // not drafts, so imports are not skipped for unconfirmed drafts.
+                    chunk.retain(|frame| {
+                        let key = |draft| (frame.project, *frame.start, frame.end.copied(), draft);
+                        let duplicate =
+                            keys.contains(&key(false)) || frame.draft && keys.contains(&key(true));
+                        uuids.insert(frame.uuid.to_owned())
+                            && !duplicate
+                            && keys.insert(key(frame.draft))
+                    });
+                }
+                // Drafts are tagged once they are confirmed.
+                Self::apply_rules(
+                    con,
+                    chunk
+                        .iter()
+                        .filter(|frame| !frame.draft)
+                        .map(|frame| (frame.project, frame.note, frame.external_ref)),
+                )?;
+                inserted += diesel::insert_into(frames::table)
+                    .values(chunk)
+                    .execute(con)?;
+            }
+            Ok(inserted)
+        })
+    }
+
+    /// Insert imported frames like [`Database::insert_frames_bulk`], but look for frames of the
+    /// same project that are already tracked for the same time first. Those are handled
+    /// according to `policy`, see [`is_duplicate`]. Unconfirmed drafts only count as tracked
+    /// for imported drafts.
+    pub fn import_frames(
+        &mut self,
+        frames: Vec<NewFrame>,
+        policy: DuplicatePolicy,
+    ) -> Result<ImportSummary> {
+        let now = Timestamp::now();
+        let end_of = |end: Option<&Timestamp>| end.copied().unwrap_or(now);
+        let (Some(first), Some(last)) = (
+            frames.iter().map(|frame| *frame.start).min(),
+            frames.iter().map(|frame| end_of(frame.end)).max(),
+        ) else {
+            return Ok(ImportSummary::default());
+        };
+        let mut stored: Vec<Frame> = frames::table
+            .filter(frames::project.eq_any(frames.iter().map(|frame| frame.project)))
+            .filter(frames::deleted_at.is_null())
+            .filter(frames::start.lt(last))
+            .filter(frames::end.ge(first).or(frames::end.is_null()))
+            .order_by(frames::start)
+            .load(&mut self.connection)?;
+
+        let mut summary = ImportSummary::default();
+        let mut merged = BTreeSet::new();
+        // Start and end of the frames to insert, which grow when later duplicates are merged.
+        let mut kept: Vec<(usize, Timestamp, Option<Timestamp>)> = Vec::new();
+        for (index, frame) in frames.iter().enumerate() {
+            let span = (*frame.start, end_of(frame.end));
+            // Like in `insert_frames_bulk`, so nothing is merged into unconfirmed drafts.
+            let stored_duplicate = stored.iter().position(|other| {
+                other.project == frame.project
+                    && (frame.draft || !other.draft)
+                    && is_duplicate(span, (other.start, end_of(other.end.as_ref())))
+            });
+            let kept_duplicate = kept.iter().position(|&(other, start, end)| {
+                frames[other].project == frame.project
+                    && (frame.draft || !frames[other].draft)
+                    && is_duplicate(span, (start, end_of(end.as_ref())))
+            });
+            let (start, end) = match (stored_duplicate, kept_duplicate) {
+                (None, None) => {
+                    kept.push((index, *frame.start, frame.end.copied()));
+                    continue;
+                }
+                (Some(position), _) => {
+                    let frame = &mut stored[position];
+                    merged.insert(frame.id());
+                    (&mut frame.start, &mut frame.end)
+                }
+                (None, Some(position)) => {
+                    let (_, start, end) = &mut kept[position];
+                    (start, end)
+                }
+            };
+            match policy {
+                DuplicatePolicy::Skip => summary.skipped.push(index),
+                // Drafts are not merged into confirmed frames either.
+                DuplicatePolicy::Merge if frame.draft => summary.skipped.push(index),
+                DuplicatePolicy::Merge => {
+                    *start = (*start).min(*frame.start);
+                    // A running frame on either side keeps the merged one running.
+                    *end = end.zip(frame.end).map(|(end, other)| end.max(*other));
+                }
+                DuplicatePolicy::Fail => {
+                    let span = TimeSpan::new(*start, end_of(end.as_ref()))
+                        .expect("Duplicates are not empty");
+                    return Err(Error::DuplicateFrame(span));
+                }
+            }
+        }
+
+        if policy == DuplicatePolicy::Merge {
+            for frame in stored
+                .iter()
+                .filter(|frame| merged.contains(&Frame::id(frame)))
+            {
+                self.check_unlocked(frame.start, end_of(frame.end.as_ref()))?;
+            }
+            write_transaction(&mut self.connection, |con| {
+                for frame in stored
+                    .iter()
+                    .filter(|frame| merged.contains(&Frame::id(frame)))
+                {
+                    diesel::update(frames::table.find(frame.id()))
+                        .set((
+                            frames::start.eq(frame.start),
+                            frames::end.eq(frame.end),
+                            frames::updated_at.eq(now),
+                        ))
+                        .execute(con)?;
+                }
+                Ok(())
+            })?;
+            summary.merged = stored
+                .into_iter()
+                .filter(|frame| merged.contains(&frame.id()))
+                .collect();
+        }
+
+        let frames: Vec<_> = kept
+            .iter()
+            .map(|(index, start, end)| NewFrame {
+                start,
+                end: end.as_ref(),
+                ..frames[*index]
+            })
+            .collect();
+        summary.inserted = self.insert_frames_bulk(frames, true)?;
+        Ok(summary)
+    }
+
+    /// Make sure no two frames overlap.
+    ///
+    /// Whenever a frame starts before the previous one ended, the previous frame is cut off at
+    /// the start of the next one. If the previous frame went on after the next one, its part
+    /// after it is kept as a new frame. Frames which end up empty are moved to the trash. The
+    /// result only depends on the stored frames, so two machines with the same frames resolve
+    /// overlaps the same way.
+    ///
+    /// With concurrent frames overlaps are intended, so nothing is changed.
+    ///
+    /// Returns the modified frames.
+    pub fn resolve_overlaps(&mut self) -> Result<Vec<Frame>> {
+        if self.concurrent_frames {
+            return Ok(Vec::new());
+        }
+        write_transaction(&mut self.connection, |con| {
+            let mut all: Vec<Frame> = frames::table
+                .filter(frames::deleted_at.is_null())
+                .filter(frames::draft.eq(false))
+                .order_by((frames::start, frames::uuid))
+                .load(con)?;
+
+            let now = Timestamp::now();
+            let mut changed = Vec::new();
+            let mut index = 0;
+            while index + 1 < all.len() {
+                let (previous, next) = (all[index].clone(), all[index + 1].clone());
+                index += 1;
+                if previous.end.is_some_and(|end| end <= next.start) {
+                    continue;
+                }
+
+                let around = next.end.filter(|&next_end| match previous.end {
+                    Some(end) => end > next_end,
+                    None => true,
+                });
+                if let Some(next_end) = around {
+                    let uuid = split_uuid(&previous, &next);
+                    let rest = NewFrame {
+                        project: previous.project,
+                        start: &next_end,
+                        end: previous.end.as_ref(),
+                        deleted_at: None,
+                        uuid: &uuid,
+                        updated_at: &now,
+                        external_ref: previous.external_ref.as_deref(),
+                        note: previous.note.as_deref(),
+                        source: previous.source,
+                        draft: false,
+                        kind: previous.kind,
+                    };
+                    let rest: Frame = diesel::insert_into(frames::table)
+                        .values(&rest)
+                        .get_result(con)?;
+                    let rest: Frame = diesel::update(&rest)
+                        .set(frames::task_id.eq(previous.task_id))
+                        .get_result(con)?;
+                    // The rest may overlap the frames after it in turn.
+                    let position = index
+                        + 1
+                        + all[index + 1..].partition_point(|frame| {
+                            (frame.start, frame.uuid()) < (rest.start, rest.uuid())
+                        });
+                    all.insert(position, rest.clone());
+                    changed.push(rest);
+                }
+
+                let mut clipped = previous;
+                clipped.end = Some(next.start);
+                clipped.updated_at = now;
+                if next.start <= clipped.start {
+                    clipped.deleted_at = Some(now);
+                }
+                diesel::update(&clipped).set(&clipped).execute(con)?;
+                changed.push(clipped);
+            }
+            Ok(changed)
+        })
+    }
+
+    /// Read a value from the key value store.
+    pub fn setting(&mut self, key: &str) -> Result<Option<String>> {
+        Ok(settings::table
+            .find(key)
+            .select(settings::value)
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+
+    /// Write a value into the key value store, replacing the previous one.
+    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        write_transaction(&mut self.connection, |con| {
+            diesel::replace_into(settings::table)
+                .values((settings::key.eq(key), settings::value.eq(value)))
+                .execute(con)?;
+            Ok(())
+        })
+    }
+
+    /// Write the given projects into the database.
+    #[allow(dead_code)]
+    pub fn write_projects<'a>(
+        &mut self,
+        items: impl IntoIterator<Item = &'a mut Project>,
+    ) -> Result<()> {
+        let mut items: Vec<_> = items.into_iter().collect();
+        write_transaction(&mut self.connection, |con| {
+            Self::write_projects_impl(con, items.iter_mut().map(|item| &mut **item))
+        })
+    }
+
+    fn write_projects_impl<'a>(
+        connection: &mut SqliteConnection,
+        items: impl IntoIterator<Item = &'a mut Project>,
+    ) -> Result<()> {
+        connection.transaction(|connection| {
+            use crate::schema::projects::dsl::*;
+            let now = Timestamp::now();
+            for item in items {
+                item.last_access_time = now;
+                diesel::insert_into(projects)
                     .values(&*item)
                     .on_conflict(id)
                     .do_update()
@@ -245,99 +1701,2909 @@ impl Database {
         })
     }
 
-    pub fn tag_projects(&mut self, mut tags: Vec<Tag>, mut projects: Vec<Project>) -> Result<()> {
-        let combination: Vec<_> = iproduct!(&projects, &tags)
-            .map(|(p, t)| TagProject {
-                project_id: p.id(),
-                tag_id: t.id(),
-            })
-            .collect();
+    /// Create a new tag and return it.
+    pub fn create_tag(&mut self, name: impl AsRef<str>) -> Result<Tag> {
+        let new_tag = NewTag {
+            name: name.as_ref(),
+            last_access_time: &Timestamp::now(),
+            uuid: &Uuid::new_v4().to_string(),
+        };
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(tags::table)
+                .values(&new_tag)
+                .get_result(con)?)
+        })
+    }
 
-        self.connection.transaction(|connection| {
-            diesel::insert_or_ignore_into(tags_per_project::table)
-                .values(combination)
-                .execute(connection)?;
-            Self::write_projects_impl(connection, &mut projects)?;
-            Self::write_tags_impl(connection, &mut tags)?;
-            Ok(())
+    /// Create a task within `project`, names are unique per project.
+    pub fn create_task(&mut self, project: &Project, name: &str) -> Result<Task> {
+        let new_task = NewTask {
+            project_id: project.id(),
+            name,
+        };
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(tasks::table)
+                .values(&new_task)
+                .get_result(con)?)
         })
     }
 
-    /// Write the given frame back into the database and update the access time of the
-    /// corresponding project.
-    fn update_frame(&mut self, frame: &Frame) -> Result<()> {
-        diesel::update(frame)
-            .set(frame)
-            .execute(&mut self.connection)?;
-        let mut project = self
-            .lookup_project(frame.project)?
-            .unwrap_or_else(|| panic!("Found no project for id {}", frame.id()));
-        project.last_access_time = Timestamp::now();
-        diesel::update(&project)
-            .set(&project)
-            .execute(&mut self.connection)?;
+    pub fn lookup_task(&mut self, project_id: i32, name: &str) -> Result<Option<Task>> {
+        Ok(tasks::table
+            .filter(tasks::project_id.eq(project_id))
+            .filter(tasks::name.eq(name))
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+
+    /// The tasks of `project_id`, or of all projects, ordered by name.
+    pub fn tasks(&mut self, project_id: Option<i32>) -> Result<Vec<Task>> {
+        let mut query = tasks::table.into_boxed();
+        if let Some(project_id) = project_id {
+            query = query.filter(tasks::project_id.eq(project_id));
+        }
+        Ok(query
+            .order_by((tasks::project_id, tasks::name))
+            .load(&mut self.connection)?)
+    }
 
+    /// Mark `task` as done, or as open again.
+    pub fn set_task_done(&mut self, task: &Task, done: bool) -> Result<Task> {
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::update(task)
+                .set(tasks::done.eq(done))
+                .get_result(con)?)
+        })
+    }
+
+    /// Create a client billed in `currency`, names are unique.
+    pub fn create_client(&mut self, name: &str, currency: Option<&str>) -> Result<Client> {
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(clients::table)
+                .values(&NewClient { name, currency })
+                .get_result(con)?)
+        })
+    }
+
+    /// Set the currency `client` is billed in, `None` falls back to the configured one.
+    pub fn set_client_currency(
+        &mut self,
+        client: &mut Client,
+        currency: Option<&str>,
+    ) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*client)
+                .set(clients::currency.eq(currency))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        client.currency = currency.map(str::to_owned);
         Ok(())
     }
 
-    /// Search the database for a project with the given name.
-    /// This function also returns archived projects.
-    pub fn lookup_project_by_name(&mut self, name: &str) -> Result<Option<Project>> {
-        Ok(projects::table
-            .filter(projects::name.eq(name))
-            .get_result(&mut self.connection)
-            .optional()?)
+    /// Set the budget of a project, replacing its previous one.
+    pub fn set_budget(&mut self, budget: &Budget) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::replace_into(budgets::table)
+                .values(budget)
+                .execute(connection)?;
+            Ok(())
+        })
     }
 
-    /// Get all tags associated to the given project.
-    pub fn lookup_tags_for_project(&mut self, project_id: i32) -> Result<Vec<Tag>> {
-        Ok(tags::table
-            .inner_join(tags_per_project::table)
-            .filter(tags_per_project::project_id.eq(project_id))
-            .select(tags::all_columns)
-            .get_results(&mut self.connection)?)
+    /// Remove the budget of `project`, returns whether it had one.
+    pub fn remove_budget(&mut self, project: &Project) -> Result<bool> {
+        write_transaction(&mut self.connection, |connection| {
+            let removed = diesel::delete(budgets::table.find(project.id())).execute(connection)?;
+            Ok(removed > 0)
+        })
     }
 
-    pub fn lookup_tag_by_name(&mut self, name: &str) -> Result<Option<Tag>> {
-        Ok(tags::table
-            .filter(tags::name.eq(name))
+    pub fn budget(&mut self, project_id: i32) -> Result<Option<Budget>> {
+        Ok(budgets::table
+            .find(project_id)
             .get_result(&mut self.connection)
             .optional()?)
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
-pub enum ArchivedState {
-    NotArchived,
-    OnlyArchived,
-    Both,
-}
+    /// All budgets with their projects, ordered by project name.
+    pub fn budgets(&mut self) -> Result<Vec<(Project, Budget)>> {
+        Ok(projects::table
+            .inner_join(budgets::table)
+            .select((projects::all_columns, budgets::all_columns))
+            .order_by(projects::name)
+            .load(&mut self.connection)?)
+    }
 
-const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+    /// Currency of the rates of every project that has one, by project id: its own, the one of
+    /// its client or `default`.
+    pub fn currencies(&mut self, default: Option<&str>) -> Result<HashMap<i32, String>> {
+        let clients: HashMap<_, _> = self
+            .clients()?
+            .into_iter()
+            .map(|client| (client.id(), client))
+            .collect();
+        Ok(self
+            .all_projects(ArchivedState::Both)?
+            .into_iter()
+            .filter_map(|project| {
+                let id = project.id();
+                let client = project.client_id.and_then(|id| clients.get(&id));
+                let currency = project
+                    .currency
+                    .or_else(|| client?.currency.clone())
+                    .or_else(|| default.map(str::to_owned))?;
+                Some((id, currency))
+            })
+            .collect())
+    }
 
-pub fn establish_connection() -> Result<SqliteConnection> {
-    let database_url = if cfg!(debug_assertions) {
-        dotenv().ok();
+    /// Set the currency of the rates of `project`, `None` uses the one of its client.
+    pub fn set_currency(&mut self, project: &mut Project, currency: Option<&str>) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*project)
+                .set(projects::currency.eq(currency))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        project.currency = currency.map(str::to_owned);
+        Ok(())
+    }
 
-        env::var("DATABASE_URL").expect("DATABASE_URL must be set")
-    } else {
-        let dirs = ProjectDirs::from("", "", "ttt").expect("Failed to get base directory paths!");
-        let data_folder = dirs.data_dir();
+    pub fn lookup_client_by_name(&mut self, name: &str) -> Result<Option<Client>> {
+        Ok(clients::table
+            .filter(clients::name.eq(name))
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
 
-        create_dir_all(data_folder)
-            .unwrap_or_else(|_| panic!("Failed to create data dir '{}'", data_folder.display()));
+    /// All clients, ordered by name.
+    pub fn clients(&mut self) -> Result<Vec<Client>> {
+        Ok(clients::table
+            .order_by(clients::name)
+            .load(&mut self.connection)?)
+    }
 
-        data_folder
-            .join("timetable.db")
-            .to_str()
-            .expect("Sorry non UTF-8 data directory names are not supported!")
-            .to_owned()
-    };
+    /// Assign `project` to `client`, `None` removes it from its client.
+    pub fn set_client(&mut self, project: &mut Project, client: Option<&Client>) -> Result<()> {
+        let client_id = client.map(Client::id);
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*project)
+                .set(projects::client_id.eq(client_id))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        project.client_id = client_id;
+        Ok(())
+    }
+
+    /// Create a new project and return it.
+    pub fn create_project(&mut self, name: impl AsRef<str>) -> Result<Project> {
+        self.create_project_with_uuid(name, &Uuid::new_v4().to_string())
+    }
 
-    let mut connection = SqliteConnection::establish(&database_url)?;
+    /// Create a project that can also be found by `aliases`, see [`Database::add_alias`].
+    /// Nothing is created if the name or an alias is taken by a project, or given twice.
+    pub fn create_project_with_aliases(
+        &mut self,
+        name: &str,
+        aliases: &[String],
+    ) -> Result<Project> {
+        let new_project = NewProject {
+            name,
+            last_access_time: &Timestamp::now(),
+            uuid: &Uuid::new_v4().to_string(),
+        };
+        write_transaction(&mut self.connection, |con| {
+            let mut names = HashSet::new();
+            for name in std::iter::once(name).chain(aliases.iter().map(String::as_str)) {
+                let taken = projects::table
+                    .filter(projects::name.eq(name))
+                    .count()
+                    .get_result::<i64>(con)?
+                    + project_aliases::table
+                        .filter(project_aliases::alias.eq(name))
+                        .count()
+                        .get_result::<i64>(con)?;
+                if taken > 0 || !names.insert(name) {
+                    return Err(Error::ProjectExists(name.to_owned()));
+                }
+            }
+            let project: Project = diesel::insert_into(projects::table)
+                .values(&new_project)
+                .get_result(con)?;
+            for alias in aliases {
+                diesel::insert_into(project_aliases::table)
+                    .values(&ProjectAlias {
+                        alias,
+                        project_id: project.id(),
+                    })
+                    .execute(con)?;
+            }
+            Ok(project)
+        })
+    }
 
-    use diesel_migrations::MigrationHarness;
-    connection.run_pending_migrations(MIGRATIONS).unwrap();
+    /// Create a project that already exists on another machine, known by its `uuid` there.
+    pub fn create_project_with_uuid(
+        &mut self,
+        name: impl AsRef<str>,
+        uuid: &str,
+    ) -> Result<Project> {
+        let new_project = NewProject {
+            name: name.as_ref(),
+            last_access_time: &Timestamp::now(),
+            uuid,
+        };
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(projects::table)
+                .values(&new_project)
+                .get_result(con)?)
+        })
+    }
 
-    Ok(connection)
+    /// Write the given tags to the database.
+    /// This function acts as a transaction, the database is only modified if all tags can be
+    /// written successfully.
+    #[allow(dead_code)]
+    pub fn write_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a mut Tag>) -> Result<()> {
+        let mut tags: Vec<_> = tags.into_iter().collect();
+        write_transaction(&mut self.connection, |con| {
+            Self::write_tags_impl(con, tags.iter_mut().map(|tag| &mut **tag))
+        })
+    }
+
+    fn write_tags_impl<'a>(
+        connection: &mut SqliteConnection,
+        items: impl IntoIterator<Item = &'a mut Tag>,
+    ) -> Result<()> {
+        connection.transaction(|connection| {
+            use crate::schema::tags::dsl::*;
+            let now = Timestamp::now();
+            for item in items {
+                item.last_access_time = now;
+                diesel::insert_into(tags)
+                    .values(&*item)
+                    .on_conflict(id)
+                    .do_update()
+                    .set(&*item)
+                    .execute(connection)?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn tag_projects(&mut self, mut tags: Vec<Tag>, mut projects: Vec<Project>) -> Result<()> {
+        let combination: Vec<_> = iproduct!(&projects, &tags)
+            .map(|(p, t)| TagProject {
+                project_id: p.id(),
+                tag_id: t.id(),
+            })
+            .collect();
+
+        write_transaction(&mut self.connection, |connection| {
+            diesel::insert_or_ignore_into(tags_per_project::table)
+                .values(&combination)
+                .execute(connection)?;
+            Self::write_projects_impl(connection, &mut projects)?;
+            Self::write_tags_impl(connection, &mut tags)?;
+            Ok(())
+        })
+    }
+
+    /// Make `tags` the default tags of `project`, replacing the previous ones. The tags are
+    /// associated to the project as well.
+    pub fn set_default_tags(&mut self, project: &Project, tags: &[Tag]) -> Result<()> {
+        let combination: Vec<_> = tags
+            .iter()
+            .map(|t| TagProject {
+                project_id: project.id(),
+                tag_id: t.id(),
+            })
+            .collect();
+        let tag_ids: Vec<_> = tags.iter().map(Tag::id).collect();
+
+        write_transaction(&mut self.connection, |connection| {
+            let of_project =
+                tags_per_project::table.filter(tags_per_project::project_id.eq(project.id()));
+            diesel::update(of_project)
+                .set(tags_per_project::is_default.eq(false))
+                .execute(connection)?;
+            diesel::insert_or_ignore_into(tags_per_project::table)
+                .values(&combination)
+                .execute(connection)?;
+            diesel::update(of_project.filter(tags_per_project::tag_id.eq_any(&tag_ids)))
+                .set(tags_per_project::is_default.eq(true))
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Get all projects that are not archived and were not used since `cutoff`, least recently
+    /// used first.
+    pub fn stale_projects(&mut self, cutoff: Timestamp) -> Result<Vec<Project>> {
+        Ok(projects::table
+            .filter(projects::archived.eq(false))
+            .filter(projects::last_access_time.lt(cutoff))
+            .order_by(projects::last_access_time)
+            .load(&mut self.connection)?)
+    }
+
+    /// Archive the given projects without touching their access time.
+    pub fn archive_projects(&mut self, projects: &mut [Project]) -> Result<()> {
+        let ids: Vec<_> = projects.iter().map(Project::id).collect();
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(projects::table.filter(projects::id.eq_any(&ids)))
+                .set(projects::archived.eq(true))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        for project in projects {
+            project.archived = true;
+        }
+        Ok(())
+    }
+
+    /// Set or clear the estimated time of `project` in minutes.
+    pub fn set_estimate(&mut self, project: &mut Project, minutes: Option<i32>) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*project)
+                .set(projects::estimate_minutes.eq(minutes))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        project.estimate_minutes = minutes;
+        Ok(())
+    }
+
+    /// Set what an hour of work on `project` is billed in cents, `None` clears the rate.
+    pub fn set_hourly_rate(&mut self, project: &mut Project, cents: Option<i32>) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*project)
+                .set(projects::hourly_rate_cents.eq(cents))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        project.hourly_rate_cents = cents;
+        Ok(())
+    }
+
+    /// Bill an hour of work on `project` with `cents` from `day` on, replacing a rate recorded
+    /// for the same day.
+    pub fn add_rate(
+        &mut self,
+        project: &Project,
+        day: chrono::NaiveDate,
+        cents: i32,
+    ) -> Result<()> {
+        let rate = Rate {
+            project_id: project.id(),
+            effective_from: day,
+            cents,
+        };
+        write_transaction(&mut self.connection, |connection| {
+            diesel::replace_into(rates::table)
+                .values(&rate)
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// The rates of all projects by project id, each ordered by the day they take effect, see
+    /// [`Project::rate_at`].
+    pub fn rates(&mut self) -> Result<HashMap<i32, Vec<Rate>>> {
+        let mut per_project = HashMap::<_, Vec<_>>::new();
+        for rate in rates::table
+            .order_by((rates::project_id, rates::effective_from))
+            .load::<Rate>(&mut self.connection)?
+        {
+            per_project.entry(rate.project_id).or_default().push(rate);
+        }
+        Ok(per_project)
+    }
+
+    /// Let `project` also be found by `alias`, see [`Database::lookup_project_by_name`].
+    #[allow(dead_code)]
+    pub fn add_alias(&mut self, project: &Project, alias: &str) -> Result<()> {
+        let alias = ProjectAlias {
+            alias,
+            project_id: project.id(),
+        };
+        write_transaction(&mut self.connection, |connection| {
+            diesel::insert_into(project_aliases::table)
+                .values(&alias)
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Give `project` a new name. The old name becomes an alias, so frames synchronized from
+    /// machines that still use it end up in the same project.
+    pub fn rename_project(&mut self, project: &mut Project, name: &str) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*project)
+                .set(projects::name.eq(name))
+                .execute(connection)?;
+            diesel::replace_into(project_aliases::table)
+                .values(&ProjectAlias {
+                    alias: &project.name,
+                    project_id: project.id(),
+                })
+                .execute(connection)?;
+            Ok(())
+        })?;
+        project.name = name.to_owned();
+        Ok(())
+    }
+
+    /// Move the frames, tags, tasks, aliases, budget and rates of `from` to `into` and delete
+    /// `from`. The name of `from` becomes an alias of `into`, frames of a task that `into` also
+    /// has get the task of `into`. Fails if a frame of `from` is in a locked period, or if both
+    /// projects have a budget or rates.
+    pub fn merge_projects(&mut self, from: &Project, into: &Project) -> Result<()> {
+        if from.id() == into.id() {
+            return Ok(());
+        }
+        let now = Timestamp::now();
+        let moved: Vec<Frame> = frames::table
+            .filter(frames::project.eq(from.id()))
+            .load(&mut self.connection)?;
+        for frame in &moved {
+            self.check_unlocked(frame.start, frame.end.unwrap_or(now))?;
+        }
+        write_transaction(&mut self.connection, |connection| {
+            // Touch the frames, so `ttt sync` passes on the new project.
+            diesel::update(frames::table.filter(frames::project.eq(from.id())))
+                .set((frames::project.eq(into.id()), frames::updated_at.eq(now)))
+                .execute(connection)?;
+
+            let tags: Vec<(i32, bool)> = tags_per_project::table
+                .filter(tags_per_project::project_id.eq(from.id()))
+                .select((tags_per_project::tag_id, tags_per_project::is_default))
+                .load(connection)?;
+            for (tag_id, is_default) in tags {
+                diesel::insert_or_ignore_into(tags_per_project::table)
+                    .values((
+                        tags_per_project::project_id.eq(into.id()),
+                        tags_per_project::tag_id.eq(tag_id),
+                        tags_per_project::is_default.eq(is_default),
+                    ))
+                    .execute(connection)?;
+            }
+            diesel::delete(
+                tags_per_project::table.filter(tags_per_project::project_id.eq(from.id())),
+            )
+            .execute(connection)?;
+
+            diesel::update(
+                project_aliases::table.filter(project_aliases::project_id.eq(from.id())),
+            )
+            .set(project_aliases::project_id.eq(into.id()))
+            .execute(connection)?;
+
+            let into_tasks: Vec<Task> = tasks::table
+                .filter(tasks::project_id.eq(into.id()))
+                .load(connection)?;
+            let from_tasks: Vec<Task> = tasks::table
+                .filter(tasks::project_id.eq(from.id()))
+                .load(connection)?;
+            for task in from_tasks {
+                match into_tasks.iter().find(|same| same.name == task.name) {
+                    Some(same) => {
+                        diesel::update(frames::table.filter(frames::task_id.eq(task.id())))
+                            .set(frames::task_id.eq(same.id()))
+                            .execute(connection)?;
+                        diesel::delete(&task).execute(connection)?;
+                    }
+                    None => {
+                        diesel::update(&task)
+                            .set(tasks::project_id.eq(into.id()))
+                            .execute(connection)?;
+                    }
+                }
+            }
+
+            let budgets = |connection: &mut SqliteConnection, id: i32| {
+                budgets::table
+                    .filter(budgets::project_id.eq(id))
+                    .count()
+                    .get_result::<i64>(connection)
+            };
+            if budgets(connection, from.id())? > 0 {
+                if budgets(connection, into.id())? > 0 {
+                    return Err(Error::MergeConflict("budget".to_owned()));
+                }
+                diesel::update(budgets::table.filter(budgets::project_id.eq(from.id())))
+                    .set(budgets::project_id.eq(into.id()))
+                    .execute(connection)?;
+            }
+            let rates = |connection: &mut SqliteConnection, id: i32| {
+                rates::table
+                    .filter(rates::project_id.eq(id))
+                    .count()
+                    .get_result::<i64>(connection)
+            };
+            if rates(connection, from.id())? > 0 {
+                if rates(connection, into.id())? > 0 {
+                    return Err(Error::MergeConflict("rates".to_owned()));
+                }
+                diesel::update(rates::table.filter(rates::project_id.eq(from.id())))
+                    .set(rates::project_id.eq(into.id()))
+                    .execute(connection)?;
+            }
+
+            diesel::delete(from).execute(connection)?;
+            diesel::replace_into(project_aliases::table)
+                .values(&ProjectAlias {
+                    alias: &from.name,
+                    project_id: into.id(),
+                })
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Give `tag` a new name.
+    pub fn rename_tag(&mut self, tag: &mut Tag, name: &str) -> Result<()> {
+        write_transaction(&mut self.connection, |connection| {
+            diesel::update(&*tag)
+                .set(tags::name.eq(name))
+                .execute(connection)?;
+            Ok(())
+        })?;
+        tag.name = name.to_owned();
+        Ok(())
+    }
+
+    /// Add `into` to all projects tagged with `from` and delete `from`.
+    pub fn merge_tags(&mut self, from: &Tag, into: &Tag) -> Result<()> {
+        if from.id() == into.id() {
+            return Ok(());
+        }
+        write_transaction(&mut self.connection, |connection| {
+            let projects: Vec<(i32, bool)> = tags_per_project::table
+                .filter(tags_per_project::tag_id.eq(from.id()))
+                .select((tags_per_project::project_id, tags_per_project::is_default))
+                .load(connection)?;
+            for (project_id, is_default) in projects {
+                diesel::insert_or_ignore_into(tags_per_project::table)
+                    .values((
+                        tags_per_project::project_id.eq(project_id),
+                        tags_per_project::tag_id.eq(into.id()),
+                        tags_per_project::is_default.eq(is_default),
+                    ))
+                    .execute(connection)?;
+            }
+            diesel::delete(tags_per_project::table.filter(tags_per_project::tag_id.eq(from.id())))
+                .execute(connection)?;
+            diesel::update(rules::table.filter(rules::tag_id.eq(from.id())))
+                .set(rules::tag_id.eq(into.id()))
+                .execute(connection)?;
+            diesel::delete(from).execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Get the default tags of the given project, see [`Database::set_default_tags`].
+    pub fn default_tags(&mut self, project_id: i32) -> Result<Vec<Tag>> {
+        Ok(tags::table
+            .inner_join(tags_per_project::table)
+            .filter(tags_per_project::project_id.eq(project_id))
+            .filter(tags_per_project::is_default.eq(true))
+            .select(tags::all_columns)
+            .get_results(&mut self.connection)?)
+    }
+
+    /// Add a rule tagging the projects of frames whose `field` matches `pattern`, see [`Rule`].
+    /// Only frames created or edited from now on are looked at.
+    ///
+    /// # Errors
+    /// Fails with [`Error::InvalidRule`] if `pattern` is not a valid regular expression.
+    pub fn add_rule(&mut self, field: RuleField, pattern: &str, tag: &Tag) -> Result<Rule> {
+        Regex::new(pattern).map_err(|err| Error::InvalidRule(err.to_string()))?;
+        write_transaction(&mut self.connection, |con| {
+            Ok(diesel::insert_into(rules::table)
+                .values((
+                    rules::field.eq(field),
+                    rules::pattern.eq(pattern),
+                    rules::tag_id.eq(tag.id()),
+                ))
+                .get_result(con)?)
+        })
+    }
+
+    /// All rules with the tag they add, in the order they were added.
+    pub fn rules(&mut self) -> Result<Vec<(Rule, Tag)>> {
+        Ok(rules::table
+            .inner_join(tags::table)
+            .order_by(rules::id)
+            .load(&mut self.connection)?)
+    }
+
+    /// Returns whether a rule with the given id existed.
+    pub fn remove_rule(&mut self, rule_id: i32) -> Result<bool> {
+        let removed = diesel::delete(rules::table.find(rule_id)).execute(&mut self.connection)?;
+        Ok(removed > 0)
+    }
+
+    /// Tag the projects of the frames matching a [`Rule`]. Frames are given as project id, note
+    /// and external reference.
+    fn apply_rules<'a>(
+        connection: &mut SqliteConnection,
+        frames: impl IntoIterator<Item = (i32, Option<&'a str>, Option<&'a str>)>,
+    ) -> Result<()> {
+        let rules: Vec<Rule> = rules::table.load(connection)?;
+        if rules.is_empty() {
+            return Ok(());
+        }
+        // Patterns are checked when adding a rule.
+        let rules: Vec<_> = rules
+            .into_iter()
+            .filter_map(|rule| Some((Regex::new(&rule.pattern).ok()?, rule)))
+            .collect();
+        let names: HashMap<i32, String> = projects::table
+            .select((projects::id, projects::name))
+            .load(connection)?
+            .into_iter()
+            .collect();
+
+        let mut tagged = BTreeSet::new();
+        for (project, note, external_ref) in frames {
+            for (regex, rule) in &rules {
+                let text = match rule.field {
+                    RuleField::Project => names.get(&project).map(String::as_str),
+                    RuleField::Note => note,
+                    RuleField::Ref => external_ref,
+                };
+                if text.is_some_and(|text| regex.is_match(text)) {
+                    tagged.insert((project, rule.tag_id));
+                }
+            }
+        }
+        let values: Vec<_> = tagged
+            .into_iter()
+            .map(|(project_id, tag_id)| TagProject { project_id, tag_id })
+            .collect();
+        diesel::insert_or_ignore_into(tags_per_project::table)
+            .values(&values)
+            .execute(connection)?;
+        Ok(())
+    }
+
+    fn apply_rules_to_frame(connection: &mut SqliteConnection, frame: &Frame) -> Result<()> {
+        Self::apply_rules(
+            connection,
+            [(
+                frame.project,
+                frame.note.as_deref(),
+                frame.external_ref.as_deref(),
+            )],
+        )
+    }
+
+    /// Write the given frame back into the database and update the access time of the
+    /// corresponding project.
+    fn update_frame(&mut self, frame: &Frame) -> Result<()> {
+        write_transaction(&mut self.connection, |con| {
+            Self::update_frame_impl(con, frame)
+        })
+    }
+
+    fn update_frame_impl(connection: &mut SqliteConnection, frame: &Frame) -> Result<()> {
+        diesel::update(frame).set(frame).execute(connection)?;
+        Self::apply_rules_to_frame(connection, frame)?;
+        let mut project: Project = projects::table
+            .find(frame.project)
+            .first(connection)
+            .optional()?
+            .unwrap_or_else(|| panic!("Found no project for id {}", frame.id()));
+        project.last_access_time = Timestamp::now();
+        diesel::update(&project).set(&project).execute(connection)?;
+
+        Ok(())
+    }
+
+    /// Search the database for a project with the given name or alias.
+    /// This function also returns archived projects.
+    pub fn lookup_project_by_name(&mut self, name: &str) -> Result<Option<Project>> {
+        let project = projects::table
+            .filter(projects::name.eq(name))
+            .get_result(&mut self.connection)
+            .optional()?;
+        if project.is_some() {
+            return Ok(project);
+        }
+        Ok(projects::table
+            .inner_join(project_aliases::table)
+            .filter(project_aliases::alias.eq(name))
+            .select(projects::all_columns)
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+
+    /// The project known by `uuid` on all machines, also when it was renamed.
+    pub fn lookup_project_by_uuid(&mut self, uuid: &str) -> Result<Option<Project>> {
+        Ok(projects::table
+            .filter(projects::uuid.eq(uuid))
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+
+    /// Get all tags associated to the given project.
+    pub fn lookup_tags_for_project(&mut self, project_id: i32) -> Result<Vec<Tag>> {
+        Ok(tags::table
+            .inner_join(tags_per_project::table)
+            .filter(tags_per_project::project_id.eq(project_id))
+            .select(tags::all_columns)
+            .get_results(&mut self.connection)?)
+    }
+
+    pub fn lookup_tag(&mut self, tag_id: i32) -> Result<Option<Tag>> {
+        Ok(tags::table
+            .find(tag_id)
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+
+    pub fn lookup_tag_by_name(&mut self, name: &str) -> Result<Option<Tag>> {
+        Ok(tags::table
+            .filter(tags::name.eq(name))
+            .get_result(&mut self.connection)
+            .optional()?)
+    }
+}
+
+/// Amount of data in the database, see [`Database::stats`].
+#[derive(Debug)]
+pub struct DatabaseStats {
+    /// Number of rows per table, trashed frames included
+    pub rows: Vec<(&'static str, i64)>,
+
+    /// Start of the first and start or end of the last frame that is not trashed
+    pub first_frame: Option<Timestamp>,
+    pub last_frame: Option<Timestamp>,
+}
+
+/// Columns and rows of a query, see [`Database::query_read_only`].
+#[derive(Debug)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+
+    /// Values as SQLite converts them to JSON, i.e. numbers, text or null
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Run `sql` as a temporary view, whose column names SQLite can list, and read every row as a
+/// JSON array, as diesel needs to know the columns of a query in advance. A view also refuses
+/// anything but a SELECT statement.
+fn query_rows(connection: &mut SqliteConnection, sql: &str) -> Result<QueryResult> {
+    #[derive(QueryableByName)]
+    struct Column {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        name: String,
+    }
+    #[derive(QueryableByName)]
+    struct Row {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        row: String,
+    }
+
+    connection.batch_execute("DROP VIEW IF EXISTS temp.ttt_query;")?;
+    diesel::sql_query(format!("CREATE TEMP VIEW ttt_query AS {sql}")).execute(connection)?;
+    let result = (|| {
+        let columns: Vec<String> =
+            diesel::sql_query("SELECT name FROM temp.pragma_table_info('ttt_query') ORDER BY cid")
+                .load::<Column>(connection)?
+                .into_iter()
+                .map(|column| column.name)
+                .collect();
+        let values: Vec<_> = columns
+            .iter()
+            .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+            .collect();
+        let rows = diesel::sql_query(format!(
+            "SELECT json_array({}) AS row FROM temp.ttt_query",
+            values.join(", ")
+        ))
+        .load::<Row>(connection)?
+        .into_iter()
+        .map(|row| serde_json::from_str(&row.row).expect("SQLite returns valid JSON"))
+        .collect();
+        Ok(QueryResult { columns, rows })
+    })();
+    connection.batch_execute("DROP VIEW IF EXISTS temp.ttt_query;")?;
+    result
+}
+
+/// The uuid of the part of `frame` after `inner` when [`Database::resolve_overlaps`] splits it,
+/// the same on every machine. Hashed with FNV-1a, which unlike the hashers of std does not
+/// change between versions of Rust.
+fn split_uuid(frame: &Frame, inner: &Frame) -> String {
+    let mut hash: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    for byte in frame.uuid().bytes().chain([0]).chain(inner.uuid().bytes()) {
+        hash ^= u128::from(byte);
+        hash = hash.wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+    }
+    Uuid::from_u128(hash).to_string()
+}
+
+/// Whether two frames of the same project, given by their start and end, record the same work:
+/// they overlap for at least 80% of the shorter one. Empty frames are never duplicates.
+pub fn is_duplicate(a: (Timestamp, Timestamp), b: (Timestamp, Timestamp)) -> bool {
+    let overlap = a.1.min(b.1).0 - a.0.max(b.0).0;
+    let shorter = (a.1 .0 - a.0 .0).min(b.1 .0 - b.0 .0);
+    shorter > chrono::Duration::zero() && overlap * 5 >= shorter * 4
+}
+
+/// What [`Database::import_frames`] does with frames that are tracked already.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Leave the imported frame out
+    #[default]
+    Skip,
+
+    /// Extend the tracked frame to also cover the imported one
+    Merge,
+
+    /// Import nothing
+    Fail,
+}
+
+/// What [`Database::import_frames`] did.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Number of frames inserted
+    pub inserted: usize,
+
+    /// Indices of the imported frames left out as duplicates
+    pub skipped: Vec<usize>,
+
+    /// Tracked frames that were extended by duplicates
+    pub merged: Vec<Frame>,
+}
+
+/// Number of frames [`Database::insert_frames_bulk`] inserts and looks up at once. Each frame
+/// takes one parameter per column of [`NewFrame`], of the 999 older SQLite versions allow per
+/// statement.
+const BULK_INSERT_CHUNK_SIZE: usize = 999 / NEW_FRAME_COLUMNS;
+
+/// Number of fields of [`NewFrame`].
+const NEW_FRAME_COLUMNS: usize = 11;
+
+/// Setting with the local time zone the `daily_totals` table was summed up in, see
+/// [`check_daily_totals`].
+const DAILY_TOTALS_TIMEZONE: &str = "daily_totals.timezone";
+
+/// The local time zone as SQLite sees it, which is what the `daily_totals` triggers use: the
+/// days of a year on which its offset changes, with the offset in minutes, e.g.
+/// "2024-01-01 60, 2024-03-31 120, 2024-10-27 60". Each day is sampled at noon UTC.
+const TIMEZONE_QUERY: &str = "(
+    WITH RECURSIVE days(day) AS (
+        SELECT '2024-01-01' UNION ALL SELECT date(day, '+1 day') FROM days WHERE day < '2024-12-31'
+    ),
+    offsets(day, minutes) AS (
+        SELECT day, CAST(round((julianday(day || ' 12:00:00', 'localtime')
+            - julianday(day || ' 12:00:00')) * 1440) AS INTEGER)
+        FROM days
+    )
+    SELECT group_concat(day || ' ' || minutes, ', ') FROM (
+        SELECT day, minutes, lag(minutes) OVER (ORDER BY day) AS previous FROM offsets
+    )
+    WHERE previous IS NULL OR previous <> minutes
+)";
+
+/// Sum up the `daily_totals` table from scratch, like its triggers do frame by frame.
+const REBUILD_DAILY_TOTALS: &str = "
+    DELETE FROM daily_totals;
+    INSERT INTO daily_totals (day, project_id, milliseconds)
+    SELECT date(start, 'localtime'), project,
+        SUM(CAST(round((julianday(end) - julianday(start)) * 86400000) AS INTEGER))
+    FROM frames
+    WHERE end IS NOT NULL AND deleted_at IS NULL AND NOT draft AND kind <> 'break'
+    GROUP BY date(start, 'localtime'), project;
+";
+
+/// Number of daily backups [`Database::back_up_daily`] keeps.
+const BACKUPS_KEPT: usize = 7;
+
+/// Number of frames [`FramesInSpan`] loads at once.
+const FRAME_CHUNK_SIZE: i64 = 1000;
+
+/// Iterator over the frames within a span, see [`Database::frames_in_span_iter`].
+pub struct FramesInSpan<'a> {
+    database: &'a mut Database,
+    span: TimeSpan,
+    include_archived: ArchivedState,
+    chunk_size: i64,
+
+    /// Start and id of the last frame loaded, the next chunk continues after it.
+    last: Option<(Timestamp, i32)>,
+    chunk: std::vec::IntoIter<(Project, Frame)>,
+
+    /// Whether the last chunk was loaded or loading failed.
+    done: bool,
+}
+
+impl Iterator for FramesInSpan<'_> {
+    type Item = Result<(Project, Frame)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(next) = self.chunk.next() {
+            return Some(Ok(next));
+        }
+        if self.done {
+            return None;
+        }
+        let chunk = self.database.frames_in_span_chunk(
+            self.span,
+            self.include_archived,
+            self.last,
+            self.chunk_size,
+        );
+        match chunk {
+            Ok(chunk) => {
+                self.done = (chunk.len() as i64) < self.chunk_size;
+                self.last = chunk.last().map(|(_, frame)| (frame.start, frame.id()));
+                self.chunk = chunk.into_iter();
+                self.chunk.next().map(Ok)
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[typeshare]
+pub enum ArchivedState {
+    NotArchived,
+    OnlyArchived,
+    Both,
+}
+
+/// Where the database connection should point to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionTarget {
+    /// `DATABASE_URL` from the environment in debug builds, the user's data directory in release
+    /// builds.
+    Default,
+
+    /// A database file at the given path. The file is created if it does not exist.
+    Path(PathBuf),
+
+    /// A new database that only exists for the lifetime of the connection.
+    InMemory,
+}
+
+impl ConnectionTarget {
+    /// The file the database is stored in, `None` for in memory databases.
+    pub fn path(&self) -> Option<PathBuf> {
+        match self {
+            ConnectionTarget::Default => Some(PathBuf::from(default_database_url())),
+            ConnectionTarget::Path(path) => Some(path.clone()),
+            ConnectionTarget::InMemory => None,
+        }
+    }
+}
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// How long SQLite itself waits for a lock held by another connection (e.g. the GUI while using
+/// the CLI) before reporting the database as busy.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a write transaction is retried after SQLite gave up waiting for a lock.
+const WRITE_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Run `f` inside of an immediate transaction, i.e. one that takes the write lock right away.
+///
+/// Should the database still be locked by another connection after [`BUSY_TIMEOUT`] the whole
+/// transaction is retried up to [`WRITE_RETRIES`] times.
+fn write_transaction<T>(
+    connection: &mut SqliteConnection,
+    mut f: impl FnMut(&mut SqliteConnection) -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match connection.immediate_transaction(&mut f) {
+            Err(Error::DatabaseError(e)) if is_busy(&e) && attempt < WRITE_RETRIES => {
+                attempt += 1;
+                thread::sleep(RETRY_DELAY * attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether the error was caused by another connection holding a lock on the database.
+fn is_busy(error: &DieselError) -> bool {
+    match error {
+        DieselError::DatabaseError(DatabaseErrorKind::Unknown, info) => {
+            let message = info.message();
+            message.contains("database is locked") || message.contains("database is busy")
+        }
+        _ => false,
+    }
+}
+
+pub fn establish_connection(target: ConnectionTarget) -> Result<SqliteConnection> {
+    let path = target.path();
+    let mut connection = connect(target)?;
+    run_migrations(&mut connection, path.as_deref())
+        .map_err(|err| detect_damage(err, path.as_deref()))?;
+    Ok(connection)
+}
+
+/// Write a consistent copy of the database of `connection` to the new file `target`.
+fn back_up(connection: &mut SqliteConnection, target: &Path) -> Result<()> {
+    let target = target
+        .to_str()
+        .expect("Sorry non UTF-8 database paths are not supported!");
+    connection.batch_execute(&format!("VACUUM INTO '{}';", target.replace('\'', "''")))?;
+    Ok(())
+}
+
+/// Open the database without applying pending migrations.
+fn connect(target: ConnectionTarget) -> Result<SqliteConnection> {
+    let path = target.path();
+    let database_url = match &path {
+        Some(path) => path
+            .to_str()
+            .expect("Sorry non UTF-8 database paths are not supported!")
+            .to_owned(),
+        None => ":memory:".to_owned(),
+    };
+
+    let mut connection = open(&database_url)?;
+
+    // WAL allows readers and a writer to work concurrently, so the CLI and the GUI can both keep
+    // the database open. It is not available for in memory databases. Being the first statement
+    // that reads the file, it also finds out whether the file is a database at all.
+    if target != ConnectionTarget::InMemory {
+        connection
+            .batch_execute("PRAGMA journal_mode = WAL;")
+            .map_err(|err| detect_damage(err.into(), path.as_deref()))?;
+    }
+    connection.batch_execute(&format!(
+        "PRAGMA busy_timeout = {};",
+        BUSY_TIMEOUT.as_millis()
+    ))?;
+
+    Ok(connection)
+}
+
+/// Open the database file at `path` so that it can only be read, see
+/// [`Database::query_read_only`].
+fn connect_read_only(path: &Path) -> Result<SqliteConnection> {
+    let path = path
+        .to_str()
+        .expect("Sorry non UTF-8 database paths are not supported!");
+    // Characters with a meaning in URIs have to be escaped.
+    let path = path
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    let mut connection = open(&format!("file:{path}?mode=ro"))?;
+    connection.batch_execute(&format!(
+        "PRAGMA busy_timeout = {};",
+        BUSY_TIMEOUT.as_millis()
+    ))?;
+    Ok(connection)
+}
+
+/// Establish a connection to `database_url`, unlocking it if it is encrypted.
+fn open(database_url: &str) -> Result<SqliteConnection> {
+    let mut connection = SqliteConnection::establish(database_url)?;
+
+    #[cfg(feature = "sqlcipher")]
+    if !crate::encryption::is_plaintext(&mut connection) {
+        // The key has to be set before anything else happens on the connection.
+        connection = SqliteConnection::establish(database_url)?;
+        crate::encryption::unlock(&mut connection, &crate::encryption::passphrase()?)?;
+    }
+    crate::filter::register_regexp(&mut connection)?;
+    Ok(connection)
+}
+
+/// Messages of SQLite errors caused by a damaged database file.
+const DAMAGE_MESSAGES: [&str; 2] = ["file is not a database", "database disk image is malformed"];
+
+/// Turn errors that SQLite reports for damaged files into [`Error::DatabaseDamaged`], so they
+/// can be recovered from with [`replace_damaged`].
+fn detect_damage(err: Error, path: Option<&Path>) -> Error {
+    let message = match &err {
+        Error::DatabaseError(e) => e.to_string(),
+        Error::Migration(message) => message.clone(),
+        _ => return err,
+    };
+    match path {
+        Some(path)
+            if DAMAGE_MESSAGES
+                .iter()
+                .any(|damage| message.contains(damage)) =>
+        {
+            Error::DatabaseDamaged(path.display().to_string())
+        }
+        _ => err,
+    }
+}
+
+/// Directory the backups of the database file at `path` are kept in, see
+/// [`Database::back_up_daily`].
+pub fn backup_dir(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push("-backups");
+    path.with_file_name(name)
+}
+
+/// The newest backup of the database file at `path`, if there is one.
+pub fn latest_backup(path: &Path) -> Option<PathBuf> {
+    backups(path).pop()
+}
+
+/// All backups of the database file at `path`, oldest first.
+fn backups(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(backup_dir(path)) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "sqlite")
+        })
+        .collect();
+    // Named by date, so the names sort by age.
+    backups.sort();
+    backups
+}
+
+/// Move the damaged database file at `path` aside, together with its write ahead log, and put a
+/// copy of `backup` in its place. Without a backup, a new database is created the next time it
+/// is opened.
+///
+/// Returns where the damaged file was moved.
+pub fn replace_damaged(path: &Path, backup: Option<&Path>) -> Result<PathBuf> {
+    let moved = |suffix: &str| {
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        name.push(suffix);
+        path.with_file_name(name)
+    };
+    let damaged = moved(".damaged");
+    std::fs::rename(path, &damaged)?;
+    for log in ["-wal", "-shm"] {
+        if moved(log).exists() {
+            std::fs::rename(moved(log), moved(&format!(".damaged{log}")))?;
+        }
+    }
+    if let Some(backup) = backup {
+        std::fs::copy(backup, path)?;
+    }
+    Ok(damaged)
+}
+
+/// Directory the copies made before migrating the database file at `path` are kept in, apart
+/// from the daily backups, see [`run_migrations`].
+fn migration_backup_dir(path: &Path) -> PathBuf {
+    backup_dir(path).join("migrations")
+}
+
+/// Apply all pending migrations, returns their names.
+///
+/// The migrations are applied in a single transaction, so if one fails SQLite rolls back all of
+/// them and the database is never left half migrated, also while other instances have it open.
+/// Unless it is new, the database file at `path` is copied to [`migration_backup_dir`] first, in
+/// case a migration loses data without failing.
+fn run_migrations(connection: &mut SqliteConnection, path: Option<&Path>) -> Result<Vec<String>> {
+    let failed = |err: Box<dyn std::error::Error + Send + Sync>| Error::Migration(err.to_string());
+    let pending = connection.pending_migrations(MIGRATIONS).map_err(failed)?;
+    let is_new = connection.applied_migrations().map_err(failed)?.is_empty();
+    if let (Some(path), Some(first), false) = (path, pending.first(), is_new) {
+        let dir = migration_backup_dir(path);
+        let target = dir.join(format!(
+            "{}-before-{}.sqlite",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S"),
+            first.name().version()
+        ));
+        create_dir_all(&dir)?;
+        back_up(connection, &target)?;
+    }
+
+    let applied = connection.immediate_transaction(|con| {
+        let mut applied = Vec::new();
+        for migration in &pending {
+            // Names the failing migration already.
+            con.run_migration(migration).map_err(|err| {
+                Error::Migration(format!("{err}, no pending migration was applied"))
+            })?;
+            applied.push(migration.name().to_string());
+        }
+        Ok::<_, Error>(applied)
+    })?;
+    // SQLite only enforces foreign keys when asked to on every connection. Enabled after the
+    // migrations, which rebuild tables other tables refer to.
+    connection.batch_execute("PRAGMA foreign_keys = ON;")?;
+    check_daily_totals(connection)?;
+    Ok(applied)
+}
+
+/// Sum up the `daily_totals` table again if the local time zone changed since it was summed up.
+/// Its triggers file frames under the local day of the process that writes them, so a frame
+/// tracked in one time zone and changed in another would be taken off a different day than it
+/// was added to.
+fn check_daily_totals(connection: &mut SqliteConnection) -> Result<()> {
+    let timezone: String = diesel::select(sql::<Text>(TIMEZONE_QUERY)).get_result(connection)?;
+    let summed_in: Option<String> = settings::table
+        .find(DAILY_TOTALS_TIMEZONE)
+        .select(settings::value)
+        .get_result(connection)
+        .optional()?;
+    if summed_in.as_ref() == Some(&timezone) {
+        return Ok(());
+    }
+    write_transaction(connection, |con| {
+        con.batch_execute(REBUILD_DAILY_TOTALS)?;
+        diesel::replace_into(settings::table)
+            .values((
+                settings::key.eq(DAILY_TOTALS_TIMEZONE),
+                settings::value.eq(&timezone),
+            ))
+            .execute(con)?;
+        Ok(())
+    })
+}
+
+fn default_database_url() -> String {
+    if cfg!(debug_assertions) {
+        dotenv().ok();
+
+        env::var("DATABASE_URL").expect("DATABASE_URL must be set")
+    } else {
+        let dirs = ProjectDirs::from("", "", "ttt").expect("Failed to get base directory paths!");
+        let data_folder = dirs.data_dir();
+
+        create_dir_all(data_folder)
+            .unwrap_or_else(|_| panic!("Failed to create data dir '{}'", data_folder.display()));
+
+        data_folder
+            .join("timetable.db")
+            .to_str()
+            .expect("Sorry non UTF-8 data directory names are not supported!")
+            .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{BudgetPeriod, LeaveKind};
+    use chrono::{Local, NaiveDate};
+
+    #[test]
+    fn test_bulk_inserts_stay_within_the_parameter_limit() {
+        let now = Timestamp::now();
+        let frames: Vec<_> = (0..BULK_INSERT_CHUNK_SIZE)
+            .map(|_| NewFrame {
+                project: 1,
+                start: &now,
+                end: Some(&now),
+                deleted_at: Some(&now),
+                uuid: "a",
+                updated_at: &now,
+                external_ref: Some("#1"),
+                note: Some("note"),
+                source: Some(FrameSource::Import),
+                draft: false,
+                kind: FrameKind::Work,
+            })
+            .collect();
+        let query = diesel::insert_into(frames::table).values(&frames);
+        let sql = diesel::debug_query::<diesel::sqlite::Sqlite, _>(&query).to_string();
+        // Diesel may insert the rows one by one, but every frame binds all its columns.
+        let parameters: Vec<_> = sql
+            .lines()
+            .filter_map(|line| line.split_once(" -- binds"))
+            .map(|(statement, _)| statement.matches('?').count())
+            .collect();
+        assert_eq!(
+            parameters.iter().sum::<usize>(),
+            BULK_INSERT_CHUNK_SIZE * NEW_FRAME_COLUMNS
+        );
+        assert!(parameters.iter().all(|&count| count <= 999));
+    }
+
+    #[test]
+    fn test_in_memory_database_starts_empty() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        assert!(db.all_projects(ArchivedState::Both).unwrap().is_empty());
+        assert!(db.all_tags(ArchivedState::Both).unwrap().is_empty());
+        assert!(matches!(db.current_frame(), Err(Error::NoActiveFrame)));
+    }
+
+    #[test]
+    fn test_start_and_stop_frame() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+
+        let started = db.start(&mut project, None, FrameSource::Cli).unwrap();
+        assert_eq!(db.current_frame().unwrap().id(), started.id());
+        assert!(matches!(
+            db.start(&mut project, None, FrameSource::Cli),
+            Err(Error::AlreadyTracking(_))
+        ));
+
+        let stopped = db.stop().unwrap().unwrap();
+        assert_eq!(stopped.id(), started.id());
+        assert!(stopped.end.is_some());
+        assert!(db.stop().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stop_snapped_to_boundary() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let start = Timestamp::now().round_down(24 * 60) - chrono::Duration::hours(1);
+        let frame = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        let mut moved = frame.clone();
+        moved.start = start;
+        db.update_frame(&moved).unwrap();
+
+        let stopped = db.stop_frame_snapped(frame.id(), Some(30)).unwrap();
+        let end = stopped.end.unwrap();
+        assert_eq!(end, end.round_down(30));
+        assert!(end > start && end <= Timestamp::now());
+
+        // Snapping back would end the frame before it started.
+        let frame = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        let stopped = db.stop_frame_snapped(frame.id(), Some(24 * 60)).unwrap();
+        assert!(stopped.end.unwrap() >= frame.start);
+
+        let frame = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        let stopped = db.stop_frame_snapped(frame.id(), Some(u32::MAX)).unwrap();
+        assert!(stopped.end.unwrap() >= frame.start);
+    }
+
+    #[test]
+    fn test_frame_source() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let started = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        assert_eq!(started.source, Some(FrameSource::Cli));
+        db.stop().unwrap();
+
+        let end = Timestamp::now();
+        let span = TimeSpan::new(Timestamp(end.0 - chrono::Duration::hours(1)), end).unwrap();
+        let added = db.add_frame(&mut website, span, FrameSource::Gui).unwrap();
+        assert_eq!(added.source, Some(FrameSource::Gui));
+        let details = db.frame_details(added.id()).unwrap();
+        assert_eq!(details.frame.source, Some(FrameSource::Gui));
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let mut client_site = db.create_project("client-a-site").unwrap();
+        let client_a = db.create_tag("client-a").unwrap();
+        let billable = db.create_tag("billable").unwrap();
+        assert!(matches!(
+            db.add_rule(RuleField::Note, "PROJ-(", &client_a),
+            Err(Error::InvalidRule(_))
+        ));
+        let note_rule = db.add_rule(RuleField::Note, "^PROJ-", &client_a).unwrap();
+        db.add_rule(RuleField::Ref, "^PROJ-", &client_a).unwrap();
+        db.add_rule(RuleField::Project, "^client-a", &billable)
+            .unwrap();
+        assert_eq!(db.rules().unwrap().len(), 3);
+        let tags = |db: &mut Database, project: &Project| -> Vec<String> {
+            let mut names: Vec<_> = db
+                .lookup_tags_for_project(project.id())
+                .unwrap()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        let frame = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        assert!(tags(&mut db, &website).is_empty());
+        db.amend_frame(frame.id(), None, None, Some("PROJ-12 login"))
+            .unwrap();
+        assert_eq!(tags(&mut db, &website), ["client-a"]);
+        db.stop().unwrap();
+
+        db.start(&mut client_site, Some("PROJ-7"), FrameSource::Cli)
+            .unwrap();
+        assert_eq!(tags(&mut db, &client_site), ["billable", "client-a"]);
+
+        assert!(db.remove_rule(note_rule.id()).unwrap());
+        assert!(!db.remove_rule(note_rule.id()).unwrap());
+        let merged = db.create_tag("customer-a").unwrap();
+        db.merge_tags(&client_a, &merged).unwrap();
+        let names: Vec<_> = db
+            .rules()
+            .unwrap()
+            .into_iter()
+            .map(|(_, tag)| tag.name)
+            .collect();
+        assert_eq!(names, ["customer-a", "billable"]);
+    }
+
+    #[test]
+    fn test_breaks_are_not_working_time() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        db.start(&mut website, None, FrameSource::Cli).unwrap();
+        db.stop().unwrap();
+        let (_, lunch) = db.start_break(&mut website, FrameSource::Cli).unwrap();
+        assert_eq!(lunch.kind, FrameKind::Break);
+        assert!(matches!(
+            db.start(&mut website, None, FrameSource::Cli),
+            Err(Error::AlreadyTracking(_))
+        ));
+        let now = Timestamp::now();
+        let span = TimeSpan::new(Timestamp(now.0 - chrono::Duration::days(1)), now).unwrap();
+        let running = db.daily_totals(span).unwrap();
+        db.stop().unwrap();
+
+        // Make the break long enough to tell apart from the work frame.
+        let mut lunch = db.frame_details(lunch.id()).unwrap().frame;
+        lunch.start = Timestamp(lunch.start.0 - chrono::Duration::minutes(30));
+        db.update_frame(&lunch).unwrap();
+        let totals = db.daily_totals(span).unwrap();
+        let tracked = |totals: &[DailyTotal]| {
+            totals
+                .iter()
+                .fold(chrono::Duration::zero(), |sum, total| sum + total.duration)
+        };
+        assert!(tracked(&totals) < chrono::Duration::minutes(1));
+        assert!(tracked(&running) < chrono::Duration::minutes(1));
+    }
+
+    #[test]
+    fn test_breaks_stop_concurrent_frames() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.allow_concurrent_frames();
+        let mut website = db.create_project("website").unwrap();
+        let mut shop = db.create_project("shop").unwrap();
+        db.start(&mut website, None, FrameSource::Cli).unwrap();
+        db.start(&mut shop, None, FrameSource::Cli).unwrap();
+
+        let (stopped, lunch) = db.start_break(&mut website, FrameSource::Cli).unwrap();
+        assert_eq!(stopped.len(), 2);
+        assert_eq!(db.current_frames().unwrap().len(), 1);
+        assert!(matches!(
+            db.start_break(&mut shop, FrameSource::Cli),
+            Err(Error::AlreadyTracking(_))
+        ));
+
+        let (ended, work) = db.end_break(lunch.id(), FrameSource::Cli).unwrap();
+        assert!(ended.end.is_some());
+        assert_eq!((work.project, work.kind), (website.id(), FrameKind::Work));
+        assert_eq!(db.current_frame().unwrap().id(), work.id());
+        assert!(matches!(
+            db.end_break(work.id(), FrameSource::Cli),
+            Err(Error::FrameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_drafts() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let end = Timestamp::now();
+        let start = Timestamp(end.0 - chrono::Duration::hours(1));
+        let span = TimeSpan::new(Timestamp(end.0 - chrono::Duration::hours(2)), end).unwrap();
+        let uuids = ["a", "b"];
+        let frames = uuids
+            .iter()
+            .map(|uuid| NewFrame {
+                project: website.id(),
+                start: &start,
+                end: Some(&end),
+                deleted_at: None,
+                uuid,
+                updated_at: &end,
+                external_ref: None,
+                note: None,
+                source: Some(FrameSource::Import),
+                draft: true,
+                kind: FrameKind::Work,
+            })
+            .collect();
+        db.insert_frames_bulk(frames, false).unwrap();
+
+        let drafts = db.drafts().unwrap();
+        assert_eq!(drafts.len(), 2);
+        assert!(db
+            .get_frames_in_span(span, ArchivedState::Both)
+            .unwrap()
+            .is_empty());
+        assert!(db.daily_totals(span).unwrap().is_empty());
+        assert!(db.frames_changed_since(None).unwrap().is_empty());
+
+        let (confirmed, discarded) = (drafts[0].1.id(), drafts[1].1.id());
+        assert_eq!(
+            db.review_drafts(&[confirmed], &[discarded]).unwrap(),
+            (1, 1)
+        );
+        assert!(db.drafts().unwrap().is_empty());
+        let frames = db.get_frames_in_span(span, ArchivedState::Both).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert!(!frames[0].1.draft);
+        let totals = db.daily_totals(span).unwrap();
+        let tracked = totals
+            .iter()
+            .fold(chrono::Duration::zero(), |sum, total| sum + total.duration);
+        assert_eq!(tracked, chrono::Duration::hours(1));
+        // Reviewing again changes nothing.
+        assert_eq!(
+            db.review_drafts(&[confirmed], &[discarded]).unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_review_drafts_in_locked_period_changes_nothing() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let hour = |hour| Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+        let (starts, ends) = ([hour(9), hour(14)], [hour(10), hour(15)]);
+        let frames = ["a", "b"]
+            .iter()
+            .zip(starts.iter().zip(&ends))
+            .map(|(uuid, (start, end))| NewFrame {
+                project: website.id(),
+                start,
+                end: Some(end),
+                deleted_at: None,
+                uuid,
+                updated_at: end,
+                external_ref: None,
+                note: None,
+                source: Some(FrameSource::Import),
+                draft: true,
+                kind: FrameKind::Work,
+            })
+            .collect();
+        db.insert_frames_bulk(frames, false).unwrap();
+        db.lock(TimeSpan::new(hour(12), hour(18)).unwrap()).unwrap();
+
+        let drafts = db.drafts().unwrap();
+        let (confirmed, discarded) = (drafts[0].1.id(), drafts[1].1.id());
+        assert!(matches!(
+            db.review_drafts(&[confirmed], &[discarded]),
+            Err(Error::PeriodLocked(_))
+        ));
+        assert_eq!(db.drafts().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_imports_are_no_duplicates_of_drafts() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let start = Timestamp::from_ymdhms(2024, 3, 4, 9, 0, 0);
+        let end = Timestamp::from_ymdhms(2024, 3, 4, 10, 0, 0);
+        let frame = |uuid, draft| NewFrame {
+            project: website.id(),
+            start: &start,
+            end: Some(&end),
+            deleted_at: None,
+            uuid,
+            updated_at: &end,
+            external_ref: None,
+            note: None,
+            source: Some(FrameSource::Import),
+            draft,
+            kind: FrameKind::Work,
+        };
+        db.insert_frames_bulk(vec![frame("a", true)], true).unwrap();
+
+        assert_eq!(
+            db.insert_frames_bulk(vec![frame("b", false)], true)
+                .unwrap(),
+            1
+        );
+        let summary = db
+            .import_frames(vec![frame("c", false)], DuplicatePolicy::Merge)
+            .unwrap();
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.merged[0].uuid(), "b");
+        // Drafts are duplicates of frames and of other drafts.
+        assert_eq!(
+            db.insert_frames_bulk(vec![frame("d", true)], true).unwrap(),
+            0
+        );
+        let summary = db
+            .import_frames(vec![frame("e", true)], DuplicatePolicy::Merge)
+            .unwrap();
+        assert_eq!(summary.skipped, [0]);
+        assert_eq!(db.drafts().unwrap()[0].1.end, Some(end));
+    }
+
+    #[test]
+    fn test_stop_detailed() {
+        let mut db = Database::open_in_memory().unwrap();
+        assert!(db.stop_detailed().unwrap().is_none());
+
+        let mut website = db.create_project("website").unwrap();
+        let billable = db.create_tag("billable").unwrap();
+        let frontend = db.create_tag("frontend").unwrap();
+        db.tag_projects(vec![frontend, billable], vec![website.clone()])
+            .unwrap();
+        let mut frame = db.start(&mut website, None, FrameSource::Cli).unwrap();
+        frame.note = Some("fixed login bug".to_owned());
+        db.update_frame(&frame).unwrap();
+
+        let stopped = db.stop_detailed().unwrap().unwrap();
+        assert_eq!(stopped.frame.id(), frame.id());
+        assert!(stopped.frame.end.is_some());
+        assert_eq!(stopped.frame.note.as_deref(), Some("fixed login bug"));
+        assert_eq!(stopped.project.name, "website");
+        let tags: Vec<_> = stopped.tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(tags, ["billable", "frontend"]);
+
+        let mut untagged = db.create_project("untagged").unwrap();
+        db.start(&mut untagged, None, FrameSource::Cli).unwrap();
+        let stopped = db.stop_detailed().unwrap().unwrap();
+        assert!(stopped.tags.is_empty());
+        assert!(stopped.frame.note.is_none());
+
+        assert!(matches!(
+            db.frame_details(-1),
+            Err(Error::FrameNotFound(-1))
+        ));
+    }
+
+    #[test]
+    fn test_concurrent_frames() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.allow_concurrent_frames();
+        let mut meeting = db.create_project("meeting").unwrap();
+        let mut on_call = db.create_project("on-call").unwrap();
+
+        let first = db.start(&mut on_call, None, FrameSource::Cli).unwrap();
+        assert!(db.stop_before_start().unwrap().is_none());
+        let second = db.start(&mut meeting, None, FrameSource::Cli).unwrap();
+        assert!(matches!(
+            db.start(&mut meeting, None, FrameSource::Cli),
+            Err(Error::AlreadyTracking(_))
+        ));
+        let running: Vec<_> = db.current_frames().unwrap().iter().map(Frame::id).collect();
+        assert_eq!(running, vec![first.id(), second.id()]);
+        assert_eq!(db.current_frame().unwrap().id(), second.id());
+
+        assert_eq!(db.stop_frame(first.id()).unwrap().id(), first.id());
+        assert!(matches!(
+            db.stop_frame(first.id()),
+            Err(Error::FrameNotFound(_))
+        ));
+        assert_eq!(db.stop().unwrap().unwrap().id(), second.id());
+        assert!(db.current_frames().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_amend_running_frame() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let mut blog = db.create_project("blog").unwrap();
+        let layout = db.create_task(&website, "layout").unwrap();
+        let frame = db
+            .start_task(&mut website, &layout, None, FrameSource::Cli)
+            .unwrap();
+
+        let amended = db
+            .amend_frame(frame.id(), None, None, Some("header"))
+            .unwrap();
+        assert_eq!(amended.task_id, Some(layout.id()));
+        let amended = db
+            .amend_frame(frame.id(), Some(&blog), None, Some("footer"))
+            .unwrap();
+        assert_eq!(
+            (amended.project, amended.task_id, amended.note.as_deref()),
+            (blog.id(), None, Some("header; footer"))
+        );
+        assert!(amended.end.is_none());
+        assert!(matches!(
+            db.amend_frame(frame.id(), None, Some(&layout), None),
+            Err(Error::TaskNotFound(_))
+        ));
+
+        db.allow_concurrent_frames();
+        db.start(&mut website, None, FrameSource::Cli).unwrap();
+        assert!(matches!(
+            db.amend_frame(frame.id(), Some(&website), None, None),
+            Err(Error::AlreadyTracking(_))
+        ));
+        db.stop_frame(frame.id()).unwrap();
+        assert!(matches!(
+            db.amend_frame(frame.id(), None, None, Some("late")),
+            Err(Error::FrameNotFound(_))
+        ));
+        blog = db.lookup_project(blog.id()).unwrap().unwrap();
+        assert!(blog.last_access_time >= frame.start);
+    }
+
+    #[test]
+    fn test_filtered_frames() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let mut blog = db.create_project("blog").unwrap();
+        let billable = db.create_tag("billable").unwrap();
+        db.tag_projects(vec![billable], vec![website.clone()])
+            .unwrap();
+        let at = |hour, minute| Timestamp::from_ymdhms(2024, 3, 4, hour, minute, 0);
+        let long = db
+            .add_frame(
+                &mut website,
+                TimeSpan::new(at(9, 0), at(11, 0)).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        let short = db
+            .add_frame(
+                &mut website,
+                TimeSpan::new(at(12, 0), at(12, 10)).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        let review = db.create_task(&blog, "review").unwrap();
+        let running = db
+            .start_task(&mut blog, &review, None, FrameSource::Cli)
+            .unwrap();
+
+        let mut ids = |span, filter: &str| -> Vec<i32> {
+            db.filtered_frames(span, &filter.parse().unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|(_, frame)| frame.id())
+                .collect()
+        };
+        assert_eq!(ids(None, r#"project =~ "^web""#), [long.id(), short.id()]);
+        assert_eq!(
+            ids(None, "tag = billable and duration > 30min"),
+            [long.id()]
+        );
+        assert_eq!(ids(None, "task != review"), [long.id(), short.id()]);
+        assert_eq!(
+            ids(None, "task = review or duration < 1h"),
+            [short.id(), running.id()]
+        );
+        let noon = TimeSpan::new(at(11, 30), at(13, 0)).unwrap();
+        assert_eq!(ids(Some(noon), "project = website"), [short.id()]);
+    }
+
+    #[test]
+    fn test_tasks() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let other = db.create_project("other").unwrap();
+        let task = db.create_task(&website, "checkout-bug").unwrap();
+        assert!(db.create_task(&website, "checkout-bug").is_err());
+        db.create_task(&other, "checkout-bug").unwrap();
+
+        let found = db
+            .lookup_task(website.id(), "checkout-bug")
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id(), task.id());
+        assert_eq!(db.tasks(Some(website.id())).unwrap().len(), 1);
+        assert_eq!(db.tasks(None).unwrap().len(), 2);
+
+        let frame = db
+            .start_task(&mut website, &task, None, FrameSource::Cli)
+            .unwrap();
+        assert_eq!(frame.task_id, Some(task.id()));
+        db.stop().unwrap();
+        let mut other = other;
+        assert!(matches!(
+            db.start_task(&mut other, &task, None, FrameSource::Cli),
+            Err(Error::TaskNotFound(_))
+        ));
+
+        assert!(db.set_task_done(&task, true).unwrap().done);
+    }
+
+    #[test]
+    fn test_clients() {
+        let mut db = Database::open_in_memory().unwrap();
+        let acme = db.create_client("acme", Some("EUR")).unwrap();
+        assert!(db.create_client("acme", None).is_err());
+        let mut initech = db.create_client("initech", None).unwrap();
+        assert_eq!(db.clients().unwrap().len(), 2);
+
+        let mut website = db.create_project("website").unwrap();
+        db.set_client(&mut website, Some(&acme)).unwrap();
+        let found = db.lookup_project_by_name("website").unwrap().unwrap();
+        assert_eq!(found.client_id, Some(acme.id()));
+        assert_eq!(
+            db.lookup_client_by_name("acme").unwrap().unwrap().id(),
+            acme.id()
+        );
+
+        db.set_client(&mut website, None).unwrap();
+        let found = db.lookup_project_by_name("website").unwrap().unwrap();
+        assert_eq!(found.client_id, None);
+
+        db.set_client_currency(&mut initech, Some("USD")).unwrap();
+        db.set_currency(&mut website, Some("CHF")).unwrap();
+        let clients = db.clients().unwrap();
+        assert_eq!(clients[0].currency.as_deref(), Some("EUR"));
+        assert_eq!(clients[1].currency.as_deref(), Some("USD"));
+        let found = db.lookup_project_by_name("website").unwrap().unwrap();
+        assert_eq!(found.currency.as_deref(), Some("CHF"));
+    }
+
+    #[test]
+    fn test_default_tags_replace_the_previous_ones() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let billable = db.create_tag("billable").unwrap();
+        let frontend = db.create_tag("frontend").unwrap();
+
+        db.set_default_tags(&project, &[billable.clone(), frontend])
+            .unwrap();
+        assert_eq!(db.default_tags(project.id()).unwrap().len(), 2);
+
+        db.set_default_tags(&project, &[billable]).unwrap();
+        let defaults = db.default_tags(project.id()).unwrap();
+        assert_eq!(defaults.len(), 1);
+        assert_eq!(defaults[0].name, "billable");
+        // Tags that are no longer default stay associated to the project.
+        assert_eq!(db.lookup_tags_for_project(project.id()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stale_projects_can_be_archived() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.create_project("website").unwrap();
+        let now = Timestamp::now();
+
+        assert!(db
+            .stale_projects(now - chrono::Duration::days(1))
+            .unwrap()
+            .is_empty());
+        let mut stale = db.stale_projects(now + chrono::Duration::days(1)).unwrap();
+        assert_eq!(stale.len(), 1);
+
+        db.archive_projects(&mut stale).unwrap();
+        assert!(db
+            .stale_projects(now + chrono::Duration::days(1))
+            .unwrap()
+            .is_empty());
+        let archived = db.all_projects(ArchivedState::OnlyArchived).unwrap();
+        assert_eq!(archived[0].last_access_time, stale[0].last_access_time);
+    }
+
+    #[test]
+    fn test_resolve_frame_by_uuid_prefix() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let now = Timestamp::now();
+        for uuid in ["abcd1234-0000", "abcd5678-0000"] {
+            db.merge_frame(
+                &NewFrame {
+                    project: project.id(),
+                    start: &now,
+                    end: Some(&now),
+                    deleted_at: None,
+                    uuid,
+                    updated_at: &now,
+                    external_ref: None,
+                    note: None,
+                    source: None,
+                    draft: false,
+                    kind: FrameKind::Work,
+                },
+                None,
+            )
+            .unwrap();
+        }
+
+        assert_eq!(db.resolve_frame("ABCD12").unwrap().uuid(), "abcd1234-0000");
+        assert!(matches!(
+            db.resolve_frame("abcd"),
+            Err(Error::AmbiguousFrameId(_))
+        ));
+        assert!(matches!(
+            db.resolve_frame("ef"),
+            Err(Error::UnknownFrameId(_))
+        ));
+        assert!(matches!(
+            db.resolve_frame("%"),
+            Err(Error::UnknownFrameId(_))
+        ));
+    }
+
+    #[test]
+    fn test_in_memory_databases_are_independent() {
+        let mut first = Database::open_in_memory().unwrap();
+        let mut second = Database::open_in_memory().unwrap();
+
+        first.create_project("website").unwrap();
+
+        assert!(second.lookup_project_by_name("website").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_deleted_frames_can_be_restored() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let frame = db.start(&mut project, None, FrameSource::Cli).unwrap();
+
+        db.delete_frame(frame.id()).unwrap();
+        assert!(matches!(db.current_frame(), Err(Error::NoActiveFrame)));
+        assert!(db.all_frames(ArchivedState::Both).unwrap().is_empty());
+        assert!(matches!(
+            db.delete_frame(frame.id()),
+            Err(Error::FrameNotFound(_))
+        ));
+
+        let trash = db.trashed_frames().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].1.id(), frame.id());
+
+        let restored = db.restore_frame(frame.id()).unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(db.current_frame().unwrap().id(), frame.id());
+        assert!(db.trashed_frames().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restoring_a_running_frame_while_tracking_fails() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let deleted = db.start(&mut project, None, FrameSource::Cli).unwrap();
+        db.delete_frame(deleted.id()).unwrap();
+        db.start(&mut project, None, FrameSource::Cli).unwrap();
+
+        assert!(matches!(
+            db.restore_frame(deleted.id()),
+            Err(Error::AlreadyTracking(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_trash() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let frame = db.start(&mut project, None, FrameSource::Cli).unwrap();
+        db.stop().unwrap();
+        db.delete_frame(frame.id()).unwrap();
+
+        assert_eq!(db.empty_trash().unwrap(), 1);
+        assert!(db.trashed_frames().unwrap().is_empty());
+        assert!(matches!(
+            db.restore_frame(frame.id()),
+            Err(Error::FrameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_locked_periods_refuse_changes() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let hour = |h| Timestamp::from_ymdhms(2024, 3, 4, h, 0, 0);
+        let frame = db
+            .add_frame(
+                &mut project,
+                TimeSpan::new(hour(8), hour(10)).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        db.lock(TimeSpan::new(hour(0), hour(12)).unwrap()).unwrap();
+
+        assert!(matches!(
+            db.add_frame(
+                &mut project,
+                TimeSpan::new(hour(11), hour(13)).unwrap(),
+                FrameSource::Cli
+            ),
+            Err(Error::PeriodLocked(_))
+        ));
+        assert!(matches!(
+            db.delete_frame(frame.id()),
+            Err(Error::PeriodLocked(_))
+        ));
+        // The end of the locked period is exclusive
+        db.add_frame(
+            &mut project,
+            TimeSpan::new(hour(12), hour(13)).unwrap(),
+            FrameSource::Cli,
+        )
+        .unwrap();
+
+        db.force_unlock();
+        db.delete_frame(frame.id()).unwrap();
+        assert_eq!(db.locked_periods().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_projects_can_be_found_by_alias() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website-relaunch").unwrap();
+        db.create_project("web").unwrap();
+        db.add_alias(&project, "wr").unwrap();
+
+        let found = db.lookup_project_by_name("wr").unwrap().unwrap();
+        assert_eq!(found.id(), project.id());
+        assert!(db.add_alias(&project, "wr").is_err());
+        // Names take precedence over aliases
+        db.add_alias(&project, "web").unwrap();
+        assert_eq!(
+            db.lookup_project_by_name("web").unwrap().unwrap().name,
+            "web"
+        );
+    }
+
+    #[test]
+    fn test_create_project_with_aliases() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.create_project("web").unwrap();
+        let aliases = |aliases: &[&str]| aliases.iter().map(|&a| a.to_owned()).collect::<Vec<_>>();
+
+        let project = db
+            .create_project_with_aliases("website-relaunch", &aliases(&["wr", "relaunch"]))
+            .unwrap();
+        let found = db.lookup_project_by_name("relaunch").unwrap().unwrap();
+        assert_eq!(found.id(), project.id());
+
+        for (name, taken) in [
+            ("shop", aliases(&["web"])),
+            ("shop", aliases(&["wr"])),
+            ("shop", aliases(&["s", "s"])),
+            ("wr", aliases(&[])),
+        ] {
+            assert!(matches!(
+                db.create_project_with_aliases(name, &taken),
+                Err(Error::ProjectExists(_))
+            ));
+        }
+        // Nothing is left of the failed attempts
+        assert!(db.lookup_project_by_name("shop").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_merge_projects() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut from = db.create_project("web").unwrap();
+        let into = db.create_project("website").unwrap();
+        let tag = db.create_tag("customer").unwrap();
+        db.set_default_tags(&from, &[tag]).unwrap();
+        db.add_alias(&from, "w").unwrap();
+        let frame = db.start(&mut from, None, FrameSource::Cli).unwrap();
+        db.stop().unwrap();
+
+        db.merge_projects(&from, &into).unwrap();
+
+        assert!(db.lookup_project(from.id()).unwrap().is_none());
+        for name in ["web", "w"] {
+            let found = db.lookup_project_by_name(name).unwrap().unwrap();
+            assert_eq!(found.id(), into.id());
+        }
+        assert_eq!(db.resolve_frame(frame.uuid()).unwrap().project, into.id());
+        assert_eq!(db.default_tags(into.id()).unwrap()[0].name, "customer");
+    }
+
+    #[test]
+    fn test_merge_projects_keeps_tasks_budget_and_rates() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut from = db.create_project("web").unwrap();
+        let into = db.create_project("website").unwrap();
+        let bug = db.create_task(&from, "checkout-bug").unwrap();
+        let shared = db.create_task(&from, "release").unwrap();
+        let release = db.create_task(&into, "release").unwrap();
+        let budget = Budget {
+            project_id: from.id(),
+            minutes: Some(600),
+            cents: None,
+            period: BudgetPeriod::Month,
+        };
+        db.set_budget(&budget).unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        db.add_rate(&from, day, 9000).unwrap();
+        let on_bug = db
+            .start_task(&mut from, &bug, None, FrameSource::Cli)
+            .unwrap();
+        db.stop().unwrap();
+        let on_release = db
+            .start_task(&mut from, &shared, None, FrameSource::Cli)
+            .unwrap();
+        db.stop().unwrap();
+
+        db.merge_projects(&from, &into).unwrap();
+
+        let bug = db.lookup_task(into.id(), "checkout-bug").unwrap().unwrap();
+        let task_of =
+            |db: &mut Database, frame: &Frame| db.resolve_frame(frame.uuid()).unwrap().task_id;
+        assert_eq!(task_of(&mut db, &on_bug), Some(bug.id()));
+        assert_eq!(task_of(&mut db, &on_release), Some(release.id()));
+        assert_eq!(db.tasks(Some(into.id())).unwrap().len(), 2);
+        assert_eq!(db.budget(into.id()).unwrap().unwrap().minutes, Some(600));
+        assert_eq!(db.rates().unwrap()[&into.id()][0].cents, 9000);
+    }
+
+    #[test]
+    fn test_merge_projects_with_two_budgets_fails() {
+        let mut db = Database::open_in_memory().unwrap();
+        let from = db.create_project("web").unwrap();
+        let into = db.create_project("website").unwrap();
+        for project in [&from, &into] {
+            db.set_budget(&Budget {
+                project_id: project.id(),
+                minutes: Some(600),
+                cents: None,
+                period: BudgetPeriod::Month,
+            })
+            .unwrap();
+        }
+
+        assert!(matches!(
+            db.merge_projects(&from, &into),
+            Err(Error::MergeConflict(_))
+        ));
+        assert!(db.lookup_project(from.id()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_foreign_keys_are_enforced() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let tag = db.create_tag("customer").unwrap();
+        db.tag_projects(vec![tag.clone()], vec![project.clone()])
+            .unwrap();
+        db.start(&mut project, None, FrameSource::Cli).unwrap();
+
+        // Frames keep their project.
+        assert!(diesel::delete(&project)
+            .execute(&mut db.connection)
+            .is_err());
+        let now = Timestamp::now();
+        let orphan = NewFrame {
+            project: project.id() + 1,
+            start: &now,
+            end: None,
+            deleted_at: None,
+            uuid: "orphan",
+            updated_at: &now,
+            external_ref: None,
+            note: None,
+            source: None,
+            draft: false,
+            kind: FrameKind::Work,
+        };
+        assert!(db.merge_frame(&orphan, None).is_err());
+
+        // Tags are removed from their projects when deleted.
+        diesel::delete(&tag).execute(&mut db.connection).unwrap();
+        assert!(db.lookup_tags_for_project(project.id()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_auto_stop() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let at = NaiveTime::from_hms_opt(18, 30, 0).unwrap();
+        assert!(db.auto_stop(at).unwrap().is_empty());
+
+        // Started after the end of the workday, so it runs until the next one.
+        let day = Local::now().date_naive() - chrono::Duration::days(2);
+        let start = Timestamp::from_naive(day.and_hms_opt(20, 0, 0).unwrap());
+        let frame = NewFrame {
+            project: project.id(),
+            start: &start,
+            end: None,
+            deleted_at: None,
+            uuid: "forgotten",
+            updated_at: &start,
+            external_ref: None,
+            note: None,
+            source: None,
+            draft: false,
+            kind: FrameKind::Work,
+        };
+        db.merge_frame(&frame, None).unwrap();
+        let stopped = db.auto_stop(at).unwrap().pop().unwrap();
+        assert_eq!(
+            stopped.end.unwrap().to_naive(),
+            (day + chrono::Duration::days(1)).and_time(at)
+        );
+        assert_eq!(
+            stopped.note.as_deref(),
+            Some("Stopped automatically at 18:30")
+        );
+        assert!(db.auto_stop(at).unwrap().is_empty());
+
+        // A note added during the day is kept.
+        let annotated = NewFrame {
+            uuid: "annotated",
+            note: Some("fixed the checkout"),
+            ..frame
+        };
+        db.merge_frame(&annotated, None).unwrap();
+        let stopped = db.auto_stop(at).unwrap().pop().unwrap();
+        assert_eq!(
+            stopped.note.as_deref(),
+            Some("fixed the checkout; Stopped automatically at 18:30")
+        );
+
+        // The end of the workday has not come yet for a frame started just now.
+        let mut project = db.lookup_project(project.id()).unwrap().unwrap();
+        db.start(&mut project, None, FrameSource::Cli).unwrap();
+        let just_passed = (Local::now() - chrono::Duration::minutes(1)).time();
+        assert!(db.auto_stop(just_passed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrations_and_stats() {
+        let mut db = Database::new_unmigrated(ConnectionTarget::InMemory).unwrap();
+        let pending = db.migrations().unwrap();
+        assert!(pending.iter().all(|(_, applied)| !applied));
+
+        let applied = db.migrate().unwrap();
+        assert_eq!(applied.len(), pending.len());
+        assert!(db.migrations().unwrap().iter().all(|(_, applied)| *applied));
+        assert!(db.migrate().unwrap().is_empty());
+
+        let stats = db.stats().unwrap();
+        assert!(stats.first_frame.is_none());
+        let mut project = db.create_project("website").unwrap();
+        let first = db.start(&mut project, None, FrameSource::Cli).unwrap();
+        let last = db.stop().unwrap().unwrap();
+        db.vacuum().unwrap();
+
+        let stats = db.stats().unwrap();
+        assert!(stats.rows.contains(&("frames", 1)));
+        assert!(stats.rows.contains(&("projects", 1)));
+        assert_eq!(stats.first_frame, Some(first.start));
+        assert_eq!(stats.last_frame, last.end);
+    }
+
+    #[test]
+    fn test_leave_days() {
+        let mut db = Database::open_in_memory().unwrap();
+        let day = |d| chrono::NaiveDate::from_ymd_opt(2024, 12, d).unwrap();
+        for (d, kind) in [(23, LeaveKind::Sick), (24, LeaveKind::Vacation)] {
+            db.add_leave(&LeaveDay {
+                day: day(d),
+                kind,
+                name: None,
+            })
+            .unwrap();
+        }
+        let christmas = LeaveDay {
+            day: day(24),
+            kind: LeaveKind::Holiday,
+            name: Some("Christmas Eve".to_owned()),
+        };
+        db.add_leave(&christmas).unwrap();
+
+        let span = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 12, 24, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 12, 25, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(db.leave_days(span).unwrap(), vec![christmas]);
+
+        assert!(db.remove_leave(day(24)).unwrap());
+        assert!(!db.remove_leave(day(24)).unwrap());
+        assert!(db.leave_days(span).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_frames_in_span_iter_loads_chunks() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let at = |hour| Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+        for hour in 8..15 {
+            db.add_frame(
+                &mut project,
+                TimeSpan::new(at(hour), at(hour + 1)).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        }
+        let span = TimeSpan::new(at(9), at(14)).unwrap();
+        let expected: Vec<_> = db
+            .get_frames_in_span(span, ArchivedState::Both)
+            .unwrap()
+            .into_iter()
+            .map(|(_, frame)| frame.id())
+            .collect();
+        assert_eq!(expected.len(), 6);
+
+        let frames = FramesInSpan {
+            chunk_size: 4,
+            ..db.frames_in_span_iter(span, ArchivedState::Both)
+        };
+        let ids: Vec<_> = frames.map(|frame| frame.unwrap().1.id()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_insert_frames_bulk_skips_duplicates() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let times: Vec<_> = (0..250)
+            .map(|minute| Timestamp::from_ymdhms(2024, 3, 4, 8 + minute / 60, minute % 60, 0))
+            .collect();
+        let uuids: Vec<_> = (0..times.len()).map(|i| format!("import-{i}")).collect();
+        fn new_frames<'a>(
+            project: i32,
+            times: &'a [Timestamp],
+            uuids: &'a [String],
+        ) -> Vec<NewFrame<'a>> {
+            times
+                .windows(2)
+                .zip(uuids)
+                .map(|(span, uuid)| NewFrame {
+                    project,
+                    start: &span[0],
+                    end: Some(&span[1]),
+                    deleted_at: None,
+                    uuid,
+                    updated_at: &span[0],
+                    external_ref: None,
+                    note: None,
+                    source: None,
+                    draft: false,
+                    kind: FrameKind::Work,
+                })
+                .collect()
+        }
+        let frames = |uuids| new_frames(project.id(), &times, uuids);
+
+        assert_eq!(db.insert_frames_bulk(frames(&uuids), false).unwrap(), 249);
+        assert_eq!(db.insert_frames_bulk(frames(&uuids), true).unwrap(), 0);
+        // The same frames exported again get new uuids.
+        let fresh: Vec<_> = uuids.iter().map(|uuid| format!("{uuid}-again")).collect();
+        assert_eq!(db.insert_frames_bulk(frames(&fresh), true).unwrap(), 0);
+        assert!(db.insert_frames_bulk(frames(&uuids), false).is_err());
+        assert_eq!(db.all_frames(ArchivedState::Both).unwrap().len(), 249);
+
+        db.lock(TimeSpan::new(times[0], times[1]).unwrap()).unwrap();
+        let locked: Vec<_> = uuids.iter().map(|uuid| format!("{uuid}-locked")).collect();
+        assert!(matches!(
+            db.insert_frames_bulk(frames(&locked), false),
+            Err(Error::PeriodLocked(_))
+        ));
+    }
+
+    #[test]
+    fn test_import_frames_detects_duplicates() {
+        let at = |hour, minute| Timestamp::from_ymdhms(2024, 3, 4, hour, minute, 0);
+        let imported = [
+            ("website", at(9, 5), at(10, 10)),
+            ("website", at(11, 0), at(12, 0)),
+            ("website", at(11, 10), at(12, 5)),
+            ("blog", at(9, 0), at(10, 0)),
+            ("website", at(10, 0), at(11, 0)),
+        ];
+        let uuids: Vec<_> = (0..imported.len()).map(|i| format!("import-{i}")).collect();
+        let import = |policy| {
+            let mut db = Database::open_in_memory().unwrap();
+            let mut website = db.create_project("website").unwrap();
+            let blog = db.create_project("blog").unwrap();
+            let tracked = db
+                .add_frame(
+                    &mut website,
+                    TimeSpan::new(at(9, 0), at(10, 0)).unwrap(),
+                    FrameSource::Cli,
+                )
+                .unwrap();
+            let frames = imported
+                .iter()
+                .zip(&uuids)
+                .map(|((project, start, end), uuid)| NewFrame {
+                    project: if *project == "blog" {
+                        blog.id()
+                    } else {
+                        website.id()
+                    },
+                    start,
+                    end: Some(end),
+                    deleted_at: None,
+                    uuid,
+                    updated_at: start,
+                    external_ref: None,
+                    note: None,
+                    source: None,
+                    draft: false,
+                    kind: FrameKind::Work,
+                })
+                .collect();
+            let summary = db.import_frames(frames, policy);
+            (db, tracked, summary)
+        };
+
+        let (mut db, _, summary) = import(DuplicatePolicy::Skip);
+        let summary = summary.unwrap();
+        assert_eq!((summary.inserted, summary.skipped), (3, vec![0, 2]));
+        assert_eq!(db.all_frames(ArchivedState::Both).unwrap().len(), 4);
+
+        let (mut db, tracked, summary) = import(DuplicatePolicy::Merge);
+        let summary = summary.unwrap();
+        assert_eq!(summary.inserted, 3);
+        assert_eq!(summary.merged.len(), 1);
+        let spans: Vec<_> = db
+            .all_frames(ArchivedState::Both)
+            .unwrap()
+            .into_iter()
+            .map(|frame| (frame.id() == tracked.id(), frame.start, frame.end.unwrap()))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (true, at(9, 0), at(10, 10)),
+                (false, at(9, 0), at(10, 0)),
+                (false, at(10, 0), at(11, 0)),
+                (false, at(11, 0), at(12, 5)),
+            ]
+        );
+
+        let (mut db, _, summary) = import(DuplicatePolicy::Fail);
+        assert!(matches!(summary, Err(Error::DuplicateFrame(_))));
+        assert_eq!(db.all_frames(ArchivedState::Both).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_adjacent_frames() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let mut blog = db.create_project("blog").unwrap();
+        let at = |hour, minute| Timestamp::from_ymdhms(2024, 3, 4, hour, minute, 0);
+        let mut add = |project: &mut Project, start, end| {
+            db.add_frame(
+                project,
+                TimeSpan::new(start, end).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap()
+        };
+        let first = add(&mut website, at(9, 0), at(9, 30));
+        add(&mut blog, at(9, 30), at(9, 31));
+        add(&mut website, at(9, 31), at(10, 0));
+        add(&mut website, at(10, 1), at(10, 30));
+        // Too far apart
+        add(&mut website, at(11, 0), at(12, 0));
+        add(&mut blog, at(12, 0), at(12, 30));
+        let span = TimeSpan::new(at(0, 0), at(23, 0)).unwrap();
+
+        let groups = db
+            .mergeable_frames(span, chrono::Duration::minutes(2))
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), 3);
+        assert_eq!(groups[0][0].id(), first.id());
+
+        let merged = db.merge_frames(&groups[0]).unwrap();
+        assert_eq!((merged.start, merged.end), (at(9, 0), Some(at(10, 30))));
+        assert_eq!(db.trashed_frames().unwrap().len(), 2);
+        assert!(db
+            .mergeable_frames(span, chrono::Duration::minutes(2))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            db.mergeable_frames(span, chrono::Duration::hours(1))
+                .unwrap()[0]
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_breaks_are_not_merged_into_work() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |hour, minute| Timestamp::from_ymdhms(2024, 3, 4, hour, minute, 0);
+        let mut add = |start, end| {
+            db.add_frame(
+                &mut website,
+                TimeSpan::new(start, end).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap()
+        };
+        add(at(9, 0), at(10, 0));
+        let pause = add(at(10, 0), at(10, 1));
+        add(at(10, 1), at(11, 0));
+        db.set_frame_kind(pause.id(), FrameKind::Break).unwrap();
+        let span = TimeSpan::new(at(0, 0), at(23, 0)).unwrap();
+
+        assert!(db
+            .mergeable_frames(span, chrono::Duration::minutes(2))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_daily_totals_are_summed_up_again_in_another_timezone() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |hour| Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+        let span = TimeSpan::new(at(9), at(11)).unwrap();
+        db.add_frame(&mut website, span, FrameSource::Cli).unwrap();
+        let totals = db.daily_totals(span).unwrap();
+        assert_eq!(totals[0].duration, chrono::Duration::hours(2));
+
+        // Left behind by triggers in another time zone
+        db.connection
+            .batch_execute("UPDATE daily_totals SET day = '2024-03-03';")
+            .unwrap();
+        db.set_setting(DAILY_TOTALS_TIMEZONE, "elsewhere").unwrap();
+        check_daily_totals(&mut db.connection).unwrap();
+
+        assert_eq!(
+            db.daily_totals(span).unwrap()[0].duration,
+            totals[0].duration
+        );
+        assert_ne!(
+            db.setting(DAILY_TOTALS_TIMEZONE).unwrap().as_deref(),
+            Some("elsewhere")
+        );
+    }
+
+    #[test]
+    fn test_daily_totals_follow_frame_changes() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut web = db.create_project("web").unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |day, hour, minute| Timestamp::from_ymdhms(2024, 3, day, hour, minute, 0);
+        let span = |start, end| TimeSpan::new(start, end).unwrap();
+        db.add_frame(&mut web, span(at(4, 8, 0), at(4, 10, 0)), FrameSource::Cli)
+            .unwrap();
+        let removed = db
+            .add_frame(
+                &mut website,
+                span(at(4, 11, 0), at(4, 11, 30)),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        db.add_frame(
+            &mut website,
+            span(at(4, 13, 0), at(4, 14, 0)),
+            FrameSource::Cli,
+        )
+        .unwrap();
+        // Only the hour before midnight is within the span.
+        db.add_frame(&mut web, span(at(5, 23, 0), at(6, 1, 0)), FrameSource::Cli)
+            .unwrap();
+        let totals = |db: &mut Database| {
+            db.daily_totals(span(at(4, 0, 0), at(6, 0, 0)))
+                .unwrap()
+                .into_iter()
+                .map(|total| {
+                    (
+                        total.day.to_string(),
+                        total.project.name,
+                        total.duration.num_minutes(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let total =
+            |day: &str, project: &str, minutes| (day.to_owned(), project.to_owned(), minutes);
+
+        let mut before = totals(&mut db);
+        before.sort();
+        assert_eq!(
+            before,
+            vec![
+                total("2024-03-04", "web", 120),
+                total("2024-03-04", "website", 90),
+                total("2024-03-05", "web", 60),
+            ]
+        );
+
+        db.delete_frame(removed.id()).unwrap();
+        db.merge_projects(&web, &website).unwrap();
+        let mut after = totals(&mut db);
+        after.sort();
+        assert_eq!(
+            after,
+            vec![
+                total("2024-03-04", "website", 180),
+                total("2024-03-05", "website", 60),
+            ]
+        );
+        assert_eq!(
+            db.daily_totals(span(at(6, 0, 0), at(7, 0, 0))).unwrap()[0]
+                .duration
+                .num_minutes(),
+            60
+        );
+    }
+
+    #[test]
+    fn test_settings_can_be_overwritten() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        assert_eq!(db.setting("key").unwrap(), None);
+        db.set_setting("key", "first").unwrap();
+        db.set_setting("key", "second").unwrap();
+        assert_eq!(db.setting("key").unwrap().as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn test_merge_frame_keeps_the_newer_version() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let start = Timestamp::from_ymdhms(2024, 3, 1, 8, 0, 0);
+        let end = Timestamp::from_ymdhms(2024, 3, 1, 9, 0, 0);
+        let later_end = Timestamp::from_ymdhms(2024, 3, 1, 10, 0, 0);
+
+        let frame = NewFrame {
+            project: project.id(),
+            start: &start,
+            end: Some(&end),
+            deleted_at: None,
+            uuid: "b1f4c4a2-4a8e-4c2b-9d55-5b0d8e7c8a10",
+            updated_at: &end,
+            external_ref: None,
+            note: None,
+            source: None,
+            draft: false,
+            kind: FrameKind::Work,
+        };
+        assert!(db.merge_frame(&frame, None).unwrap());
+        assert!(!db.merge_frame(&frame, None).unwrap());
+
+        let newer = NewFrame {
+            end: Some(&later_end),
+            updated_at: &later_end,
+            external_ref: None,
+            note: None,
+            ..frame
+        };
+        assert!(db.merge_frame(&newer, None).unwrap());
+        let older = NewFrame {
+            end: Some(&start),
+            updated_at: &start,
+            external_ref: None,
+            note: None,
+            ..newer
+        };
+        assert!(!db.merge_frame(&older, None).unwrap());
+
+        let frames = db.all_frames(ArchivedState::Both).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].end, Some(later_end));
+    }
+
+    #[test]
+    fn test_resolve_overlaps_cuts_off_earlier_frame() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let eight = Timestamp::from_ymdhms(2024, 3, 1, 8, 0, 0);
+        let nine = Timestamp::from_ymdhms(2024, 3, 1, 9, 0, 0);
+        let ten = Timestamp::from_ymdhms(2024, 3, 1, 10, 0, 0);
+
+        for (uuid, start, end) in [("a", &eight, Some(&ten)), ("b", &nine, None)] {
+            db.merge_frame(
+                &NewFrame {
+                    project: project.id(),
+                    start,
+                    end,
+                    deleted_at: None,
+                    uuid,
+                    updated_at: start,
+                    external_ref: None,
+                    note: None,
+                    source: None,
+                    draft: false,
+                    kind: FrameKind::Work,
+                },
+                None,
+            )
+            .unwrap();
+        }
+
+        let changed = db.resolve_overlaps().unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].uuid(), "a");
+        assert_eq!(changed[0].end, Some(nine));
+        assert!(db.resolve_overlaps().unwrap().is_empty());
+        assert_eq!(db.current_frame().unwrap().uuid(), "b");
+    }
+
+    #[test]
+    fn test_resolve_overlaps_splits_frame_around_another() {
+        let at = |hour| Timestamp::from_ymdhms(2024, 3, 1, hour, 0, 0);
+        let resolve = || {
+            let mut db = Database::open_in_memory().unwrap();
+            let website = db.create_project("website").unwrap();
+            let meeting = db.create_project("meeting").unwrap();
+            for (project, uuid, start, end) in [(&website, "a", 8, 12), (&meeting, "b", 9, 10)] {
+                db.merge_frame(
+                    &NewFrame {
+                        project: project.id(),
+                        start: &at(start),
+                        end: Some(&at(end)),
+                        deleted_at: None,
+                        uuid,
+                        updated_at: &at(end),
+                        external_ref: None,
+                        note: None,
+                        source: None,
+                        draft: false,
+                        kind: FrameKind::Work,
+                    },
+                    None,
+                )
+                .unwrap();
+            }
+            assert_eq!(db.resolve_overlaps().unwrap().len(), 2);
+            assert!(db.resolve_overlaps().unwrap().is_empty());
+            let mut frames = db.all_frames(ArchivedState::Both).unwrap();
+            frames.sort_by_key(|frame| frame.start);
+            frames
+        };
+
+        let frames = resolve();
+        let spans: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.project, frame.start, frame.end.unwrap()))
+            .collect();
+        let (website, meeting) = (frames[0].project, frames[1].project);
+        assert_eq!(
+            spans,
+            [
+                (website, at(8), at(9)),
+                (meeting, at(9), at(10)),
+                (website, at(10), at(12)),
+            ]
+        );
+        // Another machine with the same frames creates the same frame.
+        assert_eq!(resolve()[2].uuid(), frames[2].uuid());
+    }
+
+    #[test]
+    fn test_queries_are_read_only() {
+        let path = env::temp_dir().join(format!("ttt-test-{}-query.db", std::process::id()));
+        {
+            let mut db = Database::new(ConnectionTarget::Path(path.clone())).unwrap();
+            db.create_project("website").unwrap();
+            db.create_project("blog").unwrap();
+
+            let result = db
+                .query_read_only("SELECT name, archived AS \"is archived\", NULL AS missing FROM projects ORDER BY name;")
+                .unwrap();
+            assert_eq!(result.columns, ["name", "is archived", "missing"]);
+            assert_eq!(
+                result.rows,
+                [
+                    vec!["blog".into(), 0.into(), serde_json::Value::Null],
+                    vec!["website".into(), 0.into(), serde_json::Value::Null]
+                ]
+            );
+            assert!(db.query_read_only("DELETE FROM projects").is_err());
+            // Only the first statement is run.
+            let result = db
+                .query_read_only("SELECT 1; DELETE FROM projects")
+                .unwrap();
+            assert_eq!(result.rows.len(), 1);
+            assert!(connect_read_only(&path)
+                .unwrap()
+                .batch_execute("DELETE FROM projects")
+                .is_err());
+            assert_eq!(db.all_projects(ArchivedState::Both).unwrap().len(), 2);
+        }
+
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    // With SQLCipher a damaged file can't be told apart from an encrypted one.
+    #[cfg(not(feature = "sqlcipher"))]
+    #[test]
+    fn test_damaged_database_is_replaced_by_backup() {
+        let dir = env::temp_dir().join(format!("ttt-test-{}-damaged", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("ttt.sqlite");
+        let target = || ConnectionTarget::Path(path.clone());
+        {
+            let mut db = Database::new(target()).unwrap();
+            db.create_project("website").unwrap();
+            let backup = db.back_up_daily().unwrap().unwrap();
+            assert_eq!(latest_backup(&path), Some(backup));
+            assert_eq!(db.back_up_daily().unwrap(), None);
+        }
+
+        std::fs::write(
+            &path,
+            "not a database, just some text that is long enough".repeat(4),
+        )
+        .unwrap();
+        assert!(matches!(
+            Database::new(target()),
+            Err(Error::DatabaseDamaged(_))
+        ));
+        let damaged = replace_damaged(&path, latest_backup(&path).as_deref()).unwrap();
+        assert!(damaged.exists());
+        let mut db = Database::new(target()).unwrap();
+        assert!(db.lookup_project_by_name("website").unwrap().is_some());
+        drop(db);
+
+        replace_damaged(&path, None).unwrap();
+        let mut db = Database::new(target()).unwrap();
+        assert!(db.all_projects(ArchivedState::Both).unwrap().is_empty());
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let dir = env::temp_dir().join(format!("ttt-test-{}-migration", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("ttt.sqlite");
+        let target = || ConnectionTarget::Path(path.clone());
+        {
+            let mut db = Database::new(target()).unwrap();
+            db.create_project("website").unwrap();
+            let latest = db.connection.revert_last_migration(MIGRATIONS).unwrap();
+            db.connection.revert_last_migration(MIGRATIONS).unwrap();
+            // Makes the latest migration fail when it is applied again, after the one before it.
+            db.connection
+                .batch_execute(&format!(
+                    "CREATE TRIGGER fail_migrations BEFORE INSERT ON __diesel_schema_migrations
+                    WHEN NEW.version = '{latest}'
+                    BEGIN SELECT RAISE(ABORT, 'migration sabotaged'); END;",
+                ))
+                .unwrap();
+        }
+
+        let result = Database::new(target()).map(|_| ());
+        let Err(Error::Migration(message)) = result else {
+            panic!("The migration should fail: {result:?}");
+        };
+        assert!(message.contains("migration sabotaged"), "{message}");
+        // Kept apart from the daily backups, which are pruned.
+        assert!(backups(&path).is_empty());
+        let copies = std::fs::read_dir(migration_backup_dir(&path)).unwrap();
+        assert_eq!(copies.count(), 1);
+
+        let mut db = Database::new_unmigrated(target()).unwrap();
+        let pending: Vec<_> = db
+            .migrations()
+            .unwrap()
+            .into_iter()
+            .filter(|(_, applied)| !applied)
+            .collect();
+        // The earlier migration was rolled back with the failing one.
+        assert_eq!(pending.len(), 2);
+        assert!(db.lookup_project_by_name("website").unwrap().is_some());
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_two_connections_can_write_to_the_same_file() {
+        let path = env::temp_dir().join(format!("ttt-test-{}-concurrent.db", std::process::id()));
+        {
+            let mut cli = Database::new(ConnectionTarget::Path(path.clone())).unwrap();
+            let mut gui = Database::new(ConnectionTarget::Path(path.clone())).unwrap();
+
+            let mut project = cli.create_project("website").unwrap();
+            gui.start(&mut project, None, FrameSource::Cli).unwrap();
+            assert!(cli.stop().unwrap().is_some());
+            assert!(matches!(gui.current_frame(), Err(Error::NoActiveFrame)));
+        }
+
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
 }
@@ -0,0 +1,259 @@
+//! Formatting and parsing of durations for humans and billing tools.
+
+use std::fmt::Write as _;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// How durations are printed, see `--duration-format`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationFormat {
+    /// Units spelled out, e.g. "1h 23min"
+    #[default]
+    Hms,
+
+    /// Hours and minutes, e.g. "1:23"
+    Clock,
+
+    /// Hours with two decimals, e.g. "1.38h"
+    Decimal,
+}
+
+pub trait DurationExt {
+    /// Format the duration in the given style.
+    ///
+    /// ```
+    /// # use ttt::duration::{DurationExt, DurationFormat};
+    /// let duration = chrono::Duration::minutes(83);
+    /// assert_eq!(duration.format_as(DurationFormat::Hms), "1h 23min");
+    /// assert_eq!(duration.format_as(DurationFormat::Clock), "1:23");
+    /// assert_eq!(duration.format_as(DurationFormat::Decimal), "1.38h");
+    /// ```
+    fn format_as(&self, format: DurationFormat) -> String;
+
+    /// Format the duration as ISO 8601 for machine readable output, e.g. "PT1H23M".
+    /// Days are not used, as their length depends on the calendar.
+    ///
+    /// ```
+    /// # use ttt::duration::DurationExt;
+    /// assert_eq!(chrono::Duration::minutes(83).to_iso8601(), "PT1H23M");
+    /// assert_eq!(chrono::Duration::hours(50).to_iso8601(), "PT50H");
+    /// assert_eq!(chrono::Duration::seconds(-5).to_iso8601(), "-PT5S");
+    /// assert_eq!(chrono::Duration::zero().to_iso8601(), "PT0S");
+    /// ```
+    fn to_iso8601(&self) -> String;
+}
+
+impl DurationExt for chrono::Duration {
+    fn format_as(&self, format: DurationFormat) -> String {
+        match format {
+            DurationFormat::Hms => format_hms(*self),
+            DurationFormat::Clock => {
+                format!("{}:{:02}", self.num_hours(), self.num_minutes() % 60)
+            }
+            DurationFormat::Decimal => {
+                format!("{:.2}h", self.num_seconds() as f64 / 3600.0)
+            }
+        }
+    }
+
+    fn to_iso8601(&self) -> String {
+        let seconds = self.num_seconds().unsigned_abs();
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        let mut result = String::new();
+        if self.num_seconds() < 0 {
+            result.push('-');
+        }
+        result.push_str("PT");
+        if hours > 0 {
+            let _ = write!(result, "{hours}H");
+        }
+        if minutes > 0 {
+            let _ = write!(result, "{minutes}M");
+        }
+        if seconds > 0 || (hours == 0 && minutes == 0) {
+            let _ = write!(result, "{seconds}S");
+        }
+        result
+    }
+}
+
+/// Parse a duration written by a human, e.g. "90m", "1h30", "1.5h", "1h 30min" or "1:30".
+/// Meant as clap value parser, so errors are complete sentences.
+///
+/// Units are days, hours, minutes and seconds, each in several spellings like "h", "hr" or
+/// "hours". They must get smaller from left to right. A number without unit after hours counts
+/// as minutes and after minutes as seconds.
+///
+/// ```
+/// # use ttt::duration::parse_duration;
+/// # use chrono::Duration;
+/// assert_eq!(parse_duration("1h30"), Ok(Duration::minutes(90)));
+/// assert_eq!(parse_duration("1.5h"), Ok(Duration::minutes(90)));
+/// assert!(parse_duration("90").is_err());
+/// ```
+pub fn parse_duration(text: &str) -> Result<chrono::Duration, String> {
+    const EXAMPLES: &str = "e.g. 90m, 1h30 or 1.5h";
+    if text.trim().is_empty() {
+        return Err(format!("Expected a duration, {EXAMPLES}"));
+    }
+    if let Some((hours, minutes)) = text.trim().split_once(':') {
+        let digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if !digits(hours) || !digits(minutes) || minutes.len() != 2 {
+            return Err(format!("{text} is not a time like 1:30"));
+        }
+        return match (hours.parse::<f64>(), minutes.parse::<f64>()) {
+            (Ok(hours), Ok(minutes)) if minutes < 60.0 => {
+                clamp(hours * 3600.0 + minutes * 60.0, text)
+            }
+            _ => Err(format!("{text} is not a time like 1:30")),
+        };
+    }
+
+    let mut seconds = 0.0;
+    let mut previous_unit = None;
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let length = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let amount: f64 = match &rest[..length] {
+            "" => return Err(format!("Expected a number before \"{rest}\" in {text}")),
+            number => number
+                .parse()
+                .map_err(|_| format!("{number} is not a number"))?,
+        };
+        rest = rest[length..].trim_start();
+
+        let length = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = match (rest[..length].to_lowercase().as_str(), previous_unit) {
+            ("d" | "day" | "days", _) => 86400,
+            ("h" | "hr" | "hrs" | "hour" | "hours", _) => 3600,
+            ("m" | "min" | "mins" | "minute" | "minutes", _) => 60,
+            ("s" | "sec" | "secs" | "second" | "seconds", _) => 1,
+            ("", Some(3600)) => 60,
+            ("", Some(60)) => 1,
+            ("", _) => return Err(format!("{text} is missing a unit, {EXAMPLES}")),
+            (unit, _) => return Err(format!("Unknown unit {unit} in {text}, use d, h, m or s")),
+        };
+        if previous_unit.is_some_and(|previous| unit >= previous) {
+            return Err(format!(
+                "The units in {text} must get smaller from left to right, e.g. 1h30m"
+            ));
+        }
+        previous_unit = Some(unit);
+        seconds += amount * unit as f64;
+        rest = rest[length..].trim_start();
+    }
+    clamp(seconds, text)
+}
+
+/// Round to whole seconds, rejecting durations nobody could mean.
+fn clamp(seconds: f64, text: &str) -> Result<chrono::Duration, String> {
+    // Far more than anyone tracks, yet small enough to not overflow anywhere.
+    const LIMIT: f64 = i32::MAX as f64;
+    if seconds > LIMIT {
+        return Err(format!("{text} is too long"));
+    }
+    Ok(chrono::Duration::seconds(seconds.round() as i64))
+}
+
+fn format_hms(duration: chrono::Duration) -> String {
+    use chrono::Duration;
+    let mut mydur = duration;
+    let mut result = String::new();
+
+    let n = mydur.num_weeks();
+    if n > 0 {
+        let _ = write!(result, "{}w", n);
+        mydur = mydur - Duration::weeks(n);
+    }
+    let n = mydur.num_days();
+    if n > 0 {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        let _ = write!(result, "{}d", n);
+        mydur = mydur - Duration::days(n);
+    }
+    let n = mydur.num_hours();
+    if n > 0 {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        let _ = write!(result, "{}h", n);
+        mydur = mydur - Duration::hours(n);
+    }
+    let n = mydur.num_minutes();
+    if n > 0 {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        let _ = write!(result, "{}min", n);
+        mydur = mydur - Duration::minutes(n);
+    }
+    let n = mydur.num_seconds();
+    if n > 0 {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        let _ = write!(result, "{}s", n);
+    }
+    if result.is_empty() {
+        result.push_str("0s");
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        let minutes = |text| parse_duration(text).map(|duration| duration.num_minutes());
+        assert_eq!(minutes("90m"), Ok(90));
+        assert_eq!(minutes("90min"), Ok(90));
+        assert_eq!(minutes("1h30"), Ok(90));
+        assert_eq!(minutes("1h30m"), Ok(90));
+        assert_eq!(minutes("1h 30min"), Ok(90));
+        assert_eq!(minutes("1 hour 30 minutes"), Ok(90));
+        assert_eq!(minutes("1.5h"), Ok(90));
+        assert_eq!(minutes("1:30"), Ok(90));
+        assert_eq!(minutes("0:05"), Ok(5));
+        assert_eq!(minutes("2H"), Ok(120));
+        assert_eq!(minutes("1d2h"), Ok(26 * 60));
+        assert_eq!(minutes(" 15m "), Ok(15));
+        assert_eq!(parse_duration("1m30"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("0.5s"), Ok(Duration::seconds(1)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::zero()));
+    }
+
+    #[test]
+    fn test_parse_duration_errors() {
+        let error = |text| parse_duration(text).unwrap_err();
+        assert_eq!(error(""), "Expected a duration, e.g. 90m, 1h30 or 1.5h");
+        assert_eq!(error("90"), "90 is missing a unit, e.g. 90m, 1h30 or 1.5h");
+        assert_eq!(error("5x"), "Unknown unit x in 5x, use d, h, m or s");
+        assert_eq!(error("h"), "Expected a number before \"h\" in h");
+        assert_eq!(error("1..5h"), "1..5 is not a number");
+        assert_eq!(error("-5m"), "Expected a number before \"-5m\" in -5m");
+        assert_eq!(
+            error("30m1h"),
+            "The units in 30m1h must get smaller from left to right, e.g. 1h30m"
+        );
+        assert_eq!(
+            error("1h2h"),
+            "The units in 1h2h must get smaller from left to right, e.g. 1h30m"
+        );
+        assert_eq!(error("1:5"), "1:5 is not a time like 1:30");
+        assert_eq!(error("1:75"), "1:75 is not a time like 1:30");
+        assert_eq!(error("1:+5"), "1:+5 is not a time like 1:30");
+        assert_eq!(error("100000000h"), "100000000h is too long");
+    }
+}
@@ -0,0 +1,80 @@
+//! Encryption of the database at rest with SQLCipher, available with the `sqlcipher` feature.
+//!
+//! Unencrypted databases keep working with this feature enabled, `ttt encrypt` converts them.
+
+use std::path::Path;
+
+use diesel::{connection::SimpleConnection, SqliteConnection};
+use inquire::Password;
+
+use crate::error::{Error, Result};
+
+/// Environment variable that holds the passphrase, so no prompt is needed (e.g. for scripts).
+pub const PASSPHRASE_VARIABLE: &str = "TTT_PASSPHRASE";
+
+/// Read the passphrase from [`PASSPHRASE_VARIABLE`] or ask the user for it.
+pub fn passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+    Password::new("Database passphrase:")
+        .prompt()
+        .map_err(|_| Error::WrongPassphrase)
+}
+
+/// Ask the user for a new passphrase, making sure it was typed correctly.
+pub fn new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+    loop {
+        let first = Password::new("New passphrase:")
+            .prompt()
+            .map_err(|_| Error::WrongPassphrase)?;
+        let second = Password::new("Repeat passphrase:")
+            .prompt()
+            .map_err(|_| Error::WrongPassphrase)?;
+        if first == second {
+            return Ok(first);
+        }
+        eprintln!("The passphrases do not match, please try again.");
+    }
+}
+
+/// Whether the database behind `connection` can be read without a key.
+pub fn is_plaintext(connection: &mut SqliteConnection) -> bool {
+    connection
+        .batch_execute("SELECT count(*) FROM sqlite_master;")
+        .is_ok()
+}
+
+/// Use `passphrase` to decrypt the database. This has to happen before any other statement is
+/// executed on the connection.
+pub fn unlock(connection: &mut SqliteConnection, passphrase: &str) -> Result<()> {
+    connection.batch_execute(&format!("PRAGMA key = {};", quote(passphrase)))?;
+    if !is_plaintext(connection) {
+        return Err(Error::WrongPassphrase);
+    }
+    Ok(())
+}
+
+/// Copy the whole database into a new file at `target`, which is encrypted with `passphrase`.
+/// An empty passphrase creates an unencrypted copy.
+pub fn export(connection: &mut SqliteConnection, target: &Path, passphrase: &str) -> Result<()> {
+    let target = target
+        .to_str()
+        .expect("Sorry non UTF-8 database paths are not supported!");
+    connection.batch_execute(&format!(
+        "ATTACH DATABASE {} AS export KEY {};
+         SELECT sqlcipher_export('export');
+         DETACH DATABASE export;",
+        quote(target),
+        quote(passphrase)
+    ))?;
+    Ok(())
+}
+
+/// Quote `text` as an SQL string literal.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
@@ -1,23 +1,99 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 use serde::{Serialize, Serializer};
+use typeshare::typeshare;
 
-use crate::model::Frame;
+use crate::{
+    locale::Locale,
+    model::{Frame, TimeSpan},
+};
 
+/// Serialized for the frontend as an [`ErrorMessage`].
 #[derive(Debug)]
 pub enum Error {
     /// Trying to start a new frame, while one is already active.
-    AlreadyTracking(Frame),
+    AlreadyTracking(Box<Frame>),
 
     /// No frame is currently running
     NoActiveFrame,
 
+    /// Could not find the frame with the given id
+    FrameNotFound(i32),
+
+    /// No frame has a short id starting with the given text
+    UnknownFrameId(String),
+
+    /// More than one frame has a short id starting with the given text
+    AmbiguousFrameId(String),
+
     /// Could not find the project with the given name
     ProjectNotFound(String),
 
     /// Could not find the tag with the given name
     TagNotFound(String),
 
+    /// Could not find the task with the given name, as "project/task"
+    TaskNotFound(String),
+
+    /// Could not find the client with the given name
+    ClientNotFound(String),
+
+    /// The given name is already the name or an alias of a project
+    ProjectExists(String),
+
+    /// Both projects to merge have their own budget or rates, so one of them would be lost
+    MergeConflict(String),
+
+    /// A sync journal of another machine could not be read
+    InvalidJournal(String),
+
+    /// The configuration file could not be parsed
+    InvalidConfig(String),
+
+    /// A file to import frames from could not be read
+    InvalidImport(String),
+
+    /// A report template could not be rendered
+    InvalidTemplate(String),
+
+    /// A `ttt://` link was malformed or asked for something links may not do
+    InvalidLink(String),
+
+    /// The pattern of a tagging rule is not a valid regular expression
+    InvalidRule(String),
+
+    /// The database schema could not be brought up to date
+    Migration(String),
+
+    /// The database file at the given path is damaged, see `database::replace_damaged`
+    DatabaseDamaged(String),
+
+    /// The change affects a locked period, see `ttt lock`
+    PeriodLocked(TimeSpan),
+
+    /// An imported frame was already tracked in the given time
+    DuplicateFrame(TimeSpan),
+
+    /// The database is encrypted and could not be opened with the given passphrase
+    #[cfg(feature = "sqlcipher")]
+    WrongPassphrase,
+
+    /// The state could not be published to the MQTT broker
+    #[cfg(feature = "mqtt")]
+    Mqtt(String),
+
+    /// The chat status could not be updated
+    #[cfg(feature = "slack")]
+    Slack(String),
+
+    /// Frames could not be published to the CalDAV server
+    #[cfg(feature = "caldav")]
+    CalDav(String),
+
+    /// ttt could not be updated
+    #[cfg(feature = "self-update")]
+    Update(String),
+
     DatabaseError(diesel::result::Error),
     DatabaseConnectionError(diesel::prelude::ConnectionError),
     IoError(std::io::Error),
@@ -43,57 +119,348 @@ impl From<diesel::prelude::ConnectionError> for Error {
     }
 }
 
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Error {
+    /// Stable identifier of the kind of error.
+    pub fn code(&self) -> ErrorCode {
         match self {
-            Error::DatabaseError(e) => write!(f, "Database Error: {}", e),
-            Error::IoError(e) => write!(f, "IO Error: {}", e),
-            Error::DatabaseConnectionError(e) => write!(f, "Database Connection Error: {}", e),
-            Error::AlreadyTracking(frame) => write!(f, "Already tracking a frame: {frame:?}"),
-            Error::ProjectNotFound(name) => write!(f, "Project does not exist: {name}"),
-            Error::TagNotFound(name) => write!(f, "Tag does not exist: {name}"),
-            Error::NoActiveFrame => write!(f, "No active frame"),
+            Error::AlreadyTracking(_) => ErrorCode::AlreadyTracking,
+            Error::NoActiveFrame => ErrorCode::NoActiveFrame,
+            Error::FrameNotFound(_) => ErrorCode::FrameNotFound,
+            Error::UnknownFrameId(_) => ErrorCode::UnknownFrameId,
+            Error::AmbiguousFrameId(_) => ErrorCode::AmbiguousFrameId,
+            Error::ProjectNotFound(_) => ErrorCode::ProjectNotFound,
+            Error::TagNotFound(_) => ErrorCode::TagNotFound,
+            Error::TaskNotFound(_) => ErrorCode::TaskNotFound,
+            Error::ClientNotFound(_) => ErrorCode::ClientNotFound,
+            Error::ProjectExists(_) => ErrorCode::ProjectExists,
+            Error::MergeConflict(_) => ErrorCode::MergeConflict,
+            Error::InvalidJournal(_) => ErrorCode::InvalidJournal,
+            Error::InvalidConfig(_) => ErrorCode::InvalidConfig,
+            Error::InvalidImport(_) => ErrorCode::InvalidImport,
+            Error::InvalidTemplate(_) => ErrorCode::InvalidTemplate,
+            Error::InvalidLink(_) => ErrorCode::InvalidLink,
+            Error::InvalidRule(_) => ErrorCode::InvalidRule,
+            Error::Migration(_) => ErrorCode::Migration,
+            Error::DatabaseDamaged(_) => ErrorCode::DatabaseDamaged,
+            Error::PeriodLocked(_) => ErrorCode::PeriodLocked,
+            Error::DuplicateFrame(_) => ErrorCode::DuplicateFrame,
+            #[cfg(feature = "sqlcipher")]
+            Error::WrongPassphrase => ErrorCode::WrongPassphrase,
+            #[cfg(feature = "mqtt")]
+            Error::Mqtt(_) => ErrorCode::Mqtt,
+            #[cfg(feature = "slack")]
+            Error::Slack(_) => ErrorCode::Slack,
+            #[cfg(feature = "caldav")]
+            Error::CalDav(_) => ErrorCode::CalDav,
+            #[cfg(feature = "self-update")]
+            Error::Update(_) => ErrorCode::Update,
+            Error::DatabaseError(_) => ErrorCode::Database,
+            Error::DatabaseConnectionError(_) => ErrorCode::DatabaseConnection,
+            Error::IoError(_) => ErrorCode::Io,
+        }
+    }
+
+    /// Values for the placeholders of the message template of [`Error::code`].
+    pub fn args(&self) -> BTreeMap<String, String> {
+        let span = |span: &TimeSpan| {
+            [
+                ("start", span.start().to_local().format(TIME_FORMAT)),
+                ("end", span.end().to_local().format(TIME_FORMAT)),
+            ]
+            .map(|(name, time)| (name.to_owned(), time.to_string()))
+            .into()
+        };
+        let single = |name: &str, value: String| BTreeMap::from([(name.to_owned(), value)]);
+        match self {
+            Error::AlreadyTracking(frame) => BTreeMap::from([
+                ("frame".to_owned(), frame.short_id().to_owned()),
+                (
+                    "start".to_owned(),
+                    frame.start.to_local().format(TIME_FORMAT).to_string(),
+                ),
+            ]),
+            Error::NoActiveFrame => BTreeMap::new(),
+            Error::FrameNotFound(id) => single("id", id.to_string()),
+            Error::UnknownFrameId(id) | Error::AmbiguousFrameId(id) => single("id", id.clone()),
+            Error::ProjectNotFound(name)
+            | Error::TagNotFound(name)
+            | Error::TaskNotFound(name)
+            | Error::ClientNotFound(name)
+            | Error::ProjectExists(name) => single("name", name.clone()),
+            Error::InvalidJournal(message)
+            | Error::InvalidConfig(message)
+            | Error::InvalidImport(message)
+            | Error::InvalidTemplate(message)
+            | Error::InvalidLink(message)
+            | Error::InvalidRule(message)
+            | Error::Migration(message) => single("message", message.clone()),
+            Error::MergeConflict(setting) => single("setting", setting.clone()),
+            Error::DatabaseDamaged(path) => single("path", path.clone()),
+            Error::PeriodLocked(period) | Error::DuplicateFrame(period) => span(period),
+            #[cfg(feature = "sqlcipher")]
+            Error::WrongPassphrase => BTreeMap::new(),
+            #[cfg(feature = "mqtt")]
+            Error::Mqtt(message) => single("message", message.clone()),
+            #[cfg(feature = "slack")]
+            Error::Slack(message) => single("message", message.clone()),
+            #[cfg(feature = "caldav")]
+            Error::CalDav(message) => single("message", message.clone()),
+            #[cfg(feature = "self-update")]
+            Error::Update(message) => single("message", message.clone()),
+            Error::DatabaseError(e) => single("message", e.to_string()),
+            Error::DatabaseConnectionError(e) => single("message", e.to_string()),
+            Error::IoError(e) => single("message", e.to_string()),
         }
     }
 }
 
-impl std::error::Error for Error {}
+/// Format of the times in error messages.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
 
-impl Serialize for Error {
-    fn serialize<S>(
-        &self,
-        serializer: S,
-    ) -> std::result::Result<<S as Serializer>::Ok, <S as Serializer>::Error>
-    where
-        S: Serializer,
-    {
+/// Stable identifier of each kind of [`Error`], so the frontend can pick a dialog or a
+/// translation of the message, see [`ErrorCode::template`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[typeshare]
+pub enum ErrorCode {
+    AlreadyTracking,
+    NoActiveFrame,
+    FrameNotFound,
+    UnknownFrameId,
+    AmbiguousFrameId,
+    ProjectNotFound,
+    TagNotFound,
+    TaskNotFound,
+    ClientNotFound,
+    ProjectExists,
+    MergeConflict,
+    InvalidJournal,
+    InvalidConfig,
+    InvalidImport,
+    InvalidTemplate,
+    InvalidLink,
+    InvalidRule,
+    Migration,
+    DatabaseDamaged,
+    PeriodLocked,
+    DuplicateFrame,
+    WrongPassphrase,
+    Mqtt,
+    Slack,
+    CalDav,
+    Update,
+    Database,
+    DatabaseConnection,
+    Io,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 29] = [
+        ErrorCode::AlreadyTracking,
+        ErrorCode::NoActiveFrame,
+        ErrorCode::FrameNotFound,
+        ErrorCode::UnknownFrameId,
+        ErrorCode::AmbiguousFrameId,
+        ErrorCode::ProjectNotFound,
+        ErrorCode::TagNotFound,
+        ErrorCode::TaskNotFound,
+        ErrorCode::ClientNotFound,
+        ErrorCode::ProjectExists,
+        ErrorCode::MergeConflict,
+        ErrorCode::InvalidJournal,
+        ErrorCode::InvalidConfig,
+        ErrorCode::InvalidImport,
+        ErrorCode::InvalidTemplate,
+        ErrorCode::InvalidLink,
+        ErrorCode::InvalidRule,
+        ErrorCode::Migration,
+        ErrorCode::DatabaseDamaged,
+        ErrorCode::PeriodLocked,
+        ErrorCode::DuplicateFrame,
+        ErrorCode::WrongPassphrase,
+        ErrorCode::Mqtt,
+        ErrorCode::Slack,
+        ErrorCode::CalDav,
+        ErrorCode::Update,
+        ErrorCode::Database,
+        ErrorCode::DatabaseConnection,
+        ErrorCode::Io,
+    ];
+
+    /// The message for errors with this code in `locale`, English if there is no translation.
+    /// Placeholders like `{name}` are replaced by the values of [`Error::args`].
+    pub fn template(self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::De => self.german(),
+            Locale::En | Locale::Fr | Locale::Es => self.english(),
+        }
+    }
+
+    fn english(self) -> &'static str {
         match self {
-            Error::AlreadyTracking(frame) => {
-                serializer.serialize_newtype_variant("Error", 0, "AlreadyTracking", frame)
+            ErrorCode::AlreadyTracking => "Already tracking frame {frame} since {start}",
+            ErrorCode::NoActiveFrame => "No active frame",
+            ErrorCode::FrameNotFound => "Frame does not exist: {id}",
+            ErrorCode::UnknownFrameId => "No frame has the id {id}",
+            ErrorCode::AmbiguousFrameId => "More than one frame has an id starting with {id}",
+            ErrorCode::ProjectNotFound => "Project does not exist: {name}",
+            ErrorCode::TagNotFound => "Tag does not exist: {name}",
+            ErrorCode::TaskNotFound => "Task does not exist: {name}",
+            ErrorCode::ClientNotFound => "Client does not exist: {name}",
+            ErrorCode::ProjectExists => "{name} is already the name or alias of a project",
+            ErrorCode::MergeConflict => {
+                "Both projects have their own {setting}, remove one of them before merging"
             }
-            Error::NoActiveFrame => serializer.serialize_unit_variant("Error", 1, "NoActiveFrame"),
-            Error::ProjectNotFound(projectname) => {
-                serializer.serialize_newtype_variant("Error", 2, "ProjectNotFound", projectname)
+            ErrorCode::InvalidJournal => "Invalid sync journal: {message}",
+            ErrorCode::InvalidConfig => "Invalid configuration: {message}",
+            ErrorCode::InvalidImport => "Could not import: {message}",
+            ErrorCode::InvalidTemplate => "Invalid template: {message}",
+            ErrorCode::InvalidLink => "Invalid link: {message}",
+            ErrorCode::InvalidRule => "Invalid rule: {message}",
+            ErrorCode::Migration => "Failed to migrate the database: {message}",
+            ErrorCode::DatabaseDamaged => "The database {path} is damaged",
+            ErrorCode::PeriodLocked => {
+                "The period from {start} to {end} is locked, use --force-unlock to change it anyway"
             }
-            Error::TagNotFound(tagname) => {
-                serializer.serialize_newtype_variant("Error", 3, "TagNotFound", tagname)
-            }
-            Error::DatabaseError(dberror) => serializer.serialize_newtype_variant(
-                "Error",
-                4,
-                "DatabaseError",
-                &dberror.to_string(),
-            ),
-            Error::DatabaseConnectionError(connectionerror) => serializer
-                .serialize_newtype_variant(
-                    "Error",
-                    5,
-                    "DatabaseConnectionError",
-                    &connectionerror.to_string(),
-                ),
-            Error::IoError(ioerror) => {
-                serializer.serialize_newtype_variant("Error", 6, "IoError", &ioerror.to_string())
+            ErrorCode::DuplicateFrame => "A frame from {start} to {end} is already tracked",
+            ErrorCode::WrongPassphrase => "Wrong passphrase for the encrypted database",
+            ErrorCode::Mqtt => "MQTT error: {message}",
+            ErrorCode::Slack => "Failed to update the chat status: {message}",
+            ErrorCode::CalDav => "CalDAV error: {message}",
+            ErrorCode::Update => "Failed to update ttt: {message}",
+            ErrorCode::Database => "Database Error: {message}",
+            ErrorCode::DatabaseConnection => "Database Connection Error: {message}",
+            ErrorCode::Io => "IO Error: {message}",
+        }
+    }
+
+    fn german(self) -> &'static str {
+        match self {
+            ErrorCode::AlreadyTracking => "Frame {frame} läuft bereits seit {start}",
+            ErrorCode::NoActiveFrame => "Es läuft kein Frame",
+            ErrorCode::FrameNotFound => "Frame existiert nicht: {id}",
+            ErrorCode::UnknownFrameId => "Kein Frame hat die Id {id}",
+            ErrorCode::AmbiguousFrameId => "Mehrere Frames haben eine Id, die mit {id} beginnt",
+            ErrorCode::ProjectNotFound => "Projekt existiert nicht: {name}",
+            ErrorCode::TagNotFound => "Tag existiert nicht: {name}",
+            ErrorCode::TaskNotFound => "Aufgabe existiert nicht: {name}",
+            ErrorCode::ClientNotFound => "Kunde existiert nicht: {name}",
+            ErrorCode::ProjectExists => "{name} ist bereits Name oder Alias eines Projekts",
+            ErrorCode::MergeConflict => {
+                "Beide Projekte haben eigene Einstellungen für {setting}, entferne eine davon vor dem Zusammenführen"
             }
+            ErrorCode::InvalidJournal => "Ungültiges Sync-Journal: {message}",
+            ErrorCode::InvalidConfig => "Ungültige Konfiguration: {message}",
+            ErrorCode::InvalidImport => "Import fehlgeschlagen: {message}",
+            ErrorCode::InvalidTemplate => "Ungültige Vorlage: {message}",
+            ErrorCode::InvalidLink => "Ungültiger Link: {message}",
+            ErrorCode::InvalidRule => "Ungültige Regel: {message}",
+            ErrorCode::Migration => "Die Datenbank konnte nicht migriert werden: {message}",
+            ErrorCode::DatabaseDamaged => "Die Datenbank {path} ist beschädigt",
+            ErrorCode::PeriodLocked => "Der Zeitraum von {start} bis {end} ist gesperrt",
+            ErrorCode::DuplicateFrame => "Ein Frame von {start} bis {end} existiert bereits",
+            ErrorCode::WrongPassphrase => "Falsches Passwort für die verschlüsselte Datenbank",
+            ErrorCode::Mqtt => "MQTT-Fehler: {message}",
+            ErrorCode::Slack => "Der Chat-Status konnte nicht geändert werden: {message}",
+            ErrorCode::CalDav => "CalDAV-Fehler: {message}",
+            ErrorCode::Update => "ttt konnte nicht aktualisiert werden: {message}",
+            ErrorCode::Database => "Datenbankfehler: {message}",
+            ErrorCode::DatabaseConnection => "Verbindung zur Datenbank fehlgeschlagen: {message}",
+            ErrorCode::Io => "Ein-/Ausgabefehler: {message}",
+        }
+    }
+}
+
+/// Replace the `{placeholders}` of `template` by `args`.
+pub fn fill_template(template: &str, args: &BTreeMap<String, String>) -> String {
+    args.iter()
+        .fold(template.to_owned(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// An [`Error`] as the frontend receives it.
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct ErrorMessage {
+    pub code: ErrorCode,
+
+    /// The English message, for errors without a translation
+    pub message: String,
+
+    /// Values for the placeholders of the message template, see [`ErrorCode::template`]
+    pub args: BTreeMap<String, String>,
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ErrorMessage {
+            code: self.code(),
+            message: self.to_string(),
+            args: self.args(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let template = self.code().template(Locale::En);
+        write!(f, "{}", fill_template(template, &self.args()))
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serialize_error() {
+        assert_eq!(
+            serde_json::to_value(Error::NoActiveFrame).unwrap(),
+            serde_json::json!({"code": "no_active_frame", "message": "No active frame", "args": {}})
+        );
+        assert_eq!(
+            serde_json::to_value(Error::ProjectNotFound("website".to_owned())).unwrap(),
+            serde_json::json!({
+                "code": "project_not_found",
+                "message": "Project does not exist: website",
+                "args": {"name": "website"}
+            })
+        );
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(
+            serde_json::to_value(Error::IoError(io)).unwrap(),
+            serde_json::json!({"code": "io", "message": "IO Error: gone", "args": {"message": "gone"}})
+        );
+    }
+
+    #[test]
+    fn test_translations_use_the_same_placeholders() {
+        let placeholders = |template: &str| {
+            let mut names: Vec<_> = template
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}'))
+                .map(|(name, _)| name.to_owned())
+                .collect();
+            names.sort();
+            names
+        };
+        for code in ErrorCode::ALL {
+            let english = placeholders(code.template(Locale::En));
+            let german = placeholders(code.template(Locale::De));
+            assert!(
+                german.iter().all(|name| english.contains(name)),
+                "{code:?}: {german:?} are not all in {english:?}"
+            );
         }
+        let args = BTreeMap::from([("name".to_owned(), "website".to_owned())]);
+        assert_eq!(
+            fill_template(ErrorCode::ProjectNotFound.template(Locale::De), &args),
+            "Projekt existiert nicht: website"
+        );
     }
 }
@@ -0,0 +1,187 @@
+//! Frames as JSON for other tools, see `ttt export json`.
+//!
+//! With `--anonymize`, names of projects and tags, tickets and notes are replaced by pseudonyms
+//! like "project-3", so real workload data can be shared for analysis or bug reports without
+//! leaking the names of clients. The pseudonyms are kept in `anonymization.json` in the data
+//! directory, so every export uses the same ones and results can be traced back.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::Duration;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    model::{serialize_duration, Frame, FrameKind, Project, Tag, Timestamp},
+};
+
+/// The plain names with their pseudonyms.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pseudonyms {
+    projects: BTreeMap<String, String>,
+    tags: BTreeMap<String, String>,
+    refs: BTreeMap<String, String>,
+    notes: BTreeMap<String, String>,
+}
+
+impl Pseudonyms {
+    pub fn path() -> PathBuf {
+        let dirs = ProjectDirs::from("", "", "ttt").expect("Failed to get base directory paths!");
+        dirs.data_dir().join("anonymization.json")
+    }
+
+    /// Read the pseudonyms of earlier exports, none if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(serde_json::from_str(&text).map_err(std::io::Error::from)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::from)?;
+        Ok(fs::write(path, json + "\n")?)
+    }
+
+    /// Replace the names and the note of `frame`.
+    fn anonymize(&mut self, frame: &mut ExportedFrame) {
+        frame.project = pseudonym(&mut self.projects, "project", &frame.project);
+        for tag in &mut frame.tags {
+            *tag = pseudonym(&mut self.tags, "tag", tag);
+        }
+        if let Some(external_ref) = &mut frame.external_ref {
+            *external_ref = pseudonym(&mut self.refs, "ref", external_ref);
+        }
+        if let Some(note) = &mut frame.note {
+            *note = pseudonym(&mut self.notes, "note", note);
+        }
+    }
+}
+
+/// The pseudonym of `name` in `names`, new names are numbered in the order they are seen.
+fn pseudonym(names: &mut BTreeMap<String, String>, prefix: &str, name: &str) -> String {
+    let next = names.len() + 1;
+    names
+        .entry(name.to_owned())
+        .or_insert_with(|| format!("{prefix}-{next}"))
+        .clone()
+}
+
+/// A frame in the output of `ttt export json`.
+#[derive(Serialize)]
+struct ExportedFrame {
+    project: String,
+    tags: Vec<String>,
+    start: Timestamp,
+    end: Option<Timestamp>,
+    #[serde(serialize_with = "serialize_duration")]
+    duration: Duration,
+    kind: FrameKind,
+    external_ref: Option<String>,
+    note: Option<String>,
+}
+
+/// `frames` as JSON array, with the `tags` of each project by project id. If `pseudonyms` are
+/// given, they replace the names and notes, and new ones are added to them.
+pub fn to_json(
+    frames: &[(Project, Frame)],
+    tags: &HashMap<i32, Vec<Tag>>,
+    pseudonyms: Option<&mut Pseudonyms>,
+) -> String {
+    let mut exported: Vec<_> = frames
+        .iter()
+        .map(|(project, frame)| ExportedFrame {
+            project: project.name.clone(),
+            tags: tags
+                .get(&project.id())
+                .into_iter()
+                .flatten()
+                .map(|tag| tag.name.clone())
+                .collect(),
+            start: frame.start,
+            end: frame.end,
+            duration: frame.duration(),
+            kind: frame.kind,
+            external_ref: frame.external_ref.clone(),
+            note: frame.note.clone(),
+        })
+        .collect();
+    if let Some(pseudonyms) = pseudonyms {
+        for frame in &mut exported {
+            pseudonyms.anonymize(frame);
+        }
+    }
+    serde_json::to_string_pretty(&exported).expect("Frames are serializable") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        database::{ArchivedState, Database},
+        model::{NewFrame, TimeSpan},
+    };
+
+    #[test]
+    fn test_anonymized_export() {
+        let mut db = Database::open_in_memory().unwrap();
+        let acme = db.create_project("acme").unwrap();
+        let secret = db.create_tag("secret").unwrap();
+        db.tag_projects(vec![secret], vec![acme.clone()]).unwrap();
+        for (uuid, hour) in [("a", 8), ("b", 10)] {
+            let start = Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+            let end = Timestamp::from_ymdhms(2024, 3, 4, hour + 1, 0, 0);
+            db.merge_frame(
+                &NewFrame {
+                    project: acme.id(),
+                    start: &start,
+                    end: Some(&end),
+                    deleted_at: None,
+                    uuid,
+                    updated_at: &end,
+                    external_ref: Some("ACME-12"),
+                    note: None,
+                    source: None,
+                    draft: false,
+                    kind: FrameKind::Work,
+                },
+                None,
+            )
+            .unwrap();
+        }
+        let day = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 3, 5, 0, 0, 0),
+        )
+        .unwrap();
+        let frames = db.get_frames_in_span(day, ArchivedState::Both).unwrap();
+        let tags = HashMap::from([(acme.id(), db.lookup_tags_for_project(acme.id()).unwrap())]);
+
+        let plain = to_json(&frames, &tags, None);
+        assert!(plain.contains("\"project\": \"acme\""));
+
+        let mut pseudonyms = Pseudonyms::default();
+        pseudonyms
+            .projects
+            .insert("other".to_owned(), "project-1".to_owned());
+        let anonymized = to_json(&frames, &tags, Some(&mut pseudonyms));
+        assert!(!anonymized.contains("acme") && !anonymized.contains("ACME"));
+        assert!(!anonymized.contains("secret"));
+        // Both frames get the same pseudonyms, numbered after the known ones.
+        assert_eq!(anonymized.matches("\"project\": \"project-2\"").count(), 2);
+        assert_eq!(anonymized.matches("\"tag-1\"").count(), 2);
+        assert_eq!(anonymized.matches("\"external_ref\": \"ref-1\"").count(), 2);
+        assert_eq!(pseudonyms.projects["acme"], "project-2");
+    }
+}
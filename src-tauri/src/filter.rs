@@ -0,0 +1,521 @@
+//! Expressions selecting frames, e.g. for `ttt analyze --filter`:
+//!
+//! ```text
+//! project =~ "client.*" and tag = billable and duration > 15min
+//! not (task = review or ref != "PROJ-1")
+//! ```
+//!
+//! Fields are `project`, `tag`, `task`, `client`, `ref`, `note` and `duration`. Text fields are
+//! compared with `=`, `!=`, `=~` and `!~`, the latter two match a regular expression anywhere in
+//! the text. `tag = x` selects frames whose project has the tag. Durations like `1h30min` are
+//! compared with `<`, `<=`, `>`, `>=`, `=` and `!=`. `and` binds stronger than `or`.
+//!
+//! Filters are translated into the `WHERE` clause of the query loading the frames, see
+//! [`crate::database::Database::filtered_frames`].
+
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Duration;
+use diesel::{
+    dsl::{not, sql},
+    expression::BoxableExpression,
+    helper_types::InnerJoinQuerySource,
+    prelude::*,
+    sql_types::{Bool, Nullable, Text},
+    sqlite::{Sqlite, SqliteConnection},
+};
+use regex::Regex;
+
+use crate::{
+    duration::parse_duration,
+    schema::{clients, frames, projects, tags, tags_per_project, tasks},
+};
+
+diesel::sql_function! {
+    /// Whether `text` contains a match of the regular expression `pattern`, also available as
+    /// `text REGEXP pattern` in `ttt sql`. NULL never matches.
+    fn regexp(pattern: Text, text: Nullable<Text>) -> Bool;
+}
+
+/// Make [`regexp`] available on `connection`.
+pub(crate) fn register_regexp(connection: &mut SqliteConnection) -> QueryResult<()> {
+    // Compiled once per query instead of once per row, as the pattern rarely changes.
+    let last = Arc::new(Mutex::new(None::<(String, Option<Regex>)>));
+    regexp::register_impl(connection, move |pattern: String, text: Option<String>| {
+        let Some(text) = text else {
+            return false;
+        };
+        let mut last = last.lock().unwrap();
+        if last
+            .as_ref()
+            .is_none_or(|(previous, _)| *previous != pattern)
+        {
+            *last = Some((pattern.clone(), Regex::new(&pattern).ok()));
+        }
+        last.as_ref()
+            .and_then(|(_, regex)| regex.as_ref())
+            .is_some_and(|regex| regex.is_match(&text))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Project,
+    Tag,
+    Task,
+    Client,
+    Ref,
+    Note,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Matches,
+    NotMatches,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Duration(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Compare {
+        field: Field,
+        operator: Operator,
+        value: Value,
+    },
+}
+
+/// A filter that could not be parsed, displayed with the offending part of the input marked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    pub message: String,
+
+    /// Character offset of the error in the input
+    position: usize,
+    input: String,
+}
+
+impl std::error::Error for FilterError {}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\n    {}\n    {}^",
+            self.message,
+            self.input,
+            " ".repeat(self.position)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+/// Split `input` into tokens, each with its character offset.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, (String, usize)> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        let next = chars.get(index + 1).copied();
+        let (token, length) = match (chars[index], next) {
+            (c, _) if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('=', Some('~')) => (Token::Operator(Operator::Matches), 2),
+            ('!', Some('~')) => (Token::Operator(Operator::NotMatches), 2),
+            ('!', Some('=')) => (Token::Operator(Operator::NotEqual), 2),
+            ('<', Some('=')) => (Token::Operator(Operator::LessOrEqual), 2),
+            ('>', Some('=')) => (Token::Operator(Operator::GreaterOrEqual), 2),
+            ('=', Some('=')) => (Token::Operator(Operator::Equal), 2),
+            ('=', _) => (Token::Operator(Operator::Equal), 1),
+            ('<', _) => (Token::Operator(Operator::Less), 1),
+            ('>', _) => (Token::Operator(Operator::Greater), 1),
+            ('"', _) => {
+                let mut text = String::new();
+                let mut end = index + 1;
+                loop {
+                    match chars.get(end) {
+                        None => return Err(("Missing closing quote".to_owned(), start)),
+                        Some('"') => break,
+                        Some('\\') if chars.get(end + 1).is_some() => {
+                            text.push(chars[end + 1]);
+                            end += 2;
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            end += 1;
+                        }
+                    }
+                }
+                (Token::Quoted(text), end + 1 - index)
+            }
+            (c, _) if is_word_char(c) => {
+                let length = chars[index..]
+                    .iter()
+                    .take_while(|c| is_word_char(**c))
+                    .count();
+                let word = chars[index..index + length].iter().collect();
+                (Token::Word(word), length)
+            }
+            (c, _) => return Err((format!("Unexpected character {c}"), start)),
+        };
+        tokens.push((token, start));
+        index += length;
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | '+')
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+
+    /// Character count of the input, where errors about missing tokens point to.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter, (String, usize)> {
+        let mut filter = self.and()?;
+        while self.keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, (String, usize)> {
+        let mut filter = self.not()?;
+        while self.keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, (String, usize)> {
+        if self.keyword("not") {
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, (String, usize)> {
+        let position = self.position();
+        let field = match self.next() {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                let position = self.position();
+                return match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err(("Expected )".to_owned(), position)),
+                };
+            }
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "project" => Field::Project,
+                "tag" => Field::Tag,
+                "task" => Field::Task,
+                "client" => Field::Client,
+                "ref" => Field::Ref,
+                "note" => Field::Note,
+                "duration" => Field::Duration,
+                _ => {
+                    return Err((
+                        format!("Unknown field {word}, expected one of project, tag, task, client, ref, note or duration"),
+                        position,
+                    ))
+                }
+            },
+            _ => return Err(("Expected a field like project".to_owned(), position)),
+        };
+
+        let position = self.position();
+        let Some(Token::Operator(operator)) = self.next() else {
+            return Err(("Expected a comparison like = or =~".to_owned(), position));
+        };
+        let text_operator = matches!(
+            operator,
+            Operator::Equal | Operator::NotEqual | Operator::Matches | Operator::NotMatches
+        );
+        let duration_operator = !matches!(operator, Operator::Matches | Operator::NotMatches);
+        if (field == Field::Duration && !duration_operator)
+            || (field != Field::Duration && !text_operator)
+        {
+            return Err((format!("{field:?} can't be compared like this"), position));
+        }
+
+        let position = self.position();
+        let text = match self.next() {
+            Some(Token::Word(text) | Token::Quoted(text)) => text,
+            _ => return Err(("Expected a value".to_owned(), position)),
+        };
+        let value = if field == Field::Duration {
+            let duration = parse_duration(&text).map_err(|err| (err, position))?;
+            Value::Duration(duration)
+        } else {
+            if matches!(operator, Operator::Matches | Operator::NotMatches) {
+                Regex::new(&text).map_err(|err| (err.to_string(), position))?;
+            }
+            Value::Text(text)
+        };
+        Ok(Filter::Compare {
+            field,
+            operator,
+            value,
+        })
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = |(message, position)| FilterError {
+            message,
+            position,
+            input: input.to_owned(),
+        };
+        let mut parser = Parser {
+            tokens: tokenize(input).map_err(error)?,
+            index: 0,
+            end: input.chars().count(),
+        };
+        let filter = parser.or().map_err(error)?;
+        if parser.peek().is_some() {
+            return Err(error((
+                "Expected and, or or the end".to_owned(),
+                parser.position(),
+            )));
+        }
+        Ok(filter)
+    }
+}
+
+/// Source of the query [`Filter::to_sql`] is used in.
+pub(crate) type FrameSource = InnerJoinQuerySource<frames::table, projects::table>;
+
+type Condition = Box<dyn BoxableExpression<FrameSource, Sqlite, SqlType = Bool>>;
+
+impl Filter {
+    /// The condition of a `WHERE` clause selecting the matching frames. Every part of it is
+    /// either true or false, never NULL, so `not` selects exactly the other frames.
+    pub(crate) fn to_sql(&self) -> Condition {
+        match self {
+            Filter::And(left, right) => Box::new(left.to_sql().and(right.to_sql())),
+            Filter::Or(left, right) => Box::new(left.to_sql().or(right.to_sql())),
+            Filter::Not(filter) => Box::new(not(filter.to_sql())),
+            Filter::Compare {
+                field: Field::Duration,
+                operator,
+                value: Value::Duration(duration),
+            } => {
+                let operator = match operator {
+                    Operator::Equal => "=",
+                    Operator::NotEqual => "!=",
+                    Operator::Less => "<",
+                    Operator::LessOrEqual => "<=",
+                    Operator::Greater => ">",
+                    Operator::GreaterOrEqual => ">=",
+                    Operator::Matches | Operator::NotMatches => {
+                        unreachable!("Checked by the parser")
+                    }
+                };
+                // julianday reads the stored UTC times, like 'now' is. Running frames
+                // count until now.
+                Box::new(sql::<Bool>(&format!(
+                    "round((julianday(coalesce(frames.\"end\", 'now')) - julianday(frames.start)) * 86400) {operator} {}",
+                    duration.num_seconds()
+                )))
+            }
+            Filter::Compare {
+                field,
+                operator,
+                value: Value::Text(text),
+            } => {
+                let positive = match operator {
+                    Operator::Equal | Operator::Matches => true,
+                    Operator::NotEqual | Operator::NotMatches => false,
+                    _ => unreachable!("Checked by the parser"),
+                };
+                let regex = matches!(operator, Operator::Matches | Operator::NotMatches);
+                let matching = Self::text_condition(*field, text.clone(), regex);
+                if positive {
+                    matching
+                } else {
+                    Box::new(not(matching))
+                }
+            }
+            Filter::Compare { .. } => unreachable!("Checked by the parser"),
+        }
+    }
+
+    /// Whether `field` equals `text`, or matches it as regular expression with `regex`.
+    fn text_condition(field: Field, text: String, regex: bool) -> Condition {
+        match field {
+            Field::Project if regex => Box::new(regexp(text, projects::name.nullable())),
+            Field::Project => Box::new(projects::name.eq(text)),
+            Field::Ref if regex => Box::new(regexp(text, frames::external_ref)),
+            Field::Ref => Box::new(frames::external_ref.is(text)),
+            Field::Note if regex => Box::new(regexp(text, frames::note)),
+            Field::Note => Box::new(frames::note.is(text)),
+            Field::Tag => {
+                let tagged = tags_per_project::table
+                    .inner_join(tags::table)
+                    .select(tags_per_project::project_id);
+                if regex {
+                    Box::new(
+                        projects::id.eq_any(tagged.filter(regexp(text, tags::name.nullable()))),
+                    )
+                } else {
+                    Box::new(projects::id.eq_any(tagged.filter(tags::name.eq(text))))
+                }
+            }
+            Field::Task => {
+                let named = tasks::table.select(tasks::id.nullable());
+                let tasks: Box<dyn BoxableExpression<_, Sqlite, SqlType = _>> = if regex {
+                    Box::new(
+                        frames::task_id.eq_any(named.filter(regexp(text, tasks::name.nullable()))),
+                    )
+                } else {
+                    Box::new(frames::task_id.eq_any(named.filter(tasks::name.eq(text))))
+                };
+                // Frames without a task are never in the list, instead of maybe being in it.
+                Box::new(frames::task_id.is_not_null().and(tasks).assume_not_null())
+            }
+            Field::Client => {
+                let named = clients::table.select(clients::id.nullable());
+                let clients: Box<dyn BoxableExpression<_, Sqlite, SqlType = _>> = if regex {
+                    Box::new(
+                        projects::client_id
+                            .eq_any(named.filter(regexp(text, clients::name.nullable()))),
+                    )
+                } else {
+                    Box::new(projects::client_id.eq_any(named.filter(clients::name.eq(text))))
+                };
+                Box::new(
+                    projects::client_id
+                        .is_not_null()
+                        .and(clients)
+                        .assume_not_null(),
+                )
+            }
+            Field::Duration => unreachable!("Durations are no text"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compare(field: Field, operator: Operator, text: &str) -> Filter {
+        Filter::Compare {
+            field,
+            operator,
+            value: Value::Text(text.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let filter: Filter = "project =~ \"client.*\" and tag = billable or not duration > 1h15min"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            filter,
+            Filter::Or(
+                Box::new(Filter::And(
+                    Box::new(compare(Field::Project, Operator::Matches, "client.*")),
+                    Box::new(compare(Field::Tag, Operator::Equal, "billable")),
+                )),
+                Box::new(Filter::Not(Box::new(Filter::Compare {
+                    field: Field::Duration,
+                    operator: Operator::Greater,
+                    value: Value::Duration(Duration::minutes(75)),
+                }))),
+            )
+        );
+        assert_eq!(
+            "(ref != \"A \\\"B\\\"\")".parse::<Filter>().unwrap(),
+            compare(Field::Ref, Operator::NotEqual, "A \"B\"")
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_problem() {
+        let error = "project = x and colour = red"
+            .parse::<Filter>()
+            .unwrap_err();
+        assert_eq!(error.position, 16);
+        let error = "duration =~ 1h".parse::<Filter>().unwrap_err();
+        assert_eq!(error.position, 9);
+        assert_eq!(
+            "duration > 5 minutes"
+                .parse::<Filter>()
+                .unwrap_err()
+                .position,
+            11
+        );
+        assert_eq!(
+            "project =~ \"(\"".parse::<Filter>().unwrap_err().position,
+            11
+        );
+        assert_eq!("(tag = a".parse::<Filter>().unwrap_err().position, 8);
+    }
+}
@@ -0,0 +1,180 @@
+//! Detection of untracked time within working hours, see `ttt analyze --show-gaps`.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+
+use crate::model::{Frame, Project, TimeSpan, Timestamp};
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkingHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Find the untracked periods within the working hours of the weekdays in `span`, except for
+/// the `days_off`, see `ttt leave`.
+///
+/// Running frames count as tracked until `now`, nothing after `now` is reported. Gaps shorter
+/// than `min_length` are ignored.
+pub fn find_gaps(
+    span: TimeSpan,
+    frames: &[(Project, Frame)],
+    hours: WorkingHours,
+    days_off: &[NaiveDate],
+    min_length: Duration,
+    now: Timestamp,
+) -> Vec<TimeSpan> {
+    let mut busy: Vec<_> = frames
+        .iter()
+        .map(|(_, frame)| (frame.start, frame.end.unwrap_or(now)))
+        .collect();
+    busy.sort();
+
+    let mut gaps = Vec::new();
+    let mut push = |start: Timestamp, end: Timestamp| {
+        if end.0 - start.0 >= min_length {
+            gaps.extend(TimeSpan::new(start, end).ok());
+        }
+    };
+
+    let last_day = span.end().to_naive().date();
+    let mut day = span.start().to_naive().date();
+    while day <= last_day {
+        if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) && !days_off.contains(&day) {
+            let from = span
+                .start()
+                .max(Timestamp::from_naive(day.and_time(hours.start)));
+            let to = span
+                .end()
+                .min(Timestamp::from_naive(day.and_time(hours.end)))
+                .min(now);
+
+            let mut cursor = from;
+            for &(start, end) in &busy {
+                if end <= cursor {
+                    continue;
+                }
+                if start >= to {
+                    break;
+                }
+                if start > cursor {
+                    push(cursor, start);
+                }
+                cursor = cursor.max(end);
+            }
+            if cursor < to {
+                push(cursor, to);
+            }
+        }
+        let Some(next) = day.succ_opt() else { break };
+        day = next;
+    }
+    gaps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{ArchivedState, Database};
+
+    fn at(day: u32, hour: u32, minute: u32) -> Timestamp {
+        Timestamp::from_ymdhms(2024, 3, day, hour, minute, 0)
+    }
+
+    fn hours() -> WorkingHours {
+        WorkingHours {
+            start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        }
+    }
+
+    fn frames(spans: &[(Timestamp, Option<Timestamp>)]) -> Vec<(Project, Frame)> {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|&(start, end)| (&project, start, end))
+            .collect();
+        db.add_test_frames(&spans).unwrap();
+        let all = TimeSpan::new(at(1, 0, 0), at(31, 0, 0)).unwrap();
+        db.get_frames_in_span(all, ArchivedState::Both).unwrap()
+    }
+
+    #[test]
+    fn test_gaps_between_frames() {
+        let frames = frames(&[
+            (at(4, 8, 0), Some(at(4, 12, 0))),
+            (at(4, 11, 0), Some(at(4, 12, 30))),
+            (at(4, 13, 30), Some(at(4, 18, 0))),
+        ]);
+        let monday = TimeSpan::new(at(4, 0, 0), at(5, 0, 0)).unwrap();
+
+        let gaps = find_gaps(
+            monday,
+            &frames,
+            hours(),
+            &[],
+            Duration::minutes(5),
+            at(20, 0, 0),
+        );
+        assert_eq!(
+            gaps,
+            vec![TimeSpan::new(at(4, 12, 30), at(4, 13, 30)).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_gaps_skip_weekends_and_short_breaks() {
+        let frames = frames(&[(at(8, 8, 3), Some(at(8, 17, 0)))]);
+        // Friday to Sunday.
+        let span = TimeSpan::new(at(8, 0, 0), at(11, 0, 0)).unwrap();
+
+        let gaps = find_gaps(
+            span,
+            &frames,
+            hours(),
+            &[],
+            Duration::minutes(5),
+            at(20, 0, 0),
+        );
+        assert_eq!(
+            gaps,
+            vec![TimeSpan::new(at(8, 17, 0), at(8, 18, 0)).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_gaps_skip_days_off() {
+        // Monday and Tuesday, but Monday is a holiday.
+        let span = TimeSpan::new(at(4, 0, 0), at(6, 0, 0)).unwrap();
+        let holiday = [NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()];
+
+        let gaps = find_gaps(
+            span,
+            &[],
+            hours(),
+            &holiday,
+            Duration::minutes(5),
+            at(20, 0, 0),
+        );
+        assert_eq!(
+            gaps,
+            vec![TimeSpan::new(at(5, 8, 0), at(5, 18, 0)).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_gaps_end_now() {
+        let frames = frames(&[(at(4, 9, 0), None)]);
+        let monday = TimeSpan::new(at(4, 0, 0), at(5, 0, 0)).unwrap();
+
+        let gaps = find_gaps(
+            monday,
+            &frames,
+            hours(),
+            &[],
+            Duration::minutes(5),
+            at(4, 10, 0),
+        );
+        assert_eq!(gaps, vec![TimeSpan::new(at(4, 8, 0), at(4, 9, 0)).unwrap()]);
+    }
+}
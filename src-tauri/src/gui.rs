@@ -1,47 +1,832 @@
-use std::{process::ExitCode, sync::Mutex};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+};
+
+use auto_launch::{AutoLaunch, AutoLaunchBuilder};
+use chrono::{Local, NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    api::{dialog, notification::Notification},
+    AppHandle, CustomMenuItem, Manager, PhysicalPosition, PhysicalSize, SystemTray,
+    SystemTrayEvent, SystemTrayMenu, Window, WindowEvent,
+};
+use typeshare::typeshare;
 
 use crate::{
-    database::Database,
-    error::Result,
-    model::{Frame, Project},
+    budget,
+    config::Config,
+    database::{self, ArchivedState, Database},
+    duration::DurationFormat,
+    error::{Error, ErrorCode, Result},
+    instance::{self, Request, Response, Tracking},
+    link::{self, Link},
+    locale::Locale,
+    model::{Frame, FrameSource, Project, Tag, TimeSpan, Timestamp},
+    reminder::Reminders,
+    report::{JsonReport, Report},
+    timeline::{day_span, Timeline},
+    timespan_parser::{self, DateOrder, Diagnostic},
 };
 
+/// Shares the [`Database`] between tauri commands.
+///
+/// All queries run on tauri's thread pool for blocking work, so slow queries (e.g. large reports)
+/// don't freeze the UI.
+#[derive(Clone)]
+pub struct AsyncDatabase(Arc<Mutex<Database>>);
+
+impl AsyncDatabase {
+    pub fn new(database: Database) -> Self {
+        Self(Arc::new(Mutex::new(database)))
+    }
+
+    /// Run `f` with exclusive access to the database on a thread meant for blocking work.
+    pub async fn run<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Database) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let database = self.0.clone();
+        tauri::async_runtime::spawn_blocking(move || f(&mut database.lock().unwrap()))
+            .await
+            .expect("Database task panicked")
+    }
+
+    /// Run `f` with exclusive access to the database on the current thread.
+    pub fn run_blocking<T>(&self, f: impl FnOnce(&mut Database) -> T) -> T {
+        f(&mut self.0.lock().unwrap())
+    }
+}
+
 macro_rules! wrap {
     ($function_name:ident ($($par_name:ident :$par_type:ty),*) -> $return_type:ty) => {
         #[tauri::command]
-        fn $function_name(database: tauri::State<'_, Mutex<Database>>, $($par_name: $par_type),*) -> $return_type {
-            let mut db = database.lock().unwrap();
-            db.$function_name($($par_name),*)
+        async fn $function_name(database: tauri::State<'_, AsyncDatabase>, $($par_name: $par_type),*) -> $return_type {
+            database.run(move |db| db.$function_name($($par_name),*)).await
         }
     };
 }
 
-pub fn tauri_main(database: Database) -> ExitCode {
+/// Settings of [`parse_timespan`], see [`Config::locales`], [`Config::date_order`] and
+/// [`Config::week_start`].
+struct TimespanSettings {
+    locales: Vec<Locale>,
+    date_order: DateOrder,
+    week_start: Weekday,
+}
+
+/// Event emitted when a frame was started or stopped outside of the window, e.g. by a command
+/// forwarded from the command line or at the end of the workday, with a [`Tracking`] as payload.
+const TRACKING_CHANGED: &str = "tracking-changed";
+
+/// How often the GUI checks whether the end of the workday passed, see [`Config::auto_stop`],
+/// a reminder is due, see [`crate::reminder`], or a budget is mostly used, see [`budget`].
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Event emitted when a `ttt://` link was opened, with a [`Toast`] as payload.
+const LINK_OPENED: &str = "link-opened";
+
+/// A short message for the user that disappears by itself.
+#[derive(Debug, Clone, Serialize)]
+#[typeshare]
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+}
+
+/// Setting that holds the [`WindowGeometry`] as JSON.
+const WINDOW_GEOMETRY: &str = "gui.window_geometry";
+
+/// Position and size of the main window when it was last closed.
+#[derive(Debug, Serialize, Deserialize)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// Open the GUI. With `autostart`, i.e. when started with the session, the window stays hidden in
+/// the tray if [`Preference::StartMinimized`] is set. A `link` is opened once the window is loaded.
+pub fn tauri_main(
+    database: Database,
+    config: &Config,
+    autostart: bool,
+    link: Option<String>,
+) -> ExitCode {
+    let path = database.path().map(ToOwned::to_owned);
+    // Only one GUI per database, starting another one shows the first or opens the link there.
+    let request = match &link {
+        Some(url) => Request::Open { url: url.clone() },
+        None => Request::Show,
+    };
+    match instance::forward(path.as_deref(), &request) {
+        Some(Ok(_)) => return ExitCode::SUCCESS,
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+        None => {}
+    }
+    // Needed by `register` below. The plugin delivers the links on macOS, elsewhere they are
+    // passed as argument, see `main`.
+    tauri_plugin_deep_link::prepare("ttt");
+
+    let database = AsyncDatabase::new(database);
+    let forwarded = database.clone();
+    let auto_stop = config.auto_stop;
+    let (currency, durations) = (config.currency.clone(), config.duration_format);
+    let week_start = config.week_start.unwrap_or(Weekday::Mon);
+    let reminders = Reminders::new(config).map(|reminders| Arc::new(Mutex::new(reminders)));
+    let snoozing = reminders.clone();
+    let mut tray_menu = SystemTrayMenu::new().add_item(CustomMenuItem::new("show", "Show"));
+    if reminders.is_some() {
+        tray_menu = tray_menu.add_item(CustomMenuItem::new("snooze", "Snooze reminders"));
+    }
+    let pending_link = Mutex::new(link);
     tauri::Builder::default()
-        .manage(Mutex::new(database))
+        .system_tray(
+            SystemTray::new().with_menu(tray_menu.add_item(CustomMenuItem::new("quit", "Quit"))),
+        )
+        .on_system_tray_event(move |app, event| match event {
+            SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+            SystemTrayEvent::MenuItemClick { id, .. } if id == "show" => show_main_window(app),
+            SystemTrayEvent::MenuItemClick { id, .. } if id == "snooze" => {
+                if let Some(reminders) = &snoozing {
+                    reminders.lock().unwrap().snooze(Local::now().naive_local());
+                }
+            }
+            SystemTrayEvent::MenuItemClick { id, .. } if id == "quit" => {
+                if let Some(window) = app.get_window("main") {
+                    save_geometry(&window);
+                }
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .on_window_event(|event| {
+            if let WindowEvent::CloseRequested { api, .. } = event.event() {
+                let window = event.window();
+                save_geometry(window);
+                if preference_is_set(&window.state::<AsyncDatabase>(), Preference::CloseToTray) {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+        })
+        .setup(move |app| {
+            // The window is configured to be hidden, so it does not flash up before it is moved
+            // to its last position or stays in the tray.
+            let window = app
+                .get_window("main")
+                .expect("The main window is configured");
+            restore_geometry(&window, &forwarded);
+            if !(autostart && preference_is_set(&forwarded, Preference::StartMinimized)) {
+                window.show()?;
+            }
+
+            let app = app.handle();
+            let (linked, linked_app) = (forwarded.clone(), app.clone());
+            // Registering fails e.g. in sandboxes, tracking works without links.
+            if let Err(err) = tauri_plugin_deep_link::register(link::SCHEME, move |url| {
+                let _ = open_link(&linked, &linked_app, &url);
+            }) {
+                eprintln!("Failed to register the {}:// links: {err}", link::SCHEME);
+            }
+            let (watched, watching_app) = (forwarded.clone(), app.clone());
+            std::thread::spawn(move || {
+                // Level of the budget of each project that was last warned about.
+                let mut warned = HashMap::new();
+                loop {
+                    watch_workday(&watched, &watching_app, auto_stop, reminders.as_deref());
+                    watch_budgets(
+                        &watched,
+                        &watching_app,
+                        currency.as_deref(),
+                        durations,
+                        week_start,
+                        &mut warned,
+                    );
+                    std::thread::sleep(WATCH_INTERVAL);
+                }
+            });
+            instance::listen(path.as_deref(), move |request| {
+                answer(&forwarded, &app, request)
+            })?;
+            Ok(())
+        })
+        // The toast would get lost before the frontend listens for it.
+        .on_page_load(move |window, _| {
+            if let Some(url) = pending_link.lock().unwrap().take() {
+                let _ = open_link(&window.state::<AsyncDatabase>(), &window.app_handle(), &url);
+            }
+        })
+        .manage(database)
+        .manage(TimespanSettings {
+            locales: config.locales.clone(),
+            date_order: config.date_order,
+            week_start: config.week_start.unwrap_or(Weekday::Mon),
+        })
         .invoke_handler(tauri::generate_handler![
+            all_projects,
+            all_tags,
+            create_project,
+            create_tag,
             current_frame,
+            error_messages,
+            get_autostart,
+            get_setting,
             lookup_project,
+            merge_projects,
+            merge_tags,
+            parse_timespan,
+            rename_project,
+            rename_tag,
+            report,
+            set_autostart,
+            set_project_archived,
+            set_setting,
+            set_tag_archived,
             start,
-            stop
+            stop,
+            timeline
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
     ExitCode::SUCCESS
 }
 
+/// Ask whether to restore the latest backup of the damaged database at `path` or to start over,
+/// for when the GUI is opened without a terminal to ask in. The main window stays hidden and ttt
+/// restarts once the file was replaced, see [`database::replace_damaged`].
+pub fn recover_main(path: PathBuf) -> ExitCode {
+    tauri::Builder::default()
+        .setup(move |app| {
+            let app = app.handle();
+            match database::latest_backup(&path) {
+                Some(backup) => {
+                    let name = backup.file_stem().unwrap_or_default().to_string_lossy();
+                    let question = format!(
+                        "The database {} is damaged. Restore the backup of {name}? The damaged \
+                         file is kept.",
+                        path.display()
+                    );
+                    dialog::ask(None::<&Window>, "Damaged database", question, move |yes| {
+                        if yes {
+                            replace_damaged(&app, &path, Some(&backup));
+                        } else {
+                            ask_to_start_over(app, path);
+                        }
+                    });
+                }
+                None => ask_to_start_over(app, path),
+            }
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+    ExitCode::SUCCESS
+}
+
+fn ask_to_start_over(app: AppHandle, path: PathBuf) {
+    let question = format!(
+        "The database {} is damaged. Start over with an empty database? The damaged file is kept.",
+        path.display()
+    );
+    dialog::ask(None::<&Window>, "Damaged database", question, move |yes| {
+        if yes {
+            replace_damaged(&app, &path, None);
+        } else {
+            app.exit(1);
+        }
+    });
+}
+
+fn replace_damaged(app: &AppHandle, path: &Path, backup: Option<&Path>) {
+    match database::replace_damaged(path, backup) {
+        Ok(_) => app.restart(),
+        Err(err) => {
+            eprintln!("{err}");
+            app.exit(1);
+        }
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn save_geometry(window: &Window) {
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    let json = serde_json::to_string(&geometry).expect("The geometry is serializable");
+    let result = window
+        .state::<AsyncDatabase>()
+        .run_blocking(|db| db.set_setting(WINDOW_GEOMETRY, &json));
+    if let Err(err) = result {
+        eprintln!("Failed to save the window geometry: {err}");
+    }
+}
+
+fn restore_geometry(window: &Window, database: &AsyncDatabase) {
+    let Ok(Some(json)) = database.run_blocking(|db| db.setting(WINDOW_GEOMETRY)) else {
+        return;
+    };
+    // Ignore geometry saved by an incompatible version.
+    let Ok(geometry) = serde_json::from_str::<WindowGeometry>(&json) else {
+        return;
+    };
+    let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+}
+
+/// Whether a yes/no preference is set to "true", unset preferences are off.
+fn preference_is_set(database: &AsyncDatabase, preference: Preference) -> bool {
+    database
+        .run_blocking(|db| db.setting(preference.key()))
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true")
+}
+
+/// Stop the current frame at `auto_stop` and remind to start one during working hours.
+fn watch_workday(
+    database: &AsyncDatabase,
+    app: &AppHandle,
+    auto_stop: Option<NaiveTime>,
+    reminders: Option<&Mutex<Reminders>>,
+) {
+    if let Some(at) = auto_stop {
+        match database.run_blocking(|db| db.auto_stop(at)) {
+            Ok(stopped) => {
+                for frame in stopped {
+                    let tracking = Tracking {
+                        stopped: Some(frame),
+                        started: None,
+                    };
+                    let _ = app.emit_all(TRACKING_CHANGED, tracking);
+                }
+            }
+            Err(err) => eprintln!("Failed to stop the frame automatically: {err}"),
+        }
+    }
+
+    let Some(reminders) = reminders else {
+        return;
+    };
+    let now = Local::now().naive_local();
+    let state = database.run_blocking(|db| {
+        let tracking = match db.current_frame() {
+            Ok(_) => true,
+            Err(Error::NoActiveFrame) => false,
+            Err(err) => return Err(err),
+        };
+        let on_leave = !db.leave_days(day_span(now.date()))?.is_empty();
+        Ok((tracking, on_leave))
+    });
+    let (tracking, on_leave) = match state {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Failed to check whether to remind: {err}");
+            return;
+        }
+    };
+    let mut reminders = reminders.lock().unwrap();
+    if !reminders.is_due(now, tracking, on_leave) {
+        return;
+    }
+    reminders.reminded(now);
+    let shown = Notification::new(&app.config().tauri.bundle.identifier)
+        .title("Nothing is tracked")
+        .body("Start a frame, or snooze the reminders in the tray.")
+        .show();
+    if let Err(err) = shown {
+        eprintln!("Failed to show a reminder: {err}");
+    }
+}
+
+/// Notify when the budget of a running project reaches a higher [`budget::BudgetUse::level`] than
+/// stored in `warned`.
+fn watch_budgets(
+    database: &AsyncDatabase,
+    app: &AppHandle,
+    currency: Option<&str>,
+    durations: DurationFormat,
+    week_start: Weekday,
+    warned: &mut HashMap<i32, i64>,
+) {
+    let used = database.run_blocking(|db| -> Result<_> {
+        let currencies = db.currencies(currency)?;
+        let mut used = Vec::new();
+        for frame in db.current_frames()? {
+            let Some(project) = db.lookup_project(frame.project)? else {
+                continue;
+            };
+            let currency = currencies.get(&project.id()).map(String::as_str);
+            used.extend(budget::check(db, &project, currency, week_start)?);
+        }
+        Ok(used)
+    });
+    let used = match used {
+        Ok(used) => used,
+        Err(err) => {
+            eprintln!("Failed to check the budgets: {err}");
+            return;
+        }
+    };
+    for used in used {
+        let level = used.level();
+        let previous = warned.insert(used.budget.project_id, level);
+        if previous.is_some_and(|previous| previous >= level) {
+            continue;
+        }
+        let Some(warning) = used.warning(durations) else {
+            continue;
+        };
+        let shown = Notification::new(&app.config().tauri.bundle.identifier)
+            .title(format!("Budget of {}", used.project))
+            .body(warning)
+            .show();
+        if let Err(err) = shown {
+            eprintln!("Failed to show a budget warning: {err}");
+        }
+    }
+}
+
+/// Handle a request of the command line, see [`instance`].
+fn answer(database: &AsyncDatabase, app: &AppHandle, request: Request) -> Response {
+    let tracking = match request {
+        Request::Show => {
+            show_main_window(app);
+            return Ok(Tracking::default());
+        }
+        Request::Open { url } => return open_link(database, app, &url),
+        Request::Stop => database.run_blocking(|db| {
+            Ok(Tracking {
+                stopped: db.stop()?,
+                started: None,
+            })
+        }),
+        Request::StopFrame {
+            frame_id,
+            snap_minutes,
+        } => database.run_blocking(|db| {
+            Ok(Tracking {
+                stopped: Some(db.stop_frame_snapped(frame_id, snap_minutes)?),
+                started: None,
+            })
+        }),
+        Request::Start {
+            project_id,
+            task_id,
+            external_ref,
+            source,
+        } => database.run_blocking(|db| {
+            let mut project = db
+                .lookup_project(project_id)?
+                .ok_or_else(|| Error::ProjectNotFound(project_id.to_string()))?;
+            let task = match task_id {
+                Some(task_id) => Some(
+                    db.tasks(Some(project_id))?
+                        .into_iter()
+                        .find(|task| task.id() == task_id)
+                        .ok_or_else(|| Error::TaskNotFound(task_id.to_string()))?,
+                ),
+                None => None,
+            };
+            let stopped = db.stop_before_start()?;
+            let external_ref = external_ref.as_deref();
+            // The frame is recorded as started by whoever asked, not by the GUI.
+            let source = source.unwrap_or(FrameSource::Cli);
+            let started = match &task {
+                Some(task) => db.start_task(&mut project, task, external_ref, source),
+                None => db.start(&mut project, external_ref, source),
+            }?;
+            Ok(Tracking {
+                stopped,
+                started: Some(started),
+            })
+        }),
+    };
+    let tracking = tracking.map_err(|err: Error| err.to_string())?;
+    let _ = app.emit_all(TRACKING_CHANGED, tracking.clone());
+    Ok(tracking)
+}
+
+/// Open a `ttt://` link and confirm what it did with a toast.
+fn open_link(database: &AsyncDatabase, app: &AppHandle, url: &str) -> Response {
+    let result = Link::parse(url).and_then(|link| {
+        let tracking = database.run_blocking(|db| link.open(db, FrameSource::Gui))?;
+        Ok((link, tracking))
+    });
+    let toast = match &result {
+        Ok((Link::Start { project, .. }, _)) => Toast {
+            message: format!("Started tracking {project}"),
+            is_error: false,
+        },
+        Ok((Link::Stop, tracking)) => Toast {
+            message: match tracking.stopped {
+                Some(_) => "Stopped tracking".to_owned(),
+                None => "Nothing was tracked".to_owned(),
+            },
+            is_error: false,
+        },
+        Err(err) => Toast {
+            message: err.to_string(),
+            is_error: true,
+        },
+    };
+    let _ = app.emit_all(LINK_OPENED, toast);
+    let (_, tracking) = result.map_err(|err| err.to_string())?;
+    let _ = app.emit_all(TRACKING_CHANGED, tracking.clone());
+    Ok(tracking)
+}
+
 wrap!(current_frame () -> Result<Frame>);
 
 wrap!(lookup_project (project_id: i32) -> Result<Option<Project>>);
 
 wrap!(stop() -> Result<Option<Frame>>);
 
+wrap!(all_projects (include_archived: ArchivedState) -> Result<Vec<Project>>);
+
+wrap!(all_tags (include_archived: ArchivedState) -> Result<Vec<Tag>>);
+
+wrap!(create_project (name: String) -> Result<Project>);
+
+wrap!(create_tag (name: String) -> Result<Tag>);
+
 #[tauri::command]
-fn start(
-    database: tauri::State<'_, Mutex<Database>>,
+async fn start(
+    database: tauri::State<'_, AsyncDatabase>,
     mut project: Project,
 ) -> Result<(Project, Frame)> {
-    let mut db = database.lock().unwrap();
-    let res = db.start(&mut project);
-    Ok((project, res?))
+    database
+        .run(move |db| {
+            let frame = db.start(&mut project, None, FrameSource::Gui)?;
+            Ok((project, frame))
+        })
+        .await
+}
+
+/// Starting the GUI with the session, hidden in the tray with [`Preference::StartMinimized`].
+fn auto_launch() -> auto_launch::Result<AutoLaunch> {
+    let exe = std::env::current_exe()?;
+    AutoLaunchBuilder::new()
+        .set_app_name("ttt")
+        .set_app_path(&exe.to_string_lossy())
+        .set_use_launch_agent(true)
+        .set_args(&["gui", "--autostart"])
+        .build()
+}
+
+/// Whether the GUI is started with the session.
+#[tauri::command]
+fn get_autostart() -> std::result::Result<bool, String> {
+    auto_launch()
+        .and_then(|launch| launch.is_enabled())
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn set_autostart(enabled: bool) -> std::result::Result<(), String> {
+    let launch = auto_launch().map_err(|err| err.to_string())?;
+    let result = if enabled {
+        launch.enable()
+    } else {
+        launch.disable()
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn project_by_id(db: &mut Database, project_id: i32) -> Result<Project> {
+    db.lookup_project(project_id)?
+        .ok_or_else(|| Error::ProjectNotFound(project_id.to_string()))
+}
+
+fn tag_by_id(db: &mut Database, tag_id: i32) -> Result<Tag> {
+    db.lookup_tag(tag_id)?
+        .ok_or_else(|| Error::TagNotFound(tag_id.to_string()))
+}
+
+#[tauri::command]
+async fn rename_project(
+    database: tauri::State<'_, AsyncDatabase>,
+    project_id: i32,
+    name: String,
+) -> Result<Project> {
+    database
+        .run(move |db| {
+            let mut project = project_by_id(db, project_id)?;
+            db.rename_project(&mut project, &name)?;
+            Ok(project)
+        })
+        .await
+}
+
+#[tauri::command]
+async fn set_project_archived(
+    database: tauri::State<'_, AsyncDatabase>,
+    project_id: i32,
+    archived: bool,
+) -> Result<Project> {
+    database
+        .run(move |db| {
+            let mut project = project_by_id(db, project_id)?;
+            if archived {
+                db.archive_projects(std::slice::from_mut(&mut project))?;
+            } else {
+                project.archived = false;
+                db.write_projects([&mut project])?;
+            }
+            Ok(project)
+        })
+        .await
+}
+
+/// Merge the project `from_id` into `into_id` and return the remaining project.
+#[tauri::command]
+async fn merge_projects(
+    database: tauri::State<'_, AsyncDatabase>,
+    from_id: i32,
+    into_id: i32,
+) -> Result<Project> {
+    database
+        .run(move |db| {
+            let from = project_by_id(db, from_id)?;
+            let into = project_by_id(db, into_id)?;
+            db.merge_projects(&from, &into)?;
+            Ok(into)
+        })
+        .await
+}
+
+#[tauri::command]
+async fn rename_tag(
+    database: tauri::State<'_, AsyncDatabase>,
+    tag_id: i32,
+    name: String,
+) -> Result<Tag> {
+    database
+        .run(move |db| {
+            let mut tag = tag_by_id(db, tag_id)?;
+            db.rename_tag(&mut tag, &name)?;
+            Ok(tag)
+        })
+        .await
+}
+
+#[tauri::command]
+async fn set_tag_archived(
+    database: tauri::State<'_, AsyncDatabase>,
+    tag_id: i32,
+    archived: bool,
+) -> Result<Tag> {
+    database
+        .run(move |db| {
+            let mut tag = tag_by_id(db, tag_id)?;
+            tag.archived = archived;
+            db.write_tags([&mut tag])?;
+            Ok(tag)
+        })
+        .await
+}
+
+/// Merge the tag `from_id` into `into_id` and return the remaining tag.
+#[tauri::command]
+async fn merge_tags(
+    database: tauri::State<'_, AsyncDatabase>,
+    from_id: i32,
+    into_id: i32,
+) -> Result<Tag> {
+    database
+        .run(move |db| {
+            let from = tag_by_id(db, from_id)?;
+            let into = tag_by_id(db, into_id)?;
+            db.merge_tags(&from, &into)?;
+            Ok(into)
+        })
+        .await
+}
+
+/// The frames of `day` laid out for a calendar view.
+#[tauri::command]
+async fn timeline(database: tauri::State<'_, AsyncDatabase>, day: NaiveDate) -> Result<Timeline> {
+    database
+        .run(move |db| {
+            let frames = db.get_frames_in_span(day_span(day), ArchivedState::Both)?;
+            Ok(Timeline::new(day, &frames, Timestamp::now()))
+        })
+        .await
+}
+
+/// Time per project and day from `first` to `last`, for the dashboard. Summed up from the cached
+/// daily totals, so it stays fast for a whole year.
+#[tauri::command]
+async fn report(
+    database: tauri::State<'_, AsyncDatabase>,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Result<JsonReport> {
+    database
+        .run(move |db| {
+            let span = TimeSpan::new(
+                day_span(first.min(last)).start(),
+                day_span(first.max(last)).end(),
+            )
+            .expect("A day has a positive length");
+            Ok(Report::per_project(db, span)?.json())
+        })
+        .await
+}
+
+/// Parse a time span like "last month" relative to now, as typed into a search box. The text is
+/// split into words like on the command line.
+#[tauri::command]
+fn parse_timespan(
+    settings: tauri::State<'_, TimespanSettings>,
+    text: String,
+) -> std::result::Result<TimeSpan, Diagnostic> {
+    let words: Vec<_> = text.split_whitespace().collect();
+    let context = timespan_parser::Context {
+        now: Timestamp::now(),
+        locales: settings.locales.clone(),
+        date_order: settings.date_order,
+        timezone: Local,
+        week_start: settings.week_start,
+    };
+    timespan_parser::parse(&words, &context).map_err(|err| err.annotate(&words))
+}
+
+/// The message templates of all errors in the language of a tag like "de-AT", English for
+/// languages without translations. The frontend fills in the `args` of an
+/// [`crate::error::ErrorMessage`].
+#[tauri::command]
+fn error_messages(language: String) -> BTreeMap<ErrorCode, &'static str> {
+    let locale = Locale::from_language(&language).unwrap_or(Locale::En);
+    ErrorCode::ALL
+        .into_iter()
+        .map(|code| (code, code.template(locale)))
+        .collect()
+}
+
+/// Preferences of the GUI, stored in the database so they survive reinstalling the frontend and
+/// can be read by the command line as well. Settings shared with the command line, like
+/// [`Config::week_start`] and the reminders, are only in the configuration file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[typeshare]
+pub enum Preference {
+    /// Color scheme, e.g. "dark"
+    Theme,
+
+    /// Whether to keep the window in the tray when started with the session, "true" or "false"
+    StartMinimized,
+
+    /// Whether closing the window only hides it in the tray, "true" or "false"
+    CloseToTray,
+}
+
+impl Preference {
+    /// Key in the settings table, separate from the keys used internally, e.g. by `ttt sync`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Preference::Theme => "gui.theme",
+            Preference::StartMinimized => "gui.start_minimized",
+            Preference::CloseToTray => "gui.close_to_tray",
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_setting(
+    database: tauri::State<'_, AsyncDatabase>,
+    key: Preference,
+) -> Result<Option<String>> {
+    database.run(move |db| db.setting(key.key())).await
+}
+
+#[tauri::command]
+async fn set_setting(
+    database: tauri::State<'_, AsyncDatabase>,
+    key: Preference,
+    value: String,
+) -> Result<()> {
+    database
+        .run(move |db| db.set_setting(key.key(), &value))
+        .await
 }
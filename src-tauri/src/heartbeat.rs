@@ -0,0 +1,117 @@
+//! Tracking driven by editors: plugins send a heartbeat through `ttt api` whenever a file is
+//! edited, and ttt starts or switches to the project the file belongs to.
+//!
+//! ```text
+//! {"command": "heartbeat", "file": "/home/me/src/website/index.html", "repo": "website"}
+//! ```
+//!
+//! The project is found by the rules in `config.toml`, the first matching one wins:
+//!
+//! ```toml
+//! # Name of the repository, matched case insensitively
+//! [[heartbeat]]
+//! project = "website"
+//! repo = "website-frontend"
+//!
+//! # Files below a directory, "~" is the home directory
+//! [[heartbeat]]
+//! project = "client-x"
+//! path = "~/work/client-x"
+//! ```
+//!
+//! Without a matching rule, the project named like the repository is tracked if it exists.
+//! Heartbeats of a project that is tracked already change nothing.
+
+use std::path::{Path, PathBuf};
+
+use directories::BaseDirs;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeartbeatRule {
+    /// Project to track
+    pub project: String,
+
+    /// Name of the repository
+    pub repo: Option<String>,
+
+    /// Directory the files are in
+    pub path: Option<PathBuf>,
+}
+
+impl HeartbeatRule {
+    fn matches(&self, repo: Option<&str>, file: Option<&Path>) -> bool {
+        let repo_matches = match (&self.repo, repo) {
+            (Some(expected), Some(repo)) => expected.eq_ignore_ascii_case(repo),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        let path_matches = match (&self.path, file) {
+            (Some(directory), Some(file)) => file.starts_with(expand_home(directory)),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        (self.repo.is_some() || self.path.is_some()) && repo_matches && path_matches
+    }
+}
+
+/// Replace a leading "~" by the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), BaseDirs::new()) {
+        (Ok(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => path.to_owned(),
+    }
+}
+
+/// Name of the project to track for a heartbeat from `file` in `repo`, `None` if neither a rule
+/// nor the repository name is known.
+pub fn project_name<'a>(
+    rules: &'a [HeartbeatRule],
+    repo: Option<&'a str>,
+    file: Option<&Path>,
+) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(repo, file))
+        .map(|rule| rule.project.as_str())
+        .or(repo)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rule = |project: &str, repo: Option<&str>, path: Option<&str>| HeartbeatRule {
+            project: project.to_owned(),
+            repo: repo.map(str::to_owned),
+            path: path.map(PathBuf::from),
+        };
+        let rules = [
+            rule("website", Some("Website-Frontend"), None),
+            rule("client-x", None, Some("/work/client-x")),
+            rule("client-x-docs", None, Some("/work/client-x/docs")),
+        ];
+        let file = |path: &str| Some(Path::new(path).to_owned());
+
+        assert_eq!(
+            project_name(&rules, Some("website-frontend"), None),
+            Some("website")
+        );
+        assert_eq!(
+            project_name(
+                &rules,
+                Some("docs"),
+                file("/work/client-x/docs/a.md").as_deref()
+            ),
+            Some("client-x")
+        );
+        assert_eq!(
+            project_name(&rules, None, file("/work/client-xy/a.md").as_deref()),
+            None
+        );
+        assert_eq!(project_name(&rules, Some("blog"), None), Some("blog"));
+    }
+}
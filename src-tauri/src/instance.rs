@@ -0,0 +1,227 @@
+//! Forwarding of commands from the command line to a running GUI.
+//!
+//! The GUI listens on a Unix socket next to its database file, on Windows on a named pipe named
+//! after it. `ttt start` and `ttt stop` hand their change to it instead of writing to the
+//! database themselves, so the GUI shows it right away. Starting the GUI a second time only
+//! brings the window of the first one to the front.
+//!
+//! Without a GUI every process works on the database on its own.
+
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Frame, FrameSource};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Stop the current frame and start one for the project.
+    Start {
+        project_id: i32,
+        #[serde(default)]
+        task_id: Option<i32>,
+        external_ref: Option<String>,
+
+        /// Where the request came from, recorded for the started frame.
+        #[serde(default)]
+        source: Option<FrameSource>,
+    },
+    Stop,
+
+    /// Stop one of several concurrent frames.
+    StopFrame {
+        frame_id: i32,
+
+        /// See [`crate::database::Database::stop_frame_snapped`].
+        #[serde(default)]
+        snap_minutes: Option<u32>,
+    },
+
+    /// Open a `ttt://` link, see [`crate::link`].
+    Open {
+        url: String,
+    },
+
+    /// Bring the window to the front.
+    Show,
+}
+
+/// What changed because of a request.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Tracking {
+    pub stopped: Option<Frame>,
+    pub started: Option<Frame>,
+}
+
+/// The outcome of a request, errors are passed on as their message.
+pub type Response = Result<Tracking, String>;
+
+/// Pass `request` to the GUI using the database at `database`. `None` if no GUI is running, the
+/// request has to be handled by the caller then.
+pub fn forward(database: Option<&Path>, request: &Request) -> Option<Response> {
+    let stream = platform::connect(database?).ok()?;
+    Some(
+        protocol::exchange(&stream, request)
+            .unwrap_or_else(|err| Err(format!("The running GUI did not answer: {err}"))),
+    )
+}
+
+/// Answer the requests of other processes with `handler`, on a thread of its own. Fails if
+/// another GUI answers them already.
+pub fn listen(
+    database: Option<&Path>,
+    handler: impl Fn(Request) -> Response + Send + 'static,
+) -> io::Result<()> {
+    let Some(database) = database else {
+        return Ok(());
+    };
+    let listener = platform::bind(database)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(err) = protocol::serve(&stream, &handler) {
+                eprintln!("Failed to answer a forwarded command: {err}");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{
+        io,
+        os::unix::net::{UnixListener, UnixStream},
+        path::{Path, PathBuf},
+    };
+
+    /// The socket of the GUI that uses the database at `database`.
+    fn socket_path(database: &Path) -> PathBuf {
+        let mut path = database.as_os_str().to_owned();
+        path.push(".sock");
+        path.into()
+    }
+
+    pub fn connect(database: &Path) -> io::Result<UnixStream> {
+        UnixStream::connect(socket_path(database))
+    }
+
+    pub fn bind(database: &Path) -> io::Result<UnixListener> {
+        let path = socket_path(database);
+        match UnixStream::connect(&path) {
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "Another GUI uses the database already",
+                ))
+            }
+            // Left over from a crashed GUI, nobody listens on it anymore.
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                std::fs::remove_file(&path)?;
+            }
+            Err(_) => {}
+        }
+        UnixListener::bind(path)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        io,
+        path::Path,
+    };
+
+    use interprocess::local_socket::{
+        prelude::*, GenericNamespaced, Listener, ListenerOptions, Name, Stream,
+    };
+
+    /// The pipe of the GUI that uses the database at `database`. Pipe names can't contain
+    /// backslashes, so the path is hashed.
+    fn pipe_name(database: &Path) -> io::Result<Name<'static>> {
+        let mut hasher = DefaultHasher::new();
+        database.hash(&mut hasher);
+        format!("ttt-{:016x}", hasher.finish()).to_ns_name::<GenericNamespaced>()
+    }
+
+    pub fn connect(database: &Path) -> io::Result<Stream> {
+        Stream::connect(pipe_name(database)?)
+    }
+
+    /// Named pipes vanish with the process, so there is nothing left over to clean up.
+    pub fn bind(database: &Path) -> io::Result<Listener> {
+        ListenerOptions::new()
+            .name(pipe_name(database)?)
+            .create_sync()
+    }
+}
+
+/// Both directions send a single line of JSON.
+mod protocol {
+    use std::io::{BufRead, BufReader, Read, Result, Write};
+
+    use super::{Request, Response};
+
+    pub fn exchange(mut stream: impl Read + Write, request: &Request) -> Result<Response> {
+        writeln!(stream, "{}", serde_json::to_string(request)?)?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    pub fn serve(
+        mut stream: impl Read + Write,
+        handler: &impl Fn(Request) -> Response,
+    ) -> Result<()> {
+        let mut line = String::new();
+        BufReader::new(&mut stream).read_line(&mut line)?;
+        if line.is_empty() {
+            // Only checked whether someone listens, see `platform::bind`.
+            return Ok(());
+        }
+        let response = handler(serde_json::from_str(&line)?);
+        writeln!(stream, "{}", serde_json::to_string(&response)?)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_forwarded() {
+        let dir = std::env::temp_dir().join(format!("ttt-instance-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = dir.join("ttt.sqlite");
+        assert!(forward(Some(&database), &Request::Stop).is_none());
+
+        listen(Some(&database), |request| match request {
+            Request::Stop => Ok(Tracking::default()),
+            _ => Err("Only stopping is supported".to_owned()),
+        })
+        .unwrap();
+
+        let response = forward(Some(&database), &Request::Stop).unwrap();
+        assert!(response.unwrap().stopped.is_none());
+        let response = forward(Some(&database), &Request::Show).unwrap();
+        assert_eq!(response.unwrap_err(), "Only stopping is supported");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_only_stale_sockets_are_replaced() {
+        let dir = std::env::temp_dir().join(format!("ttt-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = dir.join("ttt.sqlite");
+
+        // A crashed GUI leaves its socket behind.
+        drop(std::os::unix::net::UnixListener::bind(dir.join("ttt.sqlite.sock")).unwrap());
+        listen(Some(&database), |_| Ok(Tracking::default())).unwrap();
+
+        let err = listen(Some(&database), |_| Ok(Tracking::default())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert!(forward(Some(&database), &Request::Stop).unwrap().is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
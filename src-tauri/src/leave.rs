@@ -0,0 +1,142 @@
+//! Public holidays from iCalendar files, see `ttt leave import`.
+//!
+//! Calendars of the public holidays of many countries and regions can be downloaded, e.g. from
+//! <https://www.thunderbird.net/calendar/holidays/>. Every all-day event becomes a holiday named
+//! after the summary of the event.
+
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    error::{Error, Result},
+    model::{LeaveDay, LeaveKind},
+};
+
+/// The days of all all-day events in the calendar, events spanning several days are split.
+pub fn parse_holidays(ics: &str) -> Result<Vec<LeaveDay>> {
+    let mut holidays = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in unfold(ics) {
+        let (name, value) = line.split_once(':').unwrap_or((&line, ""));
+        let (name, parameters) = name.split_once(';').unwrap_or((name, ""));
+        match (name.to_ascii_uppercase().as_str(), &mut event) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(Event::default()),
+            ("END", Some(current)) if value == "VEVENT" => {
+                // Events with a time of day are not holidays.
+                if let Some(start) = current.start {
+                    let end = current
+                        .end
+                        .filter(|end| *end > start)
+                        .unwrap_or(start + Duration::days(1));
+                    holidays.extend(start.iter_days().take_while(|day| *day < end).map(|day| {
+                        LeaveDay {
+                            day,
+                            kind: LeaveKind::Holiday,
+                            name: current.summary.clone(),
+                        }
+                    }));
+                }
+                event = None;
+            }
+            ("DTSTART", Some(current)) => current.start = all_day(parameters, value)?,
+            ("DTEND", Some(current)) => current.end = all_day(parameters, value)?,
+            ("SUMMARY", Some(current)) => current.summary = Some(unescape(value)),
+            _ => {}
+        }
+    }
+    if holidays.is_empty() {
+        return Err(Error::InvalidImport(
+            "The file contains no all-day events".to_owned(),
+        ));
+    }
+    Ok(holidays)
+}
+
+#[derive(Default)]
+struct Event {
+    /// Only set for all-day events
+    start: Option<NaiveDate>,
+
+    /// Exclusive
+    end: Option<NaiveDate>,
+
+    summary: Option<String>,
+}
+
+/// The date of an all-day event, or `None` if the event has a time.
+fn all_day(parameters: &str, value: &str) -> Result<Option<NaiveDate>> {
+    if !parameters.to_ascii_uppercase().contains("VALUE=DATE") || value.contains('T') {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(value.trim(), "%Y%m%d")
+        .map(Some)
+        .map_err(|e| Error::InvalidImport(format!("Invalid date {value}: {e}")))
+}
+
+/// Join lines that were folded by starting the continuation with a space, see RFC 5545
+/// section 3.1.
+pub fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+/// Undo the escaping of a text value, see RFC 5545 section 3.3.11.
+pub fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(escaped) => result.push(escaped),
+                None => {}
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_holidays() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20241224\r\n\
+            SUMMARY:Christmas Eve\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20241225\r\n\
+            DTEND;VALUE=DATE:20241227\r\n\
+            SUMMARY:Christmas Day\\, Boxing\r\n  Day\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20241230T100000Z\r\n\
+            SUMMARY:Not all day\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let holidays = parse_holidays(ics).unwrap();
+
+        let days: Vec<_> = holidays
+            .iter()
+            .map(|h| (h.day.to_string(), h.name.clone().unwrap()))
+            .collect();
+        let expected = [
+            ("2024-12-24", "Christmas Eve"),
+            ("2024-12-25", "Christmas Day, Boxing Day"),
+            ("2024-12-26", "Christmas Day, Boxing Day"),
+        ]
+        .map(|(day, name)| (day.to_owned(), name.to_owned()));
+        assert_eq!(days, expected);
+        assert!(holidays.iter().all(|h| h.kind == LeaveKind::Holiday));
+    }
+}
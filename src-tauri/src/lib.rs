@@ -1,4 +1,9 @@
 pub mod database;
+pub mod duration;
+#[cfg(feature = "sqlcipher")]
+pub mod encryption;
 pub mod error;
+pub mod filter;
+pub mod locale;
 pub mod model;
 mod schema;
@@ -0,0 +1,203 @@
+//! `ttt://` links, so a wiki or issue tracker can start tracking with a single click.
+//!
+//! - `ttt://start/<project>` stops the current frame and starts one for the project
+//! - `ttt://start/<project>?tags=<tag>,<tag>&tag_project=true` also tags the project, which
+//!   lasts beyond the frame, so links have to ask for it with `tag_project`
+//! - `ttt://stop` stops the current frame
+//!
+//! Links come from web pages, so they can only use projects and tags that already exist and are
+//! not archived, and never create or unarchive anything.
+
+use crate::{
+    database::Database,
+    error::{Error, Result},
+    instance::Tracking,
+    model::FrameSource,
+};
+
+pub const SCHEME: &str = "ttt";
+
+/// Longest project or tag name accepted from a link.
+const MAX_NAME_LENGTH: usize = 200;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Link {
+    Start { project: String, tags: Vec<String> },
+    Stop,
+}
+
+/// Whether a command line argument is a link rather than a subcommand.
+pub fn is_link(argument: &str) -> bool {
+    argument
+        .get(..SCHEME.len() + 3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{SCHEME}://")))
+}
+
+impl Link {
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = |message: &str| Error::InvalidLink(message.to_owned());
+        if !is_link(url) {
+            return Err(invalid("Links have to start with ttt://"));
+        }
+        let rest = &url[SCHEME.len() + 3..];
+        // Fragments are never sent by browsers, but may be part of copied links.
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let segments: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        match segments.as_slice() {
+            [action, project] if action.eq_ignore_ascii_case("start") => {
+                let mut tags = Vec::new();
+                let mut tag_project = false;
+                for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+                    match key {
+                        "tags" => {
+                            for tag in value.split(',') {
+                                let tag = decode(tag)?;
+                                if !tag.is_empty() {
+                                    tags.push(tag);
+                                }
+                            }
+                        }
+                        "tag_project" => tag_project = matches!(value, "true" | "1"),
+                        _ => {}
+                    }
+                }
+                // Tags belong to the project, not to the frame.
+                if !tags.is_empty() && !tag_project {
+                    return Err(invalid(
+                        "Tags of links are added to the project, add tag_project=true to the link",
+                    ));
+                }
+                let project = decode(project)?;
+                if project.is_empty() {
+                    return Err(invalid("The project name is empty"));
+                }
+                Ok(Link::Start { project, tags })
+            }
+            [action] if action.eq_ignore_ascii_case("start") => {
+                Err(invalid("No project given, use ttt://start/<project>"))
+            }
+            [action] if action.eq_ignore_ascii_case("stop") => Ok(Link::Stop),
+            _ => Err(invalid(
+                "Only ttt://start/<project> and ttt://stop are supported",
+            )),
+        }
+    }
+
+    /// Do what the link asks for, frames are started as from `source`.
+    pub fn open(&self, db: &mut Database, source: FrameSource) -> Result<Tracking> {
+        let Link::Start { project, tags } = self else {
+            return Ok(Tracking {
+                stopped: db.stop()?,
+                started: None,
+            });
+        };
+        let mut project = db
+            .lookup_project_by_name(project)?
+            .ok_or_else(|| Error::ProjectNotFound(project.clone()))?;
+        if project.archived {
+            return Err(Error::InvalidLink(format!(
+                "Project {} is archived",
+                project.name
+            )));
+        }
+        let tags = tags
+            .iter()
+            .map(|name| match db.lookup_tag_by_name(name)? {
+                Some(tag) if tag.archived => {
+                    Err(Error::InvalidLink(format!("Tag {name} is archived")))
+                }
+                Some(tag) => Ok(tag),
+                None => Err(Error::TagNotFound(name.clone())),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !tags.is_empty() {
+            db.tag_projects(tags, vec![project.clone()])?;
+        }
+
+        let stopped = db.stop_before_start()?;
+        let started = db.start(&mut project, None, source)?;
+        Ok(Tracking {
+            stopped,
+            started: Some(started),
+        })
+    }
+}
+
+/// Undo the percent encoding of a path segment or query value, `+` is taken as a space like in
+/// forms. Control characters are rejected and surrounding whitespace removed.
+fn decode(text: &str) -> Result<String> {
+    let invalid = || Error::InvalidLink(format!("Malformed percent encoding in {text:?}"));
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        bytes.push(match byte {
+            b'%' => {
+                let high = input.next().and_then(|b| (b as char).to_digit(16));
+                let low = input.next().and_then(|b| (b as char).to_digit(16));
+                let (Some(high), Some(low)) = (high, low) else {
+                    return Err(invalid());
+                };
+                (high * 16 + low) as u8
+            }
+            b'+' => b' ',
+            byte => byte,
+        });
+    }
+    let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+    if decoded.chars().any(char::is_control) {
+        return Err(Error::InvalidLink(
+            "Names must not contain control characters".to_owned(),
+        ));
+    }
+    let decoded = decoded.trim();
+    if decoded.chars().count() > MAX_NAME_LENGTH {
+        return Err(Error::InvalidLink(format!(
+            "Names are limited to {MAX_NAME_LENGTH} characters"
+        )));
+    }
+    Ok(decoded.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        assert_eq!(
+            Link::parse("ttt://start/website?tags=review,%20urgent&ref=wiki&tag_project=1")
+                .unwrap(),
+            Link::Start {
+                project: "website".to_owned(),
+                tags: vec!["review".to_owned(), "urgent".to_owned()],
+            }
+        );
+        assert_eq!(
+            Link::parse("TTT://Start/Caf%C3%A9+Umbau/").unwrap(),
+            Link::Start {
+                project: "Café Umbau".to_owned(),
+                tags: vec![],
+            }
+        );
+        assert_eq!(Link::parse("ttt://stop").unwrap(), Link::Stop);
+
+        for url in [
+            "https://start/website",
+            "ttt://start",
+            "ttt://start/%20",
+            "ttt://start/web/site",
+            "ttt://start/web%0Asite",
+            "ttt://start/web%zzsite",
+            "ttt://start/website?tags=review",
+            "ttt://start/website?tags=review&tag_project=false",
+            "ttt://delete/website",
+        ] {
+            assert!(
+                matches!(Link::parse(url), Err(Error::InvalidLink(_))),
+                "{url} was accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Names of weekdays and months in the languages the timespan parser understands.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+/// Lowercase names, some days and months have more than one.
+struct Names {
+    weekdays: [&'static [&'static str]; 7],
+    months: [&'static [&'static str]; 12],
+}
+
+const ENGLISH: Names = Names {
+    weekdays: [
+        &["monday"],
+        &["tuesday"],
+        &["wednesday"],
+        &["thursday"],
+        &["friday"],
+        &["saturday"],
+        &["sunday"],
+    ],
+    months: [
+        &["january"],
+        &["february"],
+        &["march"],
+        &["april"],
+        &["may"],
+        &["june"],
+        &["july"],
+        &["august"],
+        &["september"],
+        &["october"],
+        &["november"],
+        &["december"],
+    ],
+};
+
+const GERMAN: Names = Names {
+    weekdays: [
+        &["montag"],
+        &["dienstag"],
+        &["mittwoch"],
+        &["donnerstag"],
+        &["freitag"],
+        &["samstag", "sonnabend"],
+        &["sonntag"],
+    ],
+    months: [
+        &["januar", "jänner"],
+        &["februar"],
+        &["märz", "maerz"],
+        &["april"],
+        &["mai"],
+        &["juni"],
+        &["juli"],
+        &["august"],
+        &["september"],
+        &["oktober"],
+        &["november"],
+        &["dezember"],
+    ],
+};
+
+const FRENCH: Names = Names {
+    weekdays: [
+        &["lundi"],
+        &["mardi"],
+        &["mercredi"],
+        &["jeudi"],
+        &["vendredi"],
+        &["samedi"],
+        &["dimanche"],
+    ],
+    months: [
+        &["janvier"],
+        &["février", "fevrier"],
+        &["mars"],
+        &["avril"],
+        &["mai"],
+        &["juin"],
+        &["juillet"],
+        &["août", "aout"],
+        &["septembre"],
+        &["octobre"],
+        &["novembre"],
+        &["décembre", "decembre"],
+    ],
+};
+
+const SPANISH: Names = Names {
+    weekdays: [
+        &["lunes"],
+        &["martes"],
+        &["miércoles", "miercoles"],
+        &["jueves"],
+        &["viernes"],
+        &["sábado", "sabado"],
+        &["domingo"],
+    ],
+    months: [
+        &["enero"],
+        &["febrero"],
+        &["marzo"],
+        &["abril"],
+        &["mayo"],
+        &["junio"],
+        &["julio"],
+        &["agosto"],
+        &["septiembre", "setiembre"],
+        &["octubre"],
+        &["noviembre"],
+        &["diciembre"],
+    ],
+};
+
+impl Locale {
+    /// The locale of a language tag like "de-AT", `None` for other languages.
+    pub fn from_language(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?.to_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "fr" => Some(Locale::Fr),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    fn names(self) -> &'static Names {
+        match self {
+            Locale::En => &ENGLISH,
+            Locale::De => &GERMAN,
+            Locale::Fr => &FRENCH,
+            Locale::Es => &SPANISH,
+        }
+    }
+
+    /// All names of days and months.
+    pub fn words(self) -> impl Iterator<Item = &'static str> {
+        let names = self.names();
+        names
+            .weekdays
+            .iter()
+            .chain(names.months.iter())
+            .flat_map(|alternatives| alternatives.iter().copied())
+    }
+
+    /// Zero based day of the week called `word`, which has to be lowercase.
+    pub fn weekday(self, word: &str) -> Option<u8> {
+        position(&self.names().weekdays, word)
+    }
+
+    /// Zero based month of the year called `word`, which has to be lowercase.
+    pub fn month(self, word: &str) -> Option<u8> {
+        position(&self.names().months, word)
+    }
+}
+
+fn position(names: &[&[&str]], word: &str) -> Option<u8> {
+    names
+        .iter()
+        .position(|alternatives| alternatives.contains(&word))
+        .map(|index| index as u8)
+}
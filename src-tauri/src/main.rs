@@ -1,78 +1,152 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use std::process::ExitCode;
+use std::{io::IsTerminal, process::ExitCode};
 
 use clap::Parser;
 
-use crate::cli::{cli_main, Cli};
-use crate::database::Database;
-use crate::gui::tauri_main;
+use crate::cli::{cli_main, recover_damaged, Action, Cli};
+use crate::config::Config;
+use crate::database::{ConnectionTarget, Database};
+use crate::error::Error;
+use crate::gui::{recover_main, tauri_main};
+use crate::prompt::Prompt;
 
+mod activitywatch;
+mod api;
+mod budget;
+mod bundle;
+#[cfg(feature = "caldav")]
+mod caldav;
+mod calendar;
 mod cli;
+mod config;
 mod database;
+mod duration;
+#[cfg(feature = "sqlcipher")]
+mod encryption;
 pub mod error;
+mod export;
+mod filter;
+mod gaps;
 mod gui;
+mod heartbeat;
+mod instance;
+mod leave;
+mod link;
+mod locale;
 mod model;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod prompt;
+mod reminder;
+mod report;
+mod schedule;
 mod schema;
+mod seed;
+#[cfg(feature = "slack")]
+mod slack;
+mod stats;
+mod sync;
+mod table;
+mod template;
+mod timeline;
+#[cfg(feature = "pdf")]
+mod timesheet;
 mod timespan_parser;
+mod trend;
+#[cfg(feature = "self-update")]
+mod update;
 
-pub trait DurationExt {
-    fn format(&self) -> String;
-}
-
-impl DurationExt for chrono::Duration {
-    fn format(&self) -> String {
-        use std::fmt::Write as _;
-        let mut mydur = *self;
-        let mut result = String::new();
-
-        let n = mydur.num_weeks();
-        if n > 0 {
-            let _ = write!(result, "{}w", n);
-            mydur = mydur - Self::weeks(n);
+fn main() -> ExitCode {
+    // Desktops open `ttt://` links by running ttt with the link as the only argument.
+    let link = std::env::args()
+        .nth(1)
+        .filter(|argument| link::is_link(argument));
+    let cli = match link {
+        Some(_) => Cli::parse_from(["ttt"]),
+        None => Cli::parse(),
+    };
+    let target = match &cli.database {
+        Some(path) => ConnectionTarget::Path(path.clone()),
+        None => ConnectionTarget::Default,
+    };
+    let open = |target: ConnectionTarget| {
+        if cli.keeps_database_unmigrated() {
+            Database::new_unmigrated(target)
+        } else {
+            Database::new(target)
         }
-        let n = mydur.num_days();
-        if n > 0 {
-            if !result.is_empty() {
-                result.push(' ');
+    };
+    let mut database = match open(target.clone()) {
+        Ok(database) => database,
+        Err(err @ Error::DatabaseDamaged(_)) => {
+            eprintln!("{err}");
+            let path = target.path().expect("Only database files can be damaged");
+            let opens_gui = matches!(cli.action, None | Some(Action::Gui { .. }));
+            if (link.is_some() || opens_gui) && !std::io::stdin().is_terminal() {
+                return recover_main(path);
             }
-            let _ = write!(result, "{}d", n);
-            mydur = mydur - Self::days(n);
-        }
-        let n = mydur.num_hours();
-        if n > 0 {
-            if !result.is_empty() {
-                result.push(' ');
+            if !recover_damaged(&path, Prompt::new(cli.yes, cli.no_input)) {
+                return ExitCode::FAILURE;
             }
-            let _ = write!(result, "{}h", n);
-            mydur = mydur - Self::hours(n);
-        }
-        let n = mydur.num_minutes();
-        if n > 0 {
-            if !result.is_empty() {
-                result.push(' ');
+            match open(target) {
+                Ok(database) => database,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
             }
-            let _ = write!(result, "{}min", n);
-            mydur = mydur - Self::minutes(n);
         }
-        let n = mydur.num_seconds();
-        if n > 0 {
-            if !result.is_empty() {
-                result.push(' ');
-            }
-            let _ = write!(result, "{}s", n);
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
         }
-        result
+    };
+    if let Err(err) = database.back_up_daily() {
+        eprintln!("Warning: failed to back up the database: {err}");
     }
-}
 
-fn main() -> ExitCode {
-    let cli = Cli::parse();
-    let database = Database::new().unwrap();
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(err) if matches!(cli.action, Some(Action::DebugBundle { .. })) => {
+            eprintln!("Warning: {err}");
+            Config::default()
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(format) = cli.duration_format {
+        config.duration_format = format;
+    }
+    if config.concurrent_frames {
+        database.allow_concurrent_frames();
+    }
 
-    if cli.action.is_some() {
-        cli_main(database, cli)
+    if link.is_some() {
+        return tauri_main(database, &config, false, link);
+    }
+    // Without a subcommand, a terminal gets the interactive menu and everything else, e.g. a
+    // desktop launcher, the GUI.
+    if cli.action.is_none() && !std::io::stdin().is_terminal() {
+        return tauri_main(database, &config, false, None);
+    }
+    #[cfg(feature = "self-update")]
+    let notice = if config.update.notify
+        && std::io::stderr().is_terminal()
+        && !matches!(
+            cli.action,
+            Some(Action::SelfUpdate { .. } | Action::Api { .. })
+        ) {
+        crate::update::notice(&mut database)
     } else {
-        tauri_main(database)
+        None
+    };
+    let code = cli_main(database, cli, &config);
+    #[cfg(feature = "self-update")]
+    if let Some(notice) = notice {
+        eprintln!("{notice}");
     }
+    code
 }
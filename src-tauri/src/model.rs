@@ -3,7 +3,8 @@ use std::{
     ops::{Add, Sub},
 };
 
-use chrono::prelude::*;
+use chrono::{prelude::*, LocalResult};
+use clap::ValueEnum;
 use diesel::{
     backend::Backend,
     deserialize::FromSql,
@@ -12,12 +13,14 @@ use diesel::{
     sqlite::Sqlite,
     AsChangeset, AsExpression, FromSqlRow, Identifiable, Insertable, Queryable,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use typeshare::typeshare;
 
-use crate::schema::*;
+use crate::{duration::DurationExt, schema::*};
 
-#[derive(Queryable, Identifiable, Insertable, AsChangeset, Debug, Clone, Serialize)]
+#[derive(
+    Queryable, Identifiable, Insertable, AsChangeset, Debug, Clone, Serialize, Deserialize,
+)]
 #[typeshare]
 pub struct Frame {
     id: i32,
@@ -26,26 +29,230 @@ pub struct Frame {
 
     pub start: Timestamp,
     pub end: Option<Timestamp>,
+
+    /// When the frame was moved to the trash. Frames in the trash are ignored by all regular
+    /// queries until they are restored.
+    pub deleted_at: Option<Timestamp>,
+
+    /// Identifies the frame across machines, see `ttt sync`.
+    uuid: String,
+
+    /// Last time any of the fields above was changed.
+    pub updated_at: Timestamp,
+
+    /// Issue or ticket the time was spent on, e.g. "PROJ-123".
+    pub external_ref: Option<String>,
+
+    /// Remark about how the frame came to be, e.g. that it was stopped automatically.
+    #[serde(default)]
+    pub note: Option<String>,
+
+    /// Task of the project the time was spent on, see [`Task`].
+    #[serde(default)]
+    pub task_id: Option<i32>,
+
+    /// How the frame was created, unknown for frames created before this was recorded.
+    #[serde(default)]
+    pub source: Option<FrameSource>,
+
+    /// Suggested frame, e.g. from an import, which is ignored by all regular queries until it is
+    /// confirmed with `ttt review`.
+    #[serde(default)]
+    pub draft: bool,
+
+    /// Whether the time was work, a break or on call.
+    #[serde(default)]
+    pub kind: FrameKind,
+}
+
+/// What a frame's time was spent with. Breaks are recorded, e.g. because a contract requires
+/// them, but don't count as working time.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    AsExpression,
+    FromSqlRow,
+    ValueEnum,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "kebab-case")]
+#[typeshare]
+pub enum FrameKind {
+    #[default]
+    Work,
+    Break,
+    /// Standing by, which may be paid differently
+    OnCall,
+}
+
+impl FrameKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameKind::Work => "work",
+            FrameKind::Break => "break",
+            FrameKind::OnCall => "on-call",
+        }
+    }
+}
+
+impl Display for FrameKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl<DB> FromSql<Text, DB> for FrameKind
+where
+    DB: Backend,
+    *const str: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
+        let text = unsafe { &*text_ptr };
+        FrameKind::from_str(text, false).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, Sqlite> for FrameKind {
+    fn to_sql(
+        &self,
+        out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        out.set_value(self.name());
+        Ok(IsNull::No)
+    }
+}
+
+/// How a frame was created, to tell manual entries from imported or automated ones.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, ValueEnum, Serialize, Deserialize,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+#[typeshare]
+pub enum FrameSource {
+    Cli,
+    Gui,
+    Import,
+    /// `ttt api`, used by scripts and integrations
+    Api,
+    /// Started automatically, e.g. by editor heartbeats
+    Hook,
+}
+
+impl FrameSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameSource::Cli => "cli",
+            FrameSource::Gui => "gui",
+            FrameSource::Import => "import",
+            FrameSource::Api => "api",
+            FrameSource::Hook => "hook",
+        }
+    }
+}
+
+impl Display for FrameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
+impl<DB> FromSql<Text, DB> for FrameSource
+where
+    DB: Backend,
+    *const str: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
+        let text = unsafe { &*text_ptr };
+        FrameSource::from_str(text, false).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, Sqlite> for FrameSource {
+    fn to_sql(
+        &self,
+        out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        out.set_value(self.name());
+        Ok(IsNull::No)
+    }
+}
+
+/// Number of characters of a [`Frame::short_id`].
+pub const SHORT_ID_LENGTH: usize = 8;
+
 impl Frame {
     pub fn id(&self) -> i32 {
         self.id
     }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    /// Start of the uuid, used to refer to the frame on the command line. Unlike the row id it is
+    /// the same on all synchronized machines. See [`crate::database::Database::resolve_frame`].
+    pub fn short_id(&self) -> &str {
+        &self.uuid[..self.uuid.len().min(SHORT_ID_LENGTH)]
+    }
+
+    /// How long the frame lasted. Running frames count until now.
+    pub fn duration(&self) -> chrono::Duration {
+        self.end.unwrap_or_else(Timestamp::now).0 - self.start.0
+    }
+
+    /// The time covered by the frame, running frames up to now. `None` for frames without
+    /// duration.
+    pub fn span(&self) -> Option<TimeSpan> {
+        TimeSpan::new(self.start, self.end.unwrap_or_else(Timestamp::now)).ok()
+    }
 }
 
 #[derive(Queryable, Identifiable, Insertable, AsChangeset, Debug, Clone, Serialize)]
+#[typeshare]
 pub struct Tag {
     id: i32,
     pub name: String,
     pub archived: bool,
     pub last_access_time: Timestamp,
+
+    /// Identifies the tag across machines, unlike the id.
+    uuid: String,
 }
 
 impl Tag {
     pub fn id(&self) -> i32 {
         self.id
     }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+}
+
+/// A named piece of work within a project, e.g. a bug, which frames can be tracked against.
+#[derive(Queryable, Identifiable, AsChangeset, Debug, Clone, Serialize)]
+#[typeshare]
+pub struct Task {
+    id: i32,
+    pub project_id: i32,
+    pub name: String,
+    pub done: bool,
+}
+
+impl Task {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
 }
 
 #[derive(
@@ -64,12 +271,307 @@ pub struct Project {
     /// Last time this project was used in a `Frame` (start or end).
     /// Can be used for sorting projects in LRU fashion.
     pub last_access_time: Timestamp,
+
+    /// How long the project was planned to take, see `ttt project set-estimate`.
+    pub estimate_minutes: Option<i32>,
+
+    /// What an hour of work on the project is billed, in cents. Later rates are recorded as
+    /// [`Rate`]s, this one applies before the first of them.
+    pub hourly_rate_cents: Option<i32>,
+
+    /// Identifies the project across machines, e.g. in sync journals and exports, unlike the id.
+    uuid: String,
+
+    /// Who the project is done for, see `ttt new-client`.
+    #[serde(default)]
+    pub client_id: Option<i32>,
+
+    /// ISO 4217 code of the currency the rates are in, e.g. "EUR". The currency of the client
+    /// if not set.
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 impl Project {
     pub fn id(&self) -> i32 {
         self.id
     }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn estimate(&self) -> Option<chrono::Duration> {
+        self.estimate_minutes
+            .map(|minutes| chrono::Duration::minutes(minutes.into()))
+    }
+
+    /// Hourly rate in cents in effect on `day`, given the `rates` of the project ordered by
+    /// [`Rate::effective_from`].
+    pub fn rate_at(&self, rates: &[Rate], day: NaiveDate) -> Option<i32> {
+        rates
+            .iter()
+            .rev()
+            .find(|rate| rate.effective_from <= day)
+            .map(|rate| rate.cents)
+            .or(self.hourly_rate_cents)
+    }
+}
+
+/// What an hour of work on a project is billed from a day on, see `ttt project set-rate`.
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = rates)]
+pub struct Rate {
+    pub project_id: i32,
+    pub effective_from: NaiveDate,
+    pub cents: i32,
+}
+
+/// How long the time or money of a [`Budget`] has to last.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, ValueEnum, Serialize,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Week,
+    #[default]
+    Month,
+    Year,
+    /// The whole lifetime of the project
+    Total,
+}
+
+impl BudgetPeriod {
+    pub fn name(self) -> &'static str {
+        match self {
+            BudgetPeriod::Week => "week",
+            BudgetPeriod::Month => "month",
+            BudgetPeriod::Year => "year",
+            BudgetPeriod::Total => "total",
+        }
+    }
+}
+
+impl Display for BudgetPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl<DB> FromSql<Text, DB> for BudgetPeriod
+where
+    DB: Backend,
+    *const str: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
+        let text = unsafe { &*text_ptr };
+        BudgetPeriod::from_str(text, false).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, Sqlite> for BudgetPeriod {
+    fn to_sql(
+        &self,
+        out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        out.set_value(self.name());
+        Ok(IsNull::No)
+    }
+}
+
+/// The time or money that may be spent on a project per period, see `ttt budget`. Exactly one
+/// of `minutes` and `cents` is set.
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = budgets)]
+pub struct Budget {
+    pub project_id: i32,
+    pub minutes: Option<i32>,
+    pub cents: Option<i32>,
+    pub period: BudgetPeriod,
+}
+
+/// Why a day is not worked, see `ttt leave`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    AsExpression,
+    FromSqlRow,
+    ValueEnum,
+    Serialize,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaveKind {
+    Vacation,
+    Sick,
+    /// Public holiday
+    Holiday,
+}
+
+impl LeaveKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            LeaveKind::Vacation => "vacation",
+            LeaveKind::Sick => "sick",
+            LeaveKind::Holiday => "holiday",
+        }
+    }
+}
+
+impl Display for LeaveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl<DB> FromSql<Text, DB> for LeaveKind
+where
+    DB: Backend,
+    *const str: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
+        let text = unsafe { &*text_ptr };
+        LeaveKind::from_str(text, false).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, Sqlite> for LeaveKind {
+    fn to_sql(
+        &self,
+        out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        out.set_value(self.name());
+        Ok(IsNull::No)
+    }
+}
+
+/// A day off, which is not expected to be tracked.
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = leave_days)]
+pub struct LeaveDay {
+    pub day: NaiveDate,
+    pub kind: LeaveKind,
+
+    /// Description, e.g. the name of the holiday
+    pub name: Option<String>,
+}
+
+/// What the pattern of a [`Rule`] is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, ValueEnum, Serialize)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleField {
+    /// Name of the frame's project
+    Project,
+    Note,
+    /// External reference, e.g. a ticket id
+    Ref,
+}
+
+impl RuleField {
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleField::Project => "project",
+            RuleField::Note => "note",
+            RuleField::Ref => "ref",
+        }
+    }
+}
+
+impl Display for RuleField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl<DB> FromSql<Text, DB> for RuleField
+where
+    DB: Backend,
+    *const str: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
+        let text = unsafe { &*text_ptr };
+        RuleField::from_str(text, false).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, Sqlite> for RuleField {
+    fn to_sql(
+        &self,
+        out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        out.set_value(self.name());
+        Ok(IsNull::No)
+    }
+}
+
+/// Adds a tag to the project of every frame that is created or edited with a `field` matching
+/// the regular expression `pattern`, see `ttt rules`.
+#[derive(Queryable, Debug, Clone)]
+pub struct Rule {
+    id: i32,
+    pub field: RuleField,
+    pub pattern: String,
+    pub tag_id: i32,
+}
+
+impl Rule {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+/// Time tracked for a project on one day, see
+/// [`Database::daily_totals`](crate::database::Database::daily_totals).
+#[derive(Debug, Clone)]
+pub struct DailyTotal {
+    pub project: Project,
+    pub day: NaiveDate,
+    pub duration: chrono::Duration,
+}
+
+/// A frame with its project and the project's tags, see
+/// [`Database::frame_details`](crate::database::Database::frame_details).
+#[derive(Debug, Clone)]
+pub struct FrameDetails {
+    pub frame: Frame,
+    pub project: Project,
+    pub tags: Vec<Tag>,
+}
+
+/// A period whose frames were submitted, e.g. to payroll, and may no longer change.
+/// See `ttt lock`.
+#[derive(Queryable, Debug, Clone)]
+pub struct LockedPeriod {
+    #[allow(dead_code)]
+    id: i32,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub locked_at: Timestamp,
+}
+
+impl LockedPeriod {
+    pub fn span(&self) -> TimeSpan {
+        TimeSpan::new(self.start, self.end).expect("Locked periods are valid spans")
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = locked_periods)]
+pub struct NewLockedPeriod<'a> {
+    pub start: &'a Timestamp,
+    pub end: &'a Timestamp,
+    pub locked_at: &'a Timestamp,
 }
 
 #[derive(Insertable, Debug)]
@@ -84,6 +586,39 @@ pub struct TagProject {
 pub struct NewTag<'a> {
     pub name: &'a str,
     pub last_access_time: &'a Timestamp,
+    pub uuid: &'a str,
+}
+
+/// Who projects are done for and billed to, see `ttt new-client`.
+#[derive(Queryable, Identifiable, Debug, Clone, Serialize)]
+#[typeshare]
+pub struct Client {
+    id: i32,
+    pub name: String,
+
+    /// ISO 4217 code of the currency the client is billed in, e.g. "EUR", see
+    /// [`Project::currency`].
+    pub currency: Option<String>,
+}
+
+impl Client {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = clients)]
+pub struct NewClient<'a> {
+    pub name: &'a str,
+    pub currency: Option<&'a str>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = tasks)]
+pub struct NewTask<'a> {
+    pub project_id: i32,
+    pub name: &'a str,
 }
 
 #[derive(Insertable, Debug)]
@@ -91,6 +626,15 @@ pub struct NewTag<'a> {
 pub struct NewProject<'a> {
     pub name: &'a str,
     pub last_access_time: &'a Timestamp,
+    pub uuid: &'a str,
+}
+
+/// Another name a project can be referred to by on the command line.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = project_aliases)]
+pub struct ProjectAlias<'a> {
+    pub alias: &'a str,
+    pub project_id: i32,
 }
 
 #[derive(Insertable, Debug)]
@@ -99,43 +643,77 @@ pub struct NewFrame<'a> {
     pub project: i32,
     pub start: &'a Timestamp,
     pub end: Option<&'a Timestamp>,
+    pub deleted_at: Option<&'a Timestamp>,
+    pub uuid: &'a str,
+    pub updated_at: &'a Timestamp,
+    pub external_ref: Option<&'a str>,
+    pub note: Option<&'a str>,
+    pub source: Option<FrameSource>,
+    pub draft: bool,
+    pub kind: FrameKind,
+}
+
+/// Columns of a frame changed by `Database::amend_frame`, the ones left at `None` are not
+/// written.
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = frames)]
+pub struct FrameChanges<'a> {
+    pub project: Option<i32>,
+
+    /// `Some(None)` takes the frame off its task.
+    pub task_id: Option<Option<i32>>,
+    pub note: Option<&'a str>,
+    pub updated_at: &'a Timestamp,
 }
 
 #[derive(
-    Debug,
-    AsExpression,
-    FromSqlRow,
-    PartialEq,
-    Eq,
-    PartialOrd,
-    Ord,
-    Clone,
-    Copy,
-    Serialize,
-    Deserialize,
+    Debug, AsExpression, FromSqlRow, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Deserialize,
 )]
 #[diesel(sql_type=diesel::sql_types::Text)]
 #[typeshare(serialized_as = "string")]
 pub struct Timestamp(pub DateTime<FixedOffset>);
 
+/// Timestamps are serialized as RFC 3339 with the offset, e.g. "2024-03-04T08:00:00+01:00".
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_rfc3339())
+    }
+}
+
+/// Serialize a duration as ISO 8601, e.g. "PT1H23M", for use with `#[serde(serialize_with)]`.
+pub fn serialize_duration<S: Serializer>(
+    duration: &chrono::Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&duration.to_iso8601())
+}
+
 impl<DB> FromSql<Text, DB> for Timestamp
 where
     DB: Backend,
     *const str: FromSql<Text, DB>,
 {
+    /// Timestamps are read in the local offset at the time, see [`Timestamp::to_sql`].
     fn from_sql(bytes: <DB as Backend>::RawValue<'_>) -> diesel::deserialize::Result<Self> {
         let text_ptr = <*const str as FromSql<Text, DB>>::from_sql(bytes)?;
         let text = unsafe { &*text_ptr };
-        Ok(Timestamp(DateTime::parse_from_rfc3339(text)?))
+        let time = DateTime::parse_from_rfc3339(text)?;
+        Ok(Timestamp(time.with_timezone(&Local).fixed_offset()))
     }
 }
 
 impl ToSql<Text, Sqlite> for Timestamp {
+    /// Timestamps are stored in UTC with nine fractional digits, e.g.
+    /// "2024-03-04T07:00:00.000000000Z", so SQL compares them correctly as text.
     fn to_sql(
         &self,
         out: &mut diesel::serialize::Output<'_, '_, Sqlite>,
     ) -> diesel::serialize::Result {
-        let s = self.0.to_rfc3339();
+        let s = self
+            .0
+            .naive_utc()
+            .format("%Y-%m-%dT%H:%M:%S%.9fZ")
+            .to_string();
         out.set_value(s);
         Ok(IsNull::No)
     }
@@ -169,11 +747,24 @@ impl Timestamp {
         Self(time)
     }
 
+    /// A local time in the offset of its day, like timestamps read from the database, so it
+    /// falls on the same day in SQL.
     pub fn from_naive(time: NaiveDateTime) -> Self {
-        let local_time = chrono::Local::now();
-        let tz = chrono::FixedOffset::east_opt(local_time.offset().local_minus_utc())
-            .expect("Time offset out of bounds");
-        Timestamp(time.and_local_timezone(tz).earliest().expect("Time broke"))
+        Self::from_naive_in(time, &Local)
+    }
+
+    /// Like [`Timestamp::from_naive`], with the offsets of `timezone`.
+    pub fn from_naive_in<Tz: TimeZone>(time: NaiveDateTime, timezone: &Tz) -> Self {
+        let offset = match timezone.offset_from_local_datetime(&time) {
+            LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset.fix(),
+            // Skipped when the clocks were put forward.
+            LocalResult::None => timezone.offset_from_utc_datetime(&time).fix(),
+        };
+        Timestamp(
+            time.and_local_timezone(offset)
+                .single()
+                .expect("Fixed offsets are unambiguous"),
+        )
     }
 
     pub fn to_local(self) -> DateTime<Local> {
@@ -189,6 +780,18 @@ impl Timestamp {
         Self::now().0 - self.0
     }
 
+    /// The latest time at or before this one that is a whole multiple of `minutes` after
+    /// midnight, e.g. 10:00 for 10:14 and 15 minutes. Steps of a day or more round to midnight.
+    pub fn round_down(self, minutes: u32) -> Self {
+        let step = minutes.clamp(1, 24 * 60) * 60;
+        let past = self.0.num_seconds_from_midnight() % step;
+        let time = self
+            .0
+            .with_nanosecond(0)
+            .expect("Zero nanoseconds are valid");
+        Self(time - chrono::Duration::seconds(past.into()))
+    }
+
     /// Return a new timestamp at the same date, but at midnight (00:00:00).
     pub fn at_midnight(&self) -> Self {
         Self(
@@ -224,14 +827,20 @@ ImplOpForTimestamp!(Add, add chrono::Days => checked_add_days);
 ImplOpForTimestamp!(Sub, sub chrono::Days => checked_sub_days);
 ImplOpForTimestamp!(Add, add chrono::Months => checked_add_months);
 ImplOpForTimestamp!(Sub, sub chrono::Months => checked_sub_months);
+ImplOpForTimestamp!(Add, add chrono::Duration => checked_add_signed);
+ImplOpForTimestamp!(Sub, sub chrono::Duration => checked_sub_signed);
 
 /// Models a span of time.
 /// The span starts with the first [`Timestamp`] and ends just before the second,
 /// that is, it is a half open range.
 ///
 /// This type guarantees that `start() < end()`.
-#[derive(Debug, PartialEq, Eq, Serialize)]
-pub struct TimeSpan(Timestamp, Timestamp);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[typeshare]
+pub struct TimeSpan {
+    start: Timestamp,
+    end: Timestamp,
+}
 
 impl TimeSpan {
     pub fn new(
@@ -244,23 +853,24 @@ impl TimeSpan {
             return Err(TimeSpanError::EndBeforeStart(start, end));
         }
 
-        Ok(Self(start, end))
+        Ok(Self { start, end })
     }
 
     pub fn start(&self) -> Timestamp {
-        self.0
+        self.start
     }
 
     pub fn end(&self) -> Timestamp {
-        self.1
+        self.end
     }
 
     pub fn start_mut(&mut self) -> &mut Timestamp {
-        &mut self.0
+        &mut self.start
     }
 
+    #[allow(dead_code)]
     pub fn end_mut(&mut self) -> &mut Timestamp {
-        &mut self.1
+        &mut self.end
     }
 
     /// Return a new timespan that starts with `self` and ends with `other`.
@@ -287,6 +897,105 @@ impl TimeSpan {
     pub fn extend(&self, other: Self) -> Result<Self, TimeSpanError> {
         Self::new(self.start(), other.end())
     }
+
+    /// The time between start and end.
+    ///
+    /// ```
+    /// # use ttt::model::{Timestamp, TimeSpan};
+    /// let span = TimeSpan::new(
+    ///     Timestamp::from_ymdhms(2022, 01, 01, 9, 0, 0),
+    ///     Timestamp::from_ymdhms(2022, 01, 01, 10, 30, 0),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(span.duration(), chrono::Duration::minutes(90));
+    /// ```
+    pub fn duration(&self) -> chrono::Duration {
+        self.end.0 - self.start.0
+    }
+
+    /// Whether `timestamp` is within the span. The end is not, as the span is half open.
+    ///
+    /// ```
+    /// # use ttt::model::{Timestamp, TimeSpan};
+    /// let nine = Timestamp::from_ymdhms(2022, 01, 01, 9, 0, 0);
+    /// let ten = Timestamp::from_ymdhms(2022, 01, 01, 10, 0, 0);
+    /// let span = TimeSpan::new(nine, ten).unwrap();
+    ///
+    /// assert!(span.contains(nine));
+    /// assert!(span.contains(Timestamp::from_ymdhms(2022, 01, 01, 9, 59, 59)));
+    /// assert!(!span.contains(ten));
+    /// ```
+    pub fn contains(&self, timestamp: Timestamp) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+
+    /// The part of the time covered by both spans, `None` if they don't overlap.
+    ///
+    /// ```
+    /// # use ttt::model::{Timestamp, TimeSpan};
+    /// let at = |hour| Timestamp::from_ymdhms(2022, 01, 01, hour, 0, 0);
+    /// let morning = TimeSpan::new(at(8), at(12)).unwrap();
+    /// let meeting = TimeSpan::new(at(11), at(13)).unwrap();
+    /// let lunch = TimeSpan::new(at(12), at(13)).unwrap();
+    ///
+    /// assert_eq!(
+    ///     morning.intersection(&meeting),
+    ///     Some(TimeSpan::new(at(11), at(12)).unwrap())
+    /// );
+    /// assert_eq!(morning.intersection(&lunch), None);
+    /// ```
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Self::new(self.start.max(other.start), self.end.min(other.end)).ok()
+    }
+
+    /// Whether the span starts and ends at local midnight, so it covers whole days.
+    ///
+    /// ```
+    /// # use ttt::model::{Timestamp, TimeSpan};
+    /// let at = |day, hour| Timestamp::from_ymdhms(2022, 01, day, hour, 0, 0);
+    /// assert!(TimeSpan::new(at(1, 0), at(3, 0)).unwrap().is_whole_days());
+    /// assert!(!TimeSpan::new(at(1, 9), at(1, 12)).unwrap().is_whole_days());
+    /// ```
+    pub fn is_whole_days(&self) -> bool {
+        [self.start, self.end]
+            .iter()
+            .all(|timestamp| timestamp.to_local().time() == NaiveTime::MIN)
+    }
+
+    /// Split the span at each local midnight. The first and the last day may be partial, days
+    /// with a daylight saving time switch are 23 or 25 hours long.
+    ///
+    /// ```
+    /// # use ttt::model::{Timestamp, TimeSpan};
+    /// let span = TimeSpan::new(
+    ///     Timestamp::from_ymdhms(2022, 01, 01, 23, 0, 0),
+    ///     Timestamp::from_ymdhms(2022, 01, 03, 1, 0, 0),
+    /// )
+    /// .unwrap();
+    /// let hours: Vec<_> = span.split_by_day().map(|day| day.duration().num_hours()).collect();
+    /// assert_eq!(hours, [1, 24, 1]);
+    /// ```
+    pub fn split_by_day(self) -> impl Iterator<Item = TimeSpan> {
+        self.split_by_day_in(Local)
+    }
+
+    /// Like [`TimeSpan::split_by_day`], at the midnights of `timezone`.
+    pub fn split_by_day_in<Tz: TimeZone>(self, timezone: Tz) -> impl Iterator<Item = TimeSpan> {
+        let mut next_start = Some(self.start);
+        std::iter::from_fn(move || {
+            let start = next_start?;
+            let end = start
+                .0
+                .with_timezone(&timezone)
+                .date_naive()
+                .succ_opt()
+                .map(|day| Timestamp::from_naive_in(day.and_time(NaiveTime::MIN), &timezone))
+                .filter(|midnight| *midnight < self.end)
+                .unwrap_or(self.end);
+            next_start = (end < self.end).then_some(end);
+            Some(TimeSpan { start, end })
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -304,3 +1013,61 @@ impl Display for TimeSpanError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::{Connection, ExpressionMethods, IntoSql, RunQueryDsl, SqliteConnection};
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Any time between 1970 and 9999 in any offset.
+    fn timestamp() -> impl Strategy<Value = Timestamp> {
+        (
+            0i64..253_402_300_800,
+            0u32..1_000_000_000,
+            -86_399i32..86_400,
+        )
+            .prop_map(|(seconds, nanoseconds, offset)| {
+                let offset = FixedOffset::east_opt(offset).unwrap();
+                let time = DateTime::from_timestamp(seconds, nanoseconds).unwrap();
+                Timestamp(time.with_timezone(&offset))
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn timestamps_round_trip_through_sql(a in timestamp()) {
+            let mut connection = SqliteConnection::establish(":memory:").unwrap();
+            let read: Timestamp = diesel::select(a.into_sql::<Text>())
+                .get_result(&mut connection)
+                .unwrap();
+            prop_assert_eq!(read, a);
+        }
+
+        /// SQL compares the stored text, which has to agree with comparing the times.
+        #[test]
+        fn sql_compares_timestamps_like_rust(a in timestamp(), b in timestamp()) {
+            let mut connection = SqliteConnection::establish(":memory:").unwrap();
+            let (less, equal): (bool, bool) = diesel::select((
+                a.into_sql::<Text>().lt(b),
+                a.into_sql::<Text>().eq(b),
+            ))
+            .get_result(&mut connection)
+            .unwrap();
+            prop_assert_eq!(less, a < b);
+            prop_assert_eq!(equal, a == b);
+        }
+
+        /// The same time in another offset is stored the same.
+        #[test]
+        fn offsets_are_not_stored(a in timestamp(), offset in -86_399i32..86_400) {
+            let mut connection = SqliteConnection::establish(":memory:").unwrap();
+            let moved = Timestamp(a.0.with_timezone(&FixedOffset::east_opt(offset).unwrap()));
+            let equal: bool = diesel::select(a.into_sql::<Text>().eq(moved))
+                .get_result(&mut connection)
+                .unwrap();
+            prop_assert!(equal);
+        }
+    }
+}
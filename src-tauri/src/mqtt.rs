@@ -0,0 +1,211 @@
+//! Announce the tracking state over MQTT, available with the `mqtt` feature.
+//!
+//! The state is published as retained messages, together with Home Assistant discovery payloads
+//! so the sensors show up without further configuration. The time elapsed since the start of the
+//! frame is a timestamp sensor, which Home Assistant keeps up to date by itself:
+//!
+//! ```toml
+//! [mqtt]
+//! host = "homeassistant.local"
+//! username = "ttt"
+//! password = "secret"
+//! ```
+
+use std::time::Duration;
+
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    database::Database,
+    error::{Error, Result},
+    model::{Frame, Project},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    /// Prefix of the state topics, e.g. `ttt/state`.
+    #[serde(default = "default_topic")]
+    pub topic: String,
+
+    /// Prefix Home Assistant listens to for discovery payloads.
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic() -> String {
+    "ttt".to_owned()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_owned()
+}
+
+/// Publish whether `current` is being tracked, `None` when idle. The client id is derived from the
+/// machine id of `ttt sync`, so several machines can publish to the same broker.
+pub fn publish(
+    db: &mut Database,
+    config: &MqttConfig,
+    current: Option<(&Project, &Frame)>,
+) -> Result<()> {
+    let client_id = format!("ttt-{}", crate::sync::machine_id(db)?);
+    let mut options = MqttOptions::new(client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(5));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+    }
+
+    let messages = messages(config, current);
+    let (client, mut connection) = Client::new(options, messages.len());
+    for (topic, payload) in &messages {
+        client
+            .publish(topic, QoS::AtLeastOnce, true, payload.as_bytes())
+            .map_err(|e| Error::Mqtt(e.to_string()))?;
+    }
+
+    // The messages are only sent while the connection is polled.
+    let mut acknowledged = 0;
+    for event in connection.iter() {
+        match event.map_err(|e| Error::Mqtt(e.to_string()))? {
+            Event::Incoming(Packet::PubAck(_)) => {
+                acknowledged += 1;
+                if acknowledged == messages.len() {
+                    client
+                        .disconnect()
+                        .map_err(|e| Error::Mqtt(e.to_string()))?;
+                }
+            }
+            Event::Outgoing(Outgoing::Disconnect) => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Topics and payloads describing the state, including the discovery payloads.
+fn messages(config: &MqttConfig, current: Option<(&Project, &Frame)>) -> Vec<(String, String)> {
+    let state_topic = format!("{}/state", config.topic);
+    let attributes_topic = format!("{}/attributes", config.topic);
+    let device = json!({ "identifiers": ["ttt"], "name": "ttt" });
+
+    let tracking = json!({
+        "name": "Tracking",
+        "unique_id": "ttt_tracking",
+        "state_topic": state_topic,
+        "value_template": "{{ value_json.state }}",
+        "payload_on": "tracking",
+        "payload_off": "idle",
+        "json_attributes_topic": attributes_topic,
+        "device": device,
+    });
+    let project = json!({
+        "name": "Project",
+        "unique_id": "ttt_project",
+        "state_topic": state_topic,
+        "value_template": "{{ value_json.project }}",
+        "json_attributes_topic": attributes_topic,
+        "device": device,
+    });
+    let elapsed = json!({
+        "name": "Elapsed",
+        "unique_id": "ttt_elapsed",
+        "state_topic": state_topic,
+        "value_template": "{{ value_json.started }}",
+        "device_class": "timestamp",
+        "device": device,
+    });
+
+    let (state, attributes) = match current {
+        Some((project, frame)) => (
+            json!({ "state": "tracking", "project": project.name, "started": frame.start }),
+            json!({
+                "project": project.name,
+                "started": frame.start,
+                "elapsed_seconds": frame.start.elapsed().num_seconds(),
+            }),
+        ),
+        None => (
+            json!({ "state": "idle", "project": "", "started": null }),
+            json!({}),
+        ),
+    };
+
+    vec![
+        (
+            format!(
+                "{}/binary_sensor/ttt/tracking/config",
+                config.discovery_prefix
+            ),
+            tracking.to_string(),
+        ),
+        (
+            format!("{}/sensor/ttt/project/config", config.discovery_prefix),
+            project.to_string(),
+        ),
+        (
+            format!("{}/sensor/ttt/elapsed/config", config.discovery_prefix),
+            elapsed.to_string(),
+        ),
+        (state_topic, state.to_string()),
+        (attributes_topic, attributes.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::FrameSource;
+
+    fn config() -> MqttConfig {
+        toml::from_str("host = \"localhost\"").unwrap()
+    }
+
+    #[test]
+    fn test_idle_state() {
+        let messages = messages(&config(), None);
+
+        assert_eq!(
+            messages[0].0,
+            "homeassistant/binary_sensor/ttt/tracking/config"
+        );
+        assert_eq!(
+            messages[3],
+            (
+                "ttt/state".to_owned(),
+                r#"{"project":"","started":null,"state":"idle"}"#.to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_tracking_state() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut project = db.create_project("website").unwrap();
+        let frame = db.start(&mut project, None, FrameSource::Cli).unwrap();
+
+        let messages = messages(&config(), Some((&project, &frame)));
+
+        let elapsed: serde_json::Value = serde_json::from_str(&messages[2].1).unwrap();
+        assert_eq!(elapsed["device_class"], "timestamp");
+        let state: serde_json::Value = serde_json::from_str(&messages[3].1).unwrap();
+        assert_eq!(state["state"], "tracking");
+        assert_eq!(state["project"], "website");
+        assert_eq!(state["started"], frame.start.0.to_rfc3339());
+        let attributes: serde_json::Value = serde_json::from_str(&messages[4].1).unwrap();
+        assert_eq!(attributes["started"], frame.start.0.to_rfc3339());
+    }
+}
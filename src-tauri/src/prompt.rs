@@ -0,0 +1,111 @@
+//! Confirmations and other questions to the user.
+//!
+//! With `--yes` every confirmation is answered with yes. Without it, a command that needs a
+//! confirmation fails when stdin is not a terminal or `--no-input` is given, instead of waiting
+//! for an answer that never comes. Questions without a default answer, like selecting a project,
+//! always fail in that case.
+
+use std::{fmt::Display, io::IsTerminal};
+
+use inquire::{Confirm, InquireError};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Prompt {
+    /// Answer every confirmation with yes, see `--yes`.
+    pub assume_yes: bool,
+
+    /// Whether there is a user to ask, false with `--no-input`.
+    pub interactive: bool,
+}
+
+/// A question that could not be asked.
+#[derive(Debug)]
+pub struct NotInteractive {
+    pub question: String,
+
+    /// Whether the question is a confirmation, which `--yes` answers.
+    pub confirmation: bool,
+}
+
+impl Prompt {
+    pub fn new(assume_yes: bool, no_input: bool) -> Self {
+        Self {
+            assume_yes,
+            interactive: !no_input && std::io::stdin().is_terminal(),
+        }
+    }
+
+    /// Ask `question`, cancelling the prompt counts as no.
+    pub fn confirm(&self, question: &str, default: bool) -> Result<bool, NotInteractive> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        self.check_interactive(question)
+            .map_err(|err| NotInteractive {
+                confirmation: true,
+                ..err
+            })?;
+        match Confirm::new(question).with_default(default).prompt() {
+            Ok(answer) => Ok(answer),
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => Ok(false),
+            Err(err) => panic!("Failed to inquire confirmation: {err}"),
+        }
+    }
+
+    /// Fail if `question` cannot be asked. For prompts other than confirmations, which have no
+    /// answer to assume with `--yes`.
+    pub fn check_interactive(&self, question: &str) -> Result<(), NotInteractive> {
+        if self.interactive {
+            Ok(())
+        } else {
+            Err(NotInteractive {
+                question: question.to_owned(),
+                confirmation: false,
+            })
+        }
+    }
+}
+
+impl Display for NotInteractive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot ask \"{}\" without a terminal or with --no-input",
+            self.question
+        )?;
+        if self.confirmation {
+            write!(f, ", pass --yes to confirm")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_confirm_without_terminal() {
+        let prompt = Prompt {
+            assume_yes: false,
+            interactive: false,
+        };
+        let err = prompt.confirm("Delete 3 frames?", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot ask \"Delete 3 frames?\" without a terminal or with --no-input, pass --yes \
+             to confirm"
+        );
+        let err = prompt.check_interactive("Select a project:").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot ask \"Select a project:\" without a terminal or with --no-input"
+        );
+
+        let prompt = Prompt {
+            assume_yes: true,
+            ..prompt
+        };
+        assert!(prompt.confirm("Delete 3 frames?", false).unwrap());
+    }
+}
@@ -0,0 +1,163 @@
+//! Notifications of the GUI when nothing is tracked during working hours.
+//!
+//! ```toml
+//! [reminder]
+//! # Start of the workday, the first reminder comes `delay_minutes` later
+//! start = "08:30"
+//! delay_minutes = 15
+//!
+//! # End of the workday, `auto_stop` by default, otherwise reminders go on until midnight
+//! end = "17:30"
+//!
+//! # Time between reminders, "Snooze reminders" in the tray pauses them for `snooze_minutes`
+//! repeat_minutes = 15
+//! snooze_minutes = 60
+//!
+//! quiet_days = ["saturday", "sunday"]
+//! ```
+//!
+//! Weekdays without working hours in the [`crate::schedule`] and days off recorded with
+//! `ttt leave` are quiet as well.
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use serde::Deserialize;
+
+use crate::{config::Config, schedule::Schedule};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReminderConfig {
+    /// Start of the workday
+    pub start: NaiveTime,
+
+    /// Minutes after `start` before the first reminder
+    pub delay_minutes: u32,
+
+    /// End of the workday, [`Config::auto_stop`] if not set
+    pub end: Option<NaiveTime>,
+
+    /// Minutes between two reminders
+    pub repeat_minutes: u32,
+
+    /// Minutes without reminders after snoozing them
+    pub snooze_minutes: u32,
+
+    /// Weekdays without reminders
+    pub quiet_days: Vec<Weekday>,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::from_hms_opt(9, 0, 0).expect("Valid time"),
+            delay_minutes: 15,
+            end: None,
+            repeat_minutes: 15,
+            snooze_minutes: 60,
+            quiet_days: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+}
+
+/// Decides when to remind, shown reminders and snoozing push the next one back.
+#[derive(Debug)]
+pub struct Reminders {
+    config: ReminderConfig,
+    schedule: Option<Schedule>,
+
+    /// No reminders before this time.
+    paused_until: Option<NaiveDateTime>,
+}
+
+impl Reminders {
+    /// `None` if reminders are not configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        let mut reminder = config.reminder.clone()?;
+        reminder.end = reminder.end.or(config.auto_stop);
+        Some(Self {
+            config: reminder,
+            schedule: config.schedule.clone(),
+            paused_until: None,
+        })
+    }
+
+    /// Whether to remind at the local time `now`. `tracking` is whether a frame is running,
+    /// `on_leave` whether today is a day off.
+    pub fn is_due(&self, now: NaiveDateTime, tracking: bool, on_leave: bool) -> bool {
+        if tracking || on_leave || self.paused_until.is_some_and(|until| now < until) {
+            return false;
+        }
+        let day = now.date();
+        if self.config.quiet_days.contains(&day.weekday()) {
+            return false;
+        }
+        if let Some(schedule) = &self.schedule {
+            if schedule.expected(day).is_zero() {
+                return false;
+            }
+        }
+        let first =
+            day.and_time(self.config.start) + Duration::minutes(self.config.delay_minutes.into());
+        now >= first && self.config.end.is_none_or(|end| now.time() < end)
+    }
+
+    /// Wait `repeat_minutes` before reminding again.
+    pub fn reminded(&mut self, now: NaiveDateTime) {
+        self.paused_until = Some(now + Duration::minutes(self.config.repeat_minutes.into()));
+    }
+
+    /// Wait `snooze_minutes` before reminding again.
+    pub fn snooze(&mut self, now: NaiveDateTime) {
+        self.paused_until = Some(now + Duration::minutes(self.config.snooze_minutes.into()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_reminders_are_due_during_working_hours() {
+        let config = Config {
+            reminder: Some(ReminderConfig::default()),
+            auto_stop: NaiveTime::from_hms_opt(17, 30, 0),
+            ..Default::default()
+        };
+        let mut reminders = Reminders::new(&config).unwrap();
+        // A Monday
+        let at = |h, m| {
+            NaiveDate::from_ymd_opt(2024, 5, 20)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+
+        assert!(!reminders.is_due(at(9, 10), false, false));
+        assert!(reminders.is_due(at(9, 15), false, false));
+        assert!(!reminders.is_due(at(9, 15), true, false));
+        assert!(!reminders.is_due(at(9, 15), false, true));
+        assert!(!reminders.is_due(at(17, 30), false, false));
+        assert!(!reminders.is_due(at(9, 15) - Duration::days(1), false, false));
+
+        reminders.reminded(at(10, 0));
+        assert!(!reminders.is_due(at(10, 10), false, false));
+        assert!(reminders.is_due(at(10, 15), false, false));
+        reminders.snooze(at(10, 15));
+        assert!(!reminders.is_due(at(11, 0), false, false));
+        assert!(reminders.is_due(at(11, 15), false, false));
+
+        let config = Config {
+            reminder: Some(ReminderConfig::default()),
+            schedule: Some(Schedule {
+                tuesday: 8.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let reminders = Reminders::new(&config).unwrap();
+        assert!(!reminders.is_due(at(11, 0), false, false));
+        assert!(reminders.is_due(at(23, 0) + Duration::days(1), false, false));
+    }
+}
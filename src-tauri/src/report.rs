@@ -0,0 +1,1950 @@
+//! Summaries of the tracked time within a [`TimeSpan`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Write as _},
+};
+
+use chrono::{Duration, NaiveDate, Weekday};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    database::{ArchivedState, Database},
+    duration::{DurationExt, DurationFormat},
+    error::Result,
+    model::{
+        serialize_duration, Budget, Client, DailyTotal, Frame, FrameKind, LeaveDay, Project, Rate,
+        Tag, Task, TimeSpan,
+    },
+    schedule::{self, format_difference, DayBalance, Schedule},
+    table::{Align, Table},
+};
+
+const DOUBLE_COUNTED: &str = "Frames count fully for each tag of their project and where they \
+overlap, so the shares add up to more than 100%.";
+
+/// What the tracked time of a [`Report`] is summed up by.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Grouping {
+    #[default]
+    Project,
+
+    /// The issue or ticket referenced by the frames, see `ttt start --ref`.
+    Ref,
+
+    /// The tags of the frame's project, see [`Attribution`].
+    Tag,
+
+    /// The tasks within the projects, see `ttt task`. Time without a task is listed under the
+    /// project.
+    Task,
+
+    /// The clients of the projects, see `ttt new-client`.
+    Client,
+}
+
+/// Names of the tags, tasks and clients a [`Report`] groups by. Only the names of the
+/// [`Grouping`] in use are needed, the others can be left empty.
+#[derive(Debug, Default)]
+pub struct GroupNames {
+    /// Tags per project id
+    pub tags: HashMap<i32, Vec<Tag>>,
+
+    /// Tasks by id
+    pub tasks: HashMap<i32, Task>,
+
+    /// Clients by id
+    pub clients: HashMap<i32, Client>,
+}
+
+/// How the time of a frame is attributed when its project has several tags, or when it overlaps
+/// other frames, see [`crate::database::Database::allow_concurrent_frames`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Attribution {
+    /// Every tag and overlapping frame gets the full time, so the groups add up to more than the
+    /// total.
+    #[default]
+    Full,
+
+    /// The time is split evenly between the tags and overlapping frames.
+    Split,
+}
+
+impl Grouping {
+    fn heading(self) -> &'static str {
+        match self {
+            Grouping::Project => "Projects",
+            Grouping::Ref => "Tickets",
+            Grouping::Tag => "Tags",
+            Grouping::Task => "Tasks",
+            Grouping::Client => "Clients",
+        }
+    }
+
+    pub fn column(self) -> &'static str {
+        match self {
+            Grouping::Project => "Project",
+            Grouping::Ref => "Ticket",
+            Grouping::Tag => "Tag",
+            Grouping::Task => "Task",
+            Grouping::Client => "Client",
+        }
+    }
+
+    /// Names of the groups time of `project` with the `external_ref` and `task_id` belongs to.
+    fn keys(
+        self,
+        project: &Project,
+        external_ref: Option<&str>,
+        task_id: Option<i32>,
+        names: &GroupNames,
+    ) -> Vec<String> {
+        let keys = match self {
+            Grouping::Project => vec![project.name.clone()],
+            Grouping::Ref => external_ref.iter().map(|r| r.to_string()).collect(),
+            Grouping::Tag => names
+                .tags
+                .get(&project.id())
+                .into_iter()
+                .flatten()
+                .map(|tag| tag.name.clone())
+                .collect(),
+            Grouping::Task => vec![match task_id.and_then(|id| names.tasks.get(&id)) {
+                Some(task) => format!("{}/{}", project.name, task.name),
+                None => project.name.clone(),
+            }],
+            Grouping::Client => project
+                .client_id
+                .and_then(|id| names.clients.get(&id))
+                .map(|client| client.name.clone())
+                .into_iter()
+                .collect(),
+        };
+        if keys.is_empty() {
+            vec!["(none)".to_owned()]
+        } else {
+            keys
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub span: TimeSpan,
+    pub total: Duration,
+    pub by: Grouping,
+
+    /// Time per project, ticket, tag, task or client, the one with the most time first.
+    pub groups: Vec<(String, Duration)>,
+
+    /// Whether frames were counted for several groups, see [`Attribution::Full`].
+    pub double_counted: bool,
+
+    /// Time per day on which anything was tracked, in chronological order.
+    pub days: Vec<(NaiveDate, Duration)>,
+
+    /// Breaks per day, in chronological order. They are not part of the other times, and
+    /// unknown to reports from [`DailyTotal`]s.
+    pub breaks: Vec<(NaiveDate, Duration)>,
+
+    /// Expected compared to tracked time of every day, empty without a [`Schedule`].
+    pub balance: Vec<DayBalance>,
+
+    /// Amount billed per project, the highest first. Empty unless added with
+    /// [`Report::with_billing`].
+    pub billed: Vec<(String, Money)>,
+
+    /// Expected time at the end of a period that is not over yet, see
+    /// [`Report::with_schedule`].
+    pub forecast: Option<Forecast>,
+}
+
+/// Tracked time expected at the end of the period of a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forecast {
+    /// The time tracked so far and the working hours still ahead
+    pub total: Duration,
+
+    /// Working hours of the whole period
+    pub expected: Duration,
+
+    /// Projects with a time budget for the period, with their forecast and the limit, see
+    /// [`Report::with_budgets`].
+    pub budgets: Vec<(String, Duration, Duration)>,
+}
+
+impl Report {
+    /// Sum up `frames` by `by`, looking up the group `names`. Only the part of a frame within
+    /// `span` counts, split between the days it covers. Breaks are summed up separately.
+    pub fn new(
+        span: TimeSpan,
+        frames: &[(Project, Frame)],
+        by: Grouping,
+        attribution: Attribution,
+        names: &GroupNames,
+    ) -> Self {
+        let pieces: Vec<_> = frames
+            .iter()
+            .filter(|(_, frame)| frame.kind != FrameKind::Break)
+            .flat_map(|(project, frame)| {
+                frame
+                    .span()
+                    .and_then(|whole| whole.intersection(&span))
+                    .into_iter()
+                    .flat_map(TimeSpan::split_by_day)
+                    .map(move |piece| (project, frame, piece))
+            })
+            .collect();
+        let exclusive = split_overlaps(pieces.iter().map(|(_, _, piece)| piece));
+        let entries = pieces
+            .iter()
+            .zip(exclusive)
+            .map(|((project, frame, piece), exclusive)| {
+                (
+                    by.keys(project, frame.external_ref.as_deref(), frame.task_id, names),
+                    piece.start().0.date_naive(),
+                    piece.duration(),
+                    exclusive,
+                )
+            });
+        Self::sum(span, by, attribution, entries).with_breaks(frames)
+    }
+
+    /// Sum up the breaks among `frames` per day, only the part within the span counts. Other
+    /// frames are ignored, so reports from [`Report::from_totals`], which know no breaks, can
+    /// add them from [`Database::breaks_in_span`].
+    pub fn with_breaks(mut self, frames: &[(Project, Frame)]) -> Self {
+        let mut days = BTreeMap::<_, Duration>::new();
+        for (_, frame) in frames {
+            if frame.kind != FrameKind::Break {
+                continue;
+            }
+            let pieces = frame
+                .span()
+                .and_then(|whole| whole.intersection(&self.span))
+                .into_iter()
+                .flat_map(TimeSpan::split_by_day);
+            for piece in pieces {
+                let per_day = days
+                    .entry(piece.start().0.date_naive())
+                    .or_insert_with(Duration::zero);
+                *per_day = *per_day + piece.duration();
+            }
+        }
+        self.breaks = days.into_iter().collect();
+        self
+    }
+
+    /// Time per project and day, summed up from the cached [`DailyTotal`]s unless frames may
+    /// overlap, which the totals would count twice, or the span does not cover whole days.
+    pub fn per_project(db: &mut Database, span: TimeSpan) -> Result<Self> {
+        let (by, attribution, names) =
+            (Grouping::Project, Attribution::Full, GroupNames::default());
+        if db.allows_concurrent_frames() || !span.is_whole_days() {
+            let frames = db.get_frames_in_span(span, ArchivedState::Both)?;
+            Ok(Self::new(span, &frames, by, attribution, &names))
+        } else {
+            let totals = db.daily_totals(span)?;
+            let breaks = db.breaks_in_span(span)?;
+            Ok(Self::from_totals(span, &totals, by, attribution, &names).with_breaks(&breaks))
+        }
+    }
+
+    /// Like [`Report::new`], but from the [`DailyTotal`]s cached by the database, which is much
+    /// faster for long spans. Breaks are left out, see [`Report::with_breaks`]. The totals don't
+    /// know the frames, so grouping by [`Grouping::Ref`] puts everything into "(none)", and the
+    /// first and last day count completely even if the span starts or ends within them, see
+    /// [`TimeSpan::is_whole_days`].
+    pub fn from_totals(
+        span: TimeSpan,
+        totals: &[DailyTotal],
+        by: Grouping,
+        attribution: Attribution,
+        names: &GroupNames,
+    ) -> Self {
+        let entries = totals.iter().map(|total| {
+            (
+                by.keys(&total.project, None, None, names),
+                total.day,
+                total.duration,
+                total.duration,
+            )
+        });
+        Self::sum(span, by, attribution, entries)
+    }
+
+    /// Sum up the time of each entry, given with the groups it belongs to, its day, its duration
+    /// and the part of it not overlapping other entries, see [`split_overlaps`].
+    fn sum(
+        span: TimeSpan,
+        by: Grouping,
+        attribution: Attribution,
+        entries: impl Iterator<Item = (Vec<String>, NaiveDate, Duration, Duration)>,
+    ) -> Self {
+        let mut groups = BTreeMap::<_, Duration>::new();
+        let mut days = BTreeMap::<_, Duration>::new();
+        let mut total = Duration::zero();
+        let mut double_counted = false;
+
+        for (keys, day, duration, exclusive) in entries {
+            total = total + exclusive;
+            let share = match attribution {
+                Attribution::Full => {
+                    double_counted |= keys.len() > 1 || exclusive != duration;
+                    duration
+                }
+                Attribution::Split => exclusive / keys.len() as i32,
+            };
+            for key in keys {
+                let per_group = groups.entry(key).or_insert_with(Duration::zero);
+                *per_group = *per_group + share;
+            }
+            let per_day = days.entry(day).or_insert_with(Duration::zero);
+            *per_day = *per_day + exclusive;
+        }
+
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+
+        Self {
+            span,
+            total,
+            by,
+            groups,
+            double_counted,
+            days: days.into_iter().collect(),
+            breaks: Vec::new(),
+            balance: Vec::new(),
+            billed: Vec::new(),
+            forecast: None,
+        }
+    }
+
+    /// Compare the tracked time to the working hours of `schedule`, up to `today`. If the
+    /// period is not over yet, forecast its total assuming the remaining working hours are
+    /// worked as scheduled.
+    pub fn with_schedule(
+        mut self,
+        schedule: &Schedule,
+        leave: &[LeaveDay],
+        today: NaiveDate,
+    ) -> Self {
+        let first = self.span.start().0.date_naive();
+        // The end of the span is exclusive.
+        let end = (self.span.end().0 - Duration::nanoseconds(1)).date_naive();
+        self.balance = schedule.balance(first, end.min(today), &self.days, leave);
+        if (first..=end).contains(&today) {
+            let period = schedule.balance(first, end, &self.days, leave);
+            // What is left of today's working hours counts as well.
+            let ahead = period
+                .iter()
+                .filter(|day| day.day >= today)
+                .fold(Duration::zero(), |sum, day| {
+                    sum + (day.expected - day.worked).max(Duration::zero())
+                });
+            self.forecast = Some(Forecast {
+                total: self.total + ahead,
+                expected: period
+                    .iter()
+                    .fold(Duration::zero(), |sum, day| sum + day.expected),
+                budgets: Vec::new(),
+            });
+        }
+        self
+    }
+
+    /// Compare the forecast of each project to its time budget, if the report covers the current
+    /// period of the budget, see [`crate::budget::period_span`], with weeks starting on
+    /// `week_start`. Projects keep their share of the time until the end of the period. Only for
+    /// reports by project with a forecast.
+    pub fn with_budgets(
+        mut self,
+        budgets: &[(Project, Budget)],
+        today: NaiveDate,
+        week_start: Weekday,
+    ) -> Self {
+        let (Some(forecast), Grouping::Project) = (&mut self.forecast, self.by) else {
+            return self;
+        };
+        for (project, budget) in budgets {
+            let Some(minutes) = budget.minutes else {
+                continue;
+            };
+            if crate::budget::period_span(budget.period, today, week_start) != self.span {
+                continue;
+            }
+            let tracked = self
+                .groups
+                .iter()
+                .find(|(name, _)| *name == project.name)
+                .map_or_else(Duration::zero, |(_, duration)| *duration);
+            let projected = match self.total.num_milliseconds() {
+                0 => tracked,
+                total => Duration::milliseconds(
+                    (tracked.num_milliseconds() as f64 * forecast.total.num_milliseconds() as f64
+                        / total as f64) as i64,
+                ),
+            };
+            forecast.budgets.push((
+                project.name.clone(),
+                projected,
+                Duration::minutes(minutes.into()),
+            ));
+        }
+        self
+    }
+
+    /// Overtime of the whole report, if there is a schedule.
+    pub fn overtime(&self) -> Option<Duration> {
+        if self.balance.is_empty() {
+            return None;
+        }
+        Some(
+            self.balance
+                .iter()
+                .fold(Duration::zero(), |sum, day| sum + day.difference()),
+        )
+    }
+
+    /// Human readable description of the covered days, e.g. "2024-03-04 to 2024-03-10".
+    pub fn title(&self) -> String {
+        describe_days(&self.span)
+    }
+
+    /// Bill the time of `frames` within the span of the report with the rate of their project in
+    /// effect when they started, see [`Project::rate_at`], weighted with the `multipliers`.
+    /// Projects without a rate are left out. `currencies` maps project ids to the currency of
+    /// their rates.
+    pub fn with_billing(
+        mut self,
+        frames: &[(Project, Frame)],
+        rates: &HashMap<i32, Vec<Rate>>,
+        currencies: &HashMap<i32, String>,
+        multipliers: &Multipliers,
+    ) -> Self {
+        // Summed up as cent seconds, so rounding happens only once per project.
+        let mut billed = BTreeMap::<_, f64>::new();
+        for (project, frame, seconds) in multipliers.paid_seconds(frames, &self.span) {
+            let rates = rates.get(&project.id()).map_or(&[][..], Vec::as_slice);
+            if let Some(cents) = project.rate_at(rates, frame.start.0.date_naive()) {
+                *billed
+                    .entry((project.name.clone(), currencies.get(&project.id())))
+                    .or_default() += seconds * f64::from(cents);
+            }
+        }
+        self.billed = billed
+            .into_iter()
+            .map(|((project, currency), cent_seconds)| {
+                let cents = (cent_seconds / 3600.0).round() as i64;
+                (project, Money::new(cents, currency.map(String::as_str)))
+            })
+            .collect();
+        self.billed
+            .sort_by_key(|(_, money)| std::cmp::Reverse(money.cents));
+        self
+    }
+
+    /// Sum of [`Report::billed`] per currency, amounts in different currencies can't be added up.
+    pub fn billed_totals(&self) -> Vec<Money> {
+        let mut totals = BTreeMap::<_, i64>::new();
+        for (_, money) in &self.billed {
+            *totals.entry(money.currency.as_deref()).or_default() += money.cents;
+        }
+        totals
+            .into_iter()
+            .map(|(currency, cents)| Money::new(cents, currency))
+            .collect()
+    }
+
+    /// Time spent on breaks, not included in [`Report::total`].
+    pub fn break_total(&self) -> Duration {
+        self.breaks
+            .iter()
+            .fold(Duration::zero(), |sum, (_, duration)| sum + *duration)
+    }
+
+    /// The breaks of `day` for the days listings, e.g. " (break 45min)".
+    fn break_suffix(&self, day: NaiveDate, durations: DurationFormat) -> String {
+        match self.breaks.iter().find(|(other, _)| *other == day) {
+            Some((_, duration)) => format!(" (break {})", duration.format_as(durations)),
+            None => String::new(),
+        }
+    }
+
+    /// Share of `duration` in the total time, in percent.
+    pub fn percentage(&self, duration: Duration) -> i64 {
+        // Frames shorter than a millisecond would otherwise divide by zero.
+        if self.total.num_milliseconds() == 0 {
+            return 0;
+        }
+        duration.num_milliseconds() * 100 / self.total.num_milliseconds()
+    }
+
+    pub fn to_text(&self, durations: DurationFormat) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Tracked time {}", self.title());
+        let _ = writeln!(out, "Total: {}", self.total.format_as(durations));
+        if !self.breaks.is_empty() {
+            let _ = writeln!(
+                out,
+                "Breaks: {}, not included",
+                self.break_total().format_as(durations)
+            );
+        }
+        if let Some(forecast) = &self.forecast {
+            let _ = writeln!(
+                out,
+                "Forecast: {} of {} expected",
+                forecast.total.format_as(durations),
+                forecast.expected.format_as(durations)
+            );
+            for (project, projected, limit) in &forecast.budgets {
+                let over = if projected > limit {
+                    ", over budget"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "  {project}: {} of {} budget{over}",
+                    projected.format_as(durations),
+                    limit.format_as(durations)
+                );
+            }
+        }
+
+        if !self.groups.is_empty() {
+            let _ = writeln!(out, "\n{}:", self.by.heading());
+            for (name, duration) in &self.groups {
+                let _ = writeln!(
+                    out,
+                    "  {name}: {} ({}%)",
+                    duration.format_as(durations),
+                    self.percentage(*duration)
+                );
+            }
+            if self.double_counted {
+                let _ = writeln!(out, "{DOUBLE_COUNTED}");
+            }
+        }
+
+        if !self.billed.is_empty() {
+            let _ = writeln!(out, "\nBilled:");
+            for (project, money) in &self.billed {
+                let _ = writeln!(out, "  {project}: {money}");
+            }
+            let _ = writeln!(out, "\nTotal billed:");
+            for total in self.billed_totals() {
+                let _ = writeln!(out, "  {total}");
+            }
+        }
+
+        if let Some(overtime) = self.overtime() {
+            let _ = writeln!(out, "\nDays:");
+            for day in self.balance.iter().filter(|day| day.is_relevant()) {
+                let _ = writeln!(
+                    out,
+                    "  {}: {} ({})",
+                    day.day.format("%a %Y-%m-%d"),
+                    day.worked.format_as(durations),
+                    format_difference(day.difference(), durations)
+                );
+            }
+            let _ = writeln!(out, "\nWeeks:");
+            for week in schedule::weeks(&self.balance) {
+                let _ = writeln!(
+                    out,
+                    "  {}: {} of {} ({})",
+                    week.day,
+                    week.worked.format_as(durations),
+                    week.expected.format_as(durations),
+                    format_difference(week.difference(), durations)
+                );
+            }
+            let _ = writeln!(out, "Overtime: {}", format_difference(overtime, durations));
+        } else if !self.days.is_empty() {
+            let _ = writeln!(out, "\nDays:");
+            for (day, duration) in &self.days {
+                let _ = writeln!(
+                    out,
+                    "  {}: {}{}",
+                    day.format("%a %Y-%m-%d"),
+                    duration.format_as(durations),
+                    self.break_suffix(*day, durations)
+                );
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(&self, durations: DurationFormat) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Tracked time {}\n", self.title());
+        let _ = writeln!(out, "**Total:** {}", self.total.format_as(durations));
+        if !self.breaks.is_empty() {
+            let _ = writeln!(
+                out,
+                "\n**Breaks:** {}, not included",
+                self.break_total().format_as(durations)
+            );
+        }
+        if let Some(forecast) = &self.forecast {
+            let _ = writeln!(
+                out,
+                "\n**Forecast:** {} of {} expected",
+                forecast.total.format_as(durations),
+                forecast.expected.format_as(durations)
+            );
+            for (project, projected, limit) in &forecast.budgets {
+                let over = if projected > limit {
+                    ", over budget"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "- {}: {} of {} budget{over}",
+                    escape_markdown(project),
+                    projected.format_as(durations),
+                    limit.format_as(durations)
+                );
+            }
+        }
+
+        if !self.groups.is_empty() {
+            let _ = writeln!(out, "\n## {}\n", self.by.heading());
+            let _ = writeln!(out, "| {} | Time | Share |", self.by.column());
+            let _ = writeln!(out, "|---|---:|---:|");
+            for (name, duration) in &self.groups {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {}% |",
+                    escape_markdown(name),
+                    duration.format_as(durations),
+                    self.percentage(*duration)
+                );
+            }
+            if self.double_counted {
+                let _ = writeln!(out, "\n{DOUBLE_COUNTED}");
+            }
+        }
+
+        if !self.billed.is_empty() {
+            let _ = writeln!(out, "\n## Billed\n");
+            let _ = writeln!(out, "| Project | Amount |");
+            let _ = writeln!(out, "|---|---:|");
+            for (project, money) in &self.billed {
+                let _ = writeln!(out, "| {} | {money} |", escape_markdown(project));
+            }
+            for total in self.billed_totals() {
+                let _ = writeln!(out, "| **Total** | **{total}** |");
+            }
+        }
+
+        if let Some(overtime) = self.overtime() {
+            let _ = writeln!(out, "\n## Days\n");
+            let _ = writeln!(out, "| Day | Time | Expected | Overtime |");
+            let _ = writeln!(out, "|---|---:|---:|---:|");
+            for day in self.balance.iter().filter(|day| day.is_relevant()) {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    day.day.format("%a %Y-%m-%d"),
+                    day.worked.format_as(durations),
+                    day.expected.format_as(durations),
+                    format_difference(day.difference(), durations)
+                );
+            }
+            let _ = writeln!(out, "\n## Weeks\n");
+            let _ = writeln!(out, "| Week of | Time | Expected | Overtime |");
+            let _ = writeln!(out, "|---|---:|---:|---:|");
+            for week in schedule::weeks(&self.balance) {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    week.day,
+                    week.worked.format_as(durations),
+                    week.expected.format_as(durations),
+                    format_difference(week.difference(), durations)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "\n**Overtime:** {}",
+                format_difference(overtime, durations)
+            );
+        } else if !self.days.is_empty() {
+            let _ = writeln!(out, "\n## Days\n");
+            let _ = writeln!(out, "| Day | Time |");
+            let _ = writeln!(out, "|---|---:|");
+            for (day, duration) in &self.days {
+                let _ = writeln!(
+                    out,
+                    "| {} | {}{} |",
+                    day.format("%a %Y-%m-%d"),
+                    duration.format_as(durations),
+                    self.break_suffix(*day, durations)
+                );
+            }
+        }
+        out
+    }
+
+    /// The report as JSON, durations are ISO 8601 and timestamps RFC 3339 with offset.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.json()).expect("Reports are serializable") + "\n"
+    }
+
+    /// The report in the shape of `--format json`, e.g. for the GUI.
+    pub fn json(&self) -> JsonReport {
+        JsonReport {
+            span: self.span,
+            total: self.total,
+            groups: self
+                .groups
+                .iter()
+                .map(|(name, duration)| JsonGroup {
+                    name: name.clone(),
+                    duration: *duration,
+                    share: self.percentage(*duration),
+                })
+                .collect(),
+            double_counted: self.double_counted,
+            days: self
+                .days
+                .iter()
+                .map(|&(day, duration)| JsonDay { day, duration })
+                .collect(),
+            breaks: self
+                .breaks
+                .iter()
+                .map(|&(day, duration)| JsonDay { day, duration })
+                .collect(),
+            billed: self
+                .billed
+                .iter()
+                .map(|(project, money)| JsonAmount {
+                    project: Some(project.clone()),
+                    cents: money.cents,
+                    currency: money.currency.clone(),
+                })
+                .collect(),
+            billed_totals: self
+                .billed_totals()
+                .into_iter()
+                .map(|total| JsonAmount {
+                    project: None,
+                    cents: total.cents,
+                    currency: total.currency,
+                })
+                .collect(),
+            forecast: self.forecast.as_ref().map(|forecast| JsonForecast {
+                total: forecast.total,
+                expected: forecast.expected,
+                budgets: forecast
+                    .budgets
+                    .iter()
+                    .map(|(project, forecast, limit)| JsonBudgetForecast {
+                        project: project.clone(),
+                        forecast: *forecast,
+                        limit: *limit,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    /// The report as standalone HTML page with bar charts of the groups and days, e.g. to send
+    /// it to a client.
+    pub fn to_html(&self, durations: DurationFormat) -> String {
+        let mut out = String::new();
+        let title = format!("Tracked time {}", self.title());
+        let _ = writeln!(out, "<!DOCTYPE html>");
+        let _ = writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">");
+        let _ = writeln!(out, "<title>{}</title>", escape_html(&title));
+        let _ = writeln!(out, "<style>{HTML_STYLE}</style>\n</head>\n<body>");
+        let _ = writeln!(out, "<h1>{}</h1>", escape_html(&title));
+        let _ = writeln!(
+            out,
+            "<p><strong>Total:</strong> {}</p>",
+            self.total.format_as(durations)
+        );
+        if !self.breaks.is_empty() {
+            let _ = writeln!(
+                out,
+                "<p><strong>Breaks:</strong> {}, not included</p>",
+                self.break_total().format_as(durations)
+            );
+        }
+
+        if !self.groups.is_empty() {
+            let _ = writeln!(out, "<h2>{}</h2>", self.by.heading());
+            out.push_str(&bar_chart(&self.groups, durations));
+            let _ = writeln!(
+                out,
+                "<table>\n<tr><th>{}</th><th>Time</th><th>Share</th></tr>",
+                self.by.column()
+            );
+            for (name, duration) in &self.groups {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}%</td></tr>",
+                    escape_html(name),
+                    duration.format_as(durations),
+                    self.percentage(*duration)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+            if self.double_counted {
+                let _ = writeln!(out, "<p>{DOUBLE_COUNTED}</p>");
+            }
+        }
+
+        if !self.billed.is_empty() {
+            let _ = writeln!(out, "<h2>Billed</h2>");
+            let _ = writeln!(out, "<table>\n<tr><th>Project</th><th>Amount</th></tr>");
+            for (project, money) in &self.billed {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape_html(project),
+                    escape_html(&money.to_string())
+                );
+            }
+            for total in self.billed_totals() {
+                let _ = writeln!(
+                    out,
+                    "<tr><th>Total</th><th>{}</th></tr>",
+                    escape_html(&total.to_string())
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+
+        if !self.days.is_empty() {
+            let days: Vec<_> = self
+                .days
+                .iter()
+                .map(|(day, duration)| (day.format("%a %Y-%m-%d").to_string(), *duration))
+                .collect();
+            let _ = writeln!(out, "<h2>Days</h2>");
+            out.push_str(&bar_chart(&days, durations));
+            let _ = writeln!(out, "<table>\n<tr><th>Day</th><th>Time</th></tr>");
+            for (day, duration) in &days {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{day}</td><td>{}</td></tr>",
+                    duration.format_as(durations)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+
+    /// The markdown report as an email, ready to be piped into `sendmail -t`.
+    pub fn to_email(&self, to: Option<&str>, durations: DurationFormat) -> String {
+        let mut out = String::new();
+        if let Some(to) = to {
+            let _ = writeln!(out, "To: {to}");
+        }
+        let _ = writeln!(out, "Subject: Tracked time {}", self.title());
+        let _ = writeln!(out, "MIME-Version: 1.0");
+        let _ = writeln!(out, "Content-Type: text/markdown; charset=utf-8");
+        let _ = writeln!(out);
+        out.push_str(&self.to_markdown(durations));
+        out
+    }
+}
+
+/// Estimated compared to tracked time of a project, see `ttt project estimates`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Estimate {
+    pub project: String,
+    pub estimate: Duration,
+    pub tracked: Duration,
+}
+
+impl Estimate {
+    /// Compare the estimates of all `projects` that have one with the time tracked in `frames`.
+    pub fn compare(projects: &[Project], frames: &[Frame]) -> Vec<Self> {
+        projects
+            .iter()
+            .filter_map(|project| {
+                let tracked = frames
+                    .iter()
+                    .filter(|frame| frame.project == project.id() && frame.kind != FrameKind::Break)
+                    .fold(Duration::zero(), |sum, frame| sum + frame.duration());
+                Some(Self {
+                    project: project.name.clone(),
+                    estimate: project.estimate()?,
+                    tracked,
+                })
+            })
+            .collect()
+    }
+
+    /// How much the tracked time deviates from the estimate in percent, positive when more time
+    /// was needed than estimated. `None` for an estimate of zero.
+    pub fn deviation(&self) -> Option<i64> {
+        if self.estimate.is_zero() {
+            return None;
+        }
+        let difference = self.tracked.num_seconds() - self.estimate.num_seconds();
+        Some(difference * 100 / self.estimate.num_seconds())
+    }
+}
+
+/// The time of one group in two time spans, see [`Comparison`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub name: String,
+    #[serde(serialize_with = "serialize_duration")]
+    pub current: Duration,
+    #[serde(serialize_with = "serialize_duration")]
+    pub previous: Duration,
+}
+
+impl Change {
+    /// How much more time the current span has, negative for less.
+    pub fn difference(&self) -> Duration {
+        self.current - self.previous
+    }
+
+    /// The difference in percent of the previous time. `None` if nothing was tracked before.
+    pub fn percentage(&self) -> Option<i64> {
+        let previous = self.previous.num_milliseconds();
+        (previous != 0).then(|| self.difference().num_milliseconds() * 100 / previous)
+    }
+}
+
+/// Two reports side by side, e.g. this week compared to last week, see `ttt compare`.
+#[derive(Debug)]
+pub struct Comparison {
+    pub by: Grouping,
+    pub current: TimeSpan,
+    pub previous: TimeSpan,
+
+    /// Every group of either report, the biggest difference first.
+    pub changes: Vec<Change>,
+    pub total: Change,
+}
+
+impl Comparison {
+    /// Compare the `current` report to the `previous` one, both grouped by the same [`Grouping`].
+    pub fn new(current: &Report, previous: &Report) -> Self {
+        let mut groups = BTreeMap::<_, (Duration, Duration)>::new();
+        for (name, duration) in &current.groups {
+            groups
+                .entry(name)
+                .or_insert((Duration::zero(), Duration::zero()))
+                .0 = *duration;
+        }
+        for (name, duration) in &previous.groups {
+            groups
+                .entry(name)
+                .or_insert((Duration::zero(), Duration::zero()))
+                .1 = *duration;
+        }
+        let mut changes: Vec<_> = groups
+            .into_iter()
+            .map(|(name, (current, previous))| Change {
+                name: name.clone(),
+                current,
+                previous,
+            })
+            .collect();
+        // Stable, so groups with the same difference stay in alphabetical order.
+        changes.sort_by_key(|change| std::cmp::Reverse(change.difference().abs()));
+        Self {
+            by: current.by,
+            current: current.span,
+            previous: previous.span,
+            changes,
+            total: Change {
+                name: "Total".to_owned(),
+                current: current.total,
+                previous: previous.total,
+            },
+        }
+    }
+
+    /// A row per group and one for the total, with the days of the spans as column headers.
+    pub fn to_table(&self, durations: DurationFormat) -> Table {
+        let mut table = Table::with_columns(vec![
+            (self.by.column().to_owned(), Align::Left),
+            (describe_days(&self.current), Align::Right),
+            (describe_days(&self.previous), Align::Right),
+            ("Difference".to_owned(), Align::Right),
+            ("Change".to_owned(), Align::Right),
+        ]);
+        for change in self.changes.iter().chain([&self.total]) {
+            let percentage = match change.percentage() {
+                Some(percentage) => format!("{percentage:+}%"),
+                None if change.current.is_zero() => String::new(),
+                None => "new".to_owned(),
+            };
+            table.add_row(vec![
+                change.name.clone(),
+                change.current.format_as(durations),
+                change.previous.format_as(durations),
+                format_difference(change.difference(), durations),
+                percentage,
+            ]);
+        }
+        table
+    }
+
+    /// The comparison as JSON, durations are ISO 8601 and `change` is the percentage.
+    pub fn to_json(&self) -> String {
+        let change = |change: &Change| JsonChange {
+            change: change.percentage(),
+            difference: change.difference(),
+            times: change.clone(),
+        };
+        let json = JsonComparison {
+            current: self.current,
+            previous: self.previous,
+            groups: self.changes.iter().map(change).collect(),
+            total: change(&self.total),
+        };
+        serde_json::to_string_pretty(&json).expect("Comparisons are serializable") + "\n"
+    }
+}
+
+#[derive(Serialize)]
+struct JsonComparison {
+    current: TimeSpan,
+    previous: TimeSpan,
+    groups: Vec<JsonChange>,
+    total: JsonChange,
+}
+
+#[derive(Serialize)]
+struct JsonChange {
+    #[serde(flatten)]
+    times: Change,
+    #[serde(serialize_with = "serialize_duration")]
+    difference: Duration,
+    change: Option<i64>,
+}
+
+/// Shape of the report in `--format json`.
+#[derive(Serialize)]
+#[typeshare]
+pub struct JsonReport {
+    span: TimeSpan,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    total: Duration,
+    /// The groups of the report, called `projects` as before there were other groupings.
+    #[serde(rename = "projects")]
+    groups: Vec<JsonGroup>,
+    double_counted: bool,
+    days: Vec<JsonDay>,
+    /// Break time per day, not included in the other times.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    breaks: Vec<JsonDay>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    billed: Vec<JsonAmount>,
+    /// Sum of `billed` per currency.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    billed_totals: Vec<JsonAmount>,
+    /// Only for periods that are not over yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forecast: Option<JsonForecast>,
+}
+
+#[derive(Serialize)]
+#[typeshare]
+struct JsonForecast {
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    total: Duration,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    expected: Duration,
+    /// Forecast of projects with a time budget.
+    budgets: Vec<JsonBudgetForecast>,
+}
+
+#[derive(Serialize)]
+#[typeshare]
+struct JsonBudgetForecast {
+    project: String,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    forecast: Duration,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    limit: Duration,
+}
+
+#[derive(Serialize)]
+#[typeshare]
+struct JsonGroup {
+    /// Name of the project or ticket.
+    name: String,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    duration: Duration,
+    /// Share of the total time in percent.
+    #[typeshare(serialized_as = "I54")]
+    share: i64,
+}
+
+#[derive(Serialize)]
+#[typeshare]
+struct JsonDay {
+    /// ISO 8601 date, e.g. "2024-03-04".
+    #[typeshare(serialized_as = "String")]
+    day: NaiveDate,
+    #[serde(serialize_with = "serialize_duration")]
+    #[typeshare(serialized_as = "String")]
+    duration: Duration,
+}
+
+#[derive(Serialize)]
+#[typeshare]
+struct JsonAmount {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    /// Billed amount in cents.
+    #[typeshare(serialized_as = "I54")]
+    cents: i64,
+    /// ISO 4217 code, e.g. "EUR", if the currency is known.
+    currency: Option<String>,
+}
+
+/// An amount of money in cents, in the currency with the ISO 4217 code, if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    pub cents: i64,
+    pub currency: Option<String>,
+}
+
+impl Money {
+    pub fn new(cents: i64, currency: Option<&str>) -> Self {
+        Self {
+            cents,
+            currency: currency.map(str::to_owned),
+        }
+    }
+}
+
+/// Common currencies are written with their symbol in front, e.g. "€85.50", others with their
+/// code behind the amount, e.g. "85.50 CHF".
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let amount = format!("{}.{:02}", self.cents / 100, self.cents % 100);
+        match self.currency.as_deref() {
+            Some("EUR") => write!(f, "€{amount}"),
+            Some("USD") => write!(f, "${amount}"),
+            Some("GBP") => write!(f, "£{amount}"),
+            Some(code) => write!(f, "{amount} {code}"),
+            None => f.write_str(&amount),
+        }
+    }
+}
+
+/// Higher pay for overtime and time on call in [`Report::with_billing`], e.g. one and a half
+/// times the rate after eight hours of work a day:
+///
+/// ```toml
+/// [multipliers]
+/// overtime_after_hours = 8
+/// overtime = 1.5
+/// on_call = 0.5
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Multipliers {
+    /// Factor of the rate for frames marked as on call, see `ttt mark`
+    pub on_call: f64,
+
+    /// Hours of work per day paid with the plain rate, all of them if not set
+    pub overtime_after_hours: Option<f64>,
+
+    /// Factor of the rate for work beyond `overtime_after_hours`
+    pub overtime: f64,
+}
+
+impl Default for Multipliers {
+    fn default() -> Self {
+        Self {
+            on_call: 1.0,
+            overtime_after_hours: None,
+            overtime: 1.0,
+        }
+    }
+}
+
+impl Multipliers {
+    /// The seconds within `span` to pay for each frame that is not a break. Frames are split at
+    /// midnight and worked through in chronological order, so the last work of a day is the
+    /// overtime. On-call time does not count towards the hours of a day.
+    fn paid_seconds<'a>(
+        &self,
+        frames: &'a [(Project, Frame)],
+        span: &TimeSpan,
+    ) -> Vec<(&'a Project, &'a Frame, f64)> {
+        let mut pieces: Vec<_> = frames
+            .iter()
+            .filter(|(_, frame)| frame.kind != FrameKind::Break)
+            .flat_map(|(project, frame)| {
+                frame
+                    .span()
+                    .and_then(|whole| whole.intersection(span))
+                    .into_iter()
+                    .flat_map(TimeSpan::split_by_day)
+                    .map(move |piece| (project, frame, piece))
+            })
+            .collect();
+        pieces.sort_by_key(|(_, _, piece)| piece.start());
+
+        let threshold = self.overtime_after_hours.map(|hours| hours * 3600.0);
+        let mut day = None;
+        let mut worked = 0.0;
+        pieces
+            .into_iter()
+            .map(|(project, frame, piece)| {
+                let seconds = piece.duration().num_seconds() as f64;
+                if frame.kind == FrameKind::OnCall {
+                    return (project, frame, seconds * self.on_call);
+                }
+                let date = piece.start().0.date_naive();
+                if day != Some(date) {
+                    day = Some(date);
+                    worked = 0.0;
+                }
+                let regular = threshold.map_or(seconds, |threshold| {
+                    (threshold - worked).clamp(0.0, seconds)
+                });
+                worked += seconds;
+                (
+                    project,
+                    frame,
+                    regular + (seconds - regular) * self.overtime,
+                )
+            })
+            .collect()
+    }
+}
+
+/// The time of each span, with time covered by several spans split evenly between them, so the
+/// results add up to the time covered by any span.
+fn split_overlaps<'a>(spans: impl Iterator<Item = &'a TimeSpan>) -> Vec<Duration> {
+    let mut events = Vec::new();
+    for (index, span) in spans.enumerate() {
+        events.push((span.start().0, true, index));
+        events.push((span.end().0, false, index));
+    }
+    // Ends come before starts at the same time, so adjacent frames don't overlap.
+    events.sort_by_key(|&(time, is_start, _)| (time, is_start));
+
+    let mut shares = vec![Duration::zero(); events.len() / 2];
+    let mut running = Vec::new();
+    let mut previous = None;
+    for (time, is_start, index) in events {
+        if let Some(previous) = previous.filter(|_| !running.is_empty()) {
+            let share = (time - previous) / running.len() as i32;
+            for &frame in &running {
+                shares[frame] = shares[frame] + share;
+            }
+        }
+        previous = Some(time);
+        if is_start {
+            running.push(index);
+        } else {
+            running.retain(|&frame| frame != index);
+        }
+    }
+    shares
+}
+
+/// Describe the days covered by `span`, e.g. "2024-03-04 to 2024-03-10".
+pub fn describe_days(span: &TimeSpan) -> String {
+    let first = span.start().0.date_naive();
+    // The end of the span is exclusive.
+    let last = (span.end().0 - Duration::nanoseconds(1)).date_naive();
+    if first == last {
+        first.to_string()
+    } else {
+        format!("{first} to {last}")
+    }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 50em; margin: 2em auto; } \
+    table { border-collapse: collapse; margin: 1em 0; } \
+    th, td { padding: 0.2em 1em; border-bottom: 1px solid #ddd; text-align: left; } \
+    td + td { text-align: right; } \
+    svg text { font-size: 12px; }";
+
+/// Height of a bar in the charts of the HTML report, in pixels.
+const BAR_HEIGHT: usize = 20;
+
+/// Horizontal bar chart of labelled durations as inline SVG.
+fn bar_chart(bars: &[(String, Duration)], durations: DurationFormat) -> String {
+    let (label_width, bar_width): (i64, i64) = (160, 400);
+    let longest = bars
+        .iter()
+        .map(|(_, duration)| duration.num_milliseconds())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+        label_width + bar_width + 100,
+        bars.len() * (BAR_HEIGHT + 4)
+    );
+    for (index, (label, duration)) in bars.iter().enumerate() {
+        let y = index * (BAR_HEIGHT + 4);
+        let width = duration.num_milliseconds() * bar_width / longest;
+        let _ = writeln!(
+            svg,
+            "<text x=\"0\" y=\"{}\">{}</text>\
+             <rect x=\"{label_width}\" y=\"{y}\" width=\"{width}\" height=\"{BAR_HEIGHT}\" \
+             fill=\"#4a7ebb\"/>\
+             <text x=\"{}\" y=\"{}\">{}</text>",
+            y + 15,
+            escape_html(label),
+            label_width + width + 5,
+            y + 15,
+            duration.format_as(durations)
+        );
+    }
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// Escape characters with a special meaning in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape characters that would break a markdown table cell.
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{BudgetPeriod, FrameSource, Timestamp};
+
+    fn frames() -> Vec<(Project, Frame)> {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let client = db.create_project("client|x").unwrap();
+
+        let eight = Timestamp::from_ymdhms(2024, 3, 4, 8, 0, 0);
+        let eleven = Timestamp::from_ymdhms(2024, 3, 4, 11, 0, 0);
+        let twelve = Timestamp::from_ymdhms(2024, 3, 4, 12, 0, 0);
+        db.add_test_frames(&[
+            (&website, eight, Some(eleven)),
+            (&client, eleven, Some(twelve)),
+        ])
+        .unwrap();
+
+        let mut frames = db.get_frames_in_span(week(), ArchivedState::Both).unwrap();
+        frames[0].1.external_ref = Some("WEB-1".to_owned());
+        frames
+    }
+
+    fn week() -> TimeSpan {
+        TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 3, 11, 0, 0, 0),
+        )
+        .unwrap()
+    }
+
+    fn report(by: Grouping, attribution: Attribution) -> Report {
+        let frames = frames();
+        let mut tags = HashMap::new();
+        for (project, _) in &frames {
+            let names: &[&str] = match project.name.as_str() {
+                "website" => &["billable", "frontend"],
+                _ => &["billable"],
+            };
+            tags.insert(
+                project.id(),
+                names.iter().map(|name| tag(name)).collect::<Vec<_>>(),
+            );
+        }
+        let names = GroupNames {
+            tags,
+            ..Default::default()
+        };
+        Report::new(week(), &frames, by, attribution, &names)
+    }
+
+    fn tag(name: &str) -> Tag {
+        let mut db = Database::open_in_memory().unwrap();
+        db.create_tag(name).unwrap()
+    }
+
+    #[test]
+    fn test_report_sums_up_projects_and_days() {
+        let report = report(Grouping::Project, Attribution::Full);
+
+        assert_eq!(report.total, Duration::hours(4));
+        assert_eq!(
+            report.groups,
+            vec![
+                ("website".to_owned(), Duration::hours(3)),
+                ("client|x".to_owned(), Duration::hours(1))
+            ]
+        );
+        assert_eq!(
+            report.days,
+            vec![(
+                NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+                Duration::hours(4)
+            )]
+        );
+        assert_eq!(report.title(), "2024-03-04 to 2024-03-10");
+    }
+
+    #[test]
+    fn test_breaks_are_summed_up_separately() {
+        let mut frames = frames();
+        frames[1].0.hourly_rate_cents = Some(6000);
+        let (project, mut lunch) = frames[1].clone();
+        lunch.start = Timestamp::from_ymdhms(2024, 3, 4, 12, 0, 0);
+        lunch.end = Some(Timestamp::from_ymdhms(2024, 3, 4, 12, 30, 0));
+        lunch.kind = FrameKind::Break;
+        frames.push((project, lunch));
+        let report = Report::new(
+            week(),
+            &frames,
+            Grouping::Project,
+            Attribution::Full,
+            &GroupNames::default(),
+        )
+        .with_billing(
+            &frames,
+            &HashMap::new(),
+            &HashMap::new(),
+            &Multipliers::default(),
+        );
+
+        assert_eq!(report.total, Duration::hours(4));
+        assert_eq!(
+            report.billed,
+            vec![("client|x".to_owned(), Money::new(6000, None))]
+        );
+        assert_eq!(
+            report.groups[1],
+            ("client|x".to_owned(), Duration::hours(1))
+        );
+        assert_eq!(report.break_total(), Duration::minutes(30));
+        let text = report.to_text(DurationFormat::Hms);
+        assert!(text.contains("Breaks: 30min, not included\n"));
+        assert!(text.contains("Mon 2024-03-04: 4h (break 30min)\n"));
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let report = report(Grouping::Project, Attribution::Full);
+
+        let markdown = report.to_markdown(DurationFormat::Hms);
+        assert!(markdown.starts_with("# Tracked time 2024-03-04 to 2024-03-10\n"));
+        assert!(markdown.contains("| website | 3h | 75% |"));
+        assert!(markdown.contains("| client\\|x | 1h | 25% |"));
+        assert!(markdown.contains("| Mon 2024-03-04 | 4h |"));
+
+        let email = report.to_email(Some("me@example.com"), DurationFormat::Hms);
+        assert!(email.starts_with("To: me@example.com\nSubject: Tracked time 2024-03-04"));
+        assert!(email.ends_with(&markdown));
+    }
+
+    #[test]
+    fn test_report_duration_formats() {
+        let report = report(Grouping::Project, Attribution::Full);
+
+        let clock = report.to_text(DurationFormat::Clock);
+        assert!(clock.contains("Total: 4:00\n"));
+        assert!(clock.contains("  website: 3:00 (75%)"));
+
+        let decimal = report.to_markdown(DurationFormat::Decimal);
+        assert!(decimal.contains("| client\\|x | 1.00h | 25% |"));
+    }
+
+    #[test]
+    fn test_report_with_schedule() {
+        let schedule: Schedule = toml::from_str("monday = 3\ntuesday = 2").unwrap();
+        let wednesday = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap();
+        let report =
+            report(Grouping::Project, Attribution::Full).with_schedule(&schedule, &[], wednesday);
+
+        assert_eq!(report.overtime(), Some(Duration::hours(-1)));
+        let text = report.to_text(DurationFormat::Clock);
+        assert!(text.contains("  Mon 2024-03-04: 4:00 (+1:00)\n  Tue 2024-03-05: 0:00 (-2:00)\n"));
+        assert!(text.contains("  2024-03-04: 4:00 of 5:00 (-1:00)\nOvertime: -1:00\n"));
+        let markdown = report.to_markdown(DurationFormat::Clock);
+        assert!(markdown.contains("| Mon 2024-03-04 | 4:00 | 3:00 | +1:00 |"));
+    }
+
+    #[test]
+    fn test_forecast() {
+        let schedule: Schedule = toml::from_str("monday = 3\ntuesday = 2\nwednesday = 4").unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let frames = frames();
+        let budget = |(project, _): &(Project, Frame), minutes| {
+            let budget = Budget {
+                project_id: project.id(),
+                minutes: Some(minutes),
+                cents: None,
+                period: BudgetPeriod::Week,
+            };
+            (project.clone(), budget)
+        };
+        let budgets = [budget(&frames[0], 600), budget(&frames[1], 60)];
+        let current = report(Grouping::Project, Attribution::Full)
+            .with_schedule(&schedule, &[], tuesday)
+            .with_budgets(&budgets, tuesday, Weekday::Mon);
+
+        // Four hours so far, and the whole of tuesday and wednesday ahead.
+        let forecast = current.forecast.as_ref().unwrap();
+        assert_eq!(forecast.total, Duration::hours(10));
+        assert_eq!(forecast.expected, Duration::hours(9));
+        let text = current.to_text(DurationFormat::Clock);
+        assert!(text.contains("Forecast: 10:00 of 9:00 expected\n"));
+        assert!(text.contains("  website: 7:30 of 10:00 budget\n"));
+        assert!(text.contains("  client|x: 2:30 of 1:00 budget, over budget\n"));
+
+        // Periods that are over have no forecast.
+        let later = NaiveDate::from_ymd_opt(2024, 3, 11).unwrap();
+        let over =
+            report(Grouping::Project, Attribution::Full).with_schedule(&schedule, &[], later);
+        assert_eq!(over.forecast, None);
+    }
+
+    #[test]
+    fn test_html_report() {
+        let html = report(Grouping::Project, Attribution::Full).to_html(DurationFormat::Hms);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<tr><td>website</td><td>3h</td><td>75%</td></tr>"));
+        assert!(html.contains(">client|x</text>"));
+        // The longest bar fills the chart, the others are scaled accordingly.
+        assert!(html.contains("width=\"400\""));
+        assert!(html.contains("width=\"133\""));
+        assert!(html.contains("<tr><td>Mon 2024-03-04</td><td>4h</td></tr>"));
+    }
+
+    #[test]
+    fn test_report_by_ticket() {
+        let report = report(Grouping::Ref, Attribution::Full);
+
+        assert_eq!(
+            report.groups,
+            vec![
+                ("WEB-1".to_owned(), Duration::hours(3)),
+                ("(none)".to_owned(), Duration::hours(1))
+            ]
+        );
+        assert!(report
+            .to_markdown(DurationFormat::Hms)
+            .contains("| Ticket | Time | Share |"));
+    }
+
+    #[test]
+    fn test_report_by_tag() {
+        let full = report(Grouping::Tag, Attribution::Full);
+        assert_eq!(
+            full.groups,
+            vec![
+                ("billable".to_owned(), Duration::hours(4)),
+                ("frontend".to_owned(), Duration::hours(3))
+            ]
+        );
+        assert!(full.to_text(DurationFormat::Hms).contains(DOUBLE_COUNTED));
+
+        let split = report(Grouping::Tag, Attribution::Split);
+        assert_eq!(
+            split.groups,
+            vec![
+                ("billable".to_owned(), Duration::minutes(150)),
+                ("frontend".to_owned(), Duration::minutes(90))
+            ]
+        );
+        assert!(!split.double_counted);
+    }
+
+    #[test]
+    fn test_overlapping_frames() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.allow_concurrent_frames();
+        let on_call = db.create_project("on-call").unwrap();
+        let meeting = db.create_project("meeting").unwrap();
+        let eight = Timestamp::from_ymdhms(2024, 3, 4, 8, 0, 0);
+        let ten = Timestamp::from_ymdhms(2024, 3, 4, 10, 0, 0);
+        let eleven = Timestamp::from_ymdhms(2024, 3, 4, 11, 0, 0);
+        let twelve = Timestamp::from_ymdhms(2024, 3, 4, 12, 0, 0);
+        db.add_test_frames(&[
+            (&on_call, eight, Some(eleven)),
+            (&meeting, ten, Some(twelve)),
+        ])
+        .unwrap();
+        let frames = db.get_frames_in_span(week(), ArchivedState::Both).unwrap();
+        let report = |attribution| {
+            Report::new(
+                week(),
+                &frames,
+                Grouping::Project,
+                attribution,
+                &GroupNames::default(),
+            )
+        };
+
+        let full = report(Attribution::Full);
+        assert_eq!(full.total, Duration::hours(4));
+        assert_eq!(
+            full.groups,
+            vec![
+                ("on-call".to_owned(), Duration::hours(3)),
+                ("meeting".to_owned(), Duration::hours(2))
+            ]
+        );
+        assert!(full.double_counted);
+
+        let split = report(Attribution::Split);
+        assert_eq!(split.total, Duration::hours(4));
+        assert_eq!(
+            split.groups,
+            vec![
+                ("on-call".to_owned(), Duration::minutes(150)),
+                ("meeting".to_owned(), Duration::minutes(90))
+            ]
+        );
+        assert_eq!(split.days[0].1, Duration::hours(4));
+        assert!(!split.double_counted);
+        assert_eq!(
+            Report::per_project(&mut db, week()).unwrap().groups,
+            full.groups
+        );
+    }
+
+    #[test]
+    fn test_frames_are_split_at_midnight_and_clamped_to_the_span() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |day, hour| Timestamp::from_ymdhms(2024, 3, day, hour, 0, 0);
+        // Starts before the week and crosses midnight within it
+        for (start, end) in [(at(3, 22), at(4, 2)), (at(5, 23), at(6, 1))] {
+            db.add_frame(
+                &mut website,
+                TimeSpan::new(start, end).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        }
+        let day = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let expected = vec![
+            (day(4), Duration::hours(2)),
+            (day(5), Duration::hours(1)),
+            (day(6), Duration::hours(1)),
+        ];
+
+        let frames = db.get_frames_in_span(week(), ArchivedState::Both).unwrap();
+        let report = Report::new(
+            week(),
+            &frames,
+            Grouping::Project,
+            Attribution::Full,
+            &GroupNames::default(),
+        );
+        assert_eq!(report.total, Duration::hours(4));
+        assert_eq!(report.days, expected);
+        assert!(!report.double_counted);
+
+        let cached = Report::per_project(&mut db, week()).unwrap();
+        assert_eq!(cached.total, Duration::hours(4));
+        assert_eq!(cached.days, expected);
+    }
+
+    #[test]
+    fn test_span_starting_mid_day() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |hour| Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+        db.add_frame(
+            &mut website,
+            TimeSpan::new(at(8), at(14)).unwrap(),
+            FrameSource::Cli,
+        )
+        .unwrap();
+
+        let morning = TimeSpan::new(at(9), at(12)).unwrap();
+        let report = Report::per_project(&mut db, morning).unwrap();
+        assert_eq!(report.total, Duration::hours(3));
+        assert_eq!(
+            report.groups,
+            vec![("website".to_owned(), Duration::hours(3))]
+        );
+    }
+
+    #[test]
+    fn test_cached_reports_list_breaks() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let at = |hour| Timestamp::from_ymdhms(2024, 3, 4, hour, 0, 0);
+        db.add_frame(
+            &mut website,
+            TimeSpan::new(at(8), at(12)).unwrap(),
+            FrameSource::Cli,
+        )
+        .unwrap();
+        let lunch = db
+            .add_frame(
+                &mut website,
+                TimeSpan::new(at(12), at(13)).unwrap(),
+                FrameSource::Cli,
+            )
+            .unwrap();
+        db.set_frame_kind(lunch.id(), FrameKind::Break).unwrap();
+
+        let report = Report::per_project(&mut db, week()).unwrap();
+        assert_eq!(report.total, Duration::hours(4));
+        assert_eq!(report.break_total(), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_billing_uses_rate_in_effect() {
+        let mut frames = frames();
+        frames[0].0.hourly_rate_cents = Some(10000);
+        let website = frames[0].0.id();
+        let rate = |month, day, cents| Rate {
+            project_id: website,
+            effective_from: NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+            cents,
+        };
+        let report = |rates| {
+            Report::new(
+                week(),
+                &frames,
+                Grouping::Project,
+                Attribution::Full,
+                &GroupNames::default(),
+            )
+            .with_billing(
+                &frames,
+                &HashMap::from([(website, rates)]),
+                &HashMap::new(),
+                &Multipliers::default(),
+            )
+        };
+
+        let before = report(vec![rate(3, 5, 20000)]);
+        assert_eq!(
+            before.billed,
+            vec![("website".to_owned(), Money::new(30000, None))]
+        );
+        let changed = report(vec![rate(2, 1, 15000), rate(3, 4, 12050)]);
+        assert_eq!(changed.billed_totals(), vec![Money::new(36150, None)]);
+        assert!(changed
+            .to_text(DurationFormat::Hms)
+            .contains("  website: 361.50\n"));
+    }
+
+    #[test]
+    fn test_billing_per_currency() {
+        let mut frames = frames();
+        frames[0].0.hourly_rate_cents = Some(10000);
+        frames[1].0.hourly_rate_cents = Some(5000);
+        let currencies = HashMap::from([
+            (frames[0].0.id(), "EUR".to_owned()),
+            (frames[1].0.id(), "CHF".to_owned()),
+        ]);
+        let report = Report::new(
+            week(),
+            &frames,
+            Grouping::Project,
+            Attribution::Full,
+            &GroupNames::default(),
+        )
+        .with_billing(
+            &frames,
+            &HashMap::new(),
+            &currencies,
+            &Multipliers::default(),
+        );
+
+        assert_eq!(
+            report.billed_totals(),
+            vec![
+                Money::new(5000, Some("CHF")),
+                Money::new(30000, Some("EUR"))
+            ]
+        );
+        let text = report.to_text(DurationFormat::Hms);
+        assert!(text.contains("  website: €300.00\n  client|x: 50.00 CHF\n"));
+        assert!(text.contains("Total billed:\n  50.00 CHF\n  €300.00\n"));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let frames = frames();
+        let mut before = frames[..1].to_vec();
+        before[0].1.end = Some(Timestamp::from_ymdhms(2024, 3, 4, 9, 0, 0));
+        let report = |frames: &[(Project, Frame)]| {
+            Report::new(
+                week(),
+                frames,
+                Grouping::Project,
+                Attribution::Full,
+                &GroupNames::default(),
+            )
+        };
+
+        let comparison = Comparison::new(&report(&frames), &report(&before));
+        let names: Vec<_> = comparison.changes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["website", "client|x"]);
+        assert_eq!(comparison.changes[0].difference(), Duration::hours(2));
+        assert_eq!(comparison.changes[0].percentage(), Some(200));
+        assert_eq!(comparison.changes[1].percentage(), None);
+        assert_eq!(comparison.total.percentage(), Some(300));
+
+        let table = comparison.to_table(DurationFormat::Hms).render(true, false);
+        let rows: Vec<Vec<_>> = table
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows[2], ["client|x", "1h", "0s", "+1h", "new"]);
+        assert_eq!(rows[3], ["Total", "4h", "1h", "+3h", "+300%"]);
+        assert!(comparison.to_json().contains("\"change\": 300"));
+    }
+
+    #[test]
+    fn test_billing_multipliers() {
+        let mut frames = frames();
+        frames[0].0.hourly_rate_cents = Some(10000);
+        frames[1].0.hourly_rate_cents = Some(5000);
+        let multipliers = Multipliers {
+            on_call: 0.5,
+            overtime_after_hours: Some(2.5),
+            overtime: 2.0,
+        };
+        let billed = |frames: &[(Project, Frame)]| {
+            Report::new(
+                week(),
+                frames,
+                Grouping::Project,
+                Attribution::Full,
+                &GroupNames::default(),
+            )
+            .with_billing(frames, &HashMap::new(), &HashMap::new(), &multipliers)
+            .billed
+        };
+
+        // Half an hour of website and the hour of client|x after it are overtime.
+        assert_eq!(
+            billed(&frames),
+            vec![
+                ("website".to_owned(), Money::new(35000, None)),
+                ("client|x".to_owned(), Money::new(10000, None)),
+            ]
+        );
+
+        frames[1].1.kind = FrameKind::OnCall;
+        assert_eq!(
+            billed(&frames),
+            vec![
+                ("website".to_owned(), Money::new(35000, None)),
+                ("client|x".to_owned(), Money::new(2500, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_billing_only_counts_the_span() {
+        let mut frames = frames();
+        frames[0].0.hourly_rate_cents = Some(10000);
+        // Started the evening before the report.
+        frames[0].1.start = Timestamp::from_ymdhms(2024, 3, 3, 22, 0, 0);
+        let report = Report::new(
+            week(),
+            &frames,
+            Grouping::Project,
+            Attribution::Full,
+            &GroupNames::default(),
+        )
+        .with_billing(
+            &frames,
+            &HashMap::new(),
+            &HashMap::new(),
+            &Multipliers::default(),
+        );
+
+        assert_eq!(
+            report.groups[0],
+            ("website".to_owned(), Duration::hours(11))
+        );
+        assert_eq!(
+            report.billed,
+            vec![("website".to_owned(), Money::new(110000, None))]
+        );
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = report(Grouping::Project, Attribution::Full);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        let start = Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0).0.to_rfc3339();
+        assert_eq!(json["span"]["start"], start.as_str());
+        assert_eq!(json["total"], "PT4H");
+        assert_eq!(json["projects"][0]["name"], "website");
+        assert_eq!(json["projects"][0]["duration"], "PT3H");
+        assert_eq!(json["projects"][0]["share"], 75);
+        assert_eq!(json["days"][0]["day"], "2024-03-04");
+    }
+
+    #[test]
+    fn test_estimates() {
+        let frames = frames();
+        let mut website = frames[0].0.clone();
+        website.estimate_minutes = Some(150);
+        let mut client = frames[1].0.clone();
+        client.estimate_minutes = None;
+        let frames: Vec<_> = frames.into_iter().map(|(_, frame)| frame).collect();
+
+        let estimates = Estimate::compare(&[website, client], &frames);
+        assert_eq!(
+            estimates,
+            vec![Estimate {
+                project: "website".to_owned(),
+                estimate: Duration::minutes(150),
+                tracked: Duration::hours(3),
+            }]
+        );
+        assert_eq!(estimates[0].deviation(), Some(20));
+    }
+
+    #[test]
+    fn test_empty_report() {
+        let report = Report::new(
+            week(),
+            &[],
+            Grouping::Project,
+            Attribution::Full,
+            &GroupNames::default(),
+        );
+
+        assert_eq!(report.total, Duration::zero());
+        assert!(!report
+            .to_markdown(DurationFormat::Hms)
+            .contains("## Projects"));
+    }
+}
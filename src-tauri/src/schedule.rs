@@ -0,0 +1,189 @@
+//! Expected working hours per weekday, for the overtime in reports and `ttt balance`.
+//!
+//! Weekdays that are missing are not worked, neither are the days off recorded with
+//! `ttt leave`:
+//!
+//! ```toml
+//! [schedule]
+//! monday = 8
+//! tuesday = 8
+//! wednesday = 8
+//! thursday = 8
+//! friday = 6
+//!
+//! # Start of the flex time balance, the first tracked day by default
+//! since = "2024-01-01"
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::{
+    duration::{DurationExt, DurationFormat},
+    model::LeaveDay,
+};
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schedule {
+    /// Hours to work on each weekday
+    pub monday: f64,
+    pub tuesday: f64,
+    pub wednesday: f64,
+    pub thursday: f64,
+    pub friday: f64,
+    pub saturday: f64,
+    pub sunday: f64,
+
+    /// First day of the flex time balance
+    pub since: Option<NaiveDate>,
+}
+
+/// Expected and worked time of a day, or of a week starting on the given Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayBalance {
+    pub day: NaiveDate,
+    pub expected: Duration,
+    pub worked: Duration,
+}
+
+impl Schedule {
+    /// Time to work on `day`, without considering days off.
+    pub fn expected(&self, day: NaiveDate) -> Duration {
+        let hours = match day.weekday() {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        };
+        Duration::minutes((hours * 60.0).round() as i64)
+    }
+
+    /// Expected and `worked` time of every day from `first` to `last`, both inclusive.
+    /// `worked` holds the tracked time per day, like [`crate::report::Report::days`].
+    pub fn balance(
+        &self,
+        first: NaiveDate,
+        last: NaiveDate,
+        worked: &[(NaiveDate, Duration)],
+        leave: &[LeaveDay],
+    ) -> Vec<DayBalance> {
+        let worked: BTreeMap<_, _> = worked.iter().copied().collect();
+        first
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .map(|day| DayBalance {
+                day,
+                expected: if leave.iter().any(|leave| leave.day == day) {
+                    Duration::zero()
+                } else {
+                    self.expected(day)
+                },
+                worked: worked.get(&day).copied().unwrap_or_else(Duration::zero),
+            })
+            .collect()
+    }
+}
+
+impl DayBalance {
+    /// Overtime if positive, undertime if negative.
+    pub fn difference(&self) -> Duration {
+        self.worked - self.expected
+    }
+
+    /// Whether the day is worth showing, i.e. work was expected or done.
+    pub fn is_relevant(&self) -> bool {
+        !self.expected.is_zero() || !self.worked.is_zero()
+    }
+}
+
+/// Sum up `days` per week, every week is represented by its Monday.
+pub fn weeks(days: &[DayBalance]) -> Vec<DayBalance> {
+    let mut weeks = BTreeMap::<NaiveDate, DayBalance>::new();
+    for day in days {
+        let monday = day.day - Duration::days(day.day.weekday().num_days_from_monday().into());
+        let week = weeks.entry(monday).or_insert(DayBalance {
+            day: monday,
+            expected: Duration::zero(),
+            worked: Duration::zero(),
+        });
+        week.expected = week.expected + day.expected;
+        week.worked = week.worked + day.worked;
+    }
+    weeks.into_values().collect()
+}
+
+/// Format an overtime with its sign, e.g. "+1h 30min" or "-45min".
+pub fn format_difference(difference: Duration, durations: DurationFormat) -> String {
+    let sign = if difference < Duration::zero() {
+        '-'
+    } else {
+        '+'
+    };
+    format!("{sign}{}", difference.abs().format_as(durations))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::LeaveKind;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_balance() {
+        let schedule: Schedule = toml::from_str("monday = 8\nfriday = 6.5").unwrap();
+        let worked = [
+            (date(4), Duration::hours(9)),
+            (date(10), Duration::hours(1)),
+        ];
+        let leave = [LeaveDay {
+            day: date(11),
+            kind: LeaveKind::Vacation,
+            name: None,
+        }];
+
+        // Monday to the next Monday, which is a day off.
+        let days = schedule.balance(date(4), date(11), &worked, &leave);
+
+        let relevant: Vec<_> = days
+            .iter()
+            .filter(|day| day.is_relevant())
+            .map(|day| (day.day, day.difference()))
+            .collect();
+        assert_eq!(
+            relevant,
+            vec![
+                (date(4), Duration::hours(1)),
+                (date(8), -Duration::minutes(390)),
+                (date(10), Duration::hours(1)),
+            ]
+        );
+        assert_eq!(
+            weeks(&days),
+            vec![
+                DayBalance {
+                    day: date(4),
+                    expected: Duration::minutes(870),
+                    worked: Duration::hours(10),
+                },
+                DayBalance {
+                    day: date(11),
+                    expected: Duration::zero(),
+                    worked: Duration::zero(),
+                },
+            ]
+        );
+        assert_eq!(
+            format_difference(-Duration::minutes(390), DurationFormat::Clock),
+            "-6:30"
+        );
+    }
+}
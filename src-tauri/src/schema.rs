@@ -1,11 +1,69 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    budgets (project_id) {
+        project_id -> Integer,
+        minutes -> Nullable<Integer>,
+        cents -> Nullable<Integer>,
+        period -> Text,
+    }
+}
+
+diesel::table! {
+    clients (id) {
+        id -> Integer,
+        name -> Text,
+        currency -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    daily_totals (day, project_id) {
+        day -> Date,
+        project_id -> Integer,
+        milliseconds -> BigInt,
+    }
+}
+
 diesel::table! {
     frames (id) {
         id -> Integer,
         project -> Integer,
         start -> Text,
         end -> Nullable<Text>,
+        deleted_at -> Nullable<Text>,
+        uuid -> Text,
+        updated_at -> Text,
+        external_ref -> Nullable<Text>,
+        note -> Nullable<Text>,
+        task_id -> Nullable<Integer>,
+        source -> Nullable<Text>,
+        draft -> Bool,
+        kind -> Text,
+    }
+}
+
+diesel::table! {
+    leave_days (day) {
+        day -> Date,
+        kind -> Text,
+        name -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    locked_periods (id) {
+        id -> Integer,
+        start -> Text,
+        end -> Text,
+        locked_at -> Text,
+    }
+}
+
+diesel::table! {
+    project_aliases (alias) {
+        alias -> Text,
+        project_id -> Integer,
     }
 }
 
@@ -15,6 +73,35 @@ diesel::table! {
         name -> Text,
         archived -> Bool,
         last_access_time -> Text,
+        estimate_minutes -> Nullable<Integer>,
+        hourly_rate_cents -> Nullable<Integer>,
+        uuid -> Text,
+        client_id -> Nullable<Integer>,
+        currency -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    rates (project_id, effective_from) {
+        project_id -> Integer,
+        effective_from -> Date,
+        cents -> Integer,
+    }
+}
+
+diesel::table! {
+    rules (id) {
+        id -> Integer,
+        field -> Text,
+        pattern -> Text,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    settings (key) {
+        key -> Text,
+        value -> Text,
     }
 }
 
@@ -24,6 +111,16 @@ diesel::table! {
         name -> Text,
         archived -> Bool,
         last_access_time -> Text,
+        uuid -> Text,
+    }
+}
+
+diesel::table! {
+    tasks (id) {
+        id -> Integer,
+        project_id -> Integer,
+        name -> Text,
+        done -> Bool,
     }
 }
 
@@ -31,11 +128,35 @@ diesel::table! {
     tags_per_project (project_id, tag_id) {
         project_id -> Integer,
         tag_id -> Integer,
+        is_default -> Bool,
     }
 }
 
+diesel::joinable!(budgets -> projects (project_id));
+diesel::joinable!(daily_totals -> projects (project_id));
 diesel::joinable!(frames -> projects (project));
+diesel::joinable!(frames -> tasks (task_id));
+diesel::joinable!(project_aliases -> projects (project_id));
+diesel::joinable!(projects -> clients (client_id));
+diesel::joinable!(rates -> projects (project_id));
+diesel::joinable!(rules -> tags (tag_id));
 diesel::joinable!(tags_per_project -> projects (project_id));
 diesel::joinable!(tags_per_project -> tags (tag_id));
+diesel::joinable!(tasks -> projects (project_id));
 
-diesel::allow_tables_to_appear_in_same_query!(frames, projects, tags, tags_per_project,);
+diesel::allow_tables_to_appear_in_same_query!(
+    budgets,
+    clients,
+    daily_totals,
+    frames,
+    leave_days,
+    locked_periods,
+    project_aliases,
+    projects,
+    rates,
+    rules,
+    settings,
+    tags,
+    tags_per_project,
+    tasks,
+);
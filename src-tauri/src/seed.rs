@@ -0,0 +1,177 @@
+//! Plausible random projects, tags and frames for developing the GUI, taking screenshots and
+//! measuring performance without months of real data, see `ttt dev seed`.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Weekday};
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    error::Result,
+    model::{FrameKind, FrameSource, NewFrame, Project, Timestamp},
+};
+
+/// Projects to create with their tags, the first ones are worked on more often.
+const PROJECTS: &[(&str, &[&str])] = &[
+    ("website", &["billable", "frontend"]),
+    ("client-x", &["billable"]),
+    ("meetings", &["meeting"]),
+    ("support", &["billable", "ops"]),
+    ("internal", &[]),
+    ("research", &[]),
+];
+
+/// What was added by [`seed`].
+pub struct Seeded {
+    pub projects: usize,
+    pub frames: usize,
+}
+
+/// A xorshift generator, random enough for sample data and reproducible from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `range`, which must not be empty.
+    fn between(&mut self, range: std::ops::Range<i64>) -> i64 {
+        range.start + (self.next() % (range.end - range.start) as u64) as i64
+    }
+
+    fn chance(&mut self, percent: i64) -> bool {
+        self.between(0..100) < percent
+    }
+}
+
+/// Fill the workdays of the last `days` days before today with frames. Frames start in the
+/// morning, are between a quarter hour and a few hours long, leave a lunch break and end in
+/// the evening. Weekends are worked on rarely. The projects and tags are created if needed.
+pub fn seed(db: &mut Database, days: u32, seed: u64) -> Result<Seeded> {
+    let mut rng = Rng::new(seed);
+
+    let mut projects = Vec::new();
+    let mut created = 0;
+    for (name, tag_names) in PROJECTS {
+        let project = match db.lookup_project_by_name(name)? {
+            Some(project) => project,
+            None => {
+                created += 1;
+                db.create_project(name)?
+            }
+        };
+        let mut tags = Vec::new();
+        for tag in *tag_names {
+            tags.push(match db.lookup_tag_by_name(tag)? {
+                Some(tag) => tag,
+                None => db.create_tag(tag)?,
+            });
+        }
+        db.tag_projects(tags, vec![project.clone()])?;
+        projects.push(project);
+    }
+
+    let today = Local::now().date_naive();
+    let mut spans = Vec::new();
+    for days_ago in (1..=i64::from(days)).rev() {
+        let day = today - Duration::days(days_ago);
+        let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        if (weekend && !rng.chance(10)) || (!weekend && rng.chance(5)) {
+            continue;
+        }
+        spans.extend(workday(&mut rng, day, &projects));
+    }
+
+    let uuids: Vec<_> = spans.iter().map(|_| Uuid::new_v4().to_string()).collect();
+    let now = Timestamp::now();
+    let frames = spans
+        .iter()
+        .zip(&uuids)
+        .map(|((project, start, end), uuid)| NewFrame {
+            project: *project,
+            start,
+            end: Some(end),
+            deleted_at: None,
+            uuid,
+            updated_at: &now,
+            external_ref: None,
+            note: None,
+            // Generated in bulk, not tracked on the command line.
+            source: Some(FrameSource::Import),
+            draft: false,
+            kind: FrameKind::Work,
+        })
+        .collect();
+    let frames = db.insert_frames_bulk(frames, false)?;
+    Ok(Seeded {
+        projects: created,
+        frames,
+    })
+}
+
+/// The frames of one day as project id, start and end.
+fn workday(
+    rng: &mut Rng,
+    day: NaiveDate,
+    projects: &[Project],
+) -> Vec<(i32, Timestamp, Timestamp)> {
+    let at = |minutes: i64| {
+        Timestamp::from_naive(day.and_time(NaiveTime::MIN) + Duration::minutes(minutes))
+    };
+    let mut minute = rng.between(7 * 60 + 30..9 * 60 + 30);
+    let lunch = rng.between(11 * 60 + 45..13 * 60);
+    let end_of_day = rng.between(16 * 60 + 30..18 * 60 + 30);
+    let mut had_lunch = false;
+    let mut frames = Vec::new();
+    while minute < end_of_day {
+        // The smaller of two picks, so the first projects are worked on more often.
+        let count = projects.len() as i64;
+        let project = &projects[rng.between(0..count).min(rng.between(0..count)) as usize];
+        let length = rng.between(15..150).min(end_of_day - minute);
+        frames.push((project.id(), at(minute), at(minute + length)));
+        minute += length + rng.between(0..20);
+        if !had_lunch && minute >= lunch {
+            had_lunch = true;
+            minute += rng.between(30..60);
+        }
+    }
+    frames
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::ArchivedState;
+
+    #[test]
+    fn test_seed() {
+        let mut db = Database::open_in_memory().unwrap();
+        let seeded = seed(&mut db, 30, 42).unwrap();
+        assert_eq!(seeded.projects, PROJECTS.len());
+        assert!(seeded.frames > 20 * 5);
+
+        let mut frames = db.all_frames(ArchivedState::Both).unwrap();
+        assert_eq!(frames.len(), seeded.frames);
+        frames.sort_by_key(|frame| frame.start);
+        let today = Timestamp::from_naive(Local::now().date_naive().and_time(NaiveTime::MIN));
+        for pair in frames.windows(2) {
+            assert!(pair[0].end.unwrap() <= pair[1].start);
+        }
+        assert!(frames.iter().all(|frame| frame.end.unwrap() <= today));
+        let website = db.lookup_project_by_name("website").unwrap().unwrap();
+        let tags = db.lookup_tags_for_project(website.id()).unwrap();
+        assert_eq!(tags.len(), 2);
+
+        // Seeding again reuses the projects.
+        let seeded = seed(&mut db, 1, 1).unwrap();
+        assert_eq!(seeded.projects, 0);
+    }
+}
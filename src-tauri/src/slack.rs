@@ -0,0 +1,199 @@
+//! Show the tracked project as Slack or Mattermost status, available with the `slack` feature.
+//!
+//! The status is set on start and cleared on stop. The API token is kept in the keyring of the
+//! operating system, see `ttt slack login`.
+//!
+//! ```toml
+//! [slack]
+//! emoji = ":computer:"
+//! text = "Working on {project}"
+//!
+//! [slack.projects.website]
+//! emoji = ":globe_with_meridians:"
+//! text = "Building the new website"
+//!
+//! # For Mattermost instead of Slack
+//! # service = "mattermost"
+//! # url = "https://chat.example.com"
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+const KEYRING_SERVICE: &str = "ttt";
+const KEYRING_USER: &str = "slack";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Service {
+    #[default]
+    Slack,
+    Mattermost,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlackConfig {
+    #[serde(default)]
+    pub service: Service,
+
+    /// Address of the Mattermost server, Slack always uses slack.com.
+    pub url: Option<String>,
+
+    #[serde(default = "default_emoji")]
+    pub emoji: String,
+
+    /// Status text, `{project}` is replaced with the project name.
+    #[serde(default = "default_text")]
+    pub text: String,
+
+    /// Emoji and text for individual projects, by project name.
+    #[serde(default)]
+    pub projects: HashMap<String, StatusTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusTemplate {
+    pub emoji: Option<String>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Status {
+    emoji: String,
+    text: String,
+}
+
+fn default_emoji() -> String {
+    ":computer:".to_owned()
+}
+
+fn default_text() -> String {
+    "Working on {project}".to_owned()
+}
+
+/// Store the API token in the keyring.
+pub fn login(token: &str) -> Result<()> {
+    entry()?
+        .set_password(token)
+        .map_err(|e| Error::Slack(e.to_string()))
+}
+
+/// Remove the API token from the keyring.
+pub fn logout() -> Result<()> {
+    entry()?
+        .delete_password()
+        .map_err(|e| Error::Slack(e.to_string()))
+}
+
+/// Show `project` as status, or clear the status if `None`.
+pub fn set_status(config: &SlackConfig, project: Option<&str>) -> Result<()> {
+    let token = entry()?.get_password().map_err(|e| match e {
+        keyring::Error::NoEntry => Error::Slack("Not logged in, run `ttt slack login`".to_owned()),
+        e => Error::Slack(e.to_string()),
+    })?;
+    let status = project.map(|project| status(config, project));
+
+    match config.service {
+        Service::Slack => {
+            let profile = match status {
+                Some(status) => json!({
+                    "status_emoji": status.emoji,
+                    "status_text": status.text,
+                    "status_expiration": 0,
+                }),
+                None => json!({ "status_emoji": "", "status_text": "" }),
+            };
+            let response: Value = ureq::post("https://slack.com/api/users.profile.set")
+                .set("Authorization", &format!("Bearer {token}"))
+                .send_json(json!({ "profile": profile }))
+                .map_err(|e| Error::Slack(e.to_string()))?
+                .into_json()?;
+            // Slack reports errors with a successful HTTP status.
+            if response["ok"] != true {
+                return Err(Error::Slack(response["error"].to_string()));
+            }
+        }
+        Service::Mattermost => {
+            let url = config
+                .url
+                .as_deref()
+                .ok_or_else(|| Error::InvalidConfig("Mattermost needs a `url`".to_owned()))?;
+            let endpoint = format!(
+                "{}/api/v4/users/me/status/custom",
+                url.trim_end_matches('/')
+            );
+            let authorization = format!("Bearer {token}");
+            let request = match status {
+                Some(status) => ureq::put(&endpoint)
+                    .set("Authorization", &authorization)
+                    .send_json(json!({
+                        "emoji": status.emoji.trim_matches(':'),
+                        "text": status.text,
+                    })),
+                None => ureq::delete(&endpoint)
+                    .set("Authorization", &authorization)
+                    .call(),
+            };
+            request.map_err(|e| Error::Slack(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+fn entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| Error::Slack(e.to_string()))
+}
+
+/// The status for `project`, using the project specific template if there is one.
+fn status(config: &SlackConfig, project: &str) -> Status {
+    let template = config.projects.get(project);
+    let emoji = template
+        .and_then(|t| t.emoji.as_ref())
+        .unwrap_or(&config.emoji);
+    let text = template
+        .and_then(|t| t.text.as_ref())
+        .unwrap_or(&config.text);
+    Status {
+        emoji: emoji.clone(),
+        text: text.replace("{project}", project),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_status_templates() {
+        let config: SlackConfig = toml::from_str(
+            r#"
+            text = "Busy with {project}"
+
+            [projects.website]
+            emoji = ":globe_with_meridians:"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            status(&config, "website"),
+            Status {
+                emoji: ":globe_with_meridians:".to_owned(),
+                text: "Busy with website".to_owned(),
+            }
+        );
+        assert_eq!(
+            status(&config, "client-x"),
+            Status {
+                emoji: ":computer:".to_owned(),
+                text: "Busy with client-x".to_owned(),
+            }
+        );
+    }
+}
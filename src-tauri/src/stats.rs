@@ -0,0 +1,376 @@
+//! Metrics derived from the tracked frames, see `ttt stats`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::{
+    duration::{DurationExt, DurationFormat},
+    model::{Frame, LeaveDay, LeaveKind, Project, Tag, TimeSpan, Timestamp},
+    report::describe_days,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Session {
+    pub project: String,
+    pub start: Timestamp,
+    pub duration: Duration,
+}
+
+/// When work started and ended on a single day.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DayActivity {
+    pub day: NaiveDate,
+    pub first: Timestamp,
+    pub last: Timestamp,
+}
+
+#[derive(Debug)]
+pub struct Statistics {
+    pub span: TimeSpan,
+    pub total: Duration,
+
+    /// Number of days on which any frame started.
+    pub tracked_days: usize,
+
+    pub longest_session: Option<Session>,
+
+    /// First and last day of the longest run of consecutive tracked days.
+    pub longest_streak: Option<(NaiveDate, NaiveDate)>,
+
+    pub top_project: Option<(String, Duration)>,
+    pub top_tag: Option<(String, Duration)>,
+    pub busiest_weekday: Option<(Weekday, Duration)>,
+
+    /// Activity of every tracked day, in chronological order.
+    pub days: Vec<DayActivity>,
+
+    /// Days off within the span, see `ttt leave`.
+    pub leave: Vec<LeaveDay>,
+}
+
+impl Statistics {
+    /// Compute the statistics of `frames`. `tags` maps project ids to the tags of the project.
+    pub fn new(
+        span: TimeSpan,
+        frames: &[(Project, Frame)],
+        tags: &HashMap<i32, Vec<Tag>>,
+        leave: &[LeaveDay],
+    ) -> Self {
+        let mut total = Duration::zero();
+        let mut longest_session: Option<Session> = None;
+        let mut per_project = BTreeMap::new();
+        let mut per_tag = BTreeMap::new();
+        let mut per_weekday = BTreeMap::new();
+        let mut days = BTreeMap::<NaiveDate, DayActivity>::new();
+
+        fn add<K: Ord>(map: &mut BTreeMap<K, Duration>, key: K, duration: Duration) {
+            let entry = map.entry(key).or_insert_with(Duration::zero);
+            *entry = *entry + duration;
+        }
+
+        for (project, frame) in frames {
+            let duration = frame.duration();
+            total = total + duration;
+
+            if longest_session
+                .as_ref()
+                .is_none_or(|session| session.duration < duration)
+            {
+                longest_session = Some(Session {
+                    project: project.name.clone(),
+                    start: frame.start,
+                    duration,
+                });
+            }
+
+            add(&mut per_project, project.name.clone(), duration);
+            for tag in tags.get(&project.id()).into_iter().flatten() {
+                add(&mut per_tag, tag.name.clone(), duration);
+            }
+
+            let day = frame.start.0.date_naive();
+            add(
+                &mut per_weekday,
+                day.weekday().num_days_from_monday(),
+                duration,
+            );
+
+            let end = frame.end.unwrap_or_else(Timestamp::now);
+            days.entry(day)
+                .and_modify(|activity| {
+                    activity.first = activity.first.min(frame.start);
+                    activity.last = activity.last.max(end);
+                })
+                .or_insert(DayActivity {
+                    day,
+                    first: frame.start,
+                    last: end,
+                });
+        }
+
+        let busiest_weekday = per_weekday
+            .into_iter()
+            .max_by_key(|(_, duration)| *duration)
+            .map(|(day, duration)| (weekday(day), duration));
+
+        Self {
+            span,
+            total,
+            tracked_days: days.len(),
+            longest_session,
+            longest_streak: longest_streak(days.keys().copied()),
+            top_project: maximum(per_project),
+            top_tag: maximum(per_tag),
+            busiest_weekday,
+            days: days.into_values().collect(),
+            leave: leave.to_vec(),
+        }
+    }
+
+    /// Number of days off per kind of leave.
+    pub fn leave_per_kind(&self) -> BTreeMap<LeaveKind, usize> {
+        let mut counts = BTreeMap::new();
+        for leave in &self.leave {
+            *counts.entry(leave.kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Average tracked time of the days on which anything was tracked.
+    pub fn average_per_tracked_day(&self) -> Option<Duration> {
+        if self.tracked_days == 0 {
+            return None;
+        }
+        Some(self.total / self.tracked_days as i32)
+    }
+
+    pub fn to_text(&self, durations: DurationFormat) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Statistics {}", describe_days(&self.span));
+        let _ = writeln!(out, "Total: {}", self.total.format_as(durations));
+        let _ = writeln!(out, "Tracked days: {}", self.tracked_days);
+
+        if let Some(average) = self.average_per_tracked_day() {
+            let _ = writeln!(
+                out,
+                "Average per tracked day: {}",
+                average.format_as(durations)
+            );
+        }
+        if let Some(session) = &self.longest_session {
+            let _ = writeln!(
+                out,
+                "Longest session: {} ({}, {})",
+                session.duration.format_as(durations),
+                session.project,
+                session.start.0.format("%Y-%m-%d %H:%M")
+            );
+        }
+        if let Some((first, last)) = self.longest_streak {
+            let length = (last - first).num_days() + 1;
+            let _ = writeln!(out, "Longest streak: {length} days ({first} to {last})");
+        }
+        if let Some((name, duration)) = &self.top_project {
+            let _ = writeln!(
+                out,
+                "Most tracked project: {name} ({})",
+                duration.format_as(durations)
+            );
+        }
+        if let Some((name, duration)) = &self.top_tag {
+            let _ = writeln!(
+                out,
+                "Most tracked tag: {name} ({})",
+                duration.format_as(durations)
+            );
+        }
+        if let Some((day, duration)) = &self.busiest_weekday {
+            let _ = writeln!(
+                out,
+                "Busiest weekday: {} ({})",
+                weekday_name(*day),
+                duration.format_as(durations)
+            );
+        }
+        let leave = self.leave_per_kind();
+        if !leave.is_empty() {
+            let counts: Vec<_> = leave
+                .iter()
+                .map(|(kind, count)| format!("{count} {kind}"))
+                .collect();
+            let _ = writeln!(out, "Days off: {}", counts.join(", "));
+        }
+
+        // Tracked days and days off, in chronological order.
+        let mut lines = BTreeMap::<NaiveDate, Vec<String>>::new();
+        for activity in &self.days {
+            lines.entry(activity.day).or_default().push(format!(
+                "{} - {}",
+                activity.first.0.format("%H:%M"),
+                activity.last.0.format("%H:%M")
+            ));
+        }
+        for leave in &self.leave {
+            let line = match &leave.name {
+                Some(name) => format!("{} ({name})", leave.kind),
+                None => leave.kind.to_string(),
+            };
+            lines.entry(leave.day).or_default().push(line);
+        }
+        if !lines.is_empty() {
+            let _ = writeln!(out, "\nDays:");
+            for (day, lines) in lines {
+                let _ = writeln!(out, "  {}: {}", day.format("%a %Y-%m-%d"), lines.join(", "));
+            }
+        }
+        out
+    }
+}
+
+/// Entry with the longest duration.
+fn maximum(map: BTreeMap<String, Duration>) -> Option<(String, Duration)> {
+    map.into_iter().max_by_key(|(_, duration)| *duration)
+}
+
+/// First and last day of the longest run of consecutive days in `days`, which must be sorted.
+fn longest_streak(days: impl Iterator<Item = NaiveDate>) -> Option<(NaiveDate, NaiveDate)> {
+    let mut longest: Option<(NaiveDate, NaiveDate)> = None;
+    let mut current: Option<(NaiveDate, NaiveDate)> = None;
+
+    for day in days {
+        current = match current {
+            Some((first, last)) if last.succ_opt() == Some(day) => Some((first, day)),
+            _ => Some((day, day)),
+        };
+        let (first, last) = current.unwrap();
+        if longest.is_none_or(|(a, b)| (b - a) < (last - first)) {
+            longest = current;
+        }
+    }
+    longest
+}
+
+/// The weekday with the given number of days since monday.
+fn weekday(days_from_monday: u32) -> Weekday {
+    let mut day = Weekday::Mon;
+    for _ in 0..days_from_monday {
+        day = day.succ();
+    }
+    day
+}
+
+pub fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{ArchivedState, Database};
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_longest_streak() {
+        let days = [1, 2, 4, 5, 6, 9].map(date);
+        assert_eq!(longest_streak(days.into_iter()), Some((date(4), date(6))));
+        assert_eq!(
+            longest_streak([date(3)].into_iter()),
+            Some((date(3), date(3)))
+        );
+        assert_eq!(longest_streak(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let client = db.create_project("client").unwrap();
+        let billable = db.create_tag("billable").unwrap();
+        db.tag_projects(vec![billable], vec![client.clone()])
+            .unwrap();
+
+        let at = |day, hour| Timestamp::from_ymdhms(2024, 3, day, hour, 0, 0);
+        db.add_test_frames(&[
+            (&website, at(4, 8), Some(at(4, 10))),
+            (&client, at(4, 13), Some(at(4, 16))),
+            (&website, at(5, 9), Some(at(5, 10))),
+            (&client, at(7, 9), Some(at(7, 10))),
+        ])
+        .unwrap();
+
+        let span = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 3, 11, 0, 0, 0),
+        )
+        .unwrap();
+        let frames = db.get_frames_in_span(span, ArchivedState::Both).unwrap();
+        let mut tags = HashMap::new();
+        for (project, _) in &frames {
+            tags.insert(
+                project.id(),
+                db.lookup_tags_for_project(project.id()).unwrap(),
+            );
+        }
+
+        let leave = [LeaveDay {
+            day: date(8),
+            kind: LeaveKind::Vacation,
+            name: None,
+        }];
+
+        let stats = Statistics::new(span, &frames, &tags, &leave);
+        assert_eq!(stats.total, Duration::hours(7));
+        assert_eq!(stats.tracked_days, 3);
+        assert_eq!(
+            stats.average_per_tracked_day(),
+            Some(Duration::minutes(140))
+        );
+        assert_eq!(
+            stats.longest_session,
+            Some(Session {
+                project: "client".to_owned(),
+                start: Timestamp::from_ymdhms(2024, 3, 4, 13, 0, 0),
+                duration: Duration::hours(3),
+            })
+        );
+        assert_eq!(stats.longest_streak, Some((date(4), date(5))));
+        assert_eq!(
+            stats.top_project,
+            Some(("client".to_owned(), Duration::hours(4)))
+        );
+        assert_eq!(
+            stats.top_tag,
+            Some(("billable".to_owned(), Duration::hours(4)))
+        );
+        assert_eq!(
+            stats.busiest_weekday,
+            Some((Weekday::Mon, Duration::hours(5)))
+        );
+        assert_eq!(
+            stats.days[0],
+            DayActivity {
+                day: date(4),
+                first: Timestamp::from_ymdhms(2024, 3, 4, 8, 0, 0),
+                last: Timestamp::from_ymdhms(2024, 3, 4, 16, 0, 0),
+            }
+        );
+        let text = stats.to_text(DurationFormat::Clock);
+        assert!(text.contains("Days off: 1 vacation\n"));
+        assert!(text.contains("  Thu 2024-03-07: 09:00 - 10:00\n  Fri 2024-03-08: vacation\n"));
+    }
+}
@@ -0,0 +1,388 @@
+//! Synchronization between machines through a shared folder, e.g. one kept in sync by Syncthing
+//! or Dropbox.
+//!
+//! Every machine appends its changes to its own journal file `<machine id>.jsonl` inside of the
+//! folder and never touches the journals of other machines, so the sync tool never has to merge
+//! files. Frames are identified by their uuid; when two machines changed the same frame, the
+//! more recent change wins. Overlapping frames are resolved with
+//! [`Database::resolve_overlaps`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    error::{Error, Result},
+    model::{Frame, FrameKind, FrameSource, NewFrame, Timestamp},
+};
+
+const MACHINE_ID: &str = "sync.machine_id";
+const LAST_EXPORT: &str = "sync.last_export";
+
+/// Key under which the number of already applied lines of another machine's journal is stored.
+fn position_key(machine_id: &str) -> String {
+    format!("sync.position.{machine_id}")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Frame {
+        uuid: String,
+        project: String,
+        /// Finds the project even if it was renamed on one of the machines. Missing in journals
+        /// written before projects had uuids.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        project_uuid: Option<String>,
+        start: Timestamp,
+        end: Option<Timestamp>,
+        deleted_at: Option<Timestamp>,
+        updated_at: Timestamp,
+        /// Missing in journals written before frames could reference tickets.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        external_ref: Option<String>,
+        /// Missing in journals written before frame sources were recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<FrameSource>,
+        /// Missing in journals written before breaks were tracked.
+        #[serde(default)]
+        kind: FrameKind,
+        /// Missing in journals written before notes were synchronized.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        /// Name of the task within the project, created if the other machine doesn't have it
+        /// yet. Missing in journals written before tasks were synchronized.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Summary {
+    /// Number of frames that were created or changed on other machines.
+    pub imported: usize,
+
+    /// Number of local changes written to the journal of this machine.
+    pub exported: usize,
+
+    /// Frames that were cut off because they overlapped with a later frame.
+    pub resolved: Vec<Frame>,
+}
+
+/// The id of this machine, created the first time it is needed.
+pub fn machine_id(db: &mut Database) -> Result<String> {
+    match db.setting(MACHINE_ID)? {
+        Some(id) => Ok(id),
+        None => {
+            let id = Uuid::new_v4().to_string();
+            db.set_setting(MACHINE_ID, &id)?;
+            Ok(id)
+        }
+    }
+}
+
+/// Exchange changes with all other machines that use the folder `dir`.
+pub fn sync(db: &mut Database, dir: &Path) -> Result<Summary> {
+    let machine_id = machine_id(db)?;
+    fs::create_dir_all(dir)?;
+
+    let mut imported = HashSet::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new("jsonl")) {
+            continue;
+        }
+        let Some(other) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if other == machine_id {
+            continue;
+        }
+        import_journal(db, &path, other, &mut imported)?;
+    }
+
+    let imported_count = imported.len();
+    let resolved = db.resolve_overlaps()?;
+    for frame in &resolved {
+        // Changed locally, so other machines need to hear about it.
+        imported.remove(frame.uuid());
+    }
+
+    let journal = dir.join(format!("{machine_id}.jsonl"));
+    let exported = export_journal(db, &journal, &imported)?;
+
+    Ok(Summary {
+        imported: imported_count,
+        exported,
+        resolved,
+    })
+}
+
+/// Apply all entries of the journal at `path` that were not applied yet.
+/// The uuids of all changed frames are added to `changed`.
+fn import_journal(
+    db: &mut Database,
+    path: &Path,
+    machine_id: &str,
+    changed: &mut HashSet<String>,
+) -> Result<()> {
+    let key = position_key(machine_id);
+    let position: usize = db
+        .setting(&key)?
+        .and_then(|position| position.parse().ok())
+        .unwrap_or(0);
+
+    let content = fs::read_to_string(path)?;
+    // The sync tool might not have transferred the whole file yet, so only look at complete lines.
+    let lines: Vec<_> = content
+        .split_inclusive('\n')
+        .filter(|line| line.ends_with('\n'))
+        .collect();
+
+    for (number, line) in lines.iter().enumerate().skip(position) {
+        let entry: Entry = serde_json::from_str(line).map_err(|e| {
+            Error::InvalidJournal(format!("{}:{}: {e}", path.display(), number + 1))
+        })?;
+        match entry {
+            Entry::Frame {
+                uuid,
+                project,
+                project_uuid,
+                start,
+                end,
+                deleted_at,
+                updated_at,
+                external_ref,
+                source,
+                kind,
+                note,
+                task,
+            } => {
+                let known = match &project_uuid {
+                    Some(project_uuid) => db.lookup_project_by_uuid(project_uuid)?,
+                    None => None,
+                };
+                let project = match known {
+                    Some(project) => project,
+                    None => match (db.lookup_project_by_name(&project)?, project_uuid) {
+                        (Some(project), _) => project,
+                        (None, Some(project_uuid)) => {
+                            db.create_project_with_uuid(&project, &project_uuid)?
+                        }
+                        (None, None) => db.create_project(&project)?,
+                    },
+                };
+                let task_id = match task {
+                    Some(name) => Some(match db.lookup_task(project.id(), &name)? {
+                        Some(task) => task.id(),
+                        None => db.create_task(&project, &name)?.id(),
+                    }),
+                    None => None,
+                };
+                let frame = NewFrame {
+                    project: project.id(),
+                    start: &start,
+                    end: end.as_ref(),
+                    deleted_at: deleted_at.as_ref(),
+                    uuid: &uuid,
+                    updated_at: &updated_at,
+                    external_ref: external_ref.as_deref(),
+                    note: note.as_deref(),
+                    source,
+                    draft: false,
+                    kind,
+                };
+                if db.merge_frame(&frame, task_id)? {
+                    changed.insert(uuid);
+                }
+            }
+        }
+    }
+
+    db.set_setting(&key, &lines.len().max(position).to_string())
+}
+
+/// Append all local changes since the last export to the journal at `path`, skipping the frames
+/// in `skip`. Returns the number of written entries.
+fn export_journal(db: &mut Database, path: &Path, skip: &HashSet<String>) -> Result<usize> {
+    let now = Timestamp::now();
+    let last_export = db
+        .setting(LAST_EXPORT)?
+        .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+        .map(Timestamp);
+
+    let tasks: HashMap<_, _> = db
+        .tasks(None)?
+        .into_iter()
+        .map(|task| (task.id(), task.name))
+        .collect();
+
+    let mut journal = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut exported = 0;
+    for (project, frame) in db.frames_changed_since(last_export)? {
+        if skip.contains(frame.uuid()) {
+            continue;
+        }
+        let entry = Entry::Frame {
+            uuid: frame.uuid().to_owned(),
+            project_uuid: Some(project.uuid().to_owned()),
+            project: project.name,
+            start: frame.start,
+            end: frame.end,
+            deleted_at: frame.deleted_at,
+            updated_at: frame.updated_at,
+            external_ref: frame.external_ref,
+            source: frame.source,
+            kind: frame.kind,
+            note: frame.note,
+            task: frame.task_id.and_then(|id| tasks.get(&id).cloned()),
+        };
+        let line = serde_json::to_string(&entry).expect("Journal entries are serializable");
+        writeln!(journal, "{line}")?;
+        exported += 1;
+    }
+
+    db.set_setting(LAST_EXPORT, &now.0.to_rfc3339())?;
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::database::ArchivedState;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ttt-sync-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_frames_are_exchanged() {
+        let dir = temp_dir("exchange");
+        let mut laptop = Database::open_in_memory().unwrap();
+        let mut desktop = Database::open_in_memory().unwrap();
+
+        let mut project = laptop.create_project("website").unwrap();
+        laptop.start(&mut project, None, FrameSource::Cli).unwrap();
+        laptop.stop().unwrap();
+
+        let summary = sync(&mut laptop, &dir).unwrap();
+        assert_eq!(summary.exported, 1);
+        let summary = sync(&mut desktop, &dir).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.exported, 0);
+
+        let frames = desktop.all_frames(ArchivedState::Both).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].source, Some(FrameSource::Cli));
+        let website = desktop.lookup_project_by_name("website").unwrap().unwrap();
+        assert_eq!(website.uuid(), project.uuid());
+
+        // Renaming only affects one machine, the frames still end up in the same project.
+        laptop.rename_project(&mut project, "homepage").unwrap();
+        laptop.start(&mut project, None, FrameSource::Cli).unwrap();
+        laptop.stop().unwrap();
+        sync(&mut laptop, &dir).unwrap();
+        assert_eq!(sync(&mut desktop, &dir).unwrap().imported, 1);
+        assert!(desktop
+            .lookup_project_by_name("homepage")
+            .unwrap()
+            .is_none());
+        let frames = desktop.all_frames(ArchivedState::Both).unwrap();
+        assert!(frames.iter().all(|frame| frame.project == website.id()));
+
+        // Deleting on the desktop propagates back to the laptop.
+        desktop.delete_frame(frames[0].id()).unwrap();
+        assert_eq!(sync(&mut desktop, &dir).unwrap().exported, 1);
+        assert_eq!(sync(&mut laptop, &dir).unwrap().imported, 1);
+        assert_eq!(laptop.all_frames(ArchivedState::Both).unwrap().len(), 1);
+
+        // Nothing new, nothing to do.
+        let summary = sync(&mut laptop, &dir).unwrap();
+        assert_eq!((summary.imported, summary.exported), (0, 0));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_notes_and_tasks_are_exchanged() {
+        let dir = temp_dir("notes");
+        let mut laptop = Database::open_in_memory().unwrap();
+        let mut desktop = Database::open_in_memory().unwrap();
+
+        let mut project = laptop.create_project("website").unwrap();
+        let task = laptop.create_task(&project, "checkout-bug").unwrap();
+        let frame = laptop
+            .start_task(&mut project, &task, None, FrameSource::Cli)
+            .unwrap();
+        laptop
+            .amend_frame(frame.id(), None, None, Some("reproduced"))
+            .unwrap();
+        sync(&mut laptop, &dir).unwrap();
+        sync(&mut desktop, &dir).unwrap();
+
+        let website = desktop.lookup_project_by_name("website").unwrap().unwrap();
+        let task = desktop
+            .lookup_task(website.id(), "checkout-bug")
+            .unwrap()
+            .unwrap();
+        let synced = desktop.resolve_frame(frame.uuid()).unwrap();
+        assert_eq!(synced.note.as_deref(), Some("reproduced"));
+        assert_eq!(synced.task_id, Some(task.id()));
+
+        // Later changes of the note are passed on as well.
+        laptop
+            .amend_frame(frame.id(), None, None, Some("fixed"))
+            .unwrap();
+        sync(&mut laptop, &dir).unwrap();
+        assert_eq!(sync(&mut desktop, &dir).unwrap().imported, 1);
+        let synced = desktop.resolve_frame(frame.uuid()).unwrap();
+        assert_eq!(synced.note.as_deref(), Some("reproduced; fixed"));
+        assert_eq!(synced.task_id, Some(task.id()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_old_journals_without_notes_and_tasks_are_read() {
+        let line = r#"{"type":"frame","uuid":"a","project":"website","start":"2024-03-04T08:00:00+01:00","end":null,"deleted_at":null,"updated_at":"2024-03-04T08:00:00+01:00"}"#;
+        let Entry::Frame { note, task, .. } = serde_json::from_str(line).unwrap();
+        assert_eq!((note, task), (None, None));
+    }
+
+    #[test]
+    fn test_running_frames_on_two_machines_are_resolved() {
+        let dir = temp_dir("overlap");
+        let mut laptop = Database::open_in_memory().unwrap();
+        let mut desktop = Database::open_in_memory().unwrap();
+
+        let mut project = laptop.create_project("website").unwrap();
+        laptop.start(&mut project, None, FrameSource::Cli).unwrap();
+        let mut other = desktop.create_project("other").unwrap();
+        let later = desktop.start(&mut other, None, FrameSource::Cli).unwrap();
+
+        sync(&mut laptop, &dir).unwrap();
+        let summary = sync(&mut desktop, &dir).unwrap();
+        assert_eq!(summary.resolved.len(), 1);
+        sync(&mut laptop, &dir).unwrap();
+
+        for db in [&mut laptop, &mut desktop] {
+            assert_eq!(db.current_frame().unwrap().uuid(), later.uuid());
+            assert_eq!(db.all_frames(ArchivedState::Both).unwrap().len(), 2);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Column aligned tables for the output of `ttt list`, `ttt analyze` and `ttt sql`.
+
+use std::{fmt::Write as _, io::IsTerminal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    /// Used for numbers and durations, so their units line up.
+    Right,
+}
+
+pub struct Table {
+    columns: Vec<(String, Align)>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: &[(&'static str, Align)]) -> Self {
+        Self::with_columns(
+            columns
+                .iter()
+                .map(|(name, align)| (name.to_string(), *align))
+                .collect(),
+        )
+    }
+
+    /// Like [`Table::new`], for columns only known at runtime.
+    pub fn with_columns(columns: Vec<(String, Align)>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row, which needs one cell per column.
+    pub fn add_row(&mut self, row: Vec<String>) {
+        assert_eq!(row.len(), self.columns.len(), "Wrong number of cells");
+        self.rows.push(row);
+    }
+
+    /// Render the table, the header is bold if `color` is set.
+    pub fn render(&self, header: bool, color: bool) -> String {
+        let mut widths: Vec<_> = self
+            .columns
+            .iter()
+            .map(|(name, _)| if header { name.chars().count() } else { 0 })
+            .collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut out = String::new();
+        if header {
+            let names: Vec<_> = self.columns.iter().map(|(name, _)| name.clone()).collect();
+            let line = self.render_row(&names, &widths);
+            if color {
+                let _ = writeln!(out, "\x1b[1m{line}\x1b[0m");
+            } else {
+                let _ = writeln!(out, "{line}");
+            }
+        }
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", self.render_row(row, &widths));
+        }
+        out
+    }
+
+    /// Print the table, with a bold header when writing to a terminal.
+    pub fn print(&self, header: bool) {
+        let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        print!("{}", self.render(header, color));
+    }
+
+    fn render_row(&self, cells: &[String], widths: &[usize]) -> String {
+        let mut line = String::new();
+        for (index, ((cell, width), (_, align))) in
+            cells.iter().zip(widths).zip(&self.columns).enumerate()
+        {
+            if index > 0 {
+                line.push_str("  ");
+            }
+            let _ = match align {
+                Align::Left => write!(line, "{cell:<width$}"),
+                Align::Right => write!(line, "{cell:>width$}"),
+            };
+        }
+        line.trim_end().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&[("Project", Align::Left), ("Time", Align::Right)]);
+        table.add_row(vec!["website".to_owned(), "3h".to_owned()]);
+        table.add_row(vec!["x".to_owned(), "1h 30min".to_owned()]);
+        table
+    }
+
+    #[test]
+    fn test_columns_are_aligned() {
+        assert_eq!(
+            table().render(true, false),
+            "Project      Time\nwebsite        3h\nx        1h 30min\n"
+        );
+    }
+
+    #[test]
+    fn test_without_header() {
+        assert_eq!(
+            table().render(false, false),
+            "website        3h\nx        1h 30min\n"
+        );
+    }
+
+    #[test]
+    fn test_trailing_empty_cells_are_trimmed() {
+        let mut table = Table::new(&[("Project", Align::Left), ("Tags", Align::Left)]);
+        table.add_row(vec!["website".to_owned(), String::new()]);
+        table.add_row(vec!["x".to_owned(), "+billable".to_owned()]);
+        assert_eq!(table.render(false, false), "website\nx        +billable\n");
+    }
+}
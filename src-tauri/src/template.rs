@@ -0,0 +1,204 @@
+//! Reports in custom formats, rendered with [Handlebars] templates, see `ttt report --template`.
+//!
+//! The output is not escaped, so any text format can be produced. Templates can use these
+//! variables, where every time is an object with `text` (formatted like `--duration-format`),
+//! `iso` (ISO 8601, e.g. "PT1H30M"), `minutes` and `hours` (with decimals):
+//!
+//! - `title`: the covered days, e.g. "2024-03-04 to 2024-03-10"
+//! - `start`, `end`: the reported span as RFC 3339 timestamps, the end is exclusive
+//! - `total`: time of all frames
+//! - `by`: what the groups are, e.g. "Project"
+//! - `groups`: list of `name`, `time` and `share` (in percent), the largest first
+//! - `days`: list of `day` (e.g. "2024-03-04"), `weekday` (e.g. "Mon") and `time`
+//! - `frames`: list of `id`, `project`, `start`, `end` (RFC 3339, missing for the running frame),
+//!   `date`, `from`, `to` (e.g. "09:30"), `time` and `ref`
+//!
+//! ```handlebars
+//! Tracked time {{title}}: {{total.text}}
+//! {{#each groups}}
+//! - {{name}}: {{time.hours}}h ({{share}}%)
+//! {{/each}}
+//! ```
+//!
+//! [Handlebars]: https://handlebarsjs.com/guide/
+
+use chrono::Duration;
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::{
+    duration::{DurationExt, DurationFormat},
+    error::{Error, Result},
+    model::{Frame, Project, Timestamp},
+    report::Report,
+};
+
+#[derive(Serialize)]
+struct Context<'a> {
+    title: String,
+    start: Timestamp,
+    end: Timestamp,
+    total: Time,
+    by: &'static str,
+    groups: Vec<Group<'a>>,
+    days: Vec<Day>,
+    frames: Vec<TemplateFrame<'a>>,
+}
+
+#[derive(Serialize)]
+struct Time {
+    text: String,
+    iso: String,
+    minutes: i64,
+    hours: f64,
+}
+
+#[derive(Serialize)]
+struct Group<'a> {
+    name: &'a str,
+    time: Time,
+    share: i64,
+}
+
+#[derive(Serialize)]
+struct Day {
+    day: String,
+    weekday: String,
+    time: Time,
+}
+
+#[derive(Serialize)]
+struct TemplateFrame<'a> {
+    id: &'a str,
+    project: &'a str,
+    start: Timestamp,
+    end: Option<Timestamp>,
+    date: String,
+    from: String,
+    to: Option<String>,
+    time: Time,
+    #[serde(rename = "ref")]
+    external_ref: Option<&'a str>,
+}
+
+impl Time {
+    fn new(duration: Duration, durations: DurationFormat) -> Self {
+        Self {
+            text: duration.format_as(durations),
+            iso: duration.to_iso8601(),
+            minutes: duration.num_minutes(),
+            hours: (duration.num_minutes() as f64 / 60.0 * 100.0).round() / 100.0,
+        }
+    }
+}
+
+/// Render `template` with the data of `report`, which was created from `frames`.
+pub fn render(
+    template: &str,
+    report: &Report,
+    frames: &[(Project, Frame)],
+    durations: DurationFormat,
+) -> Result<String> {
+    let context = Context {
+        title: report.title(),
+        start: report.span.start(),
+        end: report.span.end(),
+        total: Time::new(report.total, durations),
+        by: report.by.column(),
+        groups: report
+            .groups
+            .iter()
+            .map(|(name, duration)| Group {
+                name,
+                time: Time::new(*duration, durations),
+                share: report.percentage(*duration),
+            })
+            .collect(),
+        days: report
+            .days
+            .iter()
+            .map(|(day, duration)| Day {
+                day: day.to_string(),
+                weekday: day.format("%a").to_string(),
+                time: Time::new(*duration, durations),
+            })
+            .collect(),
+        frames: frames
+            .iter()
+            .map(|(project, frame)| TemplateFrame {
+                id: frame.short_id(),
+                project: &project.name,
+                start: frame.start,
+                end: frame.end,
+                date: frame.start.0.format("%Y-%m-%d").to_string(),
+                from: frame.start.0.format("%H:%M").to_string(),
+                to: frame.end.map(|end| end.0.format("%H:%M").to_string()),
+                time: Time::new(frame.duration(), durations),
+                external_ref: frame.external_ref.as_deref(),
+            })
+            .collect(),
+    };
+
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .render_template(template, &context)
+        .map_err(|e| Error::InvalidTemplate(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        database::{ArchivedState, Database},
+        model::{FrameSource, TimeSpan},
+        report::{Attribution, Grouping},
+    };
+
+    fn render_week(template: &str) -> Result<String> {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website & shop").unwrap();
+        let start = Timestamp::from_ymdhms(2024, 3, 4, 9, 0, 0);
+        db.add_frame(
+            &mut website,
+            TimeSpan::new(start, start + Duration::minutes(90)).unwrap(),
+            FrameSource::Cli,
+        )
+        .unwrap();
+        let span = TimeSpan::new(start, start + Duration::days(7)).unwrap();
+        let frames = db.get_frames_in_span(span, ArchivedState::Both).unwrap();
+        let report = Report::new(
+            span,
+            &frames,
+            Grouping::Project,
+            Attribution::Full,
+            &Default::default(),
+        );
+        render(template, &report, &frames, DurationFormat::Clock)
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = "{{by}}s {{total.text}}\n\
+            {{#each groups}}{{name}};{{time.hours}};{{share}}\n{{/each}}\
+            {{#each days}}{{weekday}} {{day}} {{time.iso}}\n{{/each}}\
+            {{#each frames}}{{date}} {{from}}-{{to}} {{project}} {{time.minutes}}{{/each}}";
+
+        assert_eq!(
+            render_week(template).unwrap(),
+            "Projects 1:30\n\
+             website & shop;1.5;100\n\
+             Mon 2024-03-04 PT1H30M\n\
+             2024-03-04 09:00-10:30 website & shop 90"
+        );
+    }
+
+    #[test]
+    fn test_unknown_variables_are_errors() {
+        assert!(matches!(
+            render_week("{{totl.text}}"),
+            Err(Error::InvalidTemplate(_))
+        ));
+    }
+}
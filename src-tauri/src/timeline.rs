@@ -0,0 +1,207 @@
+//! Layout of the frames of a single day for the calendar view of the GUI.
+//!
+//! Frames that overlap are put side by side in lanes, like the events of a calendar app, so the
+//! frontend only has to draw boxes.
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use serde::Serialize;
+use typeshare::typeshare;
+
+use crate::model::{Frame, Project, TimeSpan, Timestamp};
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct Timeline {
+    #[typeshare(serialized_as = "String")]
+    pub day: NaiveDate,
+
+    /// From midnight to midnight
+    pub span: TimeSpan,
+
+    /// Ordered by start
+    pub entries: Vec<TimelineEntry>,
+
+    /// Untracked time between the first and the last frame of the day
+    pub gaps: Vec<TimeSpan>,
+
+    /// Time tracked by more than one frame at once
+    pub overlaps: Vec<TimeSpan>,
+}
+
+#[derive(Debug, Serialize)]
+#[typeshare]
+pub struct TimelineEntry {
+    pub frame: Frame,
+    pub project: String,
+
+    /// The part of the frame on this day, running frames end now.
+    pub span: TimeSpan,
+
+    /// Column of the entry, starting at 0.
+    pub lane: u32,
+
+    /// Number of columns needed by the entries this one overlaps with, directly or through
+    /// others. The entry gets `1 / lanes` of the width.
+    pub lanes: u32,
+}
+
+impl Timeline {
+    /// Lay out `frames`, which are ordered by start, on `day`. Nothing after `now` is shown.
+    pub fn new(day: NaiveDate, frames: &[(Project, Frame)], now: Timestamp) -> Self {
+        let span = day_span(day);
+        let mut entries: Vec<_> = frames
+            .iter()
+            .filter_map(|(project, frame)| {
+                let start = frame.start.max(span.start());
+                let end = frame.end.unwrap_or(now).min(span.end());
+                Some(TimelineEntry {
+                    frame: frame.clone(),
+                    project: project.name.clone(),
+                    span: TimeSpan::new(start, end).ok()?,
+                    lane: 0,
+                    lanes: 1,
+                })
+            })
+            .collect();
+
+        let mut gaps = Vec::new();
+        let mut overlaps: Vec<TimeSpan> = Vec::new();
+        // End of the latest entry so far, and of the entries of the current group of overlapping
+        // entries per lane.
+        let mut reach: Option<Timestamp> = None;
+        let mut lane_ends: Vec<Timestamp> = Vec::new();
+        let mut group_start = 0;
+        for i in 0..entries.len() {
+            let (start, end) = (entries[i].span.start(), entries[i].span.end());
+            match reach {
+                Some(reach) if start < reach => {
+                    let overlap = TimeSpan::new(start, end.min(reach))
+                        .expect("Entries start before they end");
+                    match overlaps.last_mut() {
+                        Some(last) if last.end() >= overlap.start() => {
+                            *last = TimeSpan::new(last.start(), last.end().max(overlap.end()))
+                                .expect("Overlaps are ordered");
+                        }
+                        _ => overlaps.push(overlap),
+                    }
+                }
+                Some(reach) => {
+                    gaps.extend(TimeSpan::new(reach, start).ok());
+                    finish_group(&mut entries[group_start..i], lane_ends.len());
+                    lane_ends.clear();
+                    group_start = i;
+                }
+                None => {}
+            }
+            reach = Some(reach.map_or(end, |reach| reach.max(end)));
+
+            let lane = match lane_ends.iter().position(|lane_end| *lane_end <= start) {
+                Some(lane) => {
+                    lane_ends[lane] = end;
+                    lane
+                }
+                None => {
+                    lane_ends.push(end);
+                    lane_ends.len() - 1
+                }
+            };
+            entries[i].lane = lane as u32;
+        }
+        let lanes = lane_ends.len();
+        finish_group(&mut entries[group_start..], lanes);
+
+        Self {
+            day,
+            span,
+            entries,
+            gaps,
+            overlaps,
+        }
+    }
+}
+
+fn finish_group(group: &mut [TimelineEntry], lanes: usize) {
+    for entry in group {
+        entry.lanes = lanes as u32;
+    }
+}
+
+/// The whole `day` in local time.
+pub fn day_span(day: NaiveDate) -> TimeSpan {
+    let midnight = day.and_time(NaiveTime::MIN);
+    TimeSpan::new(
+        Timestamp::from_naive(midnight),
+        Timestamp::from_naive(midnight + Duration::days(1)),
+    )
+    .expect("A day has a positive length")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{ArchivedState, Database};
+
+    fn at(day: u32, hour: u32, minute: u32) -> Timestamp {
+        Timestamp::from_ymdhms(2024, 3, day, hour, minute, 0)
+    }
+
+    #[test]
+    fn test_timeline_lanes_gaps_and_overlaps() {
+        let mut db = Database::open_in_memory().unwrap();
+        let project = db.create_project("website").unwrap();
+        db.add_test_frames(&[
+            // Started the day before.
+            (&project, at(3, 22, 0), Some(at(4, 1, 0))),
+            (&project, at(4, 8, 0), Some(at(4, 12, 0))),
+            (&project, at(4, 9, 0), Some(at(4, 10, 0))),
+            (&project, at(4, 11, 0), Some(at(4, 12, 30))),
+            // Still running.
+            (&project, at(4, 13, 0), None),
+        ])
+        .unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let frames = db
+            .get_frames_in_span(day_span(day), ArchivedState::Both)
+            .unwrap();
+
+        let timeline = Timeline::new(day, &frames, at(4, 14, 0));
+
+        let layout: Vec<_> = timeline
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.span.start(),
+                    entry.span.end(),
+                    entry.lane,
+                    entry.lanes,
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (at(4, 0, 0), at(4, 1, 0), 0, 1),
+                (at(4, 8, 0), at(4, 12, 0), 0, 2),
+                (at(4, 9, 0), at(4, 10, 0), 1, 2),
+                (at(4, 11, 0), at(4, 12, 30), 1, 2),
+                (at(4, 13, 0), at(4, 14, 0), 0, 1),
+            ]
+        );
+        let span = |start, end| TimeSpan::new(start, end).unwrap();
+        assert_eq!(
+            timeline.gaps,
+            vec![
+                span(at(4, 1, 0), at(4, 8, 0)),
+                span(at(4, 12, 30), at(4, 13, 0))
+            ]
+        );
+        assert_eq!(
+            timeline.overlaps,
+            vec![
+                span(at(4, 9, 0), at(4, 10, 0)),
+                span(at(4, 11, 0), at(4, 12, 0))
+            ]
+        );
+    }
+}
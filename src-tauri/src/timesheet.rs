@@ -0,0 +1,391 @@
+//! Weekly timesheets as PDF for signing, available with the `pdf` feature, see `ttt export pdf`.
+//!
+//! Every week gets a page with the time per project and day, followed by a page with the totals
+//! of the whole period and lines for the signatures. Days off are listed in a row of their own. The header is configured in `config.toml`:
+//!
+//! ```toml
+//! [timesheet]
+//! name = "Jane Doe"
+//! client = "ACME Corp."
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+use serde::Deserialize;
+
+use crate::{
+    duration::{DurationExt, DurationFormat},
+    model::{Frame, LeaveDay, LeaveKind, Project, TimeSpan},
+    report::describe_days,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimesheetConfig {
+    /// Who tracked the time
+    pub name: Option<String>,
+
+    /// Who the time is billed to
+    pub client: Option<String>,
+}
+
+/// Time per project and day of one week, starting on Monday.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Week {
+    pub monday: NaiveDate,
+    pub rows: Vec<(String, [Duration; 7])>,
+
+    /// Days off, see `ttt leave`.
+    pub leave: [Option<LeaveKind>; 7],
+}
+
+#[derive(Debug)]
+pub struct Timesheet {
+    pub span: TimeSpan,
+    pub weeks: Vec<Week>,
+
+    /// Time per project in the whole span, sorted by name.
+    pub totals: Vec<(String, Duration)>,
+    pub total: Duration,
+}
+
+/// A4 in landscape, in points.
+const PAGE_WIDTH: f32 = 842.0;
+const PAGE_HEIGHT: f32 = 595.0;
+const MARGIN: f32 = 40.0;
+const ROW_HEIGHT: f32 = 18.0;
+const PROJECT_COLUMN: f32 = 200.0;
+const DAY_COLUMN: f32 = 70.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+impl Timesheet {
+    pub fn new(span: TimeSpan, frames: &[(Project, Frame)], leave: &[LeaveDay]) -> Self {
+        let mut weeks = BTreeMap::<NaiveDate, BTreeMap<&str, [Duration; 7]>>::new();
+        let mut days_off = BTreeMap::<NaiveDate, [Option<LeaveKind>; 7]>::new();
+        let mut totals = BTreeMap::<_, Duration>::new();
+        let mut total = Duration::zero();
+
+        // Frames crossing midnight or the ends of the span count with the part on each day.
+        let days = frames.iter().flat_map(|(project, frame)| {
+            frame
+                .span()
+                .and_then(|whole| whole.intersection(&span))
+                .into_iter()
+                .flat_map(TimeSpan::split_by_day)
+                .map(move |day| (project, day))
+        });
+        for (project, day) in days {
+            let duration = day.duration();
+            let (monday, weekday) = week_of(day.start().0.date_naive());
+
+            let row = weeks
+                .entry(monday)
+                .or_default()
+                .entry(&project.name)
+                .or_insert([Duration::zero(); 7]);
+            row[weekday] = row[weekday] + duration;
+            let project_total = totals
+                .entry(project.name.clone())
+                .or_insert_with(Duration::zero);
+            *project_total = *project_total + duration;
+            total = total + duration;
+        }
+        for leave in leave {
+            let (monday, weekday) = week_of(leave.day);
+            weeks.entry(monday).or_default();
+            days_off.entry(monday).or_default()[weekday] = Some(leave.kind);
+        }
+
+        Self {
+            span,
+            weeks: weeks
+                .into_iter()
+                .map(|(monday, rows)| Week {
+                    monday,
+                    leave: days_off.remove(&monday).unwrap_or_default(),
+                    rows: rows
+                        .into_iter()
+                        .map(|(name, days)| (name.to_owned(), days))
+                        .collect(),
+                })
+                .collect(),
+            totals: totals.into_iter().collect(),
+            total,
+        }
+    }
+
+    pub fn to_pdf(&self, config: &TimesheetConfig, durations: DurationFormat) -> Vec<u8> {
+        let mut pages = Vec::new();
+        for week in &self.weeks {
+            let mut page = Page::new(self, config);
+            let sunday = week.monday + Duration::days(6);
+            page.heading(&format!("Week of {} to {sunday}", week.monday));
+
+            let mut header = vec!["Project".to_owned()];
+            header.extend((0..7).map(|i| {
+                (week.monday + Duration::days(i))
+                    .format("%a %d.%m.")
+                    .to_string()
+            }));
+            header.push("Total".to_owned());
+            page.row(&header, true);
+
+            let mut per_day = [Duration::zero(); 7];
+            for (project, days) in &week.rows {
+                let mut cells = vec![project.clone()];
+                for (sum, duration) in per_day.iter_mut().zip(days) {
+                    *sum = *sum + *duration;
+                    cells.push(format_cell(*duration, durations));
+                }
+                let sum = days.iter().fold(Duration::zero(), |a, b| a + *b);
+                cells.push(sum.format_as(durations));
+                page.row(&cells, false);
+            }
+
+            if week.leave.iter().any(Option::is_some) {
+                let mut cells = vec!["Days off".to_owned()];
+                cells.extend(week.leave.iter().map(|kind| match kind {
+                    Some(kind) => kind.to_string(),
+                    None => String::new(),
+                }));
+                page.row(&cells, false);
+            }
+
+            let mut cells = vec!["Total".to_owned()];
+            cells.extend(per_day.iter().map(|d| format_cell(*d, durations)));
+            let sum = per_day.iter().fold(Duration::zero(), |a, b| a + *b);
+            cells.push(sum.format_as(durations));
+            page.row(&cells, true);
+            pages.push(page.content);
+        }
+
+        let mut page = Page::new(self, config);
+        page.heading("Totals");
+        page.row(&["Project".to_owned(), "Time".to_owned()], true);
+        for (project, duration) in &self.totals {
+            page.row(&[project.clone(), duration.format_as(durations)], false);
+        }
+        page.row(&["Total".to_owned(), self.total.format_as(durations)], true);
+        page.signatures();
+        pages.push(page.content);
+
+        write_pdf(pages)
+    }
+}
+
+/// The Monday of the week containing `day` and the index of `day` within the week.
+fn week_of(day: NaiveDate) -> (NaiveDate, usize) {
+    let weekday = day.weekday().num_days_from_monday() as usize;
+    (day - Duration::days(weekday as i64), weekday)
+}
+
+/// Empty cells stay blank, so the days with work stand out.
+fn format_cell(duration: Duration, durations: DurationFormat) -> String {
+    if duration.is_zero() {
+        String::new()
+    } else {
+        duration.format_as(durations)
+    }
+}
+
+/// Content of a page that is filled from top to bottom.
+struct Page {
+    content: Content,
+    y: f32,
+}
+
+impl Page {
+    /// A new page, starting with the header of the timesheet.
+    fn new(timesheet: &Timesheet, config: &TimesheetConfig) -> Self {
+        let mut page = Self {
+            content: Content::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        };
+        page.text(MARGIN, page.y, "Timesheet", BOLD, 18.0);
+        page.y -= 28.0;
+        let details = [
+            ("Name", config.name.as_deref()),
+            ("Client", config.client.as_deref()),
+            ("Period", Some(&describe_days(&timesheet.span))),
+        ];
+        for (label, value) in details {
+            if let Some(value) = value {
+                page.text(MARGIN, page.y, &format!("{label}:"), BOLD, 11.0);
+                page.text(MARGIN + 60.0, page.y, value, REGULAR, 11.0);
+                page.y -= 16.0;
+            }
+        }
+        page.y -= 12.0;
+        page
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.text(MARGIN, self.y, text, BOLD, 13.0);
+        self.y -= 22.0;
+    }
+
+    /// A row of the table, the first cell is the project and the others are days or totals.
+    fn row(&mut self, cells: &[String], bold: bool) {
+        let font = if bold { BOLD } else { REGULAR };
+        for (index, cell) in cells.iter().enumerate() {
+            let x = if index == 0 {
+                MARGIN
+            } else {
+                MARGIN + PROJECT_COLUMN + (index - 1) as f32 * DAY_COLUMN
+            };
+            self.text(x, self.y, cell, font, 10.0);
+        }
+        let width = PROJECT_COLUMN + (cells.len() - 1) as f32 * DAY_COLUMN;
+        self.content
+            .set_line_width(0.5)
+            .move_to(MARGIN, self.y - 5.0)
+            .line_to(MARGIN + width, self.y - 5.0)
+            .stroke();
+        self.y -= ROW_HEIGHT;
+    }
+
+    fn signatures(&mut self) {
+        self.y -= 60.0;
+        for (x, label) in [
+            (MARGIN, "Date, signature"),
+            (PAGE_WIDTH / 2.0, "Date, signature client"),
+        ] {
+            self.content
+                .set_line_width(0.5)
+                .move_to(x, self.y)
+                .line_to(x + 250.0, self.y)
+                .stroke();
+            self.text(x, self.y - 14.0, label, REGULAR, 9.0);
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, font: Name, size: f32) {
+        self.content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, y)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+}
+
+/// Encode `text` for the standard fonts, which cover Latin-1 only.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+fn write_pdf(pages: Vec<Content>) -> Vec<u8> {
+    let catalog = Ref::new(1);
+    let page_tree = Ref::new(2);
+    let regular = Ref::new(3);
+    let bold = Ref::new(4);
+    // Every page needs a reference for itself and one for its content.
+    let page_ids: Vec<_> = (0..pages.len() as i32)
+        .map(|i| (Ref::new(5 + 2 * i), Ref::new(6 + 2 * i)))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog).pages(page_tree);
+    pdf.pages(page_tree)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(pages.len() as i32);
+    for (font, name) in [(regular, "Helvetica"), (bold, "Helvetica-Bold")] {
+        pdf.type1_font(font)
+            .base_font(Name(name.as_bytes()))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+    for ((page_id, content_id), content) in page_ids.into_iter().zip(pages) {
+        let mut page = pdf.page(page_id);
+        page.parent(page_tree)
+            .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(REGULAR, regular)
+            .pair(BOLD, bold);
+        drop(page);
+        pdf.stream(content_id, &content.finish());
+    }
+    pdf.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        database::Database,
+        model::{FrameSource, Timestamp},
+    };
+
+    #[test]
+    fn test_timesheet() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut website = db.create_project("website").unwrap();
+        let mut client = db.create_project("client-x").unwrap();
+        for (day, hours) in [(4, 3), (5, 2)] {
+            let start = Timestamp::from_ymdhms(2024, 3, day, 9, 0, 0);
+            let span = TimeSpan::new(start, start + Duration::hours(hours)).unwrap();
+            db.add_frame(&mut website, span, FrameSource::Cli).unwrap();
+        }
+        for (day, hours) in [(5, 1), (12, 4)] {
+            let start = Timestamp::from_ymdhms(2024, 3, day, 14, 0, 0);
+            let span = TimeSpan::new(start, start + Duration::hours(hours)).unwrap();
+            db.add_frame(&mut client, span, FrameSource::Cli).unwrap();
+        }
+        let span = TimeSpan::new(
+            Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0),
+            Timestamp::from_ymdhms(2024, 3, 18, 0, 0, 0),
+        )
+        .unwrap();
+        let frames = db
+            .get_frames_in_span(span, crate::database::ArchivedState::Both)
+            .unwrap();
+
+        let leave = [LeaveDay {
+            day: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            kind: LeaveKind::Sick,
+            name: None,
+        }];
+
+        let timesheet = Timesheet::new(span, &frames, &leave);
+
+        let h = Duration::hours;
+        let z = Duration::zero();
+        assert_eq!(
+            timesheet.weeks,
+            vec![
+                Week {
+                    monday: NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+                    rows: vec![
+                        ("client-x".to_owned(), [z, h(1), z, z, z, z, z]),
+                        ("website".to_owned(), [h(3), h(2), z, z, z, z, z]),
+                    ],
+                    leave: [None; 7],
+                },
+                Week {
+                    monday: NaiveDate::from_ymd_opt(2024, 3, 11).unwrap(),
+                    rows: vec![("client-x".to_owned(), [z, h(4), z, z, z, z, z])],
+                    leave: [None, None, None, None, Some(LeaveKind::Sick), None, None],
+                },
+            ]
+        );
+        assert_eq!(timesheet.total, h(10));
+
+        let config = TimesheetConfig {
+            name: Some("Jürgen".to_owned()),
+            client: None,
+        };
+        let pdf = timesheet.to_pdf(&config, DurationFormat::Hms);
+        assert!(pdf.starts_with(b"%PDF"));
+        // Two weeks and the totals
+        assert!(pdf.windows(8).any(|w| w == b"/Count 3"));
+        // Non-ASCII text is written as hex string in WinAnsiEncoding
+        assert!(pdf.windows(14).any(|w| w == b"<4AFC7267656E>"));
+    }
+}
@@ -1,16 +1,44 @@
-#![allow(dead_code)] // TODO: Use code
-
-use std::{cmp::min, iter::Peekable};
-
-use chrono::{Datelike, Days, Months};
+use std::{cmp::min, fmt::Display, ops::Range};
+
+use chrono::{
+    Datelike, Days, Local, LocalResult, Months, NaiveDate, NaiveDateTime, Offset, TimeZone, Weekday,
+};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+use crate::{
+    locale::Locale,
+    model::{TimeSpan, TimeSpanError, Timestamp},
+};
+
+/// A word of the input and where it starts, as byte offset into the words joined by spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+    pub text: String,
+    pub position: usize,
+}
 
-use crate::model::{TimeSpan, TimeSpanError, Timestamp};
+impl Lexeme {
+    fn range(&self) -> Range<usize> {
+        self.position..self.position + self.text.len()
+    }
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ParseError {
     EmptyInput,
-    InvalidToken(String),
-    UnexpectedToken(String),
+
+    /// A word that is not part of the time span language.
+    InvalidToken {
+        lexeme: Lexeme,
+        suggestion: Option<String>,
+    },
+
+    /// A known word at the wrong place, `expected` explains what would fit instead.
+    UnexpectedToken {
+        lexeme: Lexeme,
+        expected: String,
+    },
+
     MissingEnd,
 
     EndBeforeStart(Timestamp, Timestamp),
@@ -22,6 +50,65 @@ pub enum ParseError {
     LanguageIsComplicated,
 }
 
+impl std::error::Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ParseError as E;
+        match self {
+            E::EmptyInput => write!(f, "No time span given"),
+            E::InvalidToken {
+                lexeme,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unknown word '{}', did you mean '{suggestion}'?",
+                lexeme.text
+            ),
+            E::InvalidToken {
+                lexeme,
+                suggestion: None,
+            } => write!(f, "Unknown word '{}'", lexeme.text),
+            E::UnexpectedToken { lexeme, expected } => {
+                write!(f, "Unexpected '{}', {expected}", lexeme.text)
+            }
+            E::MissingEnd => write!(f, "The time span ends too early"),
+            E::EndBeforeStart(start, end) => {
+                write!(
+                    f,
+                    "The span would end ({}) before it starts ({})",
+                    end.0, start.0
+                )
+            }
+            E::OutOfRange => write!(f, "The time span is out of range"),
+            E::LanguageIsComplicated => write!(
+                f,
+                "Nobody agrees what 'this'/'last' means for a day or month, use its name alone"
+            ),
+        }
+    }
+}
+
+impl ParseError {
+    /// The part of the input that caused the error, if the error can be pinned down.
+    fn location(&self, input: &str) -> Option<Range<usize>> {
+        match self {
+            ParseError::InvalidToken { lexeme, .. }
+            | ParseError::UnexpectedToken { lexeme, .. } => Some(lexeme.range()),
+            ParseError::MissingEnd => Some(input.len()..input.len() + 1),
+            _ => None,
+        }
+    }
+
+    /// Attach the parsed input to the error, to show the user where the problem is.
+    pub fn annotate(self, text: &[impl AsRef<str>]) -> Diagnostic {
+        Diagnostic {
+            error: self,
+            input: join(text),
+        }
+    }
+}
+
 impl From<TimeSpanError> for ParseError {
     fn from(value: TimeSpanError) -> Self {
         match value {
@@ -30,191 +117,535 @@ impl From<TimeSpanError> for ParseError {
     }
 }
 
-pub struct Context {
+/// A [`ParseError`] together with the input, displayed with a caret pointing at the problem:
+///
+/// ```text
+/// Unknown word 'wek', did you mean 'week'?
+///     last wek
+///          ^^^
+/// ```
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    input: String,
+}
+
+impl Diagnostic {
+    /// The part of the input that caused the error, counted in characters. May extend one
+    /// character past the end of the input if something is missing there.
+    fn highlight(&self) -> Option<Range<usize>> {
+        let location = self.error.location(&self.input)?;
+        let start = self.input[..location.start].chars().count();
+        let width = self
+            .input
+            .get(location)
+            .map_or(1, |text| text.chars().count());
+        Some(start..start + width)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(highlight) = self.highlight() {
+            write!(
+                f,
+                "\n    {}\n    {}{}",
+                self.input,
+                " ".repeat(highlight.start),
+                "^".repeat(highlight.len())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized for the GUI as the message and the character range to highlight in the input, if
+/// the problem can be pinned down.
+impl Serialize for Diagnostic {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut diagnostic = serializer.serialize_struct("Diagnostic", 3)?;
+        diagnostic.serialize_field("message", &self.error.to_string())?;
+        diagnostic.serialize_field("input", &self.input)?;
+        diagnostic.serialize_field("highlight", &self.highlight())?;
+        diagnostic.end()
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+pub struct Context<Tz: TimeZone = Local> {
     pub now: Timestamp,
+
+    /// Languages of month and weekday names besides English.
+    pub locales: Vec<Locale>,
+
+    /// How dates like "3.4." are read.
+    pub date_order: DateOrder,
+
+    /// Where days start, independent of the offset `now` happens to be stored with. The offset
+    /// is looked up for every date, so days around a daylight saving time switch are 23 or 25
+    /// hours long.
+    pub timezone: Tz,
+
+    /// First day of "this week" and "last week".
+    pub week_start: Weekday,
+}
+
+impl<Tz: TimeZone> Context<Tz> {
+    /// Now in [`Context::timezone`], so midnight is the one of the user.
+    fn local_now(&self) -> Timestamp {
+        let now = self.now.0.with_timezone(&self.timezone);
+        Timestamp(now.with_timezone(&now.offset().fix()))
+    }
+
+    /// The moment the clocks in [`Context::timezone`] show `time`. Times skipped when the clocks
+    /// are put forward are taken with the offset from before, times that happen twice are the
+    /// earlier one.
+    fn resolve(&self, time: NaiveDateTime) -> Result<Timestamp, ParseError> {
+        let offset = match self.timezone.offset_from_local_datetime(&time) {
+            LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset,
+            LocalResult::None => {
+                // A day earlier, the clocks were not put forward yet.
+                let before = time
+                    .checked_sub_days(Days::new(1))
+                    .ok_or(ParseError::OutOfRange)?;
+                self.timezone.offset_from_utc_datetime(&before)
+            }
+        };
+        time.and_local_timezone(offset.fix())
+            .single()
+            .map(Timestamp)
+            .ok_or(ParseError::OutOfRange)
+    }
+
+    /// The start of `date`.
+    fn start_of(&self, date: NaiveDate) -> Result<Timestamp, ParseError> {
+        self.resolve(date.and_time(chrono::NaiveTime::MIN))
+    }
+
+    /// The start of the day `time` is on.
+    fn midnight(&self, time: Timestamp) -> Result<Timestamp, ParseError> {
+        self.start_of(time.0.date_naive())
+    }
+
+    /// Move `timestamp` by `count` units, into the past if `count` is negative.
+    ///
+    /// Minutes and hours are exact durations, longer units keep the time of day even if the
+    /// offset of [`Context::timezone`] changes in between. Leaving the range of representable
+    /// time is an error.
+    fn shift(&self, timestamp: Timestamp, count: i64, unit: Type) -> Result<Timestamp, ParseError> {
+        let time = timestamp.0.naive_local();
+        let backwards = count < 0;
+        let distance = count.unsigned_abs();
+        let days = |days: Option<u64>| {
+            let days = Days::new(days?);
+            if backwards {
+                time.checked_sub_days(days)
+            } else {
+                time.checked_add_days(days)
+            }
+        };
+        let months = |months: Option<u64>| {
+            let months = Months::new(u32::try_from(months?).ok()?);
+            if backwards {
+                time.checked_sub_months(months)
+            } else {
+                time.checked_add_months(months)
+            }
+        };
+        let duration = |unit: fn(i64) -> chrono::Duration, limit: i64| {
+            if count.abs() > limit {
+                return None;
+            }
+            timestamp.0.checked_add_signed(unit(count)).map(Timestamp)
+        };
+
+        let shifted = match unit {
+            // Far beyond the representable years, but small enough for chrono::Duration.
+            Type::Minute => {
+                return duration(chrono::Duration::minutes, 1 << 40).ok_or(ParseError::OutOfRange)
+            }
+            Type::Hour => {
+                return duration(chrono::Duration::hours, 1 << 34).ok_or(ParseError::OutOfRange)
+            }
+            Type::Day => days(Some(distance)),
+            Type::Week => days(distance.checked_mul(7)),
+            Type::Month => months(Some(distance)),
+            Type::Year => months(distance.checked_mul(12)),
+            Type::Workweek | Type::Weekend | Type::Weekday(_) | Type::SpecificMonth(_) => {
+                return Err(ParseError::LanguageIsComplicated)
+            }
+        };
+        self.resolve(shifted.ok_or(ParseError::OutOfRange)?)
+    }
+
+    /// Move `timestamp` back by all `quantities`, largest unit first.
+    fn shift_back(
+        &self,
+        timestamp: Timestamp,
+        quantities: &[(u32, Type)],
+    ) -> Result<Timestamp, ParseError> {
+        quantities
+            .iter()
+            .try_fold(timestamp, |time, &(count, unit)| {
+                self.shift(time, -i64::from(count), unit)
+            })
+    }
 }
 
-pub fn parse(text: &[impl AsRef<str>], context: &Context) -> Result<TimeSpan, ParseError> {
-    let mut tokens = tokenize(text).peekable();
+/// Order of day and month in dates written as numbers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateOrder {
+    /// "3.4." is the 3rd of april
+    #[default]
+    DayFirst,
+
+    /// "3.4." is march 4th
+    MonthFirst,
+}
+
+pub fn parse(
+    text: &[impl AsRef<str>],
+    context: &Context<impl TimeZone>,
+) -> Result<TimeSpan, ParseError> {
+    let mut tokens = tokenize(&join(text), &context.locales);
+    tokens.check_words(&context.locales)?;
 
     let initial_timespan = parse_simple_timespan(&mut tokens, context)?;
 
     match tokens.next() {
         None => Ok(initial_timespan),
         Some(Token::To) => {
-            let full_timespan =
-                initial_timespan.extend(parse_simple_timespan(&mut tokens, context)?)?;
-            if tokens.peek().is_some() {
-                // TODO(texel, 2023-11-21): return original lexeme
-                return Err(ParseError::UnexpectedToken(format!("{:?}", tokens.peek())));
+            // "monday to now", also written without an end as "monday to"
+            let open_ended = tokens.peek().is_none();
+            let full_timespan = if open_ended || tokens.next_if_eq(&Token::Now).is_some() {
+                TimeSpan::new(initial_timespan.start(), context.local_now())?
+            } else {
+                initial_timespan.extend(parse_simple_timespan(&mut tokens, context)?)?
+            };
+            if tokens.next().is_some() {
+                return Err(tokens.unexpected("expected the end of the time span"));
             }
             Ok(full_timespan)
         }
-        Some(other_token) => Err(ParseError::UnexpectedToken(format!("{:?}", other_token))),
+        Some(_) => Err(tokens.unexpected("expected 'to' or the end of the time span")),
     }
 }
 
 /// Parses a timespan without the token "To", e.g. "last week".
 fn parse_simple_timespan(
-    tokens: &mut Peekable<impl Iterator<Item = Token>>,
-    context: &Context,
+    tokens: &mut Tokens,
+    context: &Context<impl TimeZone>,
 ) -> Result<TimeSpan, ParseError> {
     match tokens.next().ok_or(ParseError::EmptyInput)? {
-        Token::Day(0) if tokens.peek().is_some() => Err(ParseError::UnexpectedToken(format!(
-            "Unexpected token after 'today' {:?}",
-            tokens.peek().unwrap()
-        ))),
-        Token::Day(offset) if offset <= 0 => {
-            let offset = Days::new(-offset as u64);
-            let begin = context.now.at_midnight() - offset;
-            Ok(TimeSpan::new(
-                begin,
-                min(context.now, begin + Days::new(1)),
-            )?)
+        Token::Day(0) if tokens.peek().is_some() => {
+            tokens.next();
+            Err(tokens.unexpected("nothing can follow 'today'"))
         }
-        Token::To => Err(ParseError::UnexpectedToken(
-            "Timespan cannot start with 'To/Until'".to_owned(),
-        )),
-        Token::This if matches!(tokens.peek(), Some(Token::Span(_))) => {
-            let Some(Token::Span(span)) = tokens.next() else {
-                unreachable!()
-            };
-            Ok(parse_span(span, context, true)?)
+        Token::Day(offset) => {
+            let begin = context.shift(
+                context.midnight(context.local_now())?,
+                offset.into(),
+                Type::Day,
+            )?;
+            let end = context.shift(begin, 1, Type::Day)?;
+            Ok(TimeSpan::new(begin, min(context.local_now(), end))?)
         }
-        Token::Last if matches!(tokens.peek(), Some(Token::Span(_))) => {
-            let Some(Token::Span(span)) = tokens.next() else {
-                unreachable!()
+        Token::To => Err(tokens.unexpected("a time span can't start with 'to' or 'until'")),
+        Token::Now => Err(tokens
+            .unexpected("'now' can only be the end of a time span, e.g. '2 hours ago to now'")),
+
+        // parse e.g. "3 days ago" or "2 weeks and 3 days ago"
+        Token::Number(count) => {
+            let quantities = parse_quantities(tokens, count)?;
+            match tokens.next() {
+                Some(Token::Ago) => parse_ago(&quantities, context),
+                Some(_) => Err(tokens.unexpected(format!("expected 'ago' after '{count}'"))),
+                None => Err(ParseError::MissingEnd),
+            }
+        }
+
+        // parse e.g. "past 90 minutes", "past week" or "past 1 hour 30 minutes"
+        Token::Past => {
+            let count = match tokens.next_if(|token| matches!(token, Token::Number(_))) {
+                Some(Token::Number(count)) => count,
+                _ => 1,
             };
-            Ok(parse_span(span, context, false)?)
-        }
-
-        // parse e.g. "last 3 weeks"
-        Token::Last if matches!(tokens.peek(), Some(Token::Number(_))) => {
-            // let Some(Token::Number(number)) = tokens.next() else {
-            //     unreachable!()
-            // };
-            // let Some(token) = tokens.next() else {
-            //     return Err(ParseError::MissingEnd);
-            // };
-            // let Token::Span(span @ (Type::Week | Type::Month | Type::Year)) = token else {
-            //     return Err(ParseError::UnexpectedToken(
-            //         format!("Unexpected '{token:?}' after 'last {number}', expected 'weeks', 'months' or 'years'")
-            //     ));
-            // };
-            // let mut duration = parse_span(span, context, false)?;
-            // match span {
-            //     Type::Week => {
-            //         *duration.start_mut() = duration.start() - Days::new(7*number as u64);
-            //     },
-            //     Type::Month => {
-            //         *duration.start_mut() = duration.start() - Months::new(number as u32 - 1);
-            //     },
-            //     Type::Year => todo!(),
-            //     _ => unreachable!(),
-            // }
-            // Ok(duration)
-            todo!()
+            let quantities = parse_quantities(tokens, count)?;
+            parse_past(&quantities, context)
         }
+        token @ (Token::This | Token::Last) => match tokens.next() {
+            Some(Token::Span(Type::Minute | Type::Hour)) => {
+                Err(tokens.unexpected("use e.g. 'past 1 hour' instead"))
+            }
+            Some(Token::Span(span)) => parse_span(span, context, token == Token::This),
+
+            // parse e.g. "last 3 weeks" or "last 1 month 2 weeks"
+            Some(Token::Number(count)) if token == Token::Last => {
+                if count == 0 {
+                    return Err(tokens.unexpected("expected at least 1"));
+                }
+                let quantities = parse_quantities(tokens, count)?;
+                parse_last(&quantities, context)
+            }
+            Some(_) => Err(tokens.unexpected("expected e.g. 'week' or 'month'")),
+            None => Err(ParseError::MissingEnd),
+        },
         Token::Span(Type::Weekday(day)) => {
-            let now = context.now;
-            let mut start = now.at_midnight()
-                - Days::new(now.0.weekday().num_days_from_monday() as u64)
-                + Days::new(day as u64);
+            let now = context.local_now();
+            let monday = context.shift(
+                context.midnight(now)?,
+                -i64::from(now.0.weekday().num_days_from_monday()),
+                Type::Day,
+            )?;
+            let mut start = context.shift(monday, day.into(), Type::Day)?;
             if start > now {
-                start = start - Days::new(7);
+                start = context.shift(start, -1, Type::Week)?;
             }
-            let end = start + Days::new(1);
+            let end = context.shift(start, 1, Type::Day)?;
 
             Ok(TimeSpan::new(start, end)?)
         }
+        // The current workweek or weekend, or the previous one if this one did not start yet.
+        Token::Span(span @ (Type::Workweek | Type::Weekend)) => {
+            let current = parse_span(span, context, true)?;
+            if current.start() > context.local_now() {
+                parse_span(span, context, false)
+            } else {
+                Ok(current)
+            }
+        }
+        // parse e.g. "3rd of march" or "3. März"
+        Token::Ordinal(day) => {
+            tokens.next_if_eq(&Token::Of);
+            match tokens.next() {
+                Some(Token::Span(Type::SpecificMonth(month))) => {
+                    parse_date(tokens, None, u32::from(month) + 1, day, context)
+                }
+                Some(_) => Err(tokens.unexpected("expected a month like 'march'")),
+                None => Err(ParseError::MissingEnd),
+            }
+        }
+        Token::NumericDate(first, second, year) => {
+            let (day, month) = match context.date_order {
+                DateOrder::DayFirst => (first, second),
+                DateOrder::MonthFirst => (second, first),
+            };
+            parse_date(tokens, year, month, day, context)
+        }
+        Token::IsoDate(date) => {
+            parse_date(tokens, Some(date.year()), date.month(), date.day(), context)
+        }
+        // parse e.g. "march 3" or "march 3rd"
+        Token::Span(Type::SpecificMonth(month))
+            if matches!(tokens.peek(), Some(Token::Number(_) | Token::Ordinal(_))) =>
+        {
+            let Some(Token::Number(day) | Token::Ordinal(day)) = tokens.next() else {
+                unreachable!("Checked by the guard");
+            };
+            parse_date(tokens, None, u32::from(month) + 1, day, context)
+        }
         Token::Span(Type::SpecificMonth(month)) => {
-            let now = context.now;
-            let mut start: Timestamp = now
-                .at_midnight()
-                .0
-                .with_day(1)
-                .unwrap()
-                .with_month0(month as u32)
-                .unwrap()
-                .into();
+            let now = context.local_now();
+            let first = NaiveDate::from_ymd_opt(now.0.year(), u32::from(month) + 1, 1)
+                .ok_or(ParseError::OutOfRange)?;
+            let mut start = context.start_of(first)?;
 
             if start > now {
-                start = start - Months::new(12);
+                start = context.shift(start, -1, Type::Year)?;
             }
-            let end = start + Months::new(1);
+            let end = context.shift(start, 1, Type::Month)?;
 
             Ok(TimeSpan::new(start, end)?)
         }
-        other => Err(ParseError::UnexpectedToken(format!(
-            "Unexpected token '{other:?}'"
-        ))),
+        _ => Err(tokens.unexpected("expected a time span like 'last week' or 'march'")),
     }
 }
 
-fn parse_span(span: Type, context: &Context, is_current: bool) -> Result<TimeSpan, ParseError> {
-    let timespan = match span {
-        Type::Week => {
-            let now = context.now;
-            let start =
-                now.at_midnight() - Days::new(now.0.weekday().num_days_from_monday() as u64);
-            let end = start + Days::new(7);
+/// The whole day `day` of the month `month`, counted from 1. Without `year` it is the latest
+/// such day that started before now, like "march" is the latest march.
+fn parse_date(
+    tokens: &Tokens,
+    year: Option<i32>,
+    month: u32,
+    day: u32,
+    context: &Context<impl TimeZone>,
+) -> Result<TimeSpan, ParseError> {
+    let now = context.local_now();
+    // The 29th of february is at most 8 years ago.
+    let years = year.map_or(now.0.year() - 8..=now.0.year(), |year| year..=year);
+    let start = years
+        .rev()
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .filter_map(|date| context.start_of(date).ok())
+        .find(|start| year.is_some() || *start <= now)
+        .ok_or_else(|| tokens.unexpected("there is no such date"))?;
+    Ok(TimeSpan::new(start, context.shift(start, 1, Type::Day)?)?)
+}
 
-            TimeSpan::new(start, end)
-        }
-        Type::Month => {
-            let start = context.now.at_midnight().0.with_day(1).unwrap();
-            let end = start + Months::new(1);
+/// Parses the unit of e.g. "3 days ago".
+fn parse_unit(tokens: &mut Tokens) -> Result<Type, ParseError> {
+    match tokens.next() {
+        Some(Token::Span(
+            unit @ (Type::Minute | Type::Hour | Type::Day | Type::Week | Type::Month | Type::Year),
+        )) => Ok(unit),
+        Some(_) => Err(tokens.unexpected("expected a unit like 'hours' or 'days'")),
+        None => Err(ParseError::MissingEnd),
+    }
+}
 
-            TimeSpan::new(start, end)
+/// Parses the unit after `count` and any further quantities, e.g. "weeks and 3 days" after "2".
+/// The units must get smaller, the last one is the smallest.
+fn parse_quantities(tokens: &mut Tokens, count: u32) -> Result<Vec<(u32, Type)>, ParseError> {
+    let mut quantities = vec![(count, parse_unit(tokens)?)];
+    loop {
+        let and = tokens.next_if_eq(&Token::And).is_some();
+        let count = match tokens.next_if(|token| matches!(token, Token::Number(_))) {
+            Some(Token::Number(count)) => count,
+            _ if !and => return Ok(quantities),
+            _ if tokens.next().is_some() => {
+                return Err(tokens.unexpected("expected a number after 'and'"))
+            }
+            _ => return Err(ParseError::MissingEnd),
+        };
+        let unit = parse_unit(tokens)?;
+        let (_, previous) = quantities[quantities.len() - 1];
+        if unit_size(unit) >= unit_size(previous) {
+            return Err(tokens.unexpected("units must get smaller, e.g. '2 weeks 3 days'"));
         }
-        Type::Year => {
-            let start = context
-                .now
-                .at_midnight()
-                .0
-                .with_day(1)
-                .unwrap()
-                .with_month(1)
-                .unwrap();
-            let end = start + Months::new(12);
+        quantities.push((count, unit));
+    }
+}
 
-            TimeSpan::new(start, end)
+/// Orders the units returned by [`parse_unit`] from minutes to years.
+fn unit_size(unit: Type) -> u8 {
+    match unit {
+        Type::Minute => 0,
+        Type::Hour => 1,
+        Type::Day => 2,
+        Type::Week => 3,
+        Type::Month => 4,
+        _ => 5,
+    }
+}
+
+/// "2 hours ago" is the hour that started exactly two hours before now, while "2 days ago" is the
+/// whole calendar day. With several quantities the smallest unit decides, "1 week 2 days ago" is
+/// a calendar day.
+fn parse_ago(
+    quantities: &[(u32, Type)],
+    context: &Context<impl TimeZone>,
+) -> Result<TimeSpan, ParseError> {
+    let (_, unit) = quantities[quantities.len() - 1];
+    match unit {
+        Type::Minute | Type::Hour => {
+            let start = context.shift_back(context.local_now(), quantities)?;
+            Ok(TimeSpan::new(start, context.shift(start, 1, unit)?)?)
         }
-        Type::Weekday(_) => {
-            return Err(ParseError::LanguageIsComplicated);
+        _ => {
+            let current = parse_span(unit, context, true)?;
+            Ok(TimeSpan::new(
+                context.shift_back(current.start(), quantities)?,
+                context.shift_back(current.end(), quantities)?,
+            )?)
         }
-        Type::SpecificMonth(_) => return Err(ParseError::LanguageIsComplicated),
-    }?;
-
-    Ok(match (&span, is_current) {
-        (_, true) => timespan,
-        (Type::Week | Type::Weekday(_), false) => {
-            let start = timespan.start() - Days::new(7);
-            let end = timespan.end() - Days::new(7);
+    }
+}
 
-            TimeSpan::new(start, end)?
-        }
-        (Type::Month, false) => {
-            let start = timespan.start() - Months::new(1);
-            let end = timespan.end() - Months::new(1);
+/// "past 2 weeks" are the 14 days up to now.
+fn parse_past(
+    quantities: &[(u32, Type)],
+    context: &Context<impl TimeZone>,
+) -> Result<TimeSpan, ParseError> {
+    Ok(TimeSpan::new(
+        context.shift_back(context.local_now(), quantities)?,
+        context.local_now(),
+    )?)
+}
 
-            TimeSpan::new(start, end)?
-        }
-        (Type::Year | Type::SpecificMonth(_), false) => {
-            let start = timespan.start() - Months::new(12);
-            let end = timespan.end() - Months::new(12);
+/// "last 3 weeks" are the three complete weeks before the current one, while "last 3 hours" are
+/// the same as "past 3 hours". With several quantities the smallest unit decides, "last 1 month
+/// 2 weeks" ends when the current week started.
+fn parse_last(
+    quantities: &[(u32, Type)],
+    context: &Context<impl TimeZone>,
+) -> Result<TimeSpan, ParseError> {
+    let (_, unit) = quantities[quantities.len() - 1];
+    if matches!(unit, Type::Minute | Type::Hour) {
+        return parse_past(quantities, context);
+    }
+    let end = parse_span(unit, context, true)?.start();
+    Ok(TimeSpan::new(context.shift_back(end, quantities)?, end)?)
+}
 
-            TimeSpan::new(start, end)?
+fn parse_span(
+    span: Type,
+    context: &Context<impl TimeZone>,
+    is_current: bool,
+) -> Result<TimeSpan, ParseError> {
+    let now = context.local_now();
+    let today = context.midnight(now)?;
+    let first_of = |month| {
+        let date = NaiveDate::from_ymd_opt(now.0.year(), month, 1).ok_or(ParseError::OutOfRange)?;
+        context.start_of(date)
+    };
+    let monday = || {
+        context.shift(
+            today,
+            -i64::from(now.0.weekday().num_days_from_monday()),
+            Type::Day,
+        )
+    };
+    let week_start = || {
+        let days =
+            7 + now.0.weekday().num_days_from_monday() - context.week_start.num_days_from_monday();
+        context.shift(today, -i64::from(days % 7), Type::Day)
+    };
+
+    let (start, length, unit) = match span {
+        Type::Minute | Type::Hour | Type::Weekday(_) | Type::SpecificMonth(_) => {
+            return Err(ParseError::LanguageIsComplicated)
         }
-    })
+        Type::Day => (today, 1, Type::Day),
+        Type::Week => (week_start()?, 7, Type::Day),
+        Type::Workweek => (monday()?, 5, Type::Day),
+        Type::Weekend => (context.shift(monday()?, 5, Type::Day)?, 2, Type::Day),
+        Type::Month => (first_of(now.0.month())?, 1, Type::Month),
+        Type::Year => (first_of(1)?, 1, Type::Year),
+    };
+
+    let start = if is_current {
+        start
+    } else {
+        // The previous span of the same kind, e.g. the weekend a week ago.
+        let step = match span {
+            Type::Day => Type::Day,
+            Type::Month => Type::Month,
+            Type::Year => Type::Year,
+            _ => Type::Week,
+        };
+        context.shift(start, -1, step)?
+    };
+    Ok(TimeSpan::new(start, context.shift(start, length, unit)?)?)
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum Type {
+    Minute,
+    Hour,
+    Day,
     Week,
     Month,
     Year,
 
+    /// Monday to friday of a week
+    Workweek,
+
+    /// Saturday and sunday of a week
+    Weekend,
+
     /// Day of the week, zero based
     Weekday(u8),
 
@@ -222,7 +653,7 @@ enum Type {
     SpecificMonth(u8),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Token {
     /// A point in time relative to "Now". For example "today" = `Day(0)` and "yesterday" =
     /// `Day(-1)`.
@@ -235,60 +666,211 @@ enum Token {
     To,
     Number(u32),
 
+    /// Marks a point relative to now, e.g. "3 days ago".
+    Ago,
+
+    /// Starts a span that ends now, e.g. "past 2 hours".
+    Past,
+
+    Now,
+
+    /// Joins quantities, e.g. "2 weeks and 3 days ago".
+    And,
+
+    /// Joins a day and its month, e.g. "3rd of march".
+    Of,
+
+    /// Day of a month like "3rd" or "3.".
+    Ordinal(u32),
+
+    /// Day and month in the order of [`Context::date_order`] and an optional year, e.g.
+    /// "3.4." or "3.4.2024".
+    NumericDate(u32, u32, Option<i32>),
+
     PartialIsoDate(i32, u8),
     IsoDate(chrono::NaiveDate),
 
     Error(String),
 }
 
-fn tokenize(text: &[impl AsRef<str>]) -> impl Iterator<Item = Token> + '_ {
-    text.iter().map(|word| {
-        use Token::*;
-        match word.as_ref().to_lowercase().as_ref() {
-            "yesterday" => Day(-1),
-            "today" => Day(0),
-            "last" => Last,
-            "this" => This,
-            "to" | "until" => To,
-
-            "monday" => Span(Type::Weekday(0)),
-            "tuesday" => Span(Type::Weekday(1)),
-            "wednesday" => Span(Type::Weekday(2)),
-            "thursday" => Span(Type::Weekday(3)),
-            "friday" => Span(Type::Weekday(4)),
-            "saturday" => Span(Type::Weekday(5)),
-            "sunday" => Span(Type::Weekday(6)),
-
-            "january" => Span(Type::SpecificMonth(0)),
-            "february" => Span(Type::SpecificMonth(1)),
-            "march" => Span(Type::SpecificMonth(2)),
-            "april" => Span(Type::SpecificMonth(3)),
-            "may" => Span(Type::SpecificMonth(4)),
-            "june" => Span(Type::SpecificMonth(5)),
-            "july" => Span(Type::SpecificMonth(6)),
-            "august" => Span(Type::SpecificMonth(7)),
-            "september" => Span(Type::SpecificMonth(8)),
-            "october" => Span(Type::SpecificMonth(9)),
-            "november" => Span(Type::SpecificMonth(10)),
-            "december" => Span(Type::SpecificMonth(11)),
-
-            // TODO(texel, 2024-02-21): include days? last 3 days
-            "week" | "weeks" => Span(Type::Week),
-            "month" | "months" => Span(Type::Month),
-            "year" | "years" => Span(Type::Year),
-
-            x if x.parse::<u32>().is_ok() => Number(x.parse().unwrap()),
-
-            x if x.parse::<chrono::NaiveDate>().is_ok() => IsoDate(x.parse().unwrap()),
-
-            x if parse_partial_date(x).is_some() => {
-                let tmp = parse_partial_date(x).unwrap();
-                PartialIsoDate(tmp.0, tmp.1)
+const KEYWORDS: &[(&str, Token)] = &[
+    ("yesterday", Token::Day(-1)),
+    ("today", Token::Day(0)),
+    ("last", Token::Last),
+    ("this", Token::This),
+    ("to", Token::To),
+    ("until", Token::To),
+    ("ago", Token::Ago),
+    ("past", Token::Past),
+    ("now", Token::Now),
+    ("of", Token::Of),
+    ("and", Token::And),
+    ("minute", Token::Span(Type::Minute)),
+    ("minutes", Token::Span(Type::Minute)),
+    ("min", Token::Span(Type::Minute)),
+    ("mins", Token::Span(Type::Minute)),
+    ("hour", Token::Span(Type::Hour)),
+    ("hours", Token::Span(Type::Hour)),
+    ("day", Token::Span(Type::Day)),
+    ("days", Token::Span(Type::Day)),
+    ("week", Token::Span(Type::Week)),
+    ("weeks", Token::Span(Type::Week)),
+    ("workweek", Token::Span(Type::Workweek)),
+    ("weekdays", Token::Span(Type::Workweek)),
+    ("weekend", Token::Span(Type::Weekend)),
+    ("month", Token::Span(Type::Month)),
+    ("months", Token::Span(Type::Month)),
+    ("year", Token::Span(Type::Year)),
+    ("years", Token::Span(Type::Year)),
+];
+
+/// The tokens of the input, together with the words they were made of.
+struct Tokens {
+    tokens: Vec<(Token, Lexeme)>,
+
+    /// Index of the next token.
+    next: usize,
+
+    /// Length of the input, where "the end" of it is reported.
+    end: usize,
+}
+
+impl Tokens {
+    fn next(&mut self) -> Option<Token> {
+        let (token, _) = self.tokens.get(self.next)?;
+        self.next += 1;
+        Some(token.clone())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn next_if(&mut self, predicate: impl FnOnce(&Token) -> bool) -> Option<Token> {
+        if predicate(self.peek()?) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    fn next_if_eq(&mut self, expected: &Token) -> Option<Token> {
+        self.next_if(|token| token == expected)
+    }
+
+    /// Error about the token that was returned last by [`Tokens::next`].
+    fn unexpected(&self, expected: impl Into<String>) -> ParseError {
+        let lexeme = match self.next.checked_sub(1) {
+            Some(index) => self.tokens[index].1.clone(),
+            None => Lexeme {
+                text: String::new(),
+                position: self.end,
+            },
+        };
+        ParseError::UnexpectedToken {
+            lexeme,
+            expected: expected.into(),
+        }
+    }
+
+    /// Report the first word that is not part of the language.
+    fn check_words(&self, locales: &[Locale]) -> Result<(), ParseError> {
+        for (token, lexeme) in &self.tokens {
+            if let Token::Error(word) = token {
+                return Err(ParseError::InvalidToken {
+                    lexeme: lexeme.clone(),
+                    suggestion: suggest(&word.to_lowercase(), locales),
+                });
             }
+        }
+        Ok(())
+    }
+}
 
-            _ => Error(word.as_ref().to_owned()),
+fn all_locales(locales: &[Locale]) -> impl Iterator<Item = Locale> + '_ {
+    std::iter::once(Locale::En).chain(locales.iter().copied())
+}
+
+fn tokenize(input: &str, locales: &[Locale]) -> Tokens {
+    let tokens = input
+        .split_whitespace()
+        .map(|word| {
+            // `word` is a slice of `input`, so its offset is the position.
+            let position = word.as_ptr() as usize - input.as_ptr() as usize;
+            let lexeme = Lexeme {
+                text: word.to_owned(),
+                position,
+            };
+            (tokenize_word(word, locales), lexeme)
+        })
+        .collect();
+    Tokens {
+        tokens,
+        next: 0,
+        end: input.len(),
+    }
+}
+
+fn tokenize_word(word: &str, locales: &[Locale]) -> Token {
+    use Token::*;
+    let lowercase = word.to_lowercase();
+    let x = lowercase.as_str();
+
+    if let Some((_, token)) = KEYWORDS.iter().find(|(keyword, _)| *keyword == x) {
+        return token.clone();
+    }
+    if let Some(day) = all_locales(locales).find_map(|locale| locale.weekday(x)) {
+        return Span(Type::Weekday(day));
+    }
+    if let Some(month) = all_locales(locales).find_map(|locale| locale.month(x)) {
+        return Span(Type::SpecificMonth(month));
+    }
+    if let Ok(number) = x.parse() {
+        return Number(number);
+    }
+    if let Ok(date) = x.parse() {
+        return IsoDate(date);
+    }
+    if let Some((year, month)) = parse_partial_date(x) {
+        return PartialIsoDate(year, month);
+    }
+    if let Some(day) = parse_ordinal(x) {
+        return Ordinal(day);
+    }
+    if let Some((first, second, year)) = parse_numeric_date(x) {
+        return NumericDate(first, second, year);
+    }
+    Error(word.to_owned())
+}
+
+/// Parse a day of the month like "3rd", "21st" or the German "3.".
+fn parse_ordinal(word: &str) -> Option<u32> {
+    let number = ["st", "nd", "rd", "th", "."]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))?;
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// Parse a date like "3.4." or "3.4.2024" into its numbers.
+fn parse_numeric_date(word: &str) -> Option<(u32, u32, Option<i32>)> {
+    let mut parts = word.split('.');
+    let number = |part: Option<&str>| -> Option<u32> {
+        let part = part?;
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
         }
-    })
+        part.parse().ok()
+    };
+    let first = number(parts.next())?;
+    let second = number(parts.next())?;
+    let year = match parts.next()? {
+        "" => None,
+        year => Some(i32::try_from(number(Some(year))?).ok()?),
+    };
+    parts.next().is_none().then_some((first, second, year))
 }
 
 fn parse_partial_date(date: &str) -> Option<(i32, u8)> {
@@ -296,18 +878,53 @@ fn parse_partial_date(date: &str) -> Option<(i32, u8)> {
     Some((split.0.parse().ok()?, split.1.parse().ok()?))
 }
 
+/// The known word closest to the misspelled `word`, if any is close enough.
+fn suggest(word: &str, locales: &[Locale]) -> Option<String> {
+    let keywords = KEYWORDS.iter().map(|(keyword, _)| *keyword);
+    let names = all_locales(locales).flat_map(Locale::words);
+    keywords
+        .chain(names)
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|(distance, _)| *distance <= (word.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// Number of inserted, removed or replaced characters to get from `a` to `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != *b);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Join the words of the input like they were typed on the command line.
+fn join(text: &[impl AsRef<str>]) -> String {
+    text.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(" ")
+}
+
 #[cfg(test)]
 mod test {
-    use chrono::NaiveDate;
+    use chrono::{FixedOffset, NaiveDate};
 
     use super::*;
 
+    fn token_list(text: &str) -> Vec<Token> {
+        let tokens = tokenize(text, &[]).tokens;
+        tokens.into_iter().map(|(token, _)| token).collect()
+    }
+
     #[test]
     fn test_tokenize_examples() {
         fn check(text: &str, expected: Vec<Token>) {
-            let words: Vec<_> = text.split_whitespace().collect();
-
-            assert_eq!(tokenize(&words).collect::<Vec<_>>(), expected);
+            assert_eq!(token_list(text), expected);
         }
 
         use Token::*;
@@ -348,20 +965,33 @@ mod test {
         );
     }
 
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    /// A time in [`utc`], the timezone of the tests unless they simulate another one.
     fn new_timestamp(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> Timestamp {
-        Timestamp::from_naive(
-            NaiveDate::from_ymd_opt(y, m, d)
-                .unwrap()
-                .and_hms_opt(h, min, s)
-                .unwrap(),
-        )
+        let time = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, s)
+            .unwrap();
+        Timestamp(time.and_local_timezone(utc()).unwrap())
+    }
+
+    /// The context of most tests, at `now` in [`utc`] with the default settings.
+    fn context(now: Timestamp) -> Context<FixedOffset> {
+        Context {
+            now,
+            locales: Vec::new(),
+            date_order: DateOrder::DayFirst,
+            timezone: utc(),
+            week_start: Weekday::Mon,
+        }
     }
 
     #[test]
     fn test_parse_today() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 25, 0, 0, 0),
@@ -373,9 +1003,7 @@ mod test {
 
     #[test]
     fn test_parse_yesterday() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 24, 0, 0, 0),
@@ -387,9 +1015,7 @@ mod test {
 
     #[test]
     fn test_parse_simple_range() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 24, 0, 0, 0),
@@ -404,33 +1030,27 @@ mod test {
 
     #[test]
     fn test_parse_simple_range_with_garbage_at_the_end_fails() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         assert!(matches!(
             parse(&["yesterday", "until", "today", "to"], &context),
-            Err(ParseError::UnexpectedToken(_))
+            Err(ParseError::UnexpectedToken { .. })
         ));
     }
 
     #[test]
     fn test_this_today_is_not_allowed() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         assert!(matches!(
             parse(&["this", "today"], &context),
-            Err(ParseError::UnexpectedToken(_))
+            Err(ParseError::UnexpectedToken { .. })
         ));
     }
 
     #[test]
     fn test_parse_this_week() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 23, 0, 0, 0),
@@ -442,9 +1062,7 @@ mod test {
 
     #[test]
     fn test_parse_last_week() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 16, 0, 0, 0),
@@ -456,9 +1074,7 @@ mod test {
 
     #[test]
     fn test_parse_last_month() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 9, 1, 0, 0, 0),
@@ -470,9 +1086,7 @@ mod test {
 
     #[test]
     fn test_parse_this_month() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 10, 1, 0, 0, 0),
@@ -484,9 +1098,7 @@ mod test {
 
     #[test]
     fn test_parse_this_year() {
-        let context = Context {
-            now: new_timestamp(2023, 10, 25, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2023, 10, 25, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 1, 1, 0, 0, 0),
@@ -498,9 +1110,7 @@ mod test {
 
     #[test]
     fn test_parse_last_year() {
-        let context = Context {
-            now: new_timestamp(2024, 2, 29, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2024, 2, 29, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 1, 1, 0, 0, 0),
@@ -512,10 +1122,8 @@ mod test {
 
     #[test]
     fn test_parse_wednesday() {
-        let context = Context {
-            // saturday
-            now: new_timestamp(2024, 2, 24, 12, 33, 17),
-        };
+        // saturday
+        let context = context(new_timestamp(2024, 2, 24, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2024, 2, 21, 0, 0, 0),
@@ -527,10 +1135,8 @@ mod test {
 
     #[test]
     fn test_parse_wednesday_when_today_is_wednesday() {
-        let context = Context {
-            // wednesday
-            now: new_timestamp(2024, 2, 21, 12, 33, 17),
-        };
+        // wednesday
+        let context = context(new_timestamp(2024, 2, 21, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2024, 2, 21, 0, 0, 0),
@@ -542,10 +1148,8 @@ mod test {
 
     #[test]
     fn test_parse_complicated_language() {
-        let context = Context {
-            // wednesday
-            now: new_timestamp(2024, 2, 21, 12, 33, 17),
-        };
+        // wednesday
+        let context = context(new_timestamp(2024, 2, 21, 12, 33, 17));
 
         assert_eq!(
             parse(&["this", "thursday"], &context),
@@ -559,10 +1163,8 @@ mod test {
 
     #[test]
     fn test_parse_this_thursday() {
-        let context = Context {
-            // wednesday
-            now: new_timestamp(2024, 2, 21, 12, 33, 17),
-        };
+        // wednesday
+        let context = context(new_timestamp(2024, 2, 21, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2024, 2, 15, 0, 0, 0),
@@ -574,9 +1176,7 @@ mod test {
 
     #[test]
     fn test_parse_march() {
-        let context = Context {
-            now: new_timestamp(2024, 3, 21, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2024, 3, 1, 0, 0, 0),
@@ -588,9 +1188,7 @@ mod test {
 
     #[test]
     fn test_parse_april_returns_last_years_april() {
-        let context = Context {
-            now: new_timestamp(2024, 3, 21, 12, 33, 17),
-        };
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
 
         let expected = TimeSpan::new(
             new_timestamp(2023, 4, 1, 0, 0, 0),
@@ -601,11 +1199,269 @@ mod test {
     }
 
     #[test]
-    fn test_parse_more_complicated_thing() {
+    fn test_tokenize_relative() {
+        use Token::*;
+        assert_eq!(
+            token_list("3 Days ago to now past 90 mins"),
+            vec![
+                Number(3),
+                Span(Type::Day),
+                Ago,
+                To,
+                Now,
+                Past,
+                Number(90),
+                Span(Type::Minute)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_days_ago() {
+        let context = context(new_timestamp(2024, 3, 1, 0, 10, 0));
+
+        // The whole calendar day, even shortly after midnight.
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 27, 0, 0, 0),
+            new_timestamp(2024, 2, 28, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["3", "days", "ago"], &context).unwrap(), expected);
+
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 29, 0, 0, 0),
+            new_timestamp(2024, 3, 1, 0, 10, 0),
+        )
+        .unwrap();
+        assert_eq!(
+            parse(&["1", "day", "ago", "to", "now"], &context).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_hours_ago_to_now_across_midnight() {
+        let context = context(new_timestamp(2024, 3, 1, 0, 30, 0));
+
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 29, 22, 30, 0),
+            new_timestamp(2024, 3, 1, 0, 30, 0),
+        )
+        .unwrap();
+        assert_eq!(
+            parse(&["2", "hours", "ago", "to", "now"], &context).unwrap(),
+            expected
+        );
+
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 29, 22, 30, 0),
+            new_timestamp(2024, 2, 29, 23, 30, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["2", "hours", "ago"], &context).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_open_ended_ranges() {
+        // thursday
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+
+        let since_monday = TimeSpan::new(
+            new_timestamp(2024, 3, 18, 0, 0, 0),
+            new_timestamp(2024, 3, 21, 12, 33, 17),
+        )
+        .unwrap();
+        assert_eq!(parse(&["monday", "to", "now"], &context), Ok(since_monday));
+        assert_eq!(parse(&["monday", "to"], &context), Ok(since_monday));
+        assert_eq!(parse(&["monday", "until"], &context), Ok(since_monday));
+        assert_eq!(
+            parse(&["march", "3", "to"], &context).map(|span| span.end()),
+            Ok(context.now)
+        );
+
+        assert!(matches!(
+            parse(&["now", "to"], &context),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            parse(&["monday", "to", "now", "now"], &context),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_past() {
+        let context = context(new_timestamp(2024, 3, 1, 0, 30, 0));
+
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 29, 23, 0, 0),
+            new_timestamp(2024, 3, 1, 0, 30, 0),
+        )
+        .unwrap();
+        assert_eq!(
+            parse(&["past", "90", "minutes"], &context).unwrap(),
+            expected
+        );
+
+        let expected = TimeSpan::new(
+            new_timestamp(2024, 2, 23, 0, 30, 0),
+            new_timestamp(2024, 3, 1, 0, 30, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["past", "week"], &context).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_relative_errors() {
+        let context = context(new_timestamp(2024, 3, 1, 0, 30, 0));
+
+        assert_eq!(parse(&["3", "days"], &context), Err(ParseError::MissingEnd));
+        assert!(matches!(
+            parse(&["3", "monday", "ago"], &context),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            parse(&["now"], &context),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_localized_names() {
+        let context = Context {
+            locales: vec![Locale::De, Locale::Fr],
+            ..context(new_timestamp(2024, 3, 21, 12, 33, 17))
+        };
+
+        let march = TimeSpan::new(
+            new_timestamp(2024, 3, 1, 0, 0, 0),
+            new_timestamp(2024, 4, 1, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["März"], &context).unwrap(), march);
+        assert_eq!(parse(&["mars"], &context).unwrap(), march);
+        assert_eq!(parse(&["march"], &context).unwrap(), march);
+
+        let monday = TimeSpan::new(
+            new_timestamp(2024, 3, 18, 0, 0, 0),
+            new_timestamp(2024, 3, 19, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["Montag"], &context).unwrap(), monday);
+
+        // Spanish was not enabled.
+        assert!(parse(&["lunes"], &context).is_err());
+    }
+
+    #[test]
+    fn test_tokenize_positions() {
+        let tokens = tokenize("last  wek", &[]).tokens;
+        assert_eq!(
+            tokens[1],
+            (
+                Token::Error("wek".to_owned()),
+                Lexeme {
+                    text: "wek".to_owned(),
+                    position: 6
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
         let context = Context {
-            now: new_timestamp(2024, 3, 21, 12, 33, 17),
+            locales: vec![Locale::De],
+            ..context(new_timestamp(2024, 3, 21, 12, 33, 17))
         };
 
+        let words = ["last", "wek"];
+        let error = parse(&words, &context).unwrap_err();
+        assert_eq!(
+            error.annotate(&words).to_string(),
+            "Unknown word 'wek', did you mean 'week'?\n    last wek\n         ^^^"
+        );
+
+        let words = ["last week", "yesterday"];
+        let error = parse(&words, &context).unwrap_err();
+        assert_eq!(
+            error.annotate(&words).to_string(),
+            "Unexpected 'yesterday', expected 'to' or the end of the time span\n    \
+             last week yesterday\n              ^^^^^^^^^"
+        );
+
+        let words = ["3", "days"];
+        let error = parse(&words, &context).unwrap_err();
+        assert_eq!(
+            error.annotate(&words).to_string(),
+            "The time span ends too early\n    3 days\n          ^"
+        );
+
+        let words = ["März", "wek"];
+        let error = parse(&words, &context).unwrap_err().annotate(&words);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "message": "Unknown word 'wek', did you mean 'week'?",
+                "input": "März wek",
+                "highlight": {"start": 5, "end": 8},
+            })
+        );
+
+        let error = parse(&["Mätz"], &context).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::InvalidToken { suggestion: Some(s), .. } if s == "märz"
+        ));
+        let error = parse(&["xyzzy"], &context).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::InvalidToken {
+                suggestion: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_workweek_and_weekend() {
+        // wednesday
+        let context = context(new_timestamp(2024, 2, 21, 12, 33, 17));
+
+        let this_workweek = TimeSpan::new(
+            new_timestamp(2024, 2, 19, 0, 0, 0),
+            new_timestamp(2024, 2, 24, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["workweek"], &context).unwrap(), this_workweek);
+        assert_eq!(
+            parse(&["this", "weekdays"], &context).unwrap(),
+            this_workweek
+        );
+
+        let last_weekend = TimeSpan::new(
+            new_timestamp(2024, 2, 17, 0, 0, 0),
+            new_timestamp(2024, 2, 19, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(parse(&["last", "weekend"], &context).unwrap(), last_weekend);
+        // The weekend of this week did not start yet.
+        assert_eq!(parse(&["weekend"], &context).unwrap(), last_weekend);
+
+        assert_eq!(
+            parse(&["this", "weekend"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 2, 24, 0, 0, 0),
+                new_timestamp(2024, 2, 26, 0, 0, 0),
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_more_complicated_thing() {
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+
         let expected = TimeSpan::new(
             new_timestamp(2023, 4, 1, 0, 0, 0),
             new_timestamp(2024, 3, 21, 0, 0, 0),
@@ -616,12 +1472,524 @@ mod test {
             expected
         );
         //assert_eq!(parse(&["april", "to", "2023-03-20"], &context).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_dates() {
+        let mut context = Context {
+            locales: vec![Locale::De],
+            ..context(new_timestamp(2024, 3, 21, 12, 33, 17))
+        };
+        let day = |y, m, d| {
+            let start = new_timestamp(y, m, d, 0, 0, 0);
+            TimeSpan::new(start, start + chrono::Duration::days(1)).unwrap()
+        };
+
+        let third = day(2024, 3, 3);
+        assert_eq!(parse(&["3rd", "of", "march"], &context), Ok(third));
+        assert_eq!(parse(&["3rd", "march"], &context), Ok(third));
+        assert_eq!(parse(&["march", "3"], &context), Ok(third));
+        assert_eq!(parse(&["March", "3rd"], &context), Ok(third));
+        assert_eq!(parse(&["3.", "März"], &context), Ok(third));
+        assert_eq!(parse(&["3.3."], &context), Ok(third));
+        assert_eq!(parse(&["2024-03-03"], &context), Ok(third));
+        assert_eq!(parse(&["21st", "march"], &context), Ok(day(2024, 3, 21)));
+
+        // Like months, dates without a year are the latest one that started.
+        assert_eq!(parse(&["march", "22"], &context), Ok(day(2023, 3, 22)));
+        assert_eq!(
+            parse(&["29th", "of", "february"], &context),
+            Ok(day(2024, 2, 29))
+        );
+        let leap_day = parse(
+            &["29.2."],
+            &self::context(new_timestamp(2024, 2, 28, 12, 0, 0)),
+        );
+        assert_eq!(leap_day, Ok(day(2020, 2, 29)));
+        assert_eq!(parse(&["1.4.2025"], &context), Ok(day(2025, 4, 1)));
+
+        assert_eq!(parse(&["3.4."], &context), Ok(day(2023, 4, 3)));
+        context.date_order = DateOrder::MonthFirst;
+        assert_eq!(parse(&["3.4."], &context), Ok(day(2024, 3, 4)));
+
+        assert_eq!(
+            parse(&["march", "3", "to", "5th", "of", "march"], &context),
+            Ok(TimeSpan::new(
+                new_timestamp(2024, 3, 3, 0, 0, 0),
+                new_timestamp(2024, 3, 6, 0, 0, 0)
+            )
+            .unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_date_errors() {
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+        let unexpected = |words: &[&str]| match parse(words, &context) {
+            Err(ParseError::UnexpectedToken { lexeme, expected }) => (lexeme.text, expected),
+            other => panic!("Expected an error, got {other:?}"),
+        };
+
+        assert_eq!(
+            unexpected(&["31st", "of", "april"]),
+            ("april".to_owned(), "there is no such date".to_owned())
+        );
+        assert_eq!(
+            unexpected(&["13.13."]),
+            ("13.13.".to_owned(), "there is no such date".to_owned())
+        );
+        assert_eq!(
+            unexpected(&["29.2.2023"]),
+            ("29.2.2023".to_owned(), "there is no such date".to_owned())
+        );
+        assert_eq!(
+            unexpected(&["3rd", "of", "week"]),
+            (
+                "week".to_owned(),
+                "expected a month like 'march'".to_owned()
+            )
+        );
+        assert_eq!(parse(&["3rd", "of"], &context), Err(ParseError::MissingEnd));
+        assert!(matches!(
+            parse(&["3.4.5.6"], &context),
+            Err(ParseError::InvalidToken { .. })
+        ));
+    }
 
-        // assert_eq!(
-        //     parse(&["last", "3", "weeks"], &context).unwrap(),
-        //     TimeSpan::new(
-        //         new_timestamp(2023, 4, 1, 0, 0, 0),
-        //         new_timestamp(2024, 3, 21, 12, 33, 17),
-        //     ).unwrap());
+    #[test]
+    fn test_parse_last_n_units() {
+        // thursday
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+
+        assert_eq!(
+            parse(&["last", "3", "weeks"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 2, 26, 0, 0, 0),
+                new_timestamp(2024, 3, 18, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["last", "2", "months"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 1, 1, 0, 0, 0),
+                new_timestamp(2024, 3, 1, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["last", "1", "week"], &context),
+            parse(&["last", "week"], &context)
+        );
+        assert_eq!(
+            parse(&["last", "2", "hours"], &context),
+            parse(&["past", "2", "hours"], &context)
+        );
+        assert!(matches!(
+            parse(&["last", "0", "days"], &context),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_in_other_timezones() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        // Already friday in Tokyo
+        let context = Context {
+            timezone: tokyo,
+            ..context(new_timestamp(2024, 3, 21, 20, 0, 0))
+        };
+        let midnight = |d| {
+            NaiveDate::from_ymd_opt(2024, 3, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_local_timezone(tokyo)
+                .unwrap()
+        };
+
+        assert_eq!(
+            parse(&["today"], &context).unwrap(),
+            TimeSpan::new(Timestamp(midnight(22)), context.now).unwrap()
+        );
+        assert_eq!(
+            parse(&["yesterday"], &context).unwrap(),
+            TimeSpan::new(Timestamp(midnight(21)), Timestamp(midnight(22))).unwrap()
+        );
+        assert_eq!(
+            parse(&["21.3."], &context).unwrap().start(),
+            Timestamp(midnight(21))
+        );
+    }
+
+    /// Central European time in 2024, summer time from March 31st to October 27th.
+    #[derive(Debug, Clone, Copy)]
+    struct Berlin;
+
+    impl TimeZone for Berlin {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Berlin
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(chrono::NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let matching: Vec<_> = [2, 1]
+                .into_iter()
+                .map(|hours| FixedOffset::east_opt(hours * 3600).unwrap())
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match matching[..] {
+                [offset] => LocalResult::Single(offset),
+                [earlier, later] => LocalResult::Ambiguous(earlier, later),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(chrono::NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let switch = |month, day| {
+                NaiveDate::from_ymd_opt(2024, month, day)
+                    .unwrap()
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap()
+            };
+            let summer = (switch(3, 31)..switch(10, 27)).contains(utc);
+            FixedOffset::east_opt(if summer { 2 * 3600 } else { 3600 }).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_parse_across_daylight_saving_time() {
+        let at = |month, day, hour| {
+            let time = NaiveDate::from_ymd_opt(2024, month, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+            Timestamp(
+                Berlin
+                    .from_local_datetime(&time)
+                    .earliest()
+                    .unwrap()
+                    .fixed_offset(),
+            )
+        };
+        // sunday, the clocks were put forward at 2:00
+        let context = Context {
+            now: at(3, 31, 12),
+            locales: Vec::new(),
+            date_order: DateOrder::DayFirst,
+            timezone: Berlin,
+            week_start: Weekday::Mon,
+        };
+
+        let this_week = parse(&["this", "week"], &context).unwrap();
+        assert_eq!(this_week, TimeSpan::new(at(3, 25, 0), at(4, 1, 0)).unwrap());
+        assert_eq!(this_week.duration(), chrono::Duration::hours(7 * 24 - 1));
+        assert_eq!(
+            parse(&["yesterday"], &context).unwrap(),
+            TimeSpan::new(at(3, 30, 0), at(3, 31, 0)).unwrap()
+        );
+        assert_eq!(
+            parse(&["2", "hours", "ago"], &context).unwrap(),
+            TimeSpan::new(at(3, 31, 10), at(3, 31, 11)).unwrap()
+        );
+
+        // The clocks are put back at 3:00, so the day has 25 hours.
+        let day = parse(&["27.10.2024"], &context).unwrap();
+        assert_eq!(day, TimeSpan::new(at(10, 27, 0), at(10, 28, 0)).unwrap());
+        assert_eq!(day.duration(), chrono::Duration::hours(25));
+    }
+
+    #[test]
+    fn test_split_by_day_across_daylight_saving_time() {
+        let at = |month, day, hour| {
+            let time = NaiveDate::from_ymd_opt(2024, month, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+            Timestamp::from_naive_in(time, &Berlin)
+        };
+        let hours = |start, end| {
+            TimeSpan::new(start, end)
+                .unwrap()
+                .split_by_day_in(Berlin)
+                .map(|day| day.duration().num_hours())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(hours(at(3, 30, 12), at(4, 1, 12)), [12, 23, 12]);
+        assert_eq!(hours(at(10, 26, 12), at(10, 29, 12)), [12, 25, 24, 12]);
+        let days: Vec<_> = TimeSpan::new(at(10, 26, 12), at(10, 29, 12))
+            .unwrap()
+            .split_by_day_in(Berlin)
+            .map(|day| day.start())
+            .collect();
+        assert_eq!(
+            days,
+            [at(10, 26, 12), at(10, 27, 0), at(10, 28, 0), at(10, 29, 0)]
+        );
+    }
+
+    #[test]
+    fn test_parse_week_start() {
+        // thursday
+        let mut context = Context {
+            week_start: Weekday::Sun,
+            ..context(new_timestamp(2024, 3, 21, 12, 33, 17))
+        };
+
+        assert_eq!(
+            parse(&["this", "week"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 3, 17, 0, 0, 0),
+                new_timestamp(2024, 3, 24, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["last", "week"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 3, 10, 0, 0, 0),
+                new_timestamp(2024, 3, 17, 0, 0, 0),
+            )
+            .unwrap()
+        );
+
+        // Starting on the current weekday
+        context.week_start = Weekday::Thu;
+        assert_eq!(
+            parse(&["this", "week"], &context).unwrap().start(),
+            new_timestamp(2024, 3, 21, 0, 0, 0)
+        );
+
+        // The workweek is monday to friday regardless
+        assert_eq!(
+            parse(&["workweek"], &context).unwrap().start(),
+            new_timestamp(2024, 3, 18, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_compound_quantities() {
+        // thursday
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+
+        assert_eq!(
+            parse(&["2", "weeks", "3", "days", "ago"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 3, 4, 0, 0, 0),
+                new_timestamp(2024, 3, 5, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["2", "weeks", "and", "3", "days", "ago"], &context),
+            parse(&["2", "weeks", "3", "days", "ago"], &context)
+        );
+        assert_eq!(
+            parse(&["1", "hour", "30", "minutes", "ago"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 3, 21, 11, 3, 17),
+                new_timestamp(2024, 3, 21, 11, 4, 17),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["last", "1", "month", "2", "weeks"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 2, 4, 0, 0, 0),
+                new_timestamp(2024, 3, 18, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["last", "2", "weeks", "and", "3", "days"], &context).unwrap(),
+            TimeSpan::new(
+                new_timestamp(2024, 3, 4, 0, 0, 0),
+                new_timestamp(2024, 3, 21, 0, 0, 0),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            parse(&["past", "hour", "and", "30", "minutes"], &context).unwrap(),
+            TimeSpan::new(new_timestamp(2024, 3, 21, 11, 3, 17), context.now).unwrap()
+        );
+
+        let unexpected = |words: &[&str]| match parse(words, &context) {
+            Err(ParseError::UnexpectedToken { lexeme, expected }) => (lexeme.text, expected),
+            other => panic!("Expected an error, got {other:?}"),
+        };
+        assert_eq!(
+            unexpected(&["3", "days", "2", "weeks", "ago"]),
+            (
+                "weeks".to_owned(),
+                "units must get smaller, e.g. '2 weeks 3 days'".to_owned()
+            )
+        );
+        assert_eq!(
+            unexpected(&["2", "weeks", "and", "ago"]),
+            ("ago".to_owned(), "expected a number after 'and'".to_owned())
+        );
+        assert_eq!(
+            parse(&["past", "2", "weeks", "and"], &context),
+            Err(ParseError::MissingEnd)
+        );
+    }
+
+    #[test]
+    fn test_parse_out_of_range() {
+        let context = context(new_timestamp(2024, 3, 21, 12, 33, 17));
+
+        assert_eq!(
+            parse(&["4000000000", "years", "ago"], &context),
+            Err(ParseError::OutOfRange)
+        );
+        assert_eq!(
+            parse(&["past", "4000000000", "weeks"], &context),
+            Err(ParseError::OutOfRange)
+        );
+    }
+
+    mod properties {
+        use chrono::{FixedOffset, NaiveTime, TimeZone};
+        use proptest::prelude::*;
+
+        use super::*;
+
+        const WORDS: &[&str] = &[
+            "today",
+            "yesterday",
+            "last",
+            "this",
+            "to",
+            "until",
+            "ago",
+            "past",
+            "now",
+            "0",
+            "1",
+            "3",
+            "4294967295",
+            "minutes",
+            "hours",
+            "days",
+            "weeks",
+            "month",
+            "years",
+            "workweek",
+            "weekend",
+            "monday",
+            "sunday",
+            "march",
+            "december",
+            "März",
+            "2023-07",
+            "2023-07-03",
+            "3rd",
+            "of",
+            "31.",
+            "29.2.",
+            "and",
+            "wek",
+        ];
+
+        fn word() -> impl Strategy<Value = String> {
+            prop_oneof![
+                4 => proptest::sample::select(WORDS).prop_map(str::to_owned),
+                1 => "\\PC{0,8}",
+            ]
+        }
+
+        /// Any time, including ones close to the limits of chrono to provoke overflows.
+        fn now() -> impl Strategy<Value = Timestamp> {
+            (-262_000i32..262_000, 1u32..=365, 0u32..86_400, -12i32..=12).prop_map(
+                |(year, day, second, offset)| {
+                    let date = NaiveDate::from_yo_opt(year, day).unwrap();
+                    let time = NaiveTime::from_num_seconds_from_midnight_opt(second, 0).unwrap();
+                    let offset = FixedOffset::east_opt(offset * 3600).unwrap();
+                    Timestamp(offset.from_local_datetime(&date.and_time(time)).unwrap())
+                },
+            )
+        }
+
+        const UNITS: &[&str] = &["minutes", "hours", "days", "weeks", "months", "years"];
+
+        proptest! {
+            /// One more of the smallest unit moves the start further into the past, and so does
+            /// adding a smaller unit to "past". Not to "last", whose end depends on the smallest
+            /// unit.
+            #[test]
+            fn compound_quantities_are_monotonic(
+                prefix in proptest::sample::select(&["past", "last", ""][..]),
+                larger in 1usize..UNITS.len(),
+                smaller in 0usize..UNITS.len() - 1,
+                a in 1u32..100,
+                b in 0u32..100,
+                now in now(),
+            ) {
+                prop_assume!(smaller < larger);
+                let context = Context {
+                    timezone: *now.0.offset(),
+                    ..context(now)
+                };
+                let words = |quantities: &[(u32, &str)]| {
+                    let mut words = vec![prefix.to_owned()];
+                    for (count, unit) in quantities {
+                        words.push(count.to_string());
+                        words.push(unit.to_string());
+                    }
+                    if prefix.is_empty() {
+                        words.push("ago".to_owned());
+                    }
+                    words
+                };
+                let start = |quantities: &[(u32, &str)]| {
+                    parse(&words(quantities), &context).ok().map(|span| span.start())
+                };
+                let (larger, smaller) = (UNITS[larger], UNITS[smaller]);
+
+                let compound = start(&[(a, larger), (b, smaller)]);
+                if let (Some(compound), Some(more)) =
+                    (compound, start(&[(a, larger), (b + 1, smaller)]))
+                {
+                    prop_assert!(more < compound);
+                }
+                if let ("past", Some(compound), Some(single)) =
+                    (prefix, compound, start(&[(a, larger)]))
+                {
+                    prop_assert!(compound <= single);
+                }
+            }
+
+            #[test]
+            fn parse_never_panics(
+                words in prop::collection::vec(word(), 0..6),
+                now in now(),
+                offset in -12i32..=12,
+                week_start in 0u8..7,
+            ) {
+                let context = Context {
+                    locales: vec![Locale::De],
+                    timezone: FixedOffset::east_opt(offset * 3600).unwrap(),
+                    week_start: Weekday::try_from(week_start).unwrap(),
+                    ..context(now)
+                };
+                match parse(&words, &context) {
+                    Ok(span) => prop_assert!(span.start() < span.end()),
+                    Err(err) => {
+                        // Rendering the diagnostic must not panic either.
+                        let _ = err.annotate(&words).to_string();
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,216 @@
+//! Tracked time per day, week or month over the last few of them, to see whether the load of a
+//! project grows, see `ttt trend`.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    duration::{DurationExt, DurationFormat},
+    model::{serialize_duration, DailyTotal, TimeSpan, Timestamp},
+    report::describe_days,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+}
+
+impl Window {
+    /// The last `count` windows up to the one containing `today`, the oldest first.
+    pub fn last(self, count: usize, today: NaiveDate, week_start: Weekday) -> Vec<TimeSpan> {
+        let first = match self {
+            Window::Day => today,
+            Window::Week => today.week(week_start).first_day(),
+            Window::Month => today.with_day(1).expect("Every month has a first day"),
+        };
+        let mut firsts: Vec<_> = std::iter::successors(Some(first), |&first| match self {
+            Window::Day => Some(first - Duration::days(1)),
+            Window::Week => Some(first - Duration::days(7)),
+            Window::Month => first.checked_sub_months(Months::new(1)),
+        })
+        .take(count)
+        .collect();
+        firsts.reverse();
+
+        let midnight = |day: NaiveDate| Timestamp::from_naive(day.and_time(Default::default()));
+        firsts
+            .into_iter()
+            .map(|first| {
+                let next = match self {
+                    Window::Day => first + Duration::days(1),
+                    Window::Week => first + Duration::days(7),
+                    Window::Month => first + Months::new(1),
+                };
+                TimeSpan::new(midnight(first), midnight(next))
+                    .expect("Windows end after they start")
+            })
+            .collect()
+    }
+}
+
+/// The time of each window, in total and for some projects.
+#[derive(Debug)]
+pub struct Trend {
+    /// Projects with their own column, in the order they were asked for.
+    pub projects: Vec<String>,
+
+    /// The windows, the oldest first, with the total and the time of each of the `projects`.
+    pub windows: Vec<(TimeSpan, Duration, Vec<Duration>)>,
+}
+
+impl Trend {
+    /// Sum up the daily `totals` per window, see [`Window::last`].
+    pub fn new(windows: Vec<TimeSpan>, totals: &[DailyTotal], projects: Vec<String>) -> Self {
+        let mut windows: Vec<_> = windows
+            .into_iter()
+            .map(|span| {
+                (
+                    span,
+                    Duration::zero(),
+                    vec![Duration::zero(); projects.len()],
+                )
+            })
+            .collect();
+        for total in totals {
+            let window = windows.iter_mut().find(|(span, _, _)| {
+                span.start().0.date_naive() <= total.day && total.day < span.end().0.date_naive()
+            });
+            let Some((_, sum, per_project)) = window else {
+                continue;
+            };
+            *sum = *sum + total.duration;
+            if let Some(index) = projects.iter().position(|name| *name == total.project.name) {
+                per_project[index] = per_project[index] + total.duration;
+            }
+        }
+        Self { projects, windows }
+    }
+
+    pub fn header(&self) -> Vec<String> {
+        ["Period", "Total"]
+            .into_iter()
+            .map(str::to_owned)
+            .chain(self.projects.iter().cloned())
+            .collect()
+    }
+
+    /// A row per window with the formatted durations, `None` for hours with two decimals as in
+    /// spreadsheets.
+    pub fn rows(&self, durations: Option<DurationFormat>) -> Vec<Vec<String>> {
+        let format = |duration: &Duration| match durations {
+            Some(format) => duration.format_as(format),
+            None => format!("{:.2}", duration.num_seconds() as f64 / 3600.0),
+        };
+        self.windows
+            .iter()
+            .map(|(span, total, per_project)| {
+                [describe_days(span), format(total)]
+                    .into_iter()
+                    .chain(per_project.iter().map(format))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The windows as JSON array, durations are ISO 8601.
+    pub fn to_json(&self) -> String {
+        let windows: Vec<_> = self
+            .windows
+            .iter()
+            .map(|(span, total, per_project)| JsonWindow {
+                span: *span,
+                total: *total,
+                projects: self
+                    .projects
+                    .iter()
+                    .zip(per_project)
+                    .map(|(name, &duration)| JsonProject { name, duration })
+                    .collect(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&windows).expect("Trends are serializable") + "\n"
+    }
+}
+
+#[derive(Serialize)]
+struct JsonWindow<'a> {
+    #[serde(flatten)]
+    span: TimeSpan,
+    #[serde(serialize_with = "serialize_duration")]
+    total: Duration,
+    projects: Vec<JsonProject<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonProject<'a> {
+    name: &'a str,
+    #[serde(serialize_with = "serialize_duration")]
+    duration: Duration,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::Database;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_windows() {
+        let weeks = Window::Week.last(3, date(3, 14), Weekday::Mon);
+        let starts: Vec<_> = weeks.iter().map(|span| span.start()).collect();
+        assert_eq!(
+            starts,
+            [
+                Timestamp::from_ymdhms(2024, 2, 26, 0, 0, 0),
+                Timestamp::from_ymdhms(2024, 3, 4, 0, 0, 0),
+                Timestamp::from_ymdhms(2024, 3, 11, 0, 0, 0),
+            ]
+        );
+        assert_eq!(weeks[2].end(), Timestamp::from_ymdhms(2024, 3, 18, 0, 0, 0));
+
+        let months = Window::Month.last(2, date(3, 31), Weekday::Mon);
+        assert_eq!(describe_days(&months[0]), "2024-02-01 to 2024-02-29");
+        assert_eq!(Window::Day.last(1, date(3, 14), Weekday::Mon).len(), 1);
+    }
+
+    #[test]
+    fn test_trend() {
+        let mut db = Database::open_in_memory().unwrap();
+        let website = db.create_project("website").unwrap();
+        let other = db.create_project("other").unwrap();
+        let total = |project: &crate::model::Project, day, hours| DailyTotal {
+            project: project.clone(),
+            day: date(3, day),
+            duration: Duration::hours(hours),
+        };
+        let totals = [
+            total(&website, 5, 2),
+            total(&other, 5, 1),
+            total(&website, 12, 3),
+            total(&website, 12, 4),
+            // Before the first window
+            total(&website, 1, 8),
+        ];
+        let trend = Trend::new(
+            Window::Week.last(2, date(3, 14), Weekday::Mon),
+            &totals,
+            vec!["website".to_owned()],
+        );
+
+        assert_eq!(trend.header(), ["Period", "Total", "website"]);
+        assert_eq!(
+            trend.rows(None),
+            [
+                ["2024-03-04 to 2024-03-10", "3.00", "2.00"],
+                ["2024-03-11 to 2024-03-17", "7.00", "7.00"],
+            ]
+        );
+        assert!(trend.to_json().contains("\"duration\": \"PT7H\""));
+    }
+}
@@ -0,0 +1,234 @@
+//! Updating ttt from its releases on GitHub, available with the `self-update` feature, see
+//! `ttt self-update`.
+//!
+//! Releases have a binary per platform, named like `ttt-x86_64-linux` or
+//! `ttt-x86_64-windows.exe`, each with a [minisign](https://jedisct1.github.io/minisign/)
+//! signature next to it, e.g. `ttt-x86_64-linux.minisig` made with `minisign -S -m
+//! ttt-x86_64-linux`. The public key is built into ttt from the `TTT_UPDATE_PUBLIC_KEY`
+//! environment variable, in the format of the second line of `minisign.pub`. A download whose
+//! signature does not match is not installed, and builds without a key can't update themselves.
+//!
+//! Once a day, commands look for a new release and mention it, which can be turned off:
+//!
+//! ```toml
+//! [update]
+//! notify = false
+//! ```
+
+use std::{fs, io::Read, time::Duration};
+
+use chrono::Local;
+use minisign_verify::{PublicKey, Signature};
+use serde::Deserialize;
+
+use crate::{
+    database::Database,
+    error::{Error, Result},
+};
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/texel-sensei/ttt/releases/latest";
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// Key the releases are signed with, see the module documentation.
+const PUBLIC_KEY: Option<&str> = option_env!("TTT_UPDATE_PUBLIC_KEY");
+
+/// Settings with the day of the last check for the notice and the version found then.
+const CHECKED_ON: &str = "update.checked_on";
+const LATEST: &str = "update.latest";
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdateConfig {
+    /// Mention new releases after commands, looked for at most once a day.
+    pub notify: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self { notify: true }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    /// The version without the `v` of the tag.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    pub fn is_newer(&self) -> bool {
+        is_newer(self.version(), CURRENT)
+    }
+
+    fn download(&self, name: &str) -> Result<Vec<u8>> {
+        let asset = self
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| {
+                Error::Update(format!("Release {} has no file {name}", self.tag_name))
+            })?;
+        let mut content = Vec::new();
+        ureq::get(&asset.browser_download_url)
+            .call()
+            .map_err(|e| Error::Update(e.to_string()))?
+            .into_reader()
+            .read_to_end(&mut content)?;
+        Ok(content)
+    }
+}
+
+/// Ask GitHub for the latest release, giving up after `timeout`.
+pub fn latest_release(timeout: Duration) -> Result<Release> {
+    ureq::get(LATEST_RELEASE)
+        .timeout(timeout)
+        .call()
+        .map_err(|e| Error::Update(e.to_string()))?
+        .into_json()
+        .map_err(|e| Error::Update(e.to_string()))
+}
+
+/// Download the binary of `release` for this platform, check its signature and put it in place
+/// of the running one.
+pub fn install(release: &Release) -> Result<()> {
+    let public_key = PUBLIC_KEY.ok_or_else(|| {
+        Error::Update(
+            "This build of ttt has no key to check releases with, install them by hand".to_owned(),
+        )
+    })?;
+    let name = asset_name();
+    let signature = release.download(&format!("{name}.minisig"))?;
+    let binary = release.download(&name)?;
+    verify(
+        &binary,
+        &String::from_utf8_lossy(&signature),
+        &name,
+        public_key,
+    )?;
+
+    let exe = std::env::current_exe()?;
+    let new = exe.with_extension("new");
+    fs::write(&new, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows cannot replace a running program, but it can rename it.
+    #[cfg(windows)]
+    let old = {
+        let old = exe.with_extension("old");
+        fs::rename(&exe, &old)?;
+        old
+    };
+    if let Err(e) = fs::rename(&new, &exe) {
+        // Put the old program back, so there still is one.
+        #[cfg(windows)]
+        let _ = fs::rename(&old, &exe);
+        let _ = fs::remove_file(&new);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// A notice if there is a newer release than this one. GitHub is asked at most once a day, the
+/// answer is kept in the database. Failures are ignored, the notice is not important enough.
+pub fn notice(db: &mut Database) -> Option<String> {
+    let today = Local::now().date_naive().to_string();
+    let latest = if db.setting(CHECKED_ON).ok()?.as_deref() == Some(today.as_str()) {
+        db.setting(LATEST).ok()??
+    } else {
+        // Also when GitHub cannot be reached, so commands are not slowed down all day.
+        db.set_setting(CHECKED_ON, &today).ok()?;
+        let release = latest_release(Duration::from_secs(2)).ok()?;
+        db.set_setting(LATEST, release.version()).ok()?;
+        release.version().to_owned()
+    };
+    is_newer(&latest, CURRENT)
+        .then(|| format!("ttt {latest} is available, run `ttt self-update` to install it"))
+}
+
+/// Name of the release file for this platform.
+fn asset_name() -> String {
+    format!(
+        "ttt-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// Check that `binary` was signed as `name` with the secret key of `public_key`, where
+/// `signature` is the content of a `.minisig` file. The file name is part of the signed trusted
+/// comment, so the binary of another platform is rejected as well.
+fn verify(binary: &[u8], signature: &str, name: &str, public_key: &str) -> Result<()> {
+    let invalid = |err: minisign_verify::Error| Error::Update(format!("{name}: {err}"));
+    let public_key = PublicKey::from_base64(public_key).map_err(invalid)?;
+    let signature = Signature::decode(signature).map_err(invalid)?;
+    public_key
+        .verify(binary, &signature, false)
+        .map_err(|_| Error::Update(format!("The signature of {name} does not match")))?;
+    let signed_name = signature
+        .trusted_comment()
+        .split('\t')
+        .find_map(|field| field.strip_prefix("file:"));
+    if signed_name != Some(name) {
+        return Err(Error::Update(format!("The signature is not for {name}")));
+    }
+    Ok(())
+}
+
+/// Whether `version` is later than `current`, both like `1.2.3`. Versions that cannot be
+/// read are never newer.
+fn is_newer(version: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        // Pre-releases like `1.0.0-rc.1` count as their release.
+        let release = version.split(['-', '+']).next()?;
+        release.split('.').map(|part| part.parse().ok()).collect()
+    };
+    match (parse(version), parse(current)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        assert!(is_newer("0.8.0", "0.7.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(!is_newer("0.7.0", "0.7.0"));
+        assert!(!is_newer("0.6.9", "0.7.0"));
+        assert!(!is_newer("nightly", "0.7.0"));
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        // Made with a throwaway key, like `minisign -S -m ttt-x86_64-linux` with "ttt 1.0.0" in it
+        let public_key = "RWR1mbkJ1i/DfBiNSU6FDoG5k2svdj/2n3WsSWV6yDXGgdT0FeFgH0M0";
+        let signature = "untrusted comment: signature from minisign secret key
+RUR1mbkJ1i/DfBn5IUEqya00T1NLN4DY0ixWWW/4HAR0qGsA2AVWtWw3JyBLa8j17d2ka0w51vCT3t9eba7r22gu3XGtlOJhbgM=
+trusted comment: timestamp:1717236000\tfile:ttt-x86_64-linux\thashed
+k2OxF/1REofZruO1XmAcX05T0N4abFpQtPAznfzqRPsGJSW+BPgSSTIIWEsSICJSnGWC9zFisesM3Ccb4oaSCQ==";
+        let binary = b"ttt 1.0.0";
+        assert!(verify(binary, signature, "ttt-x86_64-linux", public_key).is_ok());
+        assert!(verify(b"ttt 6.6.6", signature, "ttt-x86_64-linux", public_key).is_err());
+        assert!(verify(binary, signature, "ttt-x86_64-windows.exe", public_key).is_err());
+        let other_key = "RWR1mbkJ1i/DfHQxHoNKKcJ5oadSDNtPWkfbkPFcQly5fdVua5IwzorG";
+        assert!(verify(binary, signature, "ttt-x86_64-linux", other_key).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import type { ErrorCode, ErrorMessage } from '../backend';
+
+let templates: Promise<Record<ErrorCode, string>>|undefined = undefined;
+
+// Describe an error of a backend command in the language of the browser, falling back to the
+// English message sent along with it.
+export async function describeError(e: unknown): Promise<string> {
+  const error = e as ErrorMessage;
+  if (typeof error?.code !== 'string') {
+    return String(e);
+  }
+  templates ??= invoke('error_messages', {language: navigator.language});
+  const template = (await templates.catch(() => undefined))?.[error.code];
+  if (!template) {
+    return error.message;
+  }
+  return Object.entries(error.args).reduce(
+    (text, [name, value]) => text.replaceAll(`{${name}}`, value),
+    template,
+  );
+}